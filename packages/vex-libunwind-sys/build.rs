@@ -1,6 +1,80 @@
 #![allow(missing_docs)]
 
+use std::{
+    env,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
 fn main() {
-    let manifest_dir = env!("CARGO_MANIFEST_DIR");
-    println!("cargo:rustc-link-search=native={manifest_dir}/link");
+    println!("cargo:rerun-if-env-changed=VEX_LIBUNWIND_LIB_DIR");
+    println!("cargo:rerun-if-env-changed=VEX_LIBUNWIND_SRC_DIR");
+
+    let target_arch = env::var("CARGO_CFG_TARGET_ARCH").unwrap();
+    if target_arch == "arm" {
+        let manifest_dir = env!("CARGO_MANIFEST_DIR");
+        println!("cargo:rustc-link-search=native={manifest_dir}/link");
+        return;
+    }
+
+    // Hosts can either use an existing LLVM libunwind...
+    if let Ok(lib_dir) = env::var("VEX_LIBUNWIND_LIB_DIR") {
+        println!("cargo:rustc-link-search=native={lib_dir}");
+        println!("cargo:rustc-link-lib=unwind");
+        return;
+    }
+
+    // ...or build one from source, which by default comes from the copy of
+    // llvm-project shipped in the toolchain's `rust-src` component.
+    let src_dir = env::var_os("VEX_LIBUNWIND_SRC_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(rust_src_libunwind);
+    build_libunwind(&src_dir);
+}
+
+fn rust_src_libunwind() -> PathBuf {
+    let rustc = env::var_os("RUSTC").unwrap_or_else(|| "rustc".into());
+    let output = Command::new(rustc)
+        .args(["--print", "sysroot"])
+        .output()
+        .expect("failed to run rustc to locate the sysroot");
+    let sysroot = String::from_utf8(output.stdout).expect("sysroot path is not UTF-8");
+    Path::new(sysroot.trim()).join("lib/rustlib/src/rust/src/llvm-project/libunwind")
+}
+
+fn build_libunwind(src_dir: &Path) {
+    assert!(
+        src_dir.join("src/libunwind.cpp").exists(),
+        "libunwind sources not found at {}; install the `rust-src` component, or set \
+         VEX_LIBUNWIND_SRC_DIR to a libunwind source tree or VEX_LIBUNWIND_LIB_DIR to a \
+         directory containing a prebuilt LLVM libunwind",
+        src_dir.display()
+    );
+
+    let sources = [
+        "src/libunwind.cpp",
+        "src/UnwindRegistersSave.S",
+        "src/UnwindRegistersRestore.S",
+    ];
+    for source in sources {
+        println!("cargo:rerun-if-changed={}", src_dir.join(source).display());
+    }
+
+    // Only the `unw_*` API is built. The `_Unwind_*` personality API is left
+    // out so that it can't clash with the one the host's Rust std links.
+    cc::Build::new()
+        .cpp(true)
+        .files(sources.map(|source| src_dir.join(source)))
+        .include(src_dir.join("include"))
+        .flag("-std=c++17")
+        .flag("-nostdinc++")
+        .flag("-fno-exceptions")
+        .flag("-fno-rtti")
+        .flag_if_supported("-funwind-tables")
+        .flag_if_supported("-fstrict-aliasing")
+        .define("_LIBUNWIND_IS_NATIVE_ONLY", None)
+        .define("NDEBUG", None)
+        .cpp_link_stdlib(None)
+        .warnings(false)
+        .compile("unwind");
 }
@@ -1,14 +1,35 @@
 //! Bindings to the low-level `unw_*` LLVM libunwind APIs which are an interface
 //! defined by the HP libunwind project.
+//!
+//! On the VEX V5 (armv7a) target, a prebuilt copy of libunwind is linked. On
+//! x86_64 and aarch64 hosts, libunwind is instead built from source so that
+//! the bindings can be exercised by an ordinary `cargo test`.
 #![allow(non_camel_case_types, missing_docs)]
 #![no_std]
 
-#[cfg(not(all(
-    target_arch = "arm",
-    target_endian = "little",
-    target_pointer_width = "32"
+#[cfg(feature = "mock")]
+extern crate std;
+
+#[cfg(all(feature = "mock", target_arch = "arm"))]
+compile_error!(
+    "the `mock` feature is only for tests on hosts, since it needs the standard library"
+);
+
+#[cfg(feature = "mock")]
+pub mod mock;
+
+#[cfg(not(any(
+    all(
+        target_arch = "arm",
+        target_endian = "little",
+        target_pointer_width = "32"
+    ),
+    target_arch = "x86_64",
+    target_arch = "aarch64",
 )))]
-compile_error!("vex-libunwind-sys only supports running in an armv7a environment.");
+compile_error!(
+    "vex-libunwind-sys only supports running in an armv7a environment or on an x86_64/aarch64 host."
+);
 
 use core::ffi::{c_char, c_int, c_void};
 
@@ -48,6 +69,20 @@ pub const CONTEXT_SIZE: usize = 42;
 #[cfg(target_arch = "arm")]
 pub const CURSOR_SIZE: usize = 49;
 
+/// Architecture-specific context size
+#[cfg(target_arch = "x86_64")]
+pub const CONTEXT_SIZE: usize = 21;
+/// Architecture-specific cursor size
+#[cfg(target_arch = "x86_64")]
+pub const CURSOR_SIZE: usize = 33;
+
+/// Architecture-specific context size
+#[cfg(target_arch = "aarch64")]
+pub const CONTEXT_SIZE: usize = 66;
+/// Architecture-specific cursor size
+#[cfg(target_arch = "aarch64")]
+pub const CURSOR_SIZE: usize = 78;
+
 /// The step was successful.
 pub const UNW_STEP_SUCCESS: c_int = 1;
 /// There are no more stack frames.
@@ -69,7 +104,10 @@ pub type unw_addr_space_t = *mut c_void;
 
 pub type unw_regnum_t = c_int;
 pub type unw_word_t = usize;
+#[cfg(target_arch = "arm")]
 pub type unw_fpreg_t = u64;
+#[cfg(not(target_arch = "arm"))]
+pub type unw_fpreg_t = f64;
 
 #[repr(C)]
 #[derive(Debug, Copy, Clone)]
@@ -96,50 +134,156 @@ pub struct unw_proc_info_t {
     pub extra: unw_word_t,
 }
 
-#[link(name = "unwind")]
-extern "C" {
-    pub fn unw_getcontext(ctx: *mut unw_context_t) -> c_int;
+/// Checks that the structs above have the layout that `libunwind` was
+/// compiled with, so that a mismatch fails the build instead of corrupting
+/// memory at runtime.
+///
+/// The expected sizes come from `libunwind`'s `__libunwind_config.h` (as
+/// `_LIBUNWIND_CONTEXT_SIZE` and `_LIBUNWIND_CURSOR_SIZE`, counted in 64-bit
+/// words) and `libunwind.h`:
+///
+/// | Struct            | V5 (ARM)     | x86_64       | aarch64      |
+/// |-------------------|--------------|--------------|--------------|
+/// | `unw_context_t`   | 336 bytes, 8 | 168 bytes, 8 | 528 bytes, 8 |
+/// | `unw_cursor_t`    | 392 bytes, 8 | 264 bytes, 8 | 624 bytes, 8 |
+/// | `unw_proc_info_t` | 40 bytes, 4  | 72 bytes, 8  | 72 bytes, 8  |
+///
+/// The second number in each cell is the alignment. The V5's sizes are for
+/// `libunwind` built without `__ARM_WMMX` or `__SEH__`, as the bundled
+/// library is.
+mod layout {
+    use core::mem::{align_of, size_of};
 
-    pub fn unw_init_local(cur: *mut unw_cursor_t, ctx: *mut unw_context_t) -> c_int;
+    use super::{unw_context_t, unw_cursor_t, unw_proc_info_t};
 
-    pub fn unw_step(cur: *mut unw_cursor_t) -> c_int;
+    #[cfg(target_arch = "arm")]
+    const _: () = {
+        assert!(
+            size_of::<unw_context_t>() == 336 && align_of::<unw_context_t>() == 8,
+            "unw_context_t must be 336 bytes with 8-byte alignment on ARM"
+        );
+        assert!(
+            size_of::<unw_cursor_t>() == 392 && align_of::<unw_cursor_t>() == 8,
+            "unw_cursor_t must be 392 bytes with 8-byte alignment on ARM"
+        );
+        assert!(
+            size_of::<unw_proc_info_t>() == 40 && align_of::<unw_proc_info_t>() == 4,
+            "unw_proc_info_t must be 40 bytes with 4-byte alignment on ARM"
+        );
+    };
 
-    pub fn unw_get_reg(cur: *mut unw_cursor_t, reg: unw_regnum_t, val: *mut unw_word_t) -> c_int;
+    #[cfg(target_arch = "x86_64")]
+    const _: () = {
+        assert!(
+            size_of::<unw_context_t>() == 168 && align_of::<unw_context_t>() == 8,
+            "unw_context_t must be 168 bytes with 8-byte alignment on x86_64"
+        );
+        assert!(
+            size_of::<unw_cursor_t>() == 264 && align_of::<unw_cursor_t>() == 8,
+            "unw_cursor_t must be 264 bytes with 8-byte alignment on x86_64"
+        );
+        assert!(
+            size_of::<unw_proc_info_t>() == 72 && align_of::<unw_proc_info_t>() == 8,
+            "unw_proc_info_t must be 72 bytes with 8-byte alignment on x86_64"
+        );
+    };
 
-    pub fn unw_get_fpreg(cur: *mut unw_cursor_t, reg: unw_regnum_t, val: *mut unw_fpreg_t)
-        -> c_int;
+    #[cfg(target_arch = "aarch64")]
+    const _: () = {
+        assert!(
+            size_of::<unw_context_t>() == 528 && align_of::<unw_context_t>() == 8,
+            "unw_context_t must be 528 bytes with 8-byte alignment on aarch64"
+        );
+        assert!(
+            size_of::<unw_cursor_t>() == 624 && align_of::<unw_cursor_t>() == 8,
+            "unw_cursor_t must be 624 bytes with 8-byte alignment on aarch64"
+        );
+        assert!(
+            size_of::<unw_proc_info_t>() == 72 && align_of::<unw_proc_info_t>() == 8,
+            "unw_proc_info_t must be 72 bytes with 8-byte alignment on aarch64"
+        );
+    };
+}
 
-    pub fn unw_set_reg(cur: *mut unw_cursor_t, reg: unw_regnum_t, val: unw_word_t) -> c_int;
+// With the `mock` feature, the functions are wrapped so that calls can be
+// redirected to a scripted stack.
+#[cfg(not(feature = "mock"))]
+pub use ffi::*;
+#[cfg(feature = "mock")]
+pub use mock::dispatch::*;
 
-    pub fn unw_set_fpreg(cur: *mut unw_cursor_t, reg: unw_regnum_t, val: unw_fpreg_t) -> c_int;
+mod ffi {
+    use super::*;
 
-    #[cfg(target_arch = "arm")]
-    // Save VFP registers in FSTMX format (instead of FSTMD).
-    pub fn unw_save_vfp_as_X(cur: *mut unw_cursor_t);
+    #[cfg_attr(target_arch = "arm", link(name = "unwind"))]
+    extern "C" {
+        pub fn unw_getcontext(ctx: *mut unw_context_t) -> c_int;
 
-    pub fn unw_regname(cur: *mut unw_cursor_t, reg: unw_regnum_t) -> *const c_char;
+        pub fn unw_init_local(cur: *mut unw_cursor_t, ctx: *mut unw_context_t) -> c_int;
 
-    pub fn unw_get_proc_info(cur: *mut unw_cursor_t, info: *mut unw_proc_info_t) -> c_int;
+        pub fn unw_step(cur: *mut unw_cursor_t) -> c_int;
 
-    pub fn unw_is_fpreg(cur: *mut unw_cursor_t, reg: unw_regnum_t) -> c_int;
+        pub fn unw_get_reg(
+            cur: *mut unw_cursor_t,
+            reg: unw_regnum_t,
+            val: *mut unw_word_t,
+        ) -> c_int;
 
-    pub fn unw_is_signal_frame(cur: *mut unw_cursor_t) -> c_int;
+        pub fn unw_get_fpreg(
+            cur: *mut unw_cursor_t,
+            reg: unw_regnum_t,
+            val: *mut unw_fpreg_t,
+        ) -> c_int;
 
-    pub fn unw_get_proc_name(
-        cur: *mut unw_cursor_t,
-        buf: *mut c_char,
-        len: usize,
-        offp: *mut unw_word_t,
-    ) -> c_int;
+        pub fn unw_set_reg(cur: *mut unw_cursor_t, reg: unw_regnum_t, val: unw_word_t) -> c_int;
 
-    pub static mut unw_local_addr_space: unw_addr_space_t;
-}
+        pub fn unw_set_fpreg(cur: *mut unw_cursor_t, reg: unw_regnum_t, val: unw_fpreg_t) -> c_int;
+
+        #[cfg(target_arch = "arm")]
+        // Save VFP registers in FSTMX format (instead of FSTMD).
+        pub fn unw_save_vfp_as_X(cur: *mut unw_cursor_t);
+
+        pub fn unw_regname(cur: *mut unw_cursor_t, reg: unw_regnum_t) -> *const c_char;
+
+        pub fn unw_get_proc_info(cur: *mut unw_cursor_t, info: *mut unw_proc_info_t) -> c_int;
+
+        pub fn unw_is_fpreg(cur: *mut unw_cursor_t, reg: unw_regnum_t) -> c_int;
 
-#[link(name = "unwind")]
-extern "C-unwind" {
-    pub fn unw_resume(cur: *mut unw_cursor_t) -> c_int;
+        pub fn unw_is_signal_frame(cur: *mut unw_cursor_t) -> c_int;
+
+        pub fn unw_get_proc_name(
+            cur: *mut unw_cursor_t,
+            buf: *mut c_char,
+            len: usize,
+            offp: *mut unw_word_t,
+        ) -> c_int;
+
+        pub static mut unw_local_addr_space: unw_addr_space_t;
+    }
+
+    #[cfg_attr(target_arch = "arm", link(name = "unwind"))]
+    extern "C-unwind" {
+        pub fn unw_resume(cur: *mut unw_cursor_t) -> c_int;
+    }
 }
 
+/// Register numbers.
+///
+/// The `UNW_ARM_*` constants describe the VEX V5's registers and are available
+/// on every target so that host-side tools can refer to them.
+///
+/// # Floating-point registers
+///
+/// The V5's Cortex-A9 has a VFPv3 unit with 32 double-precision registers.
+/// `libunwind` only accepts the D registers with its floating-point register
+/// functions; the S register numbers exist in the DWARF numbering but are
+/// rejected with `UNW_EBADREG`.
+///
+/// | Constant                          | Number  | Hardware register            |
+/// |-----------------------------------|---------|------------------------------|
+/// | `UNW_ARM_S0`..=`UNW_ARM_S31`      | 64-95   | `s0`-`s31` (not unwindable)  |
+/// | `UNW_ARM_D0`..=`UNW_ARM_D15`      | 256-271 | `d0`-`d15` (`d`*n* overlaps `s`2*n* and `s`2*n*+1) |
+/// | `UNW_ARM_D16`..=`UNW_ARM_D31`     | 272-287 | `d16`-`d31`                  |
 pub mod registers {
     use super::unw_regnum_t;
 
@@ -155,25 +299,66 @@ pub mod registers {
     // they are given a non-overlapping range of register numbers.
     //
     // Commented out ranges are not preserved during unwinding.
+
+    /// General-purpose register r0
     pub const UNW_ARM_R0: unw_regnum_t = 0;
+    /// General-purpose register r1
     pub const UNW_ARM_R1: unw_regnum_t = 1;
+    /// General-purpose register r2
     pub const UNW_ARM_R2: unw_regnum_t = 2;
+    /// General-purpose register r3
     pub const UNW_ARM_R3: unw_regnum_t = 3;
+    /// General-purpose register r4
     pub const UNW_ARM_R4: unw_regnum_t = 4;
+    /// General-purpose register r5
     pub const UNW_ARM_R5: unw_regnum_t = 5;
+    /// General-purpose register r6
     pub const UNW_ARM_R6: unw_regnum_t = 6;
+    /// General-purpose register r7
     pub const UNW_ARM_R7: unw_regnum_t = 7;
+    /// General-purpose register r8
     pub const UNW_ARM_R8: unw_regnum_t = 8;
+    /// General-purpose register r9
     pub const UNW_ARM_R9: unw_regnum_t = 9;
+    /// General-purpose register r10
     pub const UNW_ARM_R10: unw_regnum_t = 10;
+    /// General-purpose register r11, also used as the frame pointer (`fp`)
     pub const UNW_ARM_R11: unw_regnum_t = 11;
+    /// General-purpose register r12, the intra-procedure-call scratch register
+    /// (`ip`)
     pub const UNW_ARM_R12: unw_regnum_t = 12;
-    pub const UNW_ARM_SP: unw_regnum_t = 13; // Logical alias for UNW_REG_SP
+    /// Stack pointer (r13)
+    ///
+    /// Logical alias for [`UNW_REG_SP`].
+    pub const UNW_ARM_SP: unw_regnum_t = 13;
+    /// Register r13, the stack pointer
     pub const UNW_ARM_R13: unw_regnum_t = 13;
+    /// Link register (r14)
     pub const UNW_ARM_LR: unw_regnum_t = 14;
+    /// Register r14, the link register
     pub const UNW_ARM_R14: unw_regnum_t = 14;
-    pub const UNW_ARM_IP: unw_regnum_t = 15; // Logical alias for UNW_REG_IP
+    /// Program counter (r15)
+    ///
+    /// Logical alias for [`UNW_REG_IP`]. Note that this is *not* the
+    /// `ip` (r12) register of the ARM procedure call standard.
+    pub const UNW_ARM_IP: unw_regnum_t = 15;
+    /// Register r15, the program counter
     pub const UNW_ARM_R15: unw_regnum_t = 15;
+    /// Program counter (r15)
+    pub const UNW_ARM_PC: unw_regnum_t = 15;
+    /// The first exception-handling data register (r0)
+    ///
+    /// Before resuming at a landing pad, a personality routine stores the
+    /// exception object's address here with `_Unwind_SetGR` (or
+    /// `unw_set_reg`). This is `__builtin_eh_return_data_regno(0)`.
+    pub const UNW_ARM_EH_DATA0: unw_regnum_t = 0;
+    /// The second exception-handling data register (r1)
+    ///
+    /// Before resuming at a landing pad, a personality routine stores the
+    /// handler switch value (which `catch` clause matched) here. This is
+    /// `__builtin_eh_return_data_regno(1)`. ARM only has two such registers;
+    /// r2 and r3 are ordinary scratch registers during dispatch.
+    pub const UNW_ARM_EH_DATA1: unw_regnum_t = 1;
     // 16-63 -- OBSOLETE. Used in VFP1 to represent both S0-S31 and D0-D31.
     pub const UNW_ARM_S0: unw_regnum_t = 64;
     pub const UNW_ARM_S1: unw_regnum_t = 65;
@@ -226,6 +411,22 @@ pub mod registers {
     pub const UNW_ARM_WR14: unw_regnum_t = 126;
     pub const UNW_ARM_WR15: unw_regnum_t = 127;
     // 128-133 -- SPSR, SPSR_{FIQ|IRQ|ABT|UND|SVC}
+    //
+    // The saved program status registers aren't preserved during unwinding
+    // either, but have constants so they can be named when asking for them.
+    // There is no DWARF register number for the CPSR itself.
+    /// Saved program status register of the current mode
+    pub const UNW_ARM_SPSR: unw_regnum_t = 128;
+    /// Saved program status register of FIQ mode
+    pub const UNW_ARM_SPSR_FIQ: unw_regnum_t = 129;
+    /// Saved program status register of IRQ mode
+    pub const UNW_ARM_SPSR_IRQ: unw_regnum_t = 130;
+    /// Saved program status register of abort mode
+    pub const UNW_ARM_SPSR_ABT: unw_regnum_t = 131;
+    /// Saved program status register of undefined mode
+    pub const UNW_ARM_SPSR_UND: unw_regnum_t = 132;
+    /// Saved program status register of supervisor mode
+    pub const UNW_ARM_SPSR_SVC: unw_regnum_t = 133;
     // 134-143 -- Reserved
     // 144-150 -- R8_USR-R14_USR
     // 151-157 -- R8_FIQ-R14_FIQ
@@ -0,0 +1,566 @@
+//! A scripted stand-in for `libunwind`, for testing code built on these
+//! bindings.
+//!
+//! While a [`MockStack`] is [installed](MockStack::install) on a thread, every
+//! `unw_*` call made on that thread is answered from the script rather than by
+//! `libunwind`: contexts and cursors capture nothing, stepping moves through
+//! the scripted frames, and any call can be made to fail. A context that the
+//! mock didn't capture is read as the V5's saved registers, and a cursor
+//! initialized from it starts at the scripted frame with the same pc and sp.
+//! Other threads (and the installing thread, once the guard is dropped) use the
+//! real `libunwind` as usual.
+//!
+//! ```
+//! use vex_libunwind_sys::{mock::*, *};
+//!
+//! let _mock = MockStack::new(vec![MockFrame::new(0x1000, 0x2000)])
+//!     .fail_init_local(error::UNW_EUNSPEC)
+//!     .install();
+//!
+//! let mut context = core::mem::MaybeUninit::uninit();
+//! let mut cursor = core::mem::MaybeUninit::uninit();
+//! unsafe {
+//!     assert_eq!(unw_getcontext(context.as_mut_ptr()), error::UNW_ESUCCESS);
+//!     assert_eq!(
+//!         unw_init_local(cursor.as_mut_ptr(), context.as_mut_ptr()),
+//!         error::UNW_EUNSPEC
+//!     );
+//! }
+//! assert_eq!(mock_calls().init_local, 1);
+//! ```
+
+use core::{
+    cell::RefCell,
+    ffi::{c_char, c_int},
+    ops::Range,
+};
+use std::vec::Vec;
+
+use crate::{
+    error, registers, unw_context_t, unw_cursor_t, unw_fpreg_t, unw_proc_info_t, unw_regnum_t,
+    unw_word_t,
+};
+
+/// A frame of a [`MockStack`].
+#[derive(Debug, Clone, Default)]
+pub struct MockFrame {
+    /// The frame's instruction pointer.
+    pub ip: unw_word_t,
+    /// The frame's stack pointer.
+    pub sp: unw_word_t,
+    /// Other registers that can be read in this frame.
+    pub registers: Vec<(unw_regnum_t, unw_word_t)>,
+    /// Floating-point registers that can be read in this frame.
+    pub fp_registers: Vec<(unw_regnum_t, unw_fpreg_t)>,
+    /// The address range of the frame's procedure, or [`None`] for
+    /// `UNW_ENOINFO`.
+    pub proc_range: Option<Range<unw_word_t>>,
+    /// The bytes of the name of the frame's procedure, or [`None`] for
+    /// `UNW_EUNSPEC`.
+    pub name: Option<Vec<u8>>,
+    /// Whether this is a signal frame.
+    pub signal_frame: bool,
+    /// An error code that stepping out of this frame fails with.
+    pub step_error: Option<c_int>,
+}
+
+impl MockFrame {
+    /// Creates a frame with the given instruction and stack pointers and
+    /// nothing else.
+    pub fn new(ip: unw_word_t, sp: unw_word_t) -> Self {
+        Self {
+            ip,
+            sp,
+            ..Default::default()
+        }
+    }
+
+    /// Places the frame in a procedure with the given address range.
+    pub const fn in_proc(mut self, range: Range<unw_word_t>) -> Self {
+        self.proc_range = Some(range);
+        self
+    }
+
+    /// Gives the frame's procedure a name.
+    pub fn named(mut self, name: &str) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+
+    /// Gives the frame's procedure a name that needn't be valid UTF-8.
+    pub fn named_bytes(mut self, name: &[u8]) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+
+    /// Adds a register that can be read in this frame.
+    pub fn with_register(mut self, register: unw_regnum_t, value: unw_word_t) -> Self {
+        self.registers.push((register, value));
+        self
+    }
+
+    /// Adds a floating-point register that can be read in this frame.
+    pub fn with_fp_register(mut self, register: unw_regnum_t, value: unw_fpreg_t) -> Self {
+        self.fp_registers.push((register, value));
+        self
+    }
+
+    /// Marks the frame as a signal frame.
+    pub const fn signal_frame(mut self) -> Self {
+        self.signal_frame = true;
+        self
+    }
+
+    /// Makes stepping out of this frame fail with the given error code.
+    pub const fn fail_step(mut self, code: c_int) -> Self {
+        self.step_error = Some(code);
+        self
+    }
+}
+
+/// A scripted call chain, innermost frame first.
+#[derive(Debug, Clone, Default)]
+pub struct MockStack {
+    frames: Vec<MockFrame>,
+    getcontext_error: Option<c_int>,
+    init_local_error: Option<c_int>,
+    loop_start: Option<usize>,
+}
+
+/// How many times each `unw_*` function has been called on a [`MockStack`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[allow(missing_docs)]
+pub struct MockCalls {
+    pub getcontext: usize,
+    pub init_local: usize,
+    pub step: usize,
+    pub get_reg: usize,
+    pub set_reg: usize,
+    pub get_fpreg: usize,
+    pub set_fpreg: usize,
+    pub get_proc_info: usize,
+    pub get_proc_name: usize,
+    pub is_signal_frame: usize,
+}
+
+struct Installed {
+    stack: MockStack,
+    calls: MockCalls,
+}
+
+std::thread_local! {
+    static INSTALLED: RefCell<Option<Installed>> = const { RefCell::new(None) };
+}
+
+/// Marks contexts and cursors created by the mock, so that they can be told
+/// apart from real ones.
+const MAGIC: u64 = 0x6d6f_636b_6564_0000;
+
+impl MockStack {
+    /// Creates a stack with the given frames, innermost first.
+    pub fn new(frames: Vec<MockFrame>) -> Self {
+        Self {
+            frames,
+            ..Default::default()
+        }
+    }
+
+    /// Makes `unw_getcontext` fail with the given error code.
+    pub const fn fail_getcontext(mut self, code: c_int) -> Self {
+        self.getcontext_error = Some(code);
+        self
+    }
+
+    /// Makes `unw_init_local` fail with the given error code.
+    pub const fn fail_init_local(mut self, code: c_int) -> Self {
+        self.init_local_error = Some(code);
+        self
+    }
+
+    /// Makes stepping out of the last frame go back to the frame at `index`
+    /// instead of reaching the end of the stack, so that stepping never ends,
+    /// as on a corrupted stack that loops back on itself.
+    pub const fn loop_back_to(mut self, index: usize) -> Self {
+        self.loop_start = Some(index);
+        self
+    }
+
+    /// Answers `unw_*` calls on the current thread from this stack until the
+    /// returned guard is dropped, replacing any stack that was already
+    /// installed.
+    pub fn install(self) -> MockGuard {
+        INSTALLED.with(|installed| {
+            *installed.borrow_mut() = Some(Installed {
+                stack: self,
+                calls: MockCalls::default(),
+            });
+        });
+        MockGuard(())
+    }
+}
+
+/// Keeps a [`MockStack`] installed until dropped.
+#[derive(Debug)]
+#[must_use = "the mock is uninstalled when the guard is dropped"]
+pub struct MockGuard(());
+
+impl Drop for MockGuard {
+    fn drop(&mut self) {
+        INSTALLED.with(|installed| *installed.borrow_mut() = None);
+    }
+}
+
+/// Returns how many times each function has been called since the current
+/// thread's mock was installed, or all zeroes if there isn't one.
+pub fn mock_calls() -> MockCalls {
+    INSTALLED.with(|installed| {
+        installed
+            .borrow()
+            .as_ref()
+            .map_or_else(MockCalls::default, |installed| installed.calls)
+    })
+}
+
+fn is_installed() -> bool {
+    INSTALLED.with(|installed| installed.borrow().is_some())
+}
+
+fn with_installed<R>(f: impl FnOnce(&mut Installed) -> R) -> R {
+    INSTALLED.with(|installed| f(installed.borrow_mut().as_mut().unwrap()))
+}
+
+/// Runs `f` with the frame a mock cursor points to, or fails with
+/// `UNW_EINVAL` if `cur` isn't a mock cursor.
+///
+/// # Safety
+///
+/// `cur` must point to an initialized cursor.
+unsafe fn with_frame(
+    cur: *mut unw_cursor_t,
+    count: impl FnOnce(&mut MockCalls) -> &mut usize,
+    f: impl FnOnce(&mut MockFrame) -> c_int,
+) -> c_int {
+    let words = cur.cast::<u64>();
+    // SAFETY: Cursors are at least two words.
+    let (magic, index) = unsafe { (*words, *words.add(1)) };
+    with_installed(|installed| {
+        *count(&mut installed.calls) += 1;
+        if magic != MAGIC {
+            return error::UNW_EINVAL;
+        }
+        match installed.stack.frames.get_mut(index as usize) {
+            Some(frame) => f(frame),
+            None => error::UNW_EINVAL,
+        }
+    })
+}
+
+/// Finds the frame that a context not captured by the mock starts in, reading
+/// the context as the V5's saved registers: r0-r12, sp, lr, and pc, as 32-bit
+/// words. The frame with the same pc and sp is the one it starts in.
+///
+/// # Safety
+///
+/// `ctx` must point to a context.
+unsafe fn task_frame(stack: &MockStack, ctx: *const unw_context_t) -> Option<usize> {
+    let words = ctx.cast::<u32>();
+    // SAFETY: Contexts are larger than sixteen 32-bit words.
+    let (sp, pc) = unsafe { (*words.add(13), *words.add(15)) };
+    stack
+        .frames
+        .iter()
+        .position(|frame| frame.ip == pc as unw_word_t && frame.sp == sp as unw_word_t)
+}
+
+fn find<T: Copy>(values: &[(unw_regnum_t, T)], register: unw_regnum_t) -> Option<T> {
+    values
+        .iter()
+        .find(|(number, _)| *number == register)
+        .map(|(_, value)| *value)
+}
+
+fn replace<T>(values: &mut Vec<(unw_regnum_t, T)>, register: unw_regnum_t, value: T) {
+    values.retain(|(number, _)| *number != register);
+    values.push((register, value));
+}
+
+/// The `unw_*` functions, sent to the installed mock when there is one.
+#[allow(clippy::missing_safety_doc)]
+pub mod dispatch {
+    use super::*;
+    use crate::ffi;
+    pub use crate::ffi::unw_local_addr_space;
+    #[cfg(target_arch = "arm")]
+    pub use crate::ffi::unw_save_vfp_as_X;
+
+    // Inlined so that a real context is captured in the caller's frame, as if
+    // it had called `libunwind` directly.
+    #[inline(always)]
+    pub unsafe fn unw_getcontext(ctx: *mut unw_context_t) -> c_int {
+        if !is_installed() {
+            return unsafe { ffi::unw_getcontext(ctx) };
+        }
+        with_installed(|installed| {
+            installed.calls.getcontext += 1;
+            if let Some(code) = installed.stack.getcontext_error {
+                return code;
+            }
+            // SAFETY: The caller provides a pointer to a context.
+            unsafe { *ctx.cast::<u64>() = MAGIC };
+            error::UNW_ESUCCESS
+        })
+    }
+
+    pub unsafe fn unw_init_local(cur: *mut unw_cursor_t, ctx: *mut unw_context_t) -> c_int {
+        if !is_installed() {
+            return unsafe { ffi::unw_init_local(cur, ctx) };
+        }
+        with_installed(|installed| {
+            installed.calls.init_local += 1;
+            if let Some(code) = installed.stack.init_local_error {
+                return code;
+            }
+            // SAFETY: The caller provides an initialized context and a pointer
+            // to a cursor, which is larger than two words.
+            unsafe {
+                let start = if *ctx.cast::<u64>() == MAGIC {
+                    (!installed.stack.frames.is_empty()).then_some(0)
+                } else {
+                    task_frame(&installed.stack, ctx)
+                };
+                let Some(start) = start else {
+                    return error::UNW_EINVAL;
+                };
+                let words = cur.cast::<u64>();
+                *words = MAGIC;
+                *words.add(1) = start as u64;
+            }
+            error::UNW_ESUCCESS
+        })
+    }
+
+    pub unsafe fn unw_step(cur: *mut unw_cursor_t) -> c_int {
+        if !is_installed() {
+            return unsafe { ffi::unw_step(cur) };
+        }
+        let (len, loop_start) = with_installed(|installed| {
+            (
+                installed.stack.frames.len() as u64,
+                installed.stack.loop_start,
+            )
+        });
+        let code = unsafe {
+            with_frame(
+                cur,
+                |calls| &mut calls.step,
+                |frame| frame.step_error.unwrap_or(crate::UNW_STEP_SUCCESS),
+            )
+        };
+        if code != crate::UNW_STEP_SUCCESS {
+            return code;
+        }
+        // SAFETY: `with_frame` checked that this is a mock cursor.
+        unsafe {
+            let index = cur.cast::<u64>().add(1);
+            if *index + 1 < len {
+                *index += 1;
+            } else if let Some(start) = loop_start {
+                *index = start as u64;
+            } else {
+                return crate::UNW_STEP_END;
+            }
+        }
+        crate::UNW_STEP_SUCCESS
+    }
+
+    pub unsafe fn unw_get_reg(
+        cur: *mut unw_cursor_t,
+        reg: unw_regnum_t,
+        val: *mut unw_word_t,
+    ) -> c_int {
+        if !is_installed() {
+            return unsafe { ffi::unw_get_reg(cur, reg, val) };
+        }
+        unsafe {
+            with_frame(
+                cur,
+                |calls| &mut calls.get_reg,
+                |frame| {
+                    let value = match reg {
+                        registers::UNW_REG_IP => Some(frame.ip),
+                        registers::UNW_REG_SP => Some(frame.sp),
+                        reg => find(&frame.registers, reg),
+                    };
+                    match value {
+                        Some(value) => {
+                            *val = value;
+                            error::UNW_ESUCCESS
+                        }
+                        None => error::UNW_EBADREG,
+                    }
+                },
+            )
+        }
+    }
+
+    pub unsafe fn unw_set_reg(cur: *mut unw_cursor_t, reg: unw_regnum_t, val: unw_word_t) -> c_int {
+        if !is_installed() {
+            return unsafe { ffi::unw_set_reg(cur, reg, val) };
+        }
+        unsafe {
+            with_frame(
+                cur,
+                |calls| &mut calls.set_reg,
+                |frame| {
+                    match reg {
+                        registers::UNW_REG_IP => frame.ip = val,
+                        registers::UNW_REG_SP => frame.sp = val,
+                        reg => replace(&mut frame.registers, reg, val),
+                    }
+                    error::UNW_ESUCCESS
+                },
+            )
+        }
+    }
+
+    pub unsafe fn unw_get_fpreg(
+        cur: *mut unw_cursor_t,
+        reg: unw_regnum_t,
+        val: *mut unw_fpreg_t,
+    ) -> c_int {
+        if !is_installed() {
+            return unsafe { ffi::unw_get_fpreg(cur, reg, val) };
+        }
+        unsafe {
+            with_frame(
+                cur,
+                |calls| &mut calls.get_fpreg,
+                |frame| match find(&frame.fp_registers, reg) {
+                    Some(value) => {
+                        *val = value;
+                        error::UNW_ESUCCESS
+                    }
+                    None => error::UNW_EBADREG,
+                },
+            )
+        }
+    }
+
+    pub unsafe fn unw_set_fpreg(
+        cur: *mut unw_cursor_t,
+        reg: unw_regnum_t,
+        val: unw_fpreg_t,
+    ) -> c_int {
+        if !is_installed() {
+            return unsafe { ffi::unw_set_fpreg(cur, reg, val) };
+        }
+        unsafe {
+            with_frame(
+                cur,
+                |calls| &mut calls.set_fpreg,
+                |frame| {
+                    replace(&mut frame.fp_registers, reg, val);
+                    error::UNW_ESUCCESS
+                },
+            )
+        }
+    }
+
+    pub unsafe fn unw_regname(cur: *mut unw_cursor_t, reg: unw_regnum_t) -> *const c_char {
+        if !is_installed() {
+            return unsafe { ffi::unw_regname(cur, reg) };
+        }
+        c"unknown register".as_ptr()
+    }
+
+    pub unsafe fn unw_get_proc_info(cur: *mut unw_cursor_t, info: *mut unw_proc_info_t) -> c_int {
+        if !is_installed() {
+            return unsafe { ffi::unw_get_proc_info(cur, info) };
+        }
+        unsafe {
+            with_frame(
+                cur,
+                |calls| &mut calls.get_proc_info,
+                |frame| match &frame.proc_range {
+                    Some(range) => {
+                        *info = unw_proc_info_t {
+                            start_ip: range.start,
+                            end_ip: range.end,
+                            lsda: 0,
+                            handler: 0,
+                            gp: 0,
+                            flags: 0,
+                            format: 0,
+                            unwind_info_size: 0,
+                            unwind_info: 0,
+                            extra: 0,
+                        };
+                        error::UNW_ESUCCESS
+                    }
+                    None => error::UNW_ENOINFO,
+                },
+            )
+        }
+    }
+
+    pub unsafe fn unw_is_fpreg(cur: *mut unw_cursor_t, reg: unw_regnum_t) -> c_int {
+        if !is_installed() {
+            return unsafe { ffi::unw_is_fpreg(cur, reg) };
+        }
+        (registers::UNW_ARM_D0..=registers::UNW_ARM_D31).contains(&reg) as c_int
+    }
+
+    pub unsafe fn unw_is_signal_frame(cur: *mut unw_cursor_t) -> c_int {
+        if !is_installed() {
+            return unsafe { ffi::unw_is_signal_frame(cur) };
+        }
+        unsafe {
+            with_frame(
+                cur,
+                |calls| &mut calls.is_signal_frame,
+                |frame| frame.signal_frame as c_int,
+            )
+        }
+    }
+
+    pub unsafe fn unw_get_proc_name(
+        cur: *mut unw_cursor_t,
+        buf: *mut c_char,
+        len: usize,
+        offp: *mut unw_word_t,
+    ) -> c_int {
+        if !is_installed() {
+            return unsafe { ffi::unw_get_proc_name(cur, buf, len, offp) };
+        }
+        unsafe {
+            with_frame(
+                cur,
+                |calls| &mut calls.get_proc_name,
+                |frame| {
+                    let Some(name) = &frame.name else {
+                        return error::UNW_EUNSPEC;
+                    };
+                    if len == 0 {
+                        return error::UNW_ENOMEM;
+                    }
+                    // Like `libunwind` on hosts, silently truncate the name to fit.
+                    let copied = name.len().min(len - 1);
+                    core::ptr::copy_nonoverlapping(name.as_ptr(), buf.cast(), copied);
+                    *buf.add(copied) = 0;
+                    *offp = frame
+                        .proc_range
+                        .as_ref()
+                        .map_or(0, |range| frame.ip - range.start);
+                    error::UNW_ESUCCESS
+                },
+            )
+        }
+    }
+
+    pub unsafe fn unw_resume(cur: *mut unw_cursor_t) -> c_int {
+        if !is_installed() {
+            return unsafe { ffi::unw_resume(cur) };
+        }
+        error::UNW_EINVAL
+    }
+}
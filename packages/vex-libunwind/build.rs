@@ -0,0 +1,11 @@
+#![allow(missing_docs)]
+
+use std::env;
+
+fn main() {
+    // On hosts, libunwind looks up procedure names with `dladdr`, which only
+    // sees symbols that were exported into the dynamic symbol table.
+    if env::var("CARGO_CFG_TARGET_OS").as_deref() == Ok("linux") {
+        println!("cargo:rustc-link-arg-tests=-rdynamic");
+    }
+}
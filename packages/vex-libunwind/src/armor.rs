@@ -0,0 +1,268 @@
+//! A text form of the [`encoding`](crate::encoding) formats, for getting a
+//! backtrace off a robot through a channel that only carries text, such as a
+//! person copying it off the brain's screen.
+//!
+//! Armored text starts with [`PREFIX`], followed by the bytes in Crockford's
+//! base32: the digits and the letters other than I, L, O, and U, each
+//! standing for five bits, most significant first. After every
+//! [`GROUP_LEN`] symbols (and after the last, shorter group) comes a check
+//! symbol, which is the sum of the group's symbol values weighted by their
+//! positions (1 for the first, 2 for the second, and so on), modulo 37. Check
+//! symbols can also be `*`, `~`, `$`, `=`, or `U`, for 32 through 36.
+//!
+//! Since 37 is prime, a check symbol catches any single mistyped symbol in
+//! its group and any two neighboring symbols swapped.
+//!
+//! [`decode_text`] is forgiving in the ways that transcription needs: it
+//! ignores case, whitespace (including line breaks), and hyphens, and reads
+//! `I` and `L` as `1` and `O` as `0`, as Crockford's base32 specifies.
+//!
+//! ```
+//! # use vex_libunwind::armor::*;
+//! let mut text = String::new();
+//! encode_text(b"backtrace", None, &mut text).unwrap();
+//! assert_eq!(text, "VXB:C9GP6TVM=-E9GP6S8$");
+//!
+//! let mut bytes = [0; 9];
+//! assert_eq!(decode_text("vxb:c9gp 6tvm=\ne9gp 6s8$", &mut bytes), Ok(9));
+//! assert_eq!(&bytes, b"backtrace");
+//! ```
+
+use core::fmt::{self, Write};
+
+use snafu::Snafu;
+
+/// What armored text starts with.
+pub const PREFIX: &str = "VXB:";
+
+/// How many symbols each check symbol covers.
+pub const GROUP_LEN: usize = 8;
+
+/// The symbols for the values 0 through 36. Only the first 32 stand for data.
+const SYMBOLS: &[u8; 37] = b"0123456789ABCDEFGHJKMNPQRSTVWXYZ*~$=U";
+
+/// An error that can occur while decoding armored text, with the offset of
+/// the character where decoding failed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Snafu)]
+pub enum TextDecodeError {
+    /// The text doesn't start with the armor's prefix.
+    #[snafu(display("text doesn't start with {PREFIX:?}"))]
+    MissingPrefix,
+    /// A character isn't a base32 symbol.
+    #[snafu(display("unexpected character at offset {offset}"))]
+    InvalidCharacter {
+        /// The byte offset of the character in the text.
+        offset: usize,
+    },
+    /// A group's check symbol doesn't match its symbols.
+    #[snafu(display("check symbol mismatch at offset {offset}"))]
+    ChecksumMismatch {
+        /// The byte offset of the check symbol in the text.
+        offset: usize,
+    },
+    /// The text ends without a check symbol after its last group.
+    #[snafu(display("text ends without a check symbol at offset {offset}"))]
+    Truncated {
+        /// The length of the text.
+        offset: usize,
+    },
+    /// The output buffer is too small for all of the bytes.
+    OutputTooSmall,
+}
+
+/// Writes `bytes` as armored text, breaking lines so that none is longer
+/// than `line_width` characters, if given.
+///
+/// Groups are separated by hyphens on a line, which [`decode_text`] skips.
+/// Lines are only broken between groups, so a line can be longer than
+/// `line_width` if it's too narrow for a whole group.
+///
+/// # Errors
+///
+/// Returns an error if writing to `w` fails.
+pub fn encode_text(bytes: &[u8], line_width: Option<usize>, w: &mut impl Write) -> fmt::Result {
+    w.write_str(PREFIX)?;
+    let mut column = PREFIX.len();
+    let groups = Symbols::new(bytes).groups();
+    for (index, (group, len)) in groups.enumerate() {
+        // The group's symbols, its check symbol, and the separator before it.
+        let needed = len + 1 + usize::from(index > 0);
+        if index > 0 {
+            if line_width.is_some_and(|width| column + needed > width) {
+                w.write_char('\n')?;
+                column = 0;
+            } else {
+                w.write_char('-')?;
+                column += 1;
+            }
+        }
+        for &value in &group[..len] {
+            w.write_char(char::from(SYMBOLS[usize::from(value)]))?;
+        }
+        w.write_char(char::from(SYMBOLS[usize::from(check(&group[..len]))]))?;
+        column += len + 1;
+    }
+    Ok(())
+}
+
+/// Decodes armored text written by [`encode_text`] into `out`, returning the
+/// number of bytes.
+///
+/// # Errors
+///
+/// Returns an error if the text isn't armored, has a character that isn't
+/// part of the armor, fails a check, or doesn't fit in `out`.
+pub fn decode_text(text: &str, out: &mut [u8]) -> Result<usize, TextDecodeError> {
+    let start = text.len() - text.trim_start().len();
+    let body = text[start..]
+        .get(..PREFIX.len())
+        .filter(|prefix| prefix.eq_ignore_ascii_case(PREFIX))
+        .map(|_| start + PREFIX.len())
+        .ok_or(TextDecodeError::MissingPrefix)?;
+
+    let mut bytes = Bytes {
+        out,
+        len: 0,
+        bits: 0,
+        pending: 0,
+    };
+    let mut group = [0; GROUP_LEN];
+    let mut len = 0;
+    let mut last_offset = text.len();
+    for (offset, c) in text[body..].char_indices() {
+        let offset = body + offset;
+        if c.is_whitespace() || c == '-' {
+            continue;
+        }
+        let value = symbol_value(c).ok_or(TextDecodeError::InvalidCharacter { offset })?;
+        last_offset = offset;
+        if len < GROUP_LEN {
+            group[len] = value;
+            len += 1;
+            continue;
+        }
+        finish_group(&group, len, value, offset, &mut bytes)?;
+        len = 0;
+    }
+
+    // The last group is shorter, so its last symbol is its check symbol.
+    if len > 0 {
+        let (check_symbol, data) = (group[len - 1], len - 1);
+        if data == 0 {
+            return Err(TextDecodeError::Truncated { offset: text.len() });
+        }
+        finish_group(&group, data, check_symbol, last_offset, &mut bytes)?;
+    }
+    Ok(bytes.len)
+}
+
+/// Checks a group against its check symbol and adds its data to `bytes`.
+fn finish_group(
+    group: &[u8; GROUP_LEN],
+    len: usize,
+    check_symbol: u8,
+    offset: usize,
+    bytes: &mut Bytes<'_>,
+) -> Result<(), TextDecodeError> {
+    if check(&group[..len]) != check_symbol || group[..len].iter().any(|&value| value >= 32) {
+        return Err(TextDecodeError::ChecksumMismatch { offset });
+    }
+    group[..len].iter().try_for_each(|&value| bytes.push(value))
+}
+
+/// Returns the check symbol value for a group of symbol values.
+fn check(group: &[u8]) -> u8 {
+    let sum: usize = group
+        .iter()
+        .enumerate()
+        .map(|(index, &value)| (index + 1) * usize::from(value))
+        .sum();
+    (sum % 37) as u8
+}
+
+/// Returns the value of a symbol, or of a check symbol.
+fn symbol_value(c: char) -> Option<u8> {
+    let c = match c.to_ascii_uppercase() {
+        'I' | 'L' => '1',
+        'O' => '0',
+        c => c,
+    };
+    let byte = u8::try_from(c).ok()?;
+    SYMBOLS
+        .iter()
+        .position(|&symbol| symbol == byte)
+        .map(|value| value as u8)
+}
+
+/// Splits bytes into five-bit symbol values.
+struct Symbols<'a> {
+    bytes: &'a [u8],
+    bit: usize,
+}
+
+impl<'a> Symbols<'a> {
+    const fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes, bit: 0 }
+    }
+
+    /// Collects the symbols into groups of up to [`GROUP_LEN`], along with
+    /// each group's length.
+    fn groups(mut self) -> impl Iterator<Item = ([u8; GROUP_LEN], usize)> + 'a {
+        core::iter::from_fn(move || {
+            let mut group = [0; GROUP_LEN];
+            let mut len = 0;
+            while len < GROUP_LEN {
+                let Some(value) = self.next() else { break };
+                group[len] = value;
+                len += 1;
+            }
+            (len > 0).then_some((group, len))
+        })
+    }
+}
+
+impl Iterator for Symbols<'_> {
+    type Item = u8;
+
+    fn next(&mut self) -> Option<u8> {
+        if self.bit >= self.bytes.len() * 8 {
+            return None;
+        }
+        // Read the 16 bits starting at the symbol's byte, padding the end of
+        // the input with zeros.
+        let byte = self.bit / 8;
+        let high = u16::from(self.bytes[byte]);
+        let low = u16::from(self.bytes.get(byte + 1).copied().unwrap_or(0));
+        let window = high << 8 | low;
+        let value = (window >> (11 - self.bit % 8)) & 0x1f;
+        self.bit += 5;
+        Some(value as u8)
+    }
+}
+
+/// Packs five-bit symbol values back into bytes, dropping the padding bits
+/// at the end.
+struct Bytes<'a> {
+    out: &'a mut [u8],
+    len: usize,
+    bits: u32,
+    pending: u32,
+}
+
+impl Bytes<'_> {
+    fn push(&mut self, value: u8) -> Result<(), TextDecodeError> {
+        self.bits = self.bits << 5 | u32::from(value);
+        self.pending += 5;
+        if self.pending >= 8 {
+            self.pending -= 8;
+            let slot = self
+                .out
+                .get_mut(self.len)
+                .ok_or(TextDecodeError::OutputTooSmall)?;
+            *slot = (self.bits >> self.pending) as u8;
+            self.bits &= (1 << self.pending) - 1;
+            self.len += 1;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,1501 @@
+//! Capturing and printing whole backtraces.
+
+use core::{
+    fmt::{self, Write},
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+#[cfg(feature = "std")]
+use crate::{
+    encoding::{decode_any, FromEncodedError},
+    BuildIdMismatch,
+};
+use crate::{
+    encoding::{encode_backtrace, EncodeError, FrameEncoding},
+    hot_image::HotImage,
+    sink::write_chunked,
+    BacktraceStyle, Frame, Styled, Symbol, UnwindContext, UnwindError, WalkConfig,
+};
+#[cfg(not(feature = "disabled"))]
+use crate::{guard::ReentrancyGuard, Frames, StackBounds, UnwindCursor, WalkEnd};
+
+/// Written instead of a backtrace when printing is attempted while another
+/// capture or print is still in progress.
+#[cfg(not(feature = "disabled"))]
+const RECURSIVE_FAULT: &str = "recursive fault; not unwinding again\n";
+
+/// Written instead of the frames of a backtrace that wasn't captured because
+/// capture was turned off.
+const DISABLED: &str = "(capture disabled)";
+
+/// Written before the frames of a backtrace that was captured at the end of
+/// its stack.
+const OVERFLOW_SUSPECTED: &str = "!!! STACK OVERFLOW SUSPECTED !!!";
+
+/// Written instead of any backtrace when the `disabled` feature is enabled.
+const COMPILED_OUT: &str = "backtraces disabled\n";
+
+/// The size of the buffer used to look up procedure names while printing.
+#[cfg(all(not(target_arch = "arm"), not(feature = "disabled")))]
+const NAME_BUFFER_SIZE: usize = 128;
+
+/// Whether [`Backtrace::capture`] and friends unwind at all.
+static ENABLED: AtomicBool = AtomicBool::new(true);
+
+/// Turns backtrace capture on or off for the whole program.
+///
+/// While capture is off, [`Backtrace::capture`] and [`BacktraceSlot::capture`]
+/// return empty backtraces with a status of [`BacktraceStatus::Disabled`], and
+/// [`capture_soa`] captures nothing, all without unwinding. Printing a
+/// backtrace of an existing context with [`write_backtrace`] still works.
+/// Capture is on by default.
+///
+/// [`BacktraceSlot::capture`]: crate::BacktraceSlot::capture
+pub fn set_capture_enabled(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+/// Checks whether backtrace capture is on; see [`set_capture_enabled`].
+///
+/// With the `disabled` feature enabled, this is always `false`.
+pub fn capture_enabled() -> bool {
+    !cfg!(feature = "disabled") && ENABLED.load(Ordering::Relaxed)
+}
+
+/// Whether a [`Backtrace`] actually went looking for frames.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum BacktraceStatus {
+    /// The call chain was unwound, although the backtrace may still have
+    /// ended early because of an unwinding error.
+    Captured,
+    /// Capture was turned off with [`set_capture_enabled`] (or compiled out
+    /// with the `disabled` feature), so the backtrace is empty.
+    Disabled,
+}
+
+/// Why a [`Backtrace`] has no more frames, as returned by
+/// [`Backtrace::stop_reason`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum BacktraceStop {
+    /// The walk reached the outermost frame of the stack.
+    EndOfStack,
+    /// The walk ran out of unwind info at a frame where the stack could
+    /// plausibly start, such as in the startup code; see
+    /// [`WalkEnd::Root`](crate::WalkEnd::Root).
+    Root,
+    /// The backtrace filled up, so the stack may go on past its last frame.
+    MaxDepth,
+    /// The walk went on for 1024 frames, the most a capture walks, which
+    /// usually means the stack is corrupted in a way that loops through more
+    /// frames than the cycle check remembers.
+    Fuel,
+    /// The call chain looped back on itself, which only a corrupted stack
+    /// does; see [`WalkEnd::Loop`](crate::WalkEnd::Loop).
+    Loop,
+    /// A [`WalkConfig`] ended the walk early.
+    Stopped,
+    /// Unwinding failed with the given error, after the backtrace's last
+    /// frame (or before its first).
+    Error(UnwindError),
+}
+
+impl BacktraceStop {
+    /// Converts the way a [`Frames`] walk ended into a stop reason.
+    #[cfg(not(feature = "disabled"))]
+    const fn from_walk_end(end: WalkEnd) -> Self {
+        match end {
+            WalkEnd::EndOfStack => Self::EndOfStack,
+            WalkEnd::Root => Self::Root,
+            WalkEnd::Stopped => Self::Stopped,
+            WalkEnd::Loop => Self::Loop,
+            WalkEnd::Error(error) => Self::Error(error),
+        }
+    }
+}
+
+/// The most stack that capturing a context, initializing a cursor, and walking
+/// the whole call chain with it uses, in bytes.
+///
+/// This covers [`Backtrace::capture`] (not counting the [`Backtrace`] itself,
+/// which is returned on the caller's stack) and [`capture_soa`]. The peak is
+/// reached while stepping, since the consumed context's stack slot is still
+/// reserved alongside the cursor; see [`UnwindCursor::PEAK_INIT_STACK`].
+///
+/// On the V5, this is an estimate built from the frame sizes in the bundled
+/// `libunwind` (whose deepest step uses a few hundred bytes) plus the 744
+/// bytes of the context and cursor, rather than a measurement. On hosts,
+/// `libunwind` parses DWARF unwind info instead, and the `stack_usage` tests
+/// check the bound by painting the stack. Unoptimized builds use several times
+/// more stack, so the constant is larger in them.
+pub const MAX_CAPTURE_STACK_BYTES: usize = match (cfg!(target_arch = "arm"), cfg!(debug_assertions))
+{
+    (true, false) => 1536,
+    (true, true) => 4096,
+    (false, false) => 2048,
+    (false, true) => 12 * 1024,
+};
+
+/// The frames of a call chain, captured into a fixed-capacity buffer.
+///
+/// Frames past the first `N` are left out. The frames are stored inline, so a
+/// backtrace takes up a little over `N` times the size of a [`Frame`] (8 bytes
+/// on the V5, plus a byte recording whether it's a signal frame) wherever
+/// it's kept.
+///
+/// Only addresses are captured. To name the frames later, convert the
+/// backtrace into a [`SymbolizedBacktrace`](crate::SymbolizedBacktrace).
+///
+/// With the `disabled` feature enabled, backtraces are zero-sized and always
+/// empty.
+#[derive(Debug, Clone)]
+pub struct Backtrace<const N: usize = 32> {
+    #[cfg(not(feature = "disabled"))]
+    frames: [Frame; N],
+    /// Whether each frame is a signal frame, which may be on a different
+    /// stack than its neighbors.
+    #[cfg(not(feature = "disabled"))]
+    signal: [bool; N],
+    #[cfg(not(feature = "disabled"))]
+    len: usize,
+    #[cfg(not(feature = "disabled"))]
+    status: BacktraceStatus,
+    #[cfg(not(feature = "disabled"))]
+    stop: Option<BacktraceStop>,
+    #[cfg(not(feature = "disabled"))]
+    overflow_suspected: bool,
+}
+
+impl<const N: usize> Backtrace<N> {
+    /// Creates a backtrace with no frames.
+    pub const fn empty() -> Self {
+        Self {
+            #[cfg(not(feature = "disabled"))]
+            frames: [Frame { ip: 0, sp: 0 }; N],
+            #[cfg(not(feature = "disabled"))]
+            signal: [false; N],
+            #[cfg(not(feature = "disabled"))]
+            len: 0,
+            #[cfg(not(feature = "disabled"))]
+            status: BacktraceStatus::Captured,
+            #[cfg(not(feature = "disabled"))]
+            stop: None,
+            #[cfg(not(feature = "disabled"))]
+            overflow_suspected: false,
+        }
+    }
+
+    /// Captures a backtrace of the calling function and its callers.
+    ///
+    /// Unwinding errors end the backtrace early rather than failing the
+    /// capture, keeping the frames found before the error, which is recorded
+    /// in [`Backtrace::error`]. If another capture or print is already in
+    /// progress (for example, because unwinding faulted and the fault
+    /// handler is capturing a backtrace of its own), an empty backtrace is
+    /// returned instead of unwinding again. If capture has been turned off
+    /// with [`set_capture_enabled`], an empty backtrace with a status of
+    /// [`BacktraceStatus::Disabled`] is returned.
+    ///
+    /// This uses up to [`MAX_CAPTURE_STACK_BYTES`] of stack in addition to the
+    /// backtrace itself.
+    #[inline(always)] // Inlining keeps this function from appearing in backtraces
+    pub fn capture() -> Self {
+        let mut backtrace = Self::empty();
+        backtrace.recapture();
+        backtrace
+    }
+
+    /// Captures a backtrace as described in [`Backtrace::capture`], walking
+    /// with the given configuration.
+    ///
+    /// This lets the walk end early, such as at `main` or in an RTOS's task
+    /// entry code, in which case the stop reason is
+    /// [`BacktraceStop::Stopped`].
+    #[inline(always)] // Inlining keeps this function from appearing in backtraces
+    pub fn capture_with(config: WalkConfig<'_>) -> Self {
+        let mut backtrace = Self::empty();
+        backtrace.recapture_with(config);
+        backtrace
+    }
+
+    /// Replaces the backtrace with one of the calling function and its
+    /// callers, as described in [`Backtrace::capture`].
+    #[inline(always)] // Inlining keeps this function from appearing in backtraces
+    pub(crate) fn recapture(&mut self) {
+        self.recapture_with(WalkConfig::new());
+    }
+
+    #[cfg(not(feature = "disabled"))]
+    #[inline(always)] // Inlining keeps this function from appearing in backtraces
+    fn recapture_with(&mut self, config: WalkConfig<'_>) {
+        *self = Self::empty();
+        if !capture_enabled() {
+            self.status = BacktraceStatus::Disabled;
+            return;
+        }
+        let Some(_guard) = ReentrancyGuard::enter() else {
+            return;
+        };
+        match UnwindContext::new() {
+            Ok(context) => self.fill(context, config),
+            Err(error) => self.stop = Some(BacktraceStop::Error(error)),
+        }
+    }
+
+    #[cfg(feature = "disabled")]
+    #[inline(always)]
+    #[allow(clippy::needless_pass_by_value)] // Taken by value, to match the real `recapture_with`
+    fn recapture_with(&mut self, _config: WalkConfig<'_>) {}
+
+    /// Captures a backtrace as described in [`Backtrace::capture`], but
+    /// leaving out the innermost frames that `skip` returns `true` for.
+    ///
+    /// This is for callers that can't inline themselves away, such as code
+    /// generated by macros in other crates.
+    #[cfg(all(feature = "snafu", not(feature = "disabled")))]
+    #[inline(always)] // Inlining keeps this function from appearing in backtraces
+    pub(crate) fn capture_skipping(skip: impl FnMut(&UnwindCursor) -> bool) -> Self {
+        let mut backtrace = Self::empty();
+        if !capture_enabled() {
+            backtrace.status = BacktraceStatus::Disabled;
+            return backtrace;
+        }
+        let Some(_guard) = ReentrancyGuard::enter() else {
+            return backtrace;
+        };
+        match UnwindContext::new().and_then(UnwindCursor::from_context) {
+            Ok(mut cursor) => backtrace.fill_skipping(&mut cursor, WalkConfig::new(), skip),
+            Err(error) => backtrace.stop = Some(BacktraceStop::Error(error)),
+        }
+        backtrace
+    }
+
+    /// Captures a backtrace of the calling function and its callers, without
+    /// any possibility of panicking.
+    ///
+    /// This walks the stack the same way as [`Backtrace::capture`], which
+    /// doesn't panic either (see [`Frames`]), and doesn't format anything, so
+    /// it's safe to call from panic and abort handlers. Formatting the
+    /// backtrace afterward isn't covered by this guarantee.
+    ///
+    /// Unlike [`Backtrace::capture`], failing to start unwinding is reported
+    /// as an error. Errors after that still end the backtrace early, keeping
+    /// the frames found so far. Reentrant captures and disabled capture give
+    /// empty backtraces, as with [`Backtrace::capture`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the context can't be captured or the cursor can't
+    /// be initialized.
+    #[cfg(not(feature = "disabled"))]
+    #[inline(always)] // Inlining keeps this function from appearing in backtraces
+    pub fn try_capture() -> Result<Self, UnwindError> {
+        let mut backtrace = Self::empty();
+        if !capture_enabled() {
+            backtrace.status = BacktraceStatus::Disabled;
+            return Ok(backtrace);
+        }
+        let Some(_guard) = ReentrancyGuard::enter() else {
+            return Ok(backtrace);
+        };
+        let mut cursor = UnwindContext::new().and_then(UnwindCursor::from_context)?;
+        backtrace.fill_from(&mut cursor, WalkConfig::new());
+        Ok(backtrace)
+    }
+
+    /// Returns an empty backtrace, since the `disabled` feature is enabled.
+    ///
+    /// # Errors
+    ///
+    /// Never returns an error; the signature matches the real function's.
+    #[cfg(feature = "disabled")]
+    #[inline(always)]
+    #[allow(clippy::missing_const_for_fn)] // Not `const`, to match the real `try_capture`
+    pub fn try_capture() -> Result<Self, UnwindError> {
+        Ok(Self::empty())
+    }
+
+    #[cfg(not(feature = "disabled"))]
+    fn fill(&mut self, context: UnwindContext, config: WalkConfig<'_>) {
+        match UnwindCursor::from_context(context) {
+            Ok(mut cursor) => self.fill_from(&mut cursor, config),
+            Err(error) => self.stop = Some(BacktraceStop::Error(error)),
+        }
+    }
+
+    #[cfg(not(feature = "disabled"))]
+    fn fill_from(&mut self, cursor: &mut UnwindCursor, config: WalkConfig<'_>) {
+        self.fill_skipping(cursor, config, |_| false);
+    }
+
+    /// Fills the backtrace from the walk starting at `cursor`, leaving out
+    /// the innermost frames that `skip` returns `true` for, and stopping once
+    /// it's full or [`MAX_WALKED_FRAMES`] frames (counting the skipped ones)
+    /// have been walked.
+    #[cfg(not(feature = "disabled"))]
+    fn fill_skipping(
+        &mut self,
+        cursor: &mut UnwindCursor,
+        config: WalkConfig<'_>,
+        mut skip: impl FnMut(&UnwindCursor) -> bool,
+    ) {
+        let mut frames = captured_frames(cursor, config);
+        let mut skipping = true;
+        let stop = loop {
+            if !skipping && self.len == N {
+                break BacktraceStop::MaxDepth;
+            }
+            if frames.frames_seen() == MAX_WALKED_FRAMES {
+                break BacktraceStop::Fuel;
+            }
+            let Some(Ok(frame)) = frames.next() else {
+                // A walk that yields nothing more has ended.
+                break frames
+                    .end()
+                    .map_or(BacktraceStop::EndOfStack, BacktraceStop::from_walk_end);
+            };
+            skipping = skipping && skip(frames.cursor());
+            if skipping {
+                continue;
+            }
+            // Only a backtrace with no room at all is full here.
+            let (Some(slot), Some(signal)) =
+                (self.frames.get_mut(self.len), self.signal.get_mut(self.len))
+            else {
+                break BacktraceStop::MaxDepth;
+            };
+            *slot = frame;
+            *signal = frames.cursor().try_is_signal_frame().unwrap_or(false);
+            // This can't overflow, since it's at most `N`, but wrapping keeps
+            // the overflow check's panic out of the code.
+            self.len = self.len.wrapping_add(1);
+        };
+        self.stop = Some(stop);
+        self.check_overflow();
+    }
+
+    /// Suspects a stack overflow if the innermost frame is too close to the
+    /// end of the registered [`StackBounds`].
+    #[cfg(not(feature = "disabled"))]
+    fn check_overflow(&mut self) {
+        let innermost = self.frames.first().filter(|_| self.len > 0);
+        self.overflow_suspected = innermost
+            .zip(StackBounds::current())
+            .is_some_and(|(frame, bounds)| bounds.overflow_suspected(frame.sp));
+    }
+
+    /// Returns the captured frames, innermost first.
+    #[cfg(not(feature = "disabled"))]
+    pub fn frames(&self) -> &[Frame] {
+        &self.frames[..self.len]
+    }
+
+    /// Returns the captured frames, innermost first.
+    #[cfg(feature = "disabled")]
+    #[allow(clippy::missing_const_for_fn)] // Not `const`, to match the real `frames`
+    pub fn frames(&self) -> &[Frame] {
+        &[]
+    }
+
+    /// Returns each frame along with the number of bytes of stack it uses,
+    /// innermost first, for finding the frames that bring a task close to
+    /// overflowing its stack.
+    ///
+    /// A frame's usage is the distance from its stack pointer to its caller's,
+    /// which covers the frame's locals, saved registers, and any arguments
+    /// it passes on the stack. The outermost frame's usage is [`None`], since
+    /// it has no caller to measure against, as is the usage of frames next to
+    /// a signal frame (which may be on a different stack) and of frames whose
+    /// caller's stack pointer is below their own.
+    pub fn stack_usage(&self) -> impl Iterator<Item = (Frame, Option<usize>)> + '_ {
+        self.frames()
+            .iter()
+            .enumerate()
+            .map(|(index, frame)| (*frame, self.frame_stack_usage(index)))
+    }
+
+    /// Returns the total number of bytes of stack used by the frames whose
+    /// usage [`Backtrace::stack_usage`] could measure.
+    pub fn total_stack_usage(&self) -> usize {
+        self.stack_usage().filter_map(|(_, usage)| usage).sum()
+    }
+
+    /// Returns the stack usage of the frame at `index`, as described in
+    /// [`Backtrace::stack_usage`].
+    pub(crate) fn frame_stack_usage(&self, index: usize) -> Option<usize> {
+        let frames = self.frames();
+        let (frame, caller) = (frames.get(index)?, frames.get(index + 1)?);
+        if self.is_signal_frame(index) || self.is_signal_frame(index + 1) {
+            return None;
+        }
+        caller.sp.checked_sub(frame.sp)
+    }
+
+    #[cfg(not(feature = "disabled"))]
+    fn is_signal_frame(&self, index: usize) -> bool {
+        self.signal.get(index).is_some_and(|&signal| signal)
+    }
+
+    #[cfg(feature = "disabled")]
+    const fn is_signal_frame(&self, _index: usize) -> bool {
+        false
+    }
+
+    /// Reads a backtrace back from any of the [`encoding`](crate::encoding)
+    /// formats, or their [`armor`](crate::armor)ed text forms, telling them
+    /// apart by their headers.
+    ///
+    /// The encodings only hold instruction pointers (and the stop reason,
+    /// if [`Backtrace::encode`] wrote one), so every frame's stack pointer is
+    /// zero. That's all that symbolizing a backtrace needs.
+    ///
+    /// With the `disabled` feature enabled, the input is still checked, but
+    /// the backtrace is empty.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the input can't be decoded, giving the offset of
+    /// the byte or character where decoding failed, or if it holds more
+    /// than `N` frames.
+    #[cfg(feature = "std")]
+    pub fn from_encoded(input: &[u8]) -> Result<Self, FromEncodedError> {
+        Self::from_encoded_for(input, &[]).map(|(backtrace, _)| backtrace)
+    }
+
+    /// Reads a backtrace back like [`Backtrace::from_encoded`], checking it
+    /// against the build ID of the binary it's about to be symbolized with,
+    /// as [`parse_build_id_note`](crate::parse_build_id_note) reads it out of
+    /// the ELF file.
+    ///
+    /// Along with the backtrace, this returns a [`BuildIdMismatch`] if the
+    /// input was encoded by a different build, so that a host tool can warn
+    /// that its names and lines may be wrong. Input without a build ID (from
+    /// a program that doesn't have one, or version 1 of the format) can't be
+    /// checked, and never gives one.
+    ///
+    /// # Errors
+    ///
+    /// Returns the same errors as [`Backtrace::from_encoded`].
+    #[cfg(feature = "std")]
+    pub fn from_encoded_for(
+        input: &[u8],
+        binary_build_id: &[u8],
+    ) -> Result<(Self, Option<BuildIdMismatch>), FromEncodedError> {
+        let mut ips = [0; N];
+        let decoded = decode_any(input, &mut ips)?;
+        let mismatch = decoded
+            .build_id
+            .filter(|recorded| recorded.as_bytes() != binary_build_id)
+            .map(|recorded| BuildIdMismatch { recorded });
+        #[allow(unused_mut)]
+        let mut backtrace = Self::empty();
+        #[cfg(not(feature = "disabled"))]
+        {
+            let len = decoded.count;
+            for (slot, ip) in backtrace.frames.iter_mut().zip(&ips[..len]) {
+                *slot = Frame { ip: *ip, sp: 0 };
+            }
+            backtrace.len = len;
+            backtrace.stop = decoded.stop;
+        }
+        #[cfg(feature = "disabled")]
+        let _ = (decoded.count, decoded.stop);
+        Ok((backtrace, mismatch))
+    }
+
+    /// Encodes the frames' instruction pointers and the backtrace's
+    /// [stop reason](Backtrace::stop_reason) into `out`, returning the number
+    /// of bytes written; see [`encoding`](crate::encoding) for the format.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`EncodeError::BufferTooSmall`] if `out` can't hold the whole
+    /// encoding.
+    pub fn encode(&self, encoding: FrameEncoding, out: &mut [u8]) -> Result<usize, EncodeError> {
+        let ips = self.frames().iter().map(|frame| frame.ip);
+        encode_backtrace(ips, encoding, self.stop_reason(), out)
+    }
+
+    /// Returns a hash of the backtrace's instruction pointers, for grouping
+    /// identical crashes.
+    ///
+    /// Addresses inside the recorded [`HotImage`] are rebased to their
+    /// offsets from its load base first, so the fingerprint doesn't depend on
+    /// where the hot image was loaded. The V5 doesn't otherwise relocate
+    /// programs, so the fingerprint is stable across runs (and robots) of the
+    /// same build, but changes whenever the program is rebuilt. Stack pointers
+    /// aren't included.
+    ///
+    /// Return addresses are hashed as they are rather than reduced to the
+    /// start of their procedure, so crashes reached through different call
+    /// sites in the same functions get different fingerprints. The hash is
+    /// 64-bit FNV-1a over each address as a little-endian `u64`.
+    ///
+    /// [`HotImage`]: crate::hot_image::HotImage
+    pub fn fingerprint(&self) -> u64 {
+        const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+        const PRIME: u64 = 0x0100_0000_01b3;
+
+        let mut hash = OFFSET_BASIS;
+        for frame in self.frames() {
+            let ip = HotImage::rebase(frame.ip) as u64;
+            for byte in ip.to_le_bytes() {
+                hash = (hash ^ u64::from(byte)).wrapping_mul(PRIME);
+            }
+        }
+        hash
+    }
+
+    /// Returns whether the backtrace was actually captured.
+    #[cfg(not(feature = "disabled"))]
+    pub const fn status(&self) -> BacktraceStatus {
+        self.status
+    }
+
+    /// Returns whether the backtrace was captured so close to the end of the
+    /// stack registered with [`StackBounds::set_current`] that the program
+    /// has probably overflowed its stack; see
+    /// [`StackBounds::overflow_suspected`].
+    ///
+    /// This is always `false` if no bounds were registered.
+    #[cfg(not(feature = "disabled"))]
+    pub const fn overflow_suspected(&self) -> bool {
+        self.overflow_suspected
+    }
+
+    /// Returns whether the backtrace was captured close to the end of the
+    /// stack, which is never the case with the `disabled` feature enabled.
+    #[cfg(feature = "disabled")]
+    pub const fn overflow_suspected(&self) -> bool {
+        false
+    }
+
+    /// Returns the error that ended the backtrace early, if unwinding failed
+    /// before the end of the stack (or the backtrace's capacity) was reached.
+    pub const fn error(&self) -> Option<UnwindError> {
+        match self.stop_reason() {
+            Some(BacktraceStop::Error(error)) => Some(error),
+            _ => None,
+        }
+    }
+
+    /// Returns why the backtrace has no more frames, or [`None`] if there was
+    /// no walk to stop: capture was turned off, or started while another
+    /// capture or print was in progress.
+    ///
+    /// This tells apart the reasons a backtrace can be short. Printing the
+    /// backtrace notes the reason after the frames, unless the walk ended
+    /// normally (at [`EndOfStack`](BacktraceStop::EndOfStack) or the
+    /// [`Root`](BacktraceStop::Root)).
+    #[cfg(not(feature = "disabled"))]
+    pub const fn stop_reason(&self) -> Option<BacktraceStop> {
+        self.stop
+    }
+
+    /// Returns why the backtrace has no more frames, which is never known
+    /// with the `disabled` feature enabled.
+    #[cfg(feature = "disabled")]
+    pub const fn stop_reason(&self) -> Option<BacktraceStop> {
+        None
+    }
+
+    /// Returns whether the backtrace was actually captured.
+    #[cfg(feature = "disabled")]
+    pub const fn status(&self) -> BacktraceStatus {
+        BacktraceStatus::Disabled
+    }
+}
+
+impl<const N: usize> Backtrace<N> {
+    /// Prints the backtrace to a transport that takes bytes, such as a serial
+    /// port, passing along the transport's errors.
+    ///
+    /// The output is the same as the backtrace's [`Display`](fmt::Display)
+    /// output, handed to `sink` in chunks of up to 64 bytes. Printing stops at
+    /// the first error `sink` returns. For example, with an
+    /// `embedded_io::Write` serial port:
+    ///
+    /// ```ignore
+    /// backtrace.write_bytes(|bytes| serial.write_all(bytes))?;
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns the first error returned by `sink`.
+    pub fn write_bytes<E>(&self, sink: impl FnMut(&[u8]) -> Result<(), E>) -> Result<(), E> {
+        write_chunked(self, sink)
+    }
+
+    /// Returns an object that prints the backtrace in the given style.
+    pub const fn display(&self, style: BacktraceStyle) -> Styled<'_, Self> {
+        Styled::new(self, style)
+    }
+
+    /// Returns an iterator over the frames that `keep` returns `true` for,
+    /// innermost first.
+    ///
+    /// `keep` sees the whole [`Frame`], so it can hide frames by address
+    /// range, by [`MemoryMap`](crate::MemoryMap) region, or by anything else
+    /// it knows about the program, such as the ranges of a logging shim or
+    /// an async executor's polling code.
+    pub fn filtered<'a>(
+        &'a self,
+        keep: impl Fn(&Frame) -> bool + 'a,
+    ) -> impl Iterator<Item = &'a Frame> + 'a {
+        self.frames().iter().filter(move |frame| keep(frame))
+    }
+
+    /// Returns an object that prints the backtrace in the given style, but
+    /// with the frames that `keep` returns `false` for hidden.
+    ///
+    /// The frames that are printed keep their numbers from the whole
+    /// backtrace, and each run of hidden frames is replaced by a note of how
+    /// many there were, so it's clear where frames are missing:
+    ///
+    /// ```text
+    /// stack backtrace:
+    ///    0: 0x03800120
+    ///   ... (2 frames hidden)
+    ///    3: 0x03800468
+    /// ```
+    pub const fn display_filtered<F: Fn(&Frame) -> bool>(
+        &self,
+        style: BacktraceStyle,
+        keep: F,
+    ) -> FilteredBacktrace<'_, N, F> {
+        FilteredBacktrace {
+            backtrace: self,
+            style,
+            keep,
+        }
+    }
+
+    pub(crate) fn write_styled(&self, w: &mut impl Write, style: BacktraceStyle) -> fmt::Result {
+        self.write_filtered(w, style, |_| true)
+    }
+
+    fn write_filtered(
+        &self,
+        w: &mut impl Write,
+        style: BacktraceStyle,
+        keep: impl Fn(&Frame) -> bool,
+    ) -> fmt::Result {
+        if write_status_note(w, self.status(), style)? {
+            return Ok(());
+        }
+        write_overflow_note(w, self.overflow_suspected(), style)?;
+        let frames = self.frames();
+        if style.outermost_first() {
+            write_stop_note(w, self.stop_reason(), frames.len())?;
+        }
+        let mut hidden = 0;
+        style.for_each_frame(frames.len(), |index| {
+            let frame = &frames[index];
+            if !keep(frame) {
+                hidden += 1;
+                return Ok(());
+            }
+            write_hidden_note(w, hidden)?;
+            hidden = 0;
+            write_frame(w, index, frame, None, self.frame_stack_usage(index), style)
+        })?;
+        write_hidden_note(w, hidden)?;
+        if style.outermost_first() {
+            return Ok(());
+        }
+        write_stop_note(w, self.stop_reason(), frames.len())
+    }
+}
+
+/// A backtrace printed with some of its frames hidden; see
+/// [`Backtrace::display_filtered`].
+pub struct FilteredBacktrace<'a, const N: usize, F> {
+    backtrace: &'a Backtrace<N>,
+    style: BacktraceStyle,
+    keep: F,
+}
+
+impl<const N: usize, F> fmt::Debug for FilteredBacktrace<'_, N, F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FilteredBacktrace")
+            .field("backtrace", self.backtrace)
+            .field("style", &self.style)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<const N: usize, F: Fn(&Frame) -> bool> fmt::Display for FilteredBacktrace<'_, N, F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.backtrace.write_filtered(f, self.style, &self.keep)
+    }
+}
+
+/// Prints how many frames were hidden in a row, if any were.
+fn write_hidden_note(w: &mut impl Write, hidden: usize) -> fmt::Result {
+    match hidden {
+        0 => Ok(()),
+        1 => w.write_str("  ... (1 frame hidden)\n"),
+        hidden => writeln!(w, "  ... ({hidden} frames hidden)"),
+    }
+}
+
+/// Walks a previously captured context, such as one saved by an exception
+/// handler, rather than the live stack.
+///
+/// As with [`Backtrace::capture`], errors after the cursor is initialized end
+/// the backtrace early, and a conversion that starts while another capture or
+/// print is in progress gives an empty backtrace. Capture being turned off
+/// with [`set_capture_enabled`] doesn't affect conversions, since the context
+/// has already been captured.
+#[cfg(not(feature = "disabled"))]
+impl<const N: usize> TryFrom<&UnwindContext> for Backtrace<N> {
+    type Error = UnwindError;
+
+    fn try_from(context: &UnwindContext) -> Result<Self, UnwindError> {
+        let mut backtrace = Self::empty();
+        let Some(_guard) = ReentrancyGuard::enter() else {
+            return Ok(backtrace);
+        };
+        let mut cursor = UnwindCursor::new(context)?;
+        backtrace.fill_from(&mut cursor, WalkConfig::new());
+        Ok(backtrace)
+    }
+}
+
+/// Gives an empty backtrace, since the `disabled` feature is enabled.
+#[cfg(feature = "disabled")]
+impl<const N: usize> TryFrom<&UnwindContext> for Backtrace<N> {
+    type Error = UnwindError;
+
+    fn try_from(_context: &UnwindContext) -> Result<Self, UnwindError> {
+        Ok(Self::empty())
+    }
+}
+
+impl<const N: usize> Default for Backtrace<N> {
+    fn default() -> Self {
+        Self::empty()
+    }
+}
+
+impl<const N: usize> fmt::Display for Backtrace<N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.write_styled(f, BacktraceStyle::new())
+    }
+}
+
+/// Prints the note that stands in for the frames of a backtrace that wasn't
+/// captured, if it wasn't, returning whether it did.
+pub(crate) fn write_status_note(
+    w: &mut impl Write,
+    status: BacktraceStatus,
+    style: BacktraceStyle,
+) -> Result<bool, fmt::Error> {
+    if cfg!(feature = "disabled") {
+        w.write_str(COMPILED_OUT)?;
+        return Ok(true);
+    }
+    if style.outermost_first() {
+        w.write_str("stack backtrace (most recent call last):\n")?;
+    } else {
+        w.write_str("stack backtrace:\n")?;
+    }
+    if status == BacktraceStatus::Disabled {
+        w.write_str("  ")?;
+        style.write_note(w, DISABLED)?;
+        w.write_char('\n')?;
+        return Ok(true);
+    }
+    Ok(false)
+}
+
+/// Prints a banner warning of a stack overflow, if one is suspected.
+pub(crate) fn write_overflow_note(
+    w: &mut impl Write,
+    overflow_suspected: bool,
+    style: BacktraceStyle,
+) -> fmt::Result {
+    if !overflow_suspected {
+        return Ok(());
+    }
+    w.write_str("  ")?;
+    style.write_note(w, OVERFLOW_SUSPECTED)?;
+    w.write_char('\n')
+}
+
+/// Prints why unwinding ended early, if it did, after the `len` frames it
+/// found.
+pub(crate) fn write_stop_note(
+    w: &mut impl Write,
+    stop: Option<BacktraceStop>,
+    len: usize,
+) -> fmt::Result {
+    match stop {
+        Some(BacktraceStop::Error(error)) => write_error_note(w, error),
+        Some(BacktraceStop::MaxDepth) => write_limit_note(w, len),
+        Some(BacktraceStop::Fuel) => w.write_str("  (unwinding stopped: too many frames)\n"),
+        Some(BacktraceStop::Loop) => {
+            w.write_str("  (unwinding stopped: stack loops back on itself)\n")
+        }
+        Some(BacktraceStop::Stopped) => w.write_str("  (stopped early)\n"),
+        _ => Ok(()),
+    }
+}
+
+/// Prints the error that ended unwinding early.
+pub(crate) fn write_error_note(w: &mut impl Write, error: UnwindError) -> fmt::Result {
+    writeln!(w, "  (unwinding stopped: {error})")
+}
+
+/// Prints that unwinding was cut off after `limit` frames.
+pub(crate) fn write_limit_note(w: &mut impl Write, limit: usize) -> fmt::Result {
+    writeln!(w, "  ... (stopped after {limit} frames)")
+}
+
+pub(crate) fn write_frame(
+    w: &mut impl Write,
+    index: usize,
+    frame: &Frame,
+    name: Option<Symbol<'_>>,
+    stack_usage: Option<usize>,
+    style: BacktraceStyle,
+) -> fmt::Result {
+    if let Some(max_width) = style.narrow_width() {
+        style.write_narrow_frame(w, index, frame.ip, name, max_width)?;
+        return w.write_char('\n');
+    }
+    write!(w, "{index:>4}: ")?;
+    style.write_address(w, frame.ip)?;
+    if let Some(offset) = style
+        .relative_base()
+        .and_then(|base| frame.relative_ip(base))
+    {
+        write!(w, " (base+{offset:#x})")?;
+    }
+    if let Some(symbol) = name {
+        w.write_str(" - ")?;
+        style.write_name(w, symbol)?;
+        write!(w, "+{:#x}", symbol.offset)?;
+    }
+    if let Some(bytes) = stack_usage.filter(|_| style.shows_stack_usage()) {
+        write!(w, " (+{bytes:#x} stack)")?;
+    }
+    w.write_char('\n')
+}
+
+/// Prints a frame, along with its procedure's name when running on a host.
+///
+/// `libunwind` can never find names on the V5, so the name buffer isn't worth
+/// its stack space there.
+#[cfg(all(not(target_arch = "arm"), not(feature = "disabled")))]
+pub(crate) fn write_named_frame(
+    w: &mut impl Write,
+    index: usize,
+    frame: &Frame,
+    cursor: &UnwindCursor,
+) -> fmt::Result {
+    let mut buf = [0; NAME_BUFFER_SIZE];
+    let name = cursor
+        .procedure_name_lossy(&mut buf)
+        .ok()
+        .map(|(name, offset)| Symbol::from_symbol_table(name, offset));
+    write_frame(w, index, frame, name, None, BacktraceStyle::new())
+}
+
+#[cfg(all(target_arch = "arm", not(feature = "disabled")))]
+pub(crate) fn write_named_frame(
+    w: &mut impl Write,
+    index: usize,
+    frame: &Frame,
+    _cursor: &UnwindCursor,
+) -> fmt::Result {
+    write_frame(w, index, frame, None, None, BacktraceStyle::new())
+}
+
+/// Prints a backtrace starting at the given context, naming each frame's
+/// procedure where `libunwind` can find one.
+///
+/// Frames are printed as they're unwound, so nothing is buffered. The walk is
+/// the same as [`Backtrace::capture`]'s, so it stops after 1024 frames or
+/// where the call chain loops back on itself, and a corrupted stack can't
+/// keep a fault handler printing forever. If unwinding fails partway through,
+/// the error is printed after the frames that were found.
+///
+/// If another capture or print is already in progress (for example, because
+/// unwinding faulted and the fault handler is printing a backtrace of its
+/// own), a short "recursive fault" message is printed instead of unwinding
+/// again.
+///
+/// # Stack usage
+///
+/// This needs more stack than [`MAX_CAPTURE_STACK_BYTES`], since `w`'s
+/// `write_str` and the formatting machinery run on top of the walk. On hosts,
+/// looking up names through the dynamic linker needs a couple of KiB more
+/// still. Make sure a fault handler that calls this has a few KiB of stack to
+/// spare.
+///
+/// # Errors
+///
+/// Returns an error if writing to `w` fails.
+#[cfg(not(feature = "disabled"))]
+pub fn write_backtrace(w: &mut impl Write, context: &UnwindContext) -> fmt::Result {
+    write_frames(w, context, MAX_WALKED_FRAMES, write_named_frame)
+}
+
+/// Prints a one-line note that backtraces are disabled, since the `disabled`
+/// feature is enabled.
+///
+/// # Errors
+///
+/// Returns an error if writing to `w` fails.
+#[cfg(feature = "disabled")]
+pub fn write_backtrace(w: &mut impl Write, _context: &UnwindContext) -> fmt::Result {
+    w.write_str(COMPILED_OUT)
+}
+
+/// Prints a backtrace like [`write_backtrace`], to a transport that takes
+/// bytes rather than implementing [`fmt::Write`], passing along the
+/// transport's errors.
+///
+/// The output is handed to `sink` in chunks of up to 64 bytes, and printing
+/// stops at the first error it returns. For example, with an
+/// `embedded_io::Write` serial port:
+///
+/// ```ignore
+/// write_backtrace_bytes(|bytes| serial.write_all(bytes), &context)?;
+/// ```
+///
+/// # Errors
+///
+/// Returns the first error returned by `sink`.
+pub fn write_backtrace_bytes<E>(
+    sink: impl FnMut(&[u8]) -> Result<(), E>,
+    context: &UnwindContext,
+) -> Result<(), E> {
+    struct Printed<'a>(&'a UnwindContext);
+
+    impl fmt::Display for Printed<'_> {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write_backtrace(f, self.0)
+        }
+    }
+
+    write_chunked(&Printed(context), sink)
+}
+
+/// The first line of [`write_backtrace_machine`]'s output, naming the format
+/// and its version.
+const MACHINE_HEADER: &str = "backtrace v2\n";
+
+/// Prints a backtrace starting at the given context in a stable line format
+/// meant for scripts to parse, rather than people to read.
+///
+/// Unlike the format of [`write_backtrace`] and [`Display`](fmt::Display),
+/// which may change to read better, this one only changes along with the
+/// version in its first line. Each line ends with a line feed:
+///
+/// ```text
+/// output = "backtrace v2" LF *frame end
+/// frame  = "#" index SP ip SP offset SP name LF
+/// index  = decimal             ; 0 for the context's frame, counting up
+/// ip     = "0x" lowercase-hex  ; the frame's instruction pointer
+/// offset = "0x" lowercase-hex  ; how far into its procedure ip is
+///        / "-"                 ; if the procedure can't be found
+/// name   = 1*char              ; the procedure's mangled name, with control
+///                              ; characters written as "?"
+///        / "-"                 ; if the name can't be found
+/// end    = "end ok" LF                 ; the end of the stack was reached
+///        / "end error " message LF     ; unwinding failed with this error
+///        / "end loop" LF               ; the call chain looped back on itself
+///        / "end limit" LF              ; the walk stopped after 1024 frames
+///        / "end busy" LF               ; another capture or print was in
+///                                      ; progress, so nothing was unwound
+///        / "end disabled" LF           ; the `disabled` feature is enabled
+/// ```
+///
+/// The walk is the same as [`Backtrace::capture`]'s, so a corrupted stack
+/// ends the output with `end loop` or `end limit` rather than printing
+/// forever. Names can have spaces in them, so a script should split each
+/// frame line into at most four fields. On the V5, where `libunwind` can't
+/// find names, every name is `-`, and the offsets are for looking the frames
+/// up in a symbol file.
+///
+/// # Errors
+///
+/// Returns an error if writing to `w` fails.
+#[cfg(not(feature = "disabled"))]
+pub fn write_backtrace_machine(w: &mut impl Write, context: &UnwindContext) -> fmt::Result {
+    w.write_str(MACHINE_HEADER)?;
+    let Some(_guard) = ReentrancyGuard::enter() else {
+        return w.write_str("end busy\n");
+    };
+    let mut cursor = match UnwindCursor::new(context) {
+        Ok(cursor) => cursor,
+        Err(error) => return writeln!(w, "end error {error}"),
+    };
+
+    let mut frames = captured_frames(&mut cursor, WalkConfig::new());
+    let mut index = 0;
+    let stop = loop {
+        let Some(Ok(frame)) = frames.next() else {
+            break frames
+                .end()
+                .map_or(BacktraceStop::EndOfStack, BacktraceStop::from_walk_end);
+        };
+        if index == MAX_WALKED_FRAMES {
+            break BacktraceStop::MaxDepth;
+        }
+        write!(w, "#{index} {:#x} ", frame.ip)?;
+        match frames
+            .cursor()
+            .proc_range()
+            .ok()
+            .and_then(|range| frame.ip.checked_sub(range.start))
+        {
+            Some(offset) => write!(w, "{offset:#x} ")?,
+            None => w.write_str("- ")?,
+        }
+        write_machine_name(w, frames.cursor())?;
+        w.write_char('\n')?;
+        index += 1;
+    };
+    match stop {
+        BacktraceStop::EndOfStack | BacktraceStop::Root | BacktraceStop::Stopped => {
+            w.write_str("end ok\n")
+        }
+        BacktraceStop::MaxDepth | BacktraceStop::Fuel => w.write_str("end limit\n"),
+        BacktraceStop::Loop => w.write_str("end loop\n"),
+        BacktraceStop::Error(error) => writeln!(w, "end error {error}"),
+    }
+}
+
+/// Prints a backtrace with no frames, since the `disabled` feature is
+/// enabled; see the real function's docs for the format.
+///
+/// # Errors
+///
+/// Returns an error if writing to `w` fails.
+#[cfg(feature = "disabled")]
+pub fn write_backtrace_machine(w: &mut impl Write, _context: &UnwindContext) -> fmt::Result {
+    w.write_str(MACHINE_HEADER)?;
+    w.write_str("end disabled\n")
+}
+
+/// Prints the name field of a [`write_backtrace_machine`] frame line.
+#[cfg(all(not(target_arch = "arm"), not(feature = "disabled")))]
+fn write_machine_name(w: &mut impl Write, cursor: &UnwindCursor) -> fmt::Result {
+    let mut buf = [0; NAME_BUFFER_SIZE];
+    match cursor.procedure_name_lossy(&mut buf) {
+        Ok((name, _)) if !name.is_empty() => name
+            .chars()
+            .try_for_each(|c| w.write_char(if c.is_control() { '?' } else { c })),
+        _ => w.write_char('-'),
+    }
+}
+
+#[cfg(all(target_arch = "arm", not(feature = "disabled")))]
+fn write_machine_name(w: &mut impl Write, _cursor: &UnwindCursor) -> fmt::Result {
+    w.write_char('-')
+}
+
+/// Prints a backtrace like [`write_backtrace`], but remembers the names of
+/// the last `N` distinct instruction pointers it looked up so that frames
+/// repeated by recursion are only looked up once.
+///
+/// The cache is kept on the stack, taking up a little over `N` times 128
+/// bytes, and only lasts for a single call. With `N` at zero, nothing is
+/// cached. On the V5, where no
+/// names can be found, this is the same as [`write_backtrace`].
+///
+/// # Errors
+///
+/// Returns an error if writing to `w` fails.
+#[cfg(all(not(target_arch = "arm"), not(feature = "disabled")))]
+pub fn write_backtrace_cached<const N: usize>(
+    w: &mut impl Write,
+    context: &UnwindContext,
+) -> fmt::Result {
+    let mut cache = NameCache::<N>::new();
+    write_frames(w, context, MAX_WALKED_FRAMES, |w, index, frame, cursor| {
+        cache.write_named_frame(w, index, frame, cursor)
+    })
+}
+
+/// Prints a backtrace like [`write_backtrace`], but remembers the names of
+/// the last `N` distinct instruction pointers it looked up so that frames
+/// repeated by recursion are only looked up once.
+///
+/// On the V5, where no names can be found, and with the `disabled` feature
+/// enabled, this is the same as [`write_backtrace`].
+///
+/// # Errors
+///
+/// Returns an error if writing to `w` fails.
+#[cfg(any(target_arch = "arm", feature = "disabled"))]
+pub fn write_backtrace_cached<const N: usize>(
+    w: &mut impl Write,
+    context: &UnwindContext,
+) -> fmt::Result {
+    write_backtrace(w, context)
+}
+
+/// Prints the header and up to `limit` frames of the walk starting at
+/// `context`, noting why the walk stopped if it didn't reach the end.
+#[cfg(not(feature = "disabled"))]
+pub(crate) fn write_frames<W: Write>(
+    w: &mut W,
+    context: &UnwindContext,
+    limit: usize,
+    mut write_frame: impl FnMut(&mut W, usize, &Frame, &UnwindCursor) -> fmt::Result,
+) -> fmt::Result {
+    let Some(_guard) = ReentrancyGuard::enter() else {
+        return w.write_str(RECURSIVE_FAULT);
+    };
+
+    w.write_str("stack backtrace:\n")?;
+    let mut cursor = match UnwindCursor::new(context) {
+        Ok(cursor) => cursor,
+        Err(error) => return writeln!(w, "  (unwinding failed: {error})"),
+    };
+
+    let mut frames = captured_frames(&mut cursor, WalkConfig::new());
+    let mut index = 0;
+    let stop = loop {
+        let Some(Ok(frame)) = frames.next() else {
+            break frames
+                .end()
+                .map_or(BacktraceStop::EndOfStack, BacktraceStop::from_walk_end);
+        };
+        if index == limit {
+            break BacktraceStop::MaxDepth;
+        }
+        write_frame(w, index, &frame, frames.cursor())?;
+        index += 1;
+    };
+    write_stop_note(w, Some(stop), limit)
+}
+
+/// A name looked up for an instruction pointer, or the lack of one.
+#[cfg(all(not(target_arch = "arm"), not(feature = "disabled")))]
+#[derive(Clone, Copy)]
+struct CachedName {
+    ip: usize,
+    name: [u8; NAME_BUFFER_SIZE],
+    /// The length of the name and its offset, if one was found.
+    found: Option<(usize, u64)>,
+    last_used: u64,
+}
+
+/// The names of the `N` most recently printed instruction pointers.
+#[cfg(all(not(target_arch = "arm"), not(feature = "disabled")))]
+struct NameCache<const N: usize> {
+    entries: [Option<CachedName>; N],
+    clock: u64,
+}
+
+#[cfg(all(not(target_arch = "arm"), not(feature = "disabled")))]
+impl<const N: usize> NameCache<N> {
+    const fn new() -> Self {
+        Self {
+            entries: [None; N],
+            clock: 0,
+        }
+    }
+
+    fn write_named_frame(
+        &mut self,
+        w: &mut impl Write,
+        index: usize,
+        frame: &Frame,
+        cursor: &UnwindCursor,
+    ) -> fmt::Result {
+        self.clock += 1;
+        let cached = self
+            .entries
+            .iter_mut()
+            .flatten()
+            .find(|entry| entry.ip == frame.ip)
+            .map(|entry| {
+                entry.last_used = self.clock;
+                *entry
+            });
+        let cached = match cached {
+            Some(cached) => cached,
+            None => {
+                let mut name = [0; NAME_BUFFER_SIZE];
+                let found = cursor
+                    .procedure_name_lossy(&mut name)
+                    .ok()
+                    .map(|(name, offset)| (name.len(), offset));
+                let entry = CachedName {
+                    ip: frame.ip,
+                    name,
+                    found,
+                    last_used: self.clock,
+                };
+                if let Some(slot) = self
+                    .entries
+                    .iter_mut()
+                    .min_by_key(|slot| slot.map_or(0, |entry| entry.last_used))
+                {
+                    *slot = Some(entry);
+                }
+                entry
+            }
+        };
+
+        let name = cached.found.and_then(|(len, offset)| {
+            let name = core::str::from_utf8(&cached.name[..len]).ok()?;
+            Some(Symbol::from_symbol_table(name, offset))
+        });
+        write_frame(w, index, frame, name, None, BacktraceStyle::new())
+    }
+}
+
+/// The walk that a capture starting at `cursor` records, before it's cut off
+/// at the backtrace's capacity or [`MAX_WALKED_FRAMES`]. The walk ends after
+/// the first error.
+///
+/// Capturing and counting both walk through this, so a count always matches
+/// what a large enough capture with the default configuration would hold.
+#[cfg(not(feature = "disabled"))]
+fn captured_frames<'a>(cursor: &'a mut UnwindCursor, config: WalkConfig<'a>) -> Frames<'a> {
+    cursor.frames(config)
+}
+
+/// The most frames [`count_frames`], [`count_frames_in`], [`nth_frame`], the
+/// captures of a [`Backtrace`], and every other walk in the crate will go
+/// through.
+pub(crate) const MAX_WALKED_FRAMES: usize = 1024;
+
+/// Counts the frames of the calling function and its callers without storing
+/// them, such as to size a buffer for a capture.
+///
+/// The count matches the number of frames [`Backtrace::capture`] would find
+/// from the same place if its capacity were unlimited: unwinding errors end
+/// the count early, and a reentrant or disabled capture counts zero frames.
+/// Walks that go on for more than 1024 frames (which usually means the stack
+/// is corrupted) stop counting there.
+///
+/// # Errors
+///
+/// Returns an error if the context can't be captured or the cursor can't be
+/// initialized.
+#[cfg(not(feature = "disabled"))]
+#[inline(always)] // Inlining keeps this function from appearing in backtraces
+pub fn count_frames() -> Result<usize, UnwindError> {
+    if !capture_enabled() {
+        return Ok(0);
+    }
+    let Some(_guard) = ReentrancyGuard::enter() else {
+        return Ok(0);
+    };
+    let context = UnwindContext::new()?;
+    let mut cursor = UnwindCursor::from_context(context)?;
+    Ok(count_from(&mut cursor))
+}
+
+/// Counts nothing, since the `disabled` feature is enabled.
+///
+/// # Errors
+///
+/// Never returns an error; the signature matches the real function's.
+#[cfg(feature = "disabled")]
+#[inline(always)]
+#[allow(clippy::missing_const_for_fn)] // Not `const`, to match the real `count_frames`
+pub fn count_frames() -> Result<usize, UnwindError> {
+    Ok(0)
+}
+
+/// Counts the frames of an existing context without storing them, matching
+/// the number of frames a large enough [`Backtrace`] converted from it would
+/// hold; see [`count_frames`].
+///
+/// As with the conversion, capture being turned off with
+/// [`set_capture_enabled`] doesn't affect the count.
+///
+/// # Errors
+///
+/// Returns an error if the cursor can't be initialized.
+#[cfg(not(feature = "disabled"))]
+pub fn count_frames_in(context: &UnwindContext) -> Result<usize, UnwindError> {
+    let Some(_guard) = ReentrancyGuard::enter() else {
+        return Ok(0);
+    };
+    let mut cursor = UnwindCursor::new(context)?;
+    Ok(count_from(&mut cursor))
+}
+
+/// Counts nothing, since the `disabled` feature is enabled.
+///
+/// # Errors
+///
+/// Never returns an error; the signature matches the real function's.
+#[cfg(feature = "disabled")]
+#[allow(clippy::missing_const_for_fn)] // Not `const`, to match the real `count_frames_in`
+pub fn count_frames_in(_context: &UnwindContext) -> Result<usize, UnwindError> {
+    Ok(0)
+}
+
+#[cfg(not(feature = "disabled"))]
+fn count_from(cursor: &mut UnwindCursor) -> usize {
+    captured_frames(cursor, WalkConfig::new())
+        .map_while(Result::ok)
+        .take(MAX_WALKED_FRAMES)
+        .count()
+}
+
+/// Finds a single frame of the call chain without capturing a whole backtrace.
+///
+/// Frames are numbered the same way as in [`Backtrace::frames`], so frame 0
+/// belongs to the calling function, frame 1 to its caller, and so on. Returns
+/// [`None`] if the stack has fewer than `n + 1` frames (or more than 1024
+/// frames would have to be walked), or if another capture or print is already
+/// in progress or capture has been turned off; see [`Backtrace::capture`].
+///
+/// # Errors
+///
+/// Returns an error if unwinding fails before frame `n` is reached.
+#[cfg(not(feature = "disabled"))]
+#[inline(always)] // Inlining keeps this function from appearing in backtraces
+pub fn nth_frame(n: usize) -> Result<Option<Frame>, UnwindError> {
+    if !capture_enabled() {
+        return Ok(None);
+    }
+    let Some(_guard) = ReentrancyGuard::enter() else {
+        return Ok(None);
+    };
+    let context = UnwindContext::new()?;
+    let mut cursor = UnwindCursor::from_context(context)?;
+    let mut frames = captured_frames(&mut cursor, WalkConfig::new()).take(MAX_WALKED_FRAMES);
+    // Skipped by hand rather than with `Iterator::nth`, which would swallow
+    // an error from one of the skipped frames.
+    for frame in frames.by_ref().take(n) {
+        frame?;
+    }
+    frames.next().transpose()
+}
+
+/// Finds nothing, since the `disabled` feature is enabled.
+///
+/// # Errors
+///
+/// Never returns an error; the signature matches the real function's.
+#[cfg(feature = "disabled")]
+#[inline(always)]
+#[allow(clippy::missing_const_for_fn)] // Not `const`, to match the real `nth_frame`
+pub fn nth_frame(_n: usize) -> Result<Option<Frame>, UnwindError> {
+    Ok(None)
+}
+
+/// Returns the address that the function `depth` levels above the calling
+/// function will return to, like GCC's `__builtin_return_address`.
+///
+/// With a `depth` of zero, this is the address in the calling function's
+/// caller that it will return to. This is [`nth_frame`]`(depth + 1)`'s
+/// instruction pointer, on a best-effort basis: [`None`] is returned if the
+/// frame can't be found for any reason.
+#[inline(always)] // Inlining keeps this function from appearing in backtraces
+pub fn return_address(depth: usize) -> Option<usize> {
+    let frame = nth_frame(depth.checked_add(1)?).ok().flatten()?;
+    Some(frame.ip)
+}
+
+/// Returns the address in the calling function's caller that it will return
+/// to; see [`return_address`].
+#[inline(always)] // Inlining keeps this function from appearing in backtraces
+pub fn caller_ip() -> Option<usize> {
+    return_address(0)
+}
+
+/// Captures the instruction pointers of the calling function and its callers
+/// into `ips`, along with each one's offset from the start of its procedure
+/// into `offsets`, returning the number of frames that were filled in.
+///
+/// The arrays are filled in parallel, so `ips[i]` and `offsets[i]` describe
+/// the same frame. The walk stops once they're full. A frame whose procedure
+/// can't be found has an offset of zero.
+///
+/// If another capture or print is already in progress, or capture has been
+/// turned off with [`set_capture_enabled`], nothing is captured and zero is
+/// returned; see [`Backtrace::capture`].
+///
+/// The walk is the same as [`Backtrace::capture`]'s, so it stops after 1024
+/// frames or where the call chain loops back on itself. An error partway
+/// through ends the walk early, like the end of the stack does, and the
+/// frames filled in before it are still counted.
+///
+/// # Errors
+///
+/// Returns an error if unwinding can't start, or if the first frame can't be
+/// read.
+///
+/// # Panics
+///
+/// Panics if `ips` and `offsets` are different lengths.
+#[cfg(not(feature = "disabled"))]
+#[inline(always)] // Inlining keeps this function from appearing in backtraces
+pub fn capture_soa(ips: &mut [usize], offsets: &mut [u64]) -> Result<usize, UnwindError> {
+    assert_eq!(
+        ips.len(),
+        offsets.len(),
+        "`ips` and `offsets` must be the same length"
+    );
+    if !capture_enabled() {
+        return Ok(0);
+    }
+    let Some(_guard) = ReentrancyGuard::enter() else {
+        return Ok(0);
+    };
+    let context = UnwindContext::new()?;
+    fill_soa(context, ips, offsets)
+}
+
+/// Captures nothing, since the `disabled` feature is enabled.
+///
+/// # Errors
+///
+/// Never returns an error; the signature matches the real function's.
+///
+/// # Panics
+///
+/// Panics if `ips` and `offsets` are different lengths.
+#[cfg(feature = "disabled")]
+#[inline(always)]
+pub fn capture_soa(ips: &mut [usize], offsets: &mut [u64]) -> Result<usize, UnwindError> {
+    assert_eq!(
+        ips.len(),
+        offsets.len(),
+        "`ips` and `offsets` must be the same length"
+    );
+    Ok(0)
+}
+
+#[cfg(not(feature = "disabled"))]
+fn fill_soa(
+    context: UnwindContext,
+    ips: &mut [usize],
+    offsets: &mut [u64],
+) -> Result<usize, UnwindError> {
+    let mut cursor = UnwindCursor::from_context(context)?;
+    let mut frames = captured_frames(&mut cursor, WalkConfig::new());
+    let mut len = 0;
+    for (ip, offset) in ips.iter_mut().zip(offsets).take(MAX_WALKED_FRAMES) {
+        match frames.next() {
+            Some(Ok(frame)) => *ip = frame.ip,
+            Some(Err(error)) if len == 0 => return Err(error),
+            Some(Err(_)) | None => break,
+        }
+        // A return address can be just past the end of its procedure, after
+        // a call that never returns, so it may be outside the range.
+        *offset = frames
+            .cursor()
+            .proc_range()
+            .ok()
+            .and_then(|range| ip.checked_sub(range.start))
+            .map_or(0, |offset| offset as u64);
+        len += 1;
+    }
+    Ok(len)
+}
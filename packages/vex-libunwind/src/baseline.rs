@@ -0,0 +1,362 @@
+//! Leaving the outer frames that every backtrace shares out of printed
+//! backtraces.
+
+use core::{
+    cell::UnsafeCell,
+    fmt::{self, Write},
+    sync::atomic::{AtomicU8, Ordering},
+};
+
+#[cfg(not(feature = "disabled"))]
+use crate::{
+    backtrace::MAX_WALKED_FRAMES, capture_enabled, guard::ReentrancyGuard, UnwindContext,
+    UnwindCursor, WalkConfig, WalkEnd,
+};
+use crate::{
+    backtrace::{write_frame, write_overflow_note, write_status_note, write_stop_note},
+    Backtrace, BacktraceStop, BacktraceStyle, Frame, Styled,
+};
+
+/// The outermost frames of a call chain, such as the startup code and task
+/// executor that every backtrace of a program ends with, for leaving out of
+/// other backtraces with [`Backtrace::trim_common_prefix`].
+///
+/// Each frame is kept with the range of its procedure when that could be
+/// found, so a frame of another backtrace matches it if it's anywhere in the
+/// same procedure. Call sites in the scaffolding can then differ (a task's
+/// backtrace shows the executor calling the task, while the baseline shows
+/// it calling whatever captured the baseline) without defeating the trim.
+/// Frames without a procedure only match the same address.
+#[derive(Debug, Clone)]
+pub struct Baseline<const N: usize = 16> {
+    /// The frames, innermost first.
+    frames: [BaselineFrame; N],
+    len: usize,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct BaselineFrame {
+    ip: usize,
+    /// The start and end of the frame's procedure, or zeros if it wasn't
+    /// found.
+    proc_start: usize,
+    proc_end: usize,
+}
+
+impl BaselineFrame {
+    const EMPTY: Self = Self {
+        ip: 0,
+        proc_start: 0,
+        proc_end: 0,
+    };
+
+    /// Checks whether `ip` is in this frame's procedure, or is this frame's
+    /// address if the procedure is unknown. The end of the procedure is
+    /// included, since a return address from a call that never returns can
+    /// be just past it.
+    const fn matches(&self, ip: usize) -> bool {
+        if self.proc_start == self.proc_end {
+            return ip == self.ip;
+        }
+        self.proc_start <= ip && ip <= self.proc_end
+    }
+}
+
+impl<const N: usize> Baseline<N> {
+    /// Creates a baseline with no frames, which trims nothing.
+    pub const fn empty() -> Self {
+        Self {
+            frames: [BaselineFrame::EMPTY; N],
+            len: 0,
+        }
+    }
+
+    /// Captures the outermost `N` frames of the calling function and its
+    /// callers, including the calling function if there are no more than
+    /// `N`.
+    ///
+    /// Capture this where the scaffolding ends, such as at the start of the
+    /// task that runs the rest of the program: everything from the calling
+    /// function out is what gets trimmed. Only whole call chains are useful
+    /// for comparing from the outermost end, so the baseline is empty if
+    /// unwinding fails before reaching the end of the stack, as it is if
+    /// capture is turned off or another capture is in progress.
+    #[cfg(not(feature = "disabled"))]
+    #[inline(always)] // Inlining keeps this function from appearing in backtraces
+    pub fn capture() -> Self {
+        let mut baseline = Self::empty();
+        if N == 0 || !capture_enabled() {
+            return baseline;
+        }
+        let Some(_guard) = ReentrancyGuard::enter() else {
+            return baseline;
+        };
+        let Ok(mut cursor) = UnwindContext::new().and_then(UnwindCursor::from_context) else {
+            return baseline;
+        };
+
+        // The outermost frames aren't known until the walk is over, so the
+        // last `N` are kept in a ring.
+        let mut frames = cursor.frames(WalkConfig::new());
+        let mut count = 0;
+        while let Some(Ok(frame)) = frames.next() {
+            if count == MAX_WALKED_FRAMES {
+                return baseline;
+            }
+            let range = frames.cursor().proc_range().unwrap_or(0..0);
+            baseline.frames[count % N] = BaselineFrame {
+                ip: frame.ip,
+                proc_start: range.start,
+                proc_end: range.end,
+            };
+            count += 1;
+        }
+        if !matches!(frames.end(), Some(WalkEnd::EndOfStack | WalkEnd::Root)) {
+            return baseline;
+        }
+        if count > N {
+            baseline.frames.rotate_left(count % N);
+        }
+        baseline.len = count.min(N);
+        baseline
+    }
+
+    /// Returns an empty baseline, since the `disabled` feature is enabled.
+    #[cfg(feature = "disabled")]
+    #[inline(always)]
+    #[allow(clippy::missing_const_for_fn)] // Not `const`, to match the real `capture`
+    pub fn capture() -> Self {
+        Self::empty()
+    }
+
+    /// Returns the number of frames in the baseline.
+    pub const fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Checks whether the baseline has no frames.
+    pub const fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the frames' instruction pointers, innermost first.
+    pub fn ips(&self) -> impl DoubleEndedIterator<Item = usize> + ExactSizeIterator + '_ {
+        self.frames[..self.len].iter().map(|frame| frame.ip)
+    }
+}
+
+impl<const N: usize> Default for Baseline<N> {
+    fn default() -> Self {
+        Self::empty()
+    }
+}
+
+/// Uses the outermost `N` frames of a whole backtrace as a baseline. The
+/// backtrace holds no procedures, so its frames only match the same
+/// addresses.
+impl<const N: usize, const M: usize> From<&Backtrace<M>> for Baseline<N> {
+    fn from(backtrace: &Backtrace<M>) -> Self {
+        let mut baseline = Self::empty();
+        if !is_whole(backtrace) {
+            return baseline;
+        }
+        let frames = backtrace.frames();
+        let outermost = &frames[frames.len().saturating_sub(N)..];
+        for (slot, frame) in baseline.frames.iter_mut().zip(outermost) {
+            *slot = BaselineFrame {
+                ip: frame.ip,
+                ..BaselineFrame::EMPTY
+            };
+        }
+        baseline.len = outermost.len();
+        baseline
+    }
+}
+
+/// Checks whether a backtrace goes all the way to the end of its stack, so
+/// that its outermost frame is the stack's.
+const fn is_whole<const N: usize>(backtrace: &Backtrace<N>) -> bool {
+    matches!(
+        backtrace.stop_reason(),
+        Some(BacktraceStop::EndOfStack | BacktraceStop::Root)
+    )
+}
+
+/// Where [`capture_baseline`] keeps the program's baseline.
+struct GlobalBaseline {
+    state: AtomicU8,
+    baseline: UnsafeCell<Baseline>,
+}
+
+const UNSET: u8 = 0;
+const CAPTURING: u8 = 1;
+const SET: u8 = 2;
+
+// SAFETY: The baseline is only written by the `capture_baseline` call that
+// moved the state out of `UNSET`, and only read once the state is `SET`, after
+// which it never changes.
+unsafe impl Sync for GlobalBaseline {}
+
+static BASELINE: GlobalBaseline = GlobalBaseline {
+    state: AtomicU8::new(UNSET),
+    baseline: UnsafeCell::new(Baseline::empty()),
+};
+
+/// Captures the program's [`Baseline`] for [`Backtrace::trim_to_baseline`],
+/// returning whether it did.
+///
+/// Call this once at startup, from where the scaffolding ends, as described
+/// in [`Baseline::capture`]. Only the first call captures anything; later
+/// ones return `false` and leave the baseline as it was. The baseline holds
+/// up to 16 frames.
+///
+/// ```
+/// # use vex_libunwind::{capture_baseline, Backtrace};
+/// fn run_robot() {
+///     // ...
+/// }
+///
+/// capture_baseline();
+/// run_robot();
+///
+/// // Later, in a panic handler:
+/// let backtrace = Backtrace::<32>::capture();
+/// println!("{}", backtrace.trim_to_baseline());
+/// ```
+#[inline(always)] // Inlining keeps this function from appearing in backtraces
+pub fn capture_baseline() -> bool {
+    if BASELINE
+        .state
+        .compare_exchange(UNSET, CAPTURING, Ordering::Acquire, Ordering::Relaxed)
+        .is_err()
+    {
+        return false;
+    }
+    // SAFETY: The state was just moved out of `UNSET`, so nothing else writes
+    // the baseline, and nothing reads it until the state is `SET`.
+    unsafe { *BASELINE.baseline.get() = Baseline::capture() };
+    BASELINE.state.store(SET, Ordering::Release);
+    true
+}
+
+/// Returns the baseline captured by [`capture_baseline`], if it's been
+/// captured.
+pub fn captured_baseline() -> Option<&'static Baseline> {
+    if BASELINE.state.load(Ordering::Acquire) != SET {
+        return None;
+    }
+    // SAFETY: The baseline never changes once the state is `SET`.
+    Some(unsafe { &*BASELINE.baseline.get() })
+}
+
+impl<const N: usize> Backtrace<N> {
+    /// Returns a view of the backtrace without the outer frames it shares
+    /// with `baseline`, compared from the outermost end.
+    ///
+    /// Frames are matched by procedure, as described in [`Baseline`]. The
+    /// innermost frame is always kept, and nothing is trimmed unless the
+    /// backtrace goes all the way to the end of its stack (rather than
+    /// running out of room or failing partway), since otherwise its
+    /// outermost frames aren't the stack's.
+    pub fn trim_common_prefix<const M: usize>(
+        &self,
+        baseline: &Baseline<M>,
+    ) -> TrimmedBacktrace<'_, N> {
+        let frames = self.frames();
+        let omitted = if is_whole(self) {
+            frames
+                .iter()
+                .rev()
+                .zip(baseline.frames[..baseline.len].iter().rev())
+                .take_while(|(frame, base)| base.matches(frame.ip))
+                .count()
+                .min(frames.len().saturating_sub(1))
+        } else {
+            0
+        };
+        TrimmedBacktrace {
+            backtrace: self,
+            omitted,
+        }
+    }
+
+    /// Returns a view of the backtrace without the outer frames it shares
+    /// with the baseline captured by [`capture_baseline`], as described in
+    /// [`Backtrace::trim_common_prefix`]. Nothing is trimmed if the baseline
+    /// hasn't been captured.
+    pub fn trim_to_baseline(&self) -> TrimmedBacktrace<'_, N> {
+        self.trim_common_prefix(captured_baseline().unwrap_or(&Baseline::empty()))
+    }
+}
+
+/// A backtrace without the outer frames it shares with a [`Baseline`], as
+/// returned by [`Backtrace::trim_common_prefix`].
+///
+/// Printing it prints the kept frames as the backtrace would, then a note of
+/// how many outer frames were left out.
+#[derive(Debug, Clone, Copy)]
+pub struct TrimmedBacktrace<'a, const N: usize> {
+    backtrace: &'a Backtrace<N>,
+    omitted: usize,
+}
+
+impl<'a, const N: usize> TrimmedBacktrace<'a, N> {
+    /// Returns the frames that weren't trimmed, innermost first.
+    pub fn frames(&self) -> &'a [Frame] {
+        let frames = self.backtrace.frames();
+        &frames[..frames.len() - self.omitted]
+    }
+
+    /// Returns the number of outer frames that were trimmed.
+    pub const fn omitted(&self) -> usize {
+        self.omitted
+    }
+
+    /// Returns the whole backtrace.
+    pub const fn backtrace(&self) -> &'a Backtrace<N> {
+        self.backtrace
+    }
+
+    /// Returns an object that prints the trimmed backtrace in the given
+    /// style.
+    pub const fn display(&self, style: BacktraceStyle) -> Styled<'_, Self> {
+        Styled::new(self, style)
+    }
+
+    pub(crate) fn write_styled(&self, w: &mut impl Write, style: BacktraceStyle) -> fmt::Result {
+        let backtrace = self.backtrace;
+        if write_status_note(w, backtrace.status(), style)? {
+            return Ok(());
+        }
+        write_overflow_note(w, backtrace.overflow_suspected(), style)?;
+        if style.outermost_first() {
+            self.write_outer_note(w)?;
+        }
+        let frames = self.frames();
+        style.for_each_frame(frames.len(), |index| {
+            let stack_usage = backtrace.frame_stack_usage(index);
+            write_frame(w, index, &frames[index], None, stack_usage, style)
+        })?;
+        if style.outermost_first() {
+            return Ok(());
+        }
+        self.write_outer_note(w)
+    }
+
+    /// Prints how many outer frames were omitted, or why unwinding ended
+    /// early if none were.
+    fn write_outer_note(&self, w: &mut impl Write) -> fmt::Result {
+        let backtrace = self.backtrace;
+        match self.omitted {
+            0 => write_stop_note(w, backtrace.stop_reason(), backtrace.frames().len()),
+            1 => w.write_str("  ... (1 outer frame omitted)\n"),
+            omitted => writeln!(w, "  ... ({omitted} outer frames omitted)"),
+        }
+    }
+}
+
+impl<const N: usize> fmt::Display for TrimmedBacktrace<'_, N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.write_styled(f, BacktraceStyle::new())
+    }
+}
@@ -0,0 +1,151 @@
+//! Identifying the exact build that's running.
+
+use core::{fmt, slice};
+
+use snafu::Snafu;
+
+/// The bounds of the `.note.gnu.build-id` section, which a linker script can
+/// keep and mark with these symbols. They're weak, so a program that doesn't
+/// still links, with each missing symbol's address reading as null.
+mod symbols {
+    extern "C" {
+        #[linkage = "extern_weak"]
+        pub(super) static __build_id_start: *const u8;
+        #[linkage = "extern_weak"]
+        pub(super) static __build_id_end: *const u8;
+    }
+}
+
+/// The note type of a GNU build ID.
+const NT_GNU_BUILD_ID: u32 = 3;
+/// The owner name of GNU notes, including its terminator.
+const GNU_NAME: &[u8] = b"GNU\0";
+
+/// Returns the GNU build ID of the running program, or [`None`] if it doesn't
+/// have one.
+///
+/// The build ID is a hash that the linker writes into a note when it's run
+/// with `--build-id`, so two binaries with the same ID were built from the
+/// same inputs. Recording it alongside a backtrace tells a host tool exactly
+/// which binary to symbolize the addresses against.
+///
+/// The note is found between the `__build_id_start` and `__build_id_end`
+/// linker symbols, which the linker script has to define around a kept
+/// `.note.gnu.build-id` section:
+///
+/// ```text
+/// .note.gnu.build-id : {
+///     __build_id_start = .;
+///     KEEP(*(.note.gnu.build-id))
+///     __build_id_end = .;
+/// }
+/// ```
+///
+/// If the symbols are missing, or what's between them isn't a build ID note,
+/// this returns `None`.
+pub fn build_id() -> Option<&'static [u8]> {
+    // SAFETY: Reading a weak symbol's address is always allowed; it's null
+    // if the symbol is missing.
+    let (start, end) = unsafe {
+        (
+            symbols::__build_id_start as usize,
+            symbols::__build_id_end as usize,
+        )
+    };
+    if start == 0 || start > end {
+        return None;
+    }
+    // SAFETY: The linker script puts the symbols around the note section,
+    // which is loaded with the program and never written.
+    let note = unsafe { slice::from_raw_parts(start as *const u8, end - start) };
+    parse_build_id_note(note)
+}
+
+/// Returns the build ID held by a GNU build ID note, or [`None`] if `note`
+/// doesn't start with one.
+///
+/// This is the parsing half of [`build_id`], for host tools that read the
+/// `.note.gnu.build-id` section out of an ELF file to check that it's the
+/// binary a backtrace came from. The note's fields are read in the host's
+/// byte order, which matches the V5's.
+pub fn parse_build_id_note(note: &[u8]) -> Option<&[u8]> {
+    let word = |index: usize| {
+        let bytes = note.get(index * 4..index * 4 + 4)?;
+        Some(u32::from_ne_bytes(bytes.try_into().ok()?))
+    };
+    let name_len = word(0)? as usize;
+    let id_len = word(1)? as usize;
+    if word(2)? != NT_GNU_BUILD_ID {
+        return None;
+    }
+
+    // The name is padded to a multiple of four bytes.
+    let name_start: usize = 12;
+    let id_start = name_start.checked_add(name_len.checked_next_multiple_of(4)?)?;
+    if note.get(name_start..name_start.checked_add(name_len)?)? != GNU_NAME {
+        return None;
+    }
+    note.get(id_start..id_start.checked_add(id_len)?)
+}
+
+/// The longest build ID that an [encoded](crate::encoding) backtrace holds.
+///
+/// Linkers write a 20-byte SHA-1 by default, or a 16-byte MD5 or UUID, so
+/// this only leaves out IDs given by hand with `--build-id=0x...`.
+pub const MAX_BUILD_ID_LEN: usize = 32;
+
+/// A build ID read back out of an [encoded](crate::encoding) backtrace.
+///
+/// Formatting it prints the ID in lowercase hex, as `readelf -n` and `file`
+/// do.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct BuildId {
+    bytes: [u8; MAX_BUILD_ID_LEN],
+    len: u8,
+}
+
+impl BuildId {
+    /// Copies a build ID, or returns [`None`] if it's longer than
+    /// [`MAX_BUILD_ID_LEN`].
+    pub fn new(id: &[u8]) -> Option<Self> {
+        let mut bytes = [0; MAX_BUILD_ID_LEN];
+        bytes.get_mut(..id.len())?.copy_from_slice(id);
+        Some(Self {
+            bytes,
+            len: id.len() as u8,
+        })
+    }
+
+    /// Returns the ID's bytes.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes[..usize::from(self.len)]
+    }
+}
+
+impl fmt::Display for BuildId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.as_bytes()
+            .iter()
+            .try_for_each(|byte| write!(f, "{byte:02x}"))
+    }
+}
+
+impl fmt::Debug for BuildId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "BuildId({self})")
+    }
+}
+
+/// A warning that an encoded backtrace was recorded by a different build
+/// than the binary it's about to be symbolized against, so its addresses
+/// probably point into different code.
+///
+/// See [`Backtrace::from_encoded_for`](crate::Backtrace::from_encoded_for).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Snafu)]
+#[snafu(display(
+    "backtrace was recorded by build {recorded}, not this binary's; its addresses may be wrong"
+))]
+pub struct BuildIdMismatch {
+    /// The build ID the backtrace was encoded with.
+    pub recorded: BuildId,
+}
@@ -0,0 +1,67 @@
+//! Measuring how long unwinding takes, for profilers that need to subtract
+//! their own overhead.
+
+use crate::{backtrace::MAX_WALKED_FRAMES, capture_cursor, WalkConfig};
+
+/// How many times the walk is timed; the fastest run is reported.
+const RUNS: usize = 8;
+
+/// Measures how long capturing a context and walking it to the end of the
+/// stack (or the first 1024 frames of it) takes, using `now` to read
+/// timestamps.
+///
+/// The walk starts from the caller's frame, so the result covers the caller's
+/// stack depth; call this from the same place a profiler would capture its
+/// samples for a representative number. The walk is timed several times and
+/// the fastest run is returned (so that runs slowed down by interrupts don't
+/// count), with the cost of calling `now` itself subtracted. The result is in
+/// whatever unit `now` counts in.
+#[inline(always)] // Inlining keeps this function from appearing in backtraces
+pub fn measure_walk_cost_with(mut now: impl FnMut() -> u64) -> u64 {
+    let mut best = u64::MAX;
+    for _ in 0..RUNS {
+        let start = now();
+        let overhead = now().wrapping_sub(start);
+
+        let start = now();
+        if let Ok(mut cursor) = capture_cursor() {
+            cursor
+                .frames(WalkConfig::new())
+                .take(MAX_WALKED_FRAMES)
+                .for_each(drop);
+        }
+        let elapsed = now().wrapping_sub(start);
+
+        best = best.min(elapsed.saturating_sub(overhead));
+    }
+    best
+}
+
+/// Measures how many CPU cycles capturing a context and walking it to the
+/// end of the stack takes; see [`measure_walk_cost_with`].
+///
+/// This reads the Cortex-A9's cycle counter (`PMCCNTR`), which has to have
+/// been enabled (and, outside of privileged modes, made accessible through
+/// `PMUSERENR`) beforehand; reading it otherwise is an undefined instruction
+/// or gives zero. The counter is 32 bits wide, so a run that it wraps during
+/// is thrown out along with the other slow ones.
+#[cfg(all(target_arch = "arm", feature = "cycle-counter"))]
+#[inline(always)] // Inlining keeps this function from appearing in backtraces
+pub fn measure_walk_cost() -> u64 {
+    measure_walk_cost_with(read_cycle_counter)
+}
+
+#[cfg(all(target_arch = "arm", feature = "cycle-counter"))]
+fn read_cycle_counter() -> u64 {
+    let cycles: u32;
+    // SAFETY: Reading PMCCNTR has no side effects. The caller of
+    // `measure_walk_cost` is responsible for it being accessible.
+    unsafe {
+        core::arch::asm!(
+            "mrc p15, 0, {}, c9, c13, 0",
+            out(reg) cycles,
+            options(nomem, nostack, preserves_flags),
+        );
+    }
+    u64::from(cycles)
+}
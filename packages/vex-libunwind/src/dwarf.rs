@@ -0,0 +1,765 @@
+//! Finding the source file and line of an address in DWARF line tables.
+//! Only available with the `dwarf` feature.
+//!
+//! `libunwind` can name a frame's procedure, but only the debug info knows
+//! which line of which file an instruction came from. That's in the ELF's
+//! `.debug_line` section, which isn't part of the binary uploaded to the V5,
+//! so it has to be shipped separately for [`DwarfSymbols`] to read, for
+//! example by extracting it from the ELF at build time:
+//!
+//! ```text
+//! objcopy --dump-section .debug_line=debug_line.bin target/.../program
+//! ```
+//!
+//! and embedding the file with `include_bytes!` (which makes the program as
+//! much larger as the section is, often hundreds of kilobytes) or reading it
+//! off the SD card. DWARF 5 line tables keep their file names in the
+//! `.debug_line_str` section (or `.debug_str`), which has to be shipped as
+//! well and handed to [`DwarfSymbols::with_line_strings`] (or
+//! [`DwarfSymbols::with_strings`]).
+//!
+//! Line tables of DWARF versions 2 through 5 are read, in 32- or 64-bit
+//! DWARF. Nothing is allocated or copied: each lookup runs the line programs
+//! until it finds the address, so lookups take time proportional to the size
+//! of the section, which is fine for a crash report but too slow for a
+//! profiler's every sample.
+//!
+//! ```
+//! # use vex_libunwind::dwarf::*;
+//! # let debug_line: &[u8] = &[];
+//! let symbols = DwarfSymbols::from_bytes(debug_line).unwrap();
+//! if let Some(location) = symbols.resolve(0x0380_1234) {
+//!     println!("at {location}");
+//! }
+//! ```
+
+use core::{fmt, ops::Range};
+
+use snafu::Snafu;
+
+use crate::ProcInfo;
+
+/// An error that can occur while reading line tables, with the offset in
+/// `.debug_line` where reading failed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Snafu)]
+pub enum DwarfError {
+    /// The section ended in the middle of a value.
+    #[snafu(display("section ends early at byte {offset}"))]
+    Truncated {
+        /// The length of the section.
+        offset: usize,
+    },
+    /// A line table was written with an unsupported version of DWARF.
+    #[snafu(display("unsupported DWARF version {version} at byte {offset}"))]
+    UnsupportedVersion {
+        /// The version the line table says it was written with.
+        version: u16,
+        /// The offset of the version.
+        offset: usize,
+    },
+    /// A DWARF 5 line table describes its files with a form that can't be
+    /// read.
+    #[snafu(display("unsupported attribute form {form:#x} at byte {offset}"))]
+    UnsupportedForm {
+        /// The form's code.
+        form: u64,
+        /// The offset of the form's code.
+        offset: usize,
+    },
+    /// A line table's header has values that make its program unreadable,
+    /// such as a line range of zero.
+    #[snafu(display("invalid line table header at byte {offset}"))]
+    InvalidHeader {
+        /// The offset of the line table.
+        offset: usize,
+    },
+    /// A value doesn't fit in 64 bits, or an offset doesn't fit in a `usize`.
+    #[snafu(display("value too large at byte {offset}"))]
+    TooLarge {
+        /// The offset of the value's first byte.
+        offset: usize,
+    },
+}
+
+/// The source line that an address was compiled from, as found by
+/// [`DwarfSymbols::resolve`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SourceLocation<'a> {
+    /// The directory that [`file`](SourceLocation::file) is relative to, if
+    /// the line table names one. DWARF versions before 5 only name the
+    /// compilation directory in `.debug_info`, so files in it have no
+    /// directory here.
+    pub directory: Option<&'a str>,
+    /// The file's path, as given to the compiler.
+    pub file: &'a str,
+    /// The line number, starting at 1. Zero means the address doesn't
+    /// belong to any line, as with code the compiler generated.
+    pub line: u32,
+}
+
+/// Prints `directory/file:line`, leaving out the directory if the file's
+/// path is absolute.
+impl fmt::Display for SourceLocation<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.directory {
+            Some(directory) if !self.file.starts_with('/') => {
+                write!(f, "{directory}/{}:{}", self.file, self.line)
+            }
+            _ => write!(f, "{}:{}", self.file, self.line),
+        }
+    }
+}
+
+/// The line tables of a program, for looking up the source lines of
+/// addresses; see the [module docs](self).
+///
+/// This is meant to be used alongside the names from
+/// [`UnwindCursor::procedure_name`](crate::UnwindCursor::procedure_name) or a
+/// [`Symbolize`](crate::Symbolize) implementation: those say which function
+/// a frame is in, and this says where in its source.
+#[derive(Debug, Clone, Copy)]
+pub struct DwarfSymbols<'a> {
+    debug_line: &'a [u8],
+    debug_line_str: &'a [u8],
+    debug_str: &'a [u8],
+}
+
+impl<'a> DwarfSymbols<'a> {
+    /// Reads the line tables in the contents of a `.debug_line` section.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a line table's header can't be read. The line
+    /// programs themselves are only read by lookups, which skip line tables
+    /// whose programs turn out to be malformed.
+    pub fn from_bytes(debug_line: &'a [u8]) -> Result<Self, DwarfError> {
+        let symbols = Self {
+            debug_line,
+            debug_line_str: &[],
+            debug_str: &[],
+        };
+        for table in symbols.tables() {
+            let table = table?;
+            // Reading the file table checks the forms of DWARF 5 entries.
+            table.file(0, &symbols)?;
+        }
+        Ok(symbols)
+    }
+
+    /// Adds the contents of the `.debug_line_str` section, which DWARF 5
+    /// line tables keep their file and directory names in.
+    pub const fn with_line_strings(mut self, debug_line_str: &'a [u8]) -> Self {
+        self.debug_line_str = debug_line_str;
+        self
+    }
+
+    /// Adds the contents of the `.debug_str` section, which some DWARF 5
+    /// line tables keep their file and directory names in.
+    pub const fn with_strings(mut self, debug_str: &'a [u8]) -> Self {
+        self.debug_str = debug_str;
+        self
+    }
+
+    /// Finds the source line that the instruction at `ip` was compiled from.
+    ///
+    /// The instruction pointers of callers' frames are return addresses,
+    /// which point after the call, so the line of the call itself is found
+    /// by looking up `ip - 1`. Addresses that no line table covers (and
+    /// files whose names are in a string section that wasn't added) give
+    /// [`None`], and directories whose names are in such a section are left
+    /// out.
+    ///
+    /// Lines in functions that were inlined are those of the inlined code,
+    /// since line tables don't record what it was inlined into.
+    pub fn resolve(&self, ip: usize) -> Option<SourceLocation<'a>> {
+        let ip = ip as u64;
+        for table in self.tables() {
+            let Ok(table) = table else {
+                return None;
+            };
+            let Ok(Some(row)) = table.find(ip) else {
+                continue;
+            };
+            let (directory, file) = table.file(row.file, self).ok()??;
+            return Some(SourceLocation {
+                directory,
+                file,
+                line: u32::try_from(row.line).ok()?,
+            });
+        }
+        None
+    }
+
+    /// Finds the source line of a procedure's first instruction, which is
+    /// usually the line that the function is declared on.
+    pub fn resolve_proc(&self, proc_info: &ProcInfo) -> Option<SourceLocation<'a>> {
+        self.resolve(proc_info.start_ip())
+    }
+
+    const fn tables(&self) -> LineTables<'a> {
+        LineTables {
+            reader: Reader::new(self.debug_line, 0),
+            done: false,
+        }
+    }
+}
+
+/// An iterator over the line tables in `.debug_line`, which ends after the
+/// first one that can't be read.
+struct LineTables<'a> {
+    reader: Reader<'a>,
+    done: bool,
+}
+
+impl<'a> Iterator for LineTables<'a> {
+    type Item = Result<LineTable<'a>, DwarfError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done || self.reader.is_empty() {
+            return None;
+        }
+        let table = LineTable::read(&mut self.reader);
+        self.done = table.is_err();
+        Some(table)
+    }
+}
+
+/// The header of a line table, and where its program is.
+struct LineTable<'a> {
+    section: &'a [u8],
+    version: u16,
+    /// 4 in 32-bit DWARF, and 8 in 64-bit DWARF.
+    offset_size: u8,
+    min_instruction_length: u8,
+    line_base: i8,
+    line_range: u8,
+    opcode_base: u8,
+    standard_opcode_lengths: &'a [u8],
+    /// The offset of the directory and file tables.
+    tables: usize,
+    program: Range<usize>,
+}
+
+impl<'a> LineTable<'a> {
+    fn read(reader: &mut Reader<'a>) -> Result<Self, DwarfError> {
+        let start = reader.offset;
+        let (unit_length, offset_size) = match reader.u32()? {
+            0xffff_ffff => (reader.u64()?, 8),
+            length => (u64::from(length), 4),
+        };
+        let end = reader.offset_after(unit_length)?;
+        let section = reader.section;
+        let mut header = reader.split(end)?;
+        reader.offset = end;
+
+        let version_offset = header.offset;
+        let version = header.u16()?;
+        if !(2..=5).contains(&version) {
+            return Err(DwarfError::UnsupportedVersion {
+                version,
+                offset: version_offset,
+            });
+        }
+        if version >= 5 {
+            // The address and segment selector sizes, which set_address
+            // makes unnecessary.
+            header.bytes(2)?;
+        }
+        let header_length = header.offset_value(offset_size)?;
+        let program_start = header.offset_after(header_length)?;
+        let min_instruction_length = header.u8()?;
+        if version >= 4 {
+            // The maximum operations per instruction, which is 1 except on
+            // VLIW machines.
+            header.u8()?;
+        }
+        // Whether rows start statements by default, which doesn't matter
+        // for finding lines.
+        header.u8()?;
+        let line_base = header.u8()? as i8;
+        let line_range = header.u8()?;
+        let opcode_base = header.u8()?;
+        if line_range == 0 || opcode_base == 0 {
+            return Err(DwarfError::InvalidHeader { offset: start });
+        }
+        let standard_opcode_lengths = header.bytes(usize::from(opcode_base) - 1)?;
+        // The fields read so far have to fit in `header_length`, since the
+        // directory and file tables are read up to where the program starts.
+        if header.offset > program_start || program_start > end {
+            return Err(DwarfError::InvalidHeader { offset: start });
+        }
+        Ok(Self {
+            section,
+            version,
+            offset_size,
+            min_instruction_length,
+            line_base,
+            line_range,
+            opcode_base,
+            standard_opcode_lengths,
+            tables: header.offset,
+            program: program_start..end,
+        })
+    }
+
+    /// Runs the line program until it finds the row covering `ip`.
+    fn find(&self, ip: u64) -> Result<Option<Row>, DwarfError> {
+        let mut program = Reader::new(self.section, self.program.start).split(self.program.end)?;
+        let min_length = u64::from(self.min_instruction_length);
+        let mut row = Row::START;
+        // The last row of the current sequence, which covers the addresses
+        // from its own up to the next row's.
+        let mut previous: Option<Row> = None;
+
+        while !program.is_empty() {
+            let opcode = program.u8()?;
+            let mut emit = false;
+            let mut end_sequence = false;
+            if opcode >= self.opcode_base {
+                let adjusted = opcode - self.opcode_base;
+                let advance = u64::from(adjusted / self.line_range) * min_length;
+                row.address = row.address.wrapping_add(advance);
+                let line_advance =
+                    i64::from(self.line_base) + i64::from(adjusted % self.line_range);
+                row.line = row.line.wrapping_add_signed(line_advance);
+                emit = true;
+            } else {
+                match opcode {
+                    0 => {
+                        let length = program.uleb()?;
+                        let end = program.offset_after(length)?;
+                        let mut extended = program.split(end)?;
+                        program.offset = end;
+                        match extended.u8()? {
+                            // DW_LNE_end_sequence
+                            1 => {
+                                emit = true;
+                                end_sequence = true;
+                            }
+                            // DW_LNE_set_address
+                            2 => row.address = extended.address()?,
+                            // DW_LNE_define_file, DW_LNE_set_discriminator,
+                            // and vendor extensions don't affect the row.
+                            _ => {}
+                        }
+                    }
+                    // DW_LNS_copy
+                    1 => emit = true,
+                    // DW_LNS_advance_pc
+                    2 => row.address = row.address.wrapping_add(program.uleb()? * min_length),
+                    // DW_LNS_advance_line
+                    3 => row.line = row.line.wrapping_add_signed(program.sleb()?),
+                    // DW_LNS_set_file
+                    4 => row.file = program.uleb()?,
+                    // DW_LNS_const_add_pc
+                    8 => {
+                        let adjusted = 255 - self.opcode_base;
+                        let advance = u64::from(adjusted / self.line_range) * min_length;
+                        row.address = row.address.wrapping_add(advance);
+                    }
+                    // DW_LNS_fixed_advance_pc
+                    9 => row.address = row.address.wrapping_add(u64::from(program.u16()?)),
+                    // DW_LNS_set_column, DW_LNS_negate_stmt, and the rest
+                    // don't affect the row, so only their operands are
+                    // skipped.
+                    opcode => {
+                        let operands = self.standard_opcode_lengths[usize::from(opcode) - 1];
+                        for _ in 0..operands {
+                            program.uleb()?;
+                        }
+                    }
+                }
+            }
+
+            if emit {
+                if let Some(previous) = previous {
+                    if previous.address <= ip && ip < row.address {
+                        return Ok(Some(previous));
+                    }
+                }
+                previous = Some(row);
+            }
+            if end_sequence {
+                row = Row::START;
+                previous = None;
+            }
+        }
+        Ok(None)
+    }
+
+    /// Returns the directory and path of the file at `index` in the file
+    /// table, or [`None`] if there's no such file or its name can't be
+    /// found.
+    fn file(
+        &self,
+        index: u64,
+        symbols: &DwarfSymbols<'a>,
+    ) -> Result<Option<(Option<&'a str>, &'a str)>, DwarfError> {
+        let mut tables = Reader::new(self.section, self.tables).split(self.program.start)?;
+        if self.version >= 5 {
+            return self.file_v5(&mut tables, index, symbols);
+        }
+
+        // Directories and files are lists of strings ended by an empty one,
+        // and numbered from 1.
+        let directories = tables.clone();
+        while !tables.cstr()?.is_empty() {}
+        let mut number = 1;
+        loop {
+            let path = tables.cstr()?;
+            if path.is_empty() {
+                return Ok(None);
+            }
+            let directory = tables.uleb()?;
+            // The modification time and length.
+            tables.uleb()?;
+            tables.uleb()?;
+            if number == index {
+                let directory = match directory {
+                    0 => None,
+                    directory => nth_cstr(directories, directory - 1)?,
+                };
+                return Ok(to_str(path).map(|path| (directory, path)));
+            }
+            number += 1;
+        }
+    }
+
+    fn file_v5(
+        &self,
+        tables: &mut Reader<'a>,
+        index: u64,
+        symbols: &DwarfSymbols<'a>,
+    ) -> Result<Option<(Option<&'a str>, &'a str)>, DwarfError> {
+        // Directories and files are lists of entries whose fields are
+        // described by a list of (content type, form) pairs, and numbered
+        // from 0.
+        let directory_formats = EntryFormats::read(tables)?;
+        let directory_count = tables.uleb()?;
+        let directories = tables.clone();
+        for _ in 0..directory_count {
+            directory_formats.read_entry(tables, self, symbols)?;
+        }
+        let file_formats = EntryFormats::read(tables)?;
+        let file_count = tables.uleb()?;
+        let mut found = None;
+        for number in 0..file_count {
+            let entry = file_formats.read_entry(tables, self, symbols)?;
+            if number == index {
+                found = Some(entry);
+            }
+        }
+        let Some(entry) = found else {
+            return Ok(None);
+        };
+
+        let mut directories = directories;
+        let mut directory = None;
+        for number in 0..directory_count.min(entry.directory + 1) {
+            let path = directory_formats
+                .read_entry(&mut directories, self, symbols)?
+                .path;
+            if number == entry.directory {
+                directory = path;
+            }
+        }
+        Ok(entry.path.map(|path| (directory, path)))
+    }
+}
+
+/// A row of a line table, as far as finding lines needs.
+#[derive(Clone, Copy)]
+struct Row {
+    address: u64,
+    file: u64,
+    line: u64,
+}
+
+impl Row {
+    /// The state at the start of each sequence.
+    const START: Self = Self {
+        address: 0,
+        file: 1,
+        line: 1,
+    };
+}
+
+/// The fields of a DWARF 5 directory or file entry, as (content type, form)
+/// pairs.
+struct EntryFormats<'a> {
+    formats: Reader<'a>,
+    count: u8,
+}
+
+/// The fields of a DWARF 5 directory or file entry that finding lines needs.
+struct Entry<'a> {
+    /// The path, if its string could be found.
+    path: Option<&'a str>,
+    directory: u64,
+}
+
+/// `DW_LNCT_path`
+const LNCT_PATH: u64 = 1;
+/// `DW_LNCT_directory_index`
+const LNCT_DIRECTORY_INDEX: u64 = 2;
+
+impl<'a> EntryFormats<'a> {
+    fn read(reader: &mut Reader<'a>) -> Result<Self, DwarfError> {
+        let count = reader.u8()?;
+        let formats = reader.clone();
+        for _ in 0..count {
+            reader.uleb()?;
+            reader.uleb()?;
+        }
+        Ok(Self { formats, count })
+    }
+
+    fn read_entry(
+        &self,
+        reader: &mut Reader<'a>,
+        table: &LineTable<'a>,
+        symbols: &DwarfSymbols<'a>,
+    ) -> Result<Entry<'a>, DwarfError> {
+        let mut entry = Entry {
+            path: None,
+            directory: 0,
+        };
+        let mut formats = self.formats.clone();
+        for _ in 0..self.count {
+            let content = formats.uleb()?;
+            let form_offset = formats.offset;
+            let form = formats.uleb()?;
+            let value = reader.form(form, form_offset, table.offset_size)?;
+            match (content, value) {
+                (LNCT_PATH, Value::String(path)) => entry.path = to_str(path),
+                (LNCT_PATH, Value::LineStringOffset(offset)) => {
+                    entry.path = string_at(symbols.debug_line_str, offset);
+                }
+                (LNCT_PATH, Value::StringOffset(offset)) => {
+                    entry.path = string_at(symbols.debug_str, offset);
+                }
+                (LNCT_DIRECTORY_INDEX, Value::Number(index)) => entry.directory = index,
+                _ => {}
+            }
+        }
+        Ok(entry)
+    }
+}
+
+/// A DWARF 5 entry field's value.
+enum Value<'a> {
+    String(&'a [u8]),
+    LineStringOffset(u64),
+    StringOffset(u64),
+    Number(u64),
+    Other,
+}
+
+/// Returns the NUL-terminated string at `offset` in a string section.
+fn string_at(section: &[u8], offset: u64) -> Option<&str> {
+    let bytes = section.get(usize::try_from(offset).ok()?..)?;
+    let len = bytes.iter().position(|&byte| byte == 0)?;
+    to_str(&bytes[..len])
+}
+
+/// Returns the `n`th of a list of NUL-terminated strings ended by an empty
+/// one.
+fn nth_cstr(mut reader: Reader<'_>, n: u64) -> Result<Option<&str>, DwarfError> {
+    for _ in 0..n {
+        if reader.cstr()?.is_empty() {
+            return Ok(None);
+        }
+    }
+    let string = reader.cstr()?;
+    Ok(if string.is_empty() {
+        None
+    } else {
+        to_str(string)
+    })
+}
+
+fn to_str(bytes: &[u8]) -> Option<&str> {
+    core::str::from_utf8(bytes).ok()
+}
+
+/// Reads little-endian values from part of a section, keeping track of the
+/// offset in the whole section for errors.
+#[derive(Clone)]
+struct Reader<'a> {
+    section: &'a [u8],
+    offset: usize,
+    end: usize,
+}
+
+impl<'a> Reader<'a> {
+    const fn new(section: &'a [u8], offset: usize) -> Self {
+        Self {
+            section,
+            offset,
+            end: section.len(),
+        }
+    }
+
+    /// Returns a reader of the bytes from here up to `end`.
+    const fn split(&self, end: usize) -> Result<Self, DwarfError> {
+        if end > self.end {
+            return Err(DwarfError::Truncated {
+                offset: self.section.len(),
+            });
+        }
+        Ok(Self {
+            section: self.section,
+            offset: self.offset,
+            end,
+        })
+    }
+
+    const fn is_empty(&self) -> bool {
+        self.offset >= self.end
+    }
+
+    /// Returns the offset `length` bytes from here.
+    fn offset_after(&self, length: u64) -> Result<usize, DwarfError> {
+        usize::try_from(length)
+            .ok()
+            .and_then(|length| self.offset.checked_add(length))
+            .ok_or(DwarfError::TooLarge {
+                offset: self.offset,
+            })
+    }
+
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8], DwarfError> {
+        let truncated = DwarfError::Truncated {
+            offset: self.section.len(),
+        };
+        let end = self.offset.checked_add(len).ok_or(truncated)?;
+        if end > self.end {
+            return Err(truncated);
+        }
+        let bytes = &self.section[self.offset..end];
+        self.offset = end;
+        Ok(bytes)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], DwarfError> {
+        let mut array = [0; N];
+        array.copy_from_slice(self.bytes(N)?);
+        Ok(array)
+    }
+
+    fn u8(&mut self) -> Result<u8, DwarfError> {
+        Ok(self.array::<1>()?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, DwarfError> {
+        self.array().map(u16::from_le_bytes)
+    }
+
+    fn u32(&mut self) -> Result<u32, DwarfError> {
+        self.array().map(u32::from_le_bytes)
+    }
+
+    fn u64(&mut self) -> Result<u64, DwarfError> {
+        self.array().map(u64::from_le_bytes)
+    }
+
+    /// Reads an offset into a section, which is as wide as the DWARF format.
+    fn offset_value(&mut self, offset_size: u8) -> Result<u64, DwarfError> {
+        match offset_size {
+            8 => self.u64(),
+            _ => self.u32().map(u64::from),
+        }
+    }
+
+    /// Reads an address, which is as wide as the rest of the reader.
+    fn address(&mut self) -> Result<u64, DwarfError> {
+        match self.end - self.offset {
+            8 => self.u64(),
+            4 => self.u32().map(u64::from),
+            2 => self.u16().map(u64::from),
+            _ => Err(DwarfError::TooLarge {
+                offset: self.offset,
+            }),
+        }
+    }
+
+    fn uleb(&mut self) -> Result<u64, DwarfError> {
+        let start = self.offset;
+        let mut value = 0;
+        let mut shift = 0;
+        loop {
+            let byte = self.u8()?;
+            if shift >= 64 || (shift == 63 && byte & 0x7e != 0) {
+                return Err(DwarfError::TooLarge { offset: start });
+            }
+            value |= u64::from(byte & 0x7f) << shift;
+            shift += 7;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+    }
+
+    fn sleb(&mut self) -> Result<i64, DwarfError> {
+        let start = self.offset;
+        let mut value = 0;
+        let mut shift = 0;
+        loop {
+            let byte = self.u8()?;
+            if shift >= 64 {
+                return Err(DwarfError::TooLarge { offset: start });
+            }
+            value |= i64::from(byte & 0x7f) << shift;
+            shift += 7;
+            if byte & 0x80 == 0 {
+                if shift < 64 && byte & 0x40 != 0 {
+                    value |= -1 << shift;
+                }
+                return Ok(value);
+            }
+        }
+    }
+
+    /// Reads a NUL-terminated string, without the NUL.
+    fn cstr(&mut self) -> Result<&'a [u8], DwarfError> {
+        let truncated = DwarfError::Truncated {
+            offset: self.section.len(),
+        };
+        let rest = self.section.get(self.offset..self.end).ok_or(truncated)?;
+        let len = rest.iter().position(|&byte| byte == 0).ok_or(truncated)?;
+        self.offset += len + 1;
+        Ok(&rest[..len])
+    }
+
+    /// Reads a value of the given form, as found in DWARF 5 entries.
+    fn form(&mut self, form: u64, offset: usize, offset_size: u8) -> Result<Value<'a>, DwarfError> {
+        Ok(match form {
+            // DW_FORM_string
+            0x08 => Value::String(self.cstr()?),
+            // DW_FORM_line_strp
+            0x1f => Value::LineStringOffset(self.offset_value(offset_size)?),
+            // DW_FORM_strp
+            0x0e => Value::StringOffset(self.offset_value(offset_size)?),
+            // DW_FORM_udata
+            0x0f => Value::Number(self.uleb()?),
+            // DW_FORM_data1, data2, data4, and data8
+            0x0b => Value::Number(self.u8()?.into()),
+            0x05 => Value::Number(self.u16()?.into()),
+            0x06 => Value::Number(self.u32()?.into()),
+            0x07 => Value::Number(self.u64()?),
+            // DW_FORM_data16, as used for MD5 hashes
+            0x1e => {
+                self.bytes(16)?;
+                Value::Other
+            }
+            // DW_FORM_block
+            0x09 => {
+                let len = self.uleb()?;
+                let end = self.offset_after(len)?;
+                self.bytes(end - self.offset)?;
+                Value::Other
+            }
+            form => return Err(DwarfError::UnsupportedForm { form, offset }),
+        })
+    }
+}
@@ -0,0 +1,653 @@
+//! Compact binary encodings of a backtrace's addresses, for sending them
+//! over links where every byte counts.
+//!
+//! An encoded list starts with a header, so that a decoder can tell how it
+//! was encoded:
+//!
+//! | Bytes    | Contents                                                   |
+//! |----------|------------------------------------------------------------|
+//! | 1        | The format version, [`FORMAT_VERSION`]                     |
+//! | 1        | The [`FrameEncoding`]: 0 for plain, 1 for delta            |
+//! | 1        | The width of an address on the encoding side, in bytes     |
+//! | 1        | The length of the encoding program's build ID, or 0        |
+//! | 0 to 32  | The [build ID](crate::build_id), if there is one           |
+//! | varint   | The number of addresses                                    |
+//! | varies   | The addresses, as described by their [`FrameEncoding`]     |
+//! | varies   | Optionally, why the backtrace stopped                      |
+//! | 4        | A CRC-32 of every byte before it, little-endian            |
+//!
+//! Varints are LEB128: seven bits per byte, least significant group first,
+//! with the top bit set on every byte but the last.
+//!
+//! The checksum is the CRC-32 that zlib and Ethernet use (the polynomial
+//! `0x04c11db7`, bit-reversed, starting from and finished by inverting every
+//! bit), so a host tool can check it with `zlib.crc32` and the like. Decoders
+//! check it before reading anything else, so a backtrace that lost or flipped
+//! bits on a serial link or in a half-written file fails with
+//! [`DecodeError::ChecksumMismatch`] instead of decoding to wrong addresses.
+//!
+//! Version 1 of the format is the same, but without the build ID, its
+//! length, or the checksum; it's still decoded.
+//!
+//! [`Backtrace::encode`](crate::Backtrace::encode) adds the backtrace's
+//! [stop reason](crate::Backtrace::stop_reason) after the addresses as a
+//! byte: 1 for the end of the stack, 2 for its root, 3 for a full backtrace,
+//! 4 for a walk stopped early, 5 for a `libunwind` error (followed by its
+//! code as a zigzag varint), 6 for an interrupted walk, 7 for a cursor that
+//! was already in use, 8 for a walk that hit the frame limit, and 9 for a
+//! call chain that looped back on itself. Decoders skip what they don't
+//! recognize there, so [`decode_ips`] reads backtraces with or without it.
+//!
+//! With the `std` feature,
+//! [`Backtrace::from_encoded`](crate::Backtrace::from_encoded) reads this
+//! format or its [`armor`](crate::armor)ed text form back into a backtrace, for
+//! host tools, and
+//! [`Backtrace::from_encoded_for`](crate::Backtrace::from_encoded_for) also
+//! checks its build ID against the binary the tool is about to symbolize it
+//! with.
+//!
+//! ```
+//! # use vex_libunwind::encoding::*;
+//! let ips = [0x0380_1234, 0x0380_1010, 0x0380_2f00];
+//! let mut bytes = [0; 32];
+//! let len = encode_ips(ips.into_iter(), FrameEncoding::Delta, &mut bytes).unwrap();
+//!
+//! let mut decoded = [0; 3];
+//! assert_eq!(decode_ips(&bytes[..len], &mut decoded), Ok(3));
+//! assert_eq!(decoded, ips);
+//! ```
+
+use snafu::Snafu;
+
+use crate::{build_id, BacktraceStop, BuildId, UnwindError, MAX_BUILD_ID_LEN};
+
+/// The version of the format written by [`encode_ips`]. [`decode_ips`] reads
+/// this version and every one before it.
+pub const FORMAT_VERSION: u8 = 2;
+
+/// The first version whose header holds a build ID.
+const BUILD_ID_VERSION: u8 = 2;
+
+/// The first version that ends with a checksum.
+const CHECKSUM_VERSION: u8 = 2;
+
+/// The length of the checksum at the end.
+const CHECKSUM_LEN: usize = 4;
+
+/// The length of the fixed part of the header, before the build ID.
+const HEADER_LEN: usize = 4;
+
+/// The most bytes a stop reason takes: its kind, and an error code as a
+/// zigzag varint.
+const STOP_LEN: usize = 1 + 5;
+
+/// How the addresses after the header are stored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FrameEncoding {
+    /// Each address takes the header's address width, little-endian.
+    Plain,
+    /// The first address is an unsigned varint, and each address after it is
+    /// the zigzag-encoded varint of its difference from the one before.
+    ///
+    /// Return addresses in one call chain tend to be close together, so most
+    /// differences fit in two or three bytes, against four for a plain V5
+    /// address.
+    Delta,
+}
+
+impl FrameEncoding {
+    const fn to_byte(self) -> u8 {
+        match self {
+            Self::Plain => 0,
+            Self::Delta => 1,
+        }
+    }
+
+    const fn from_byte(byte: u8) -> Option<Self> {
+        match byte {
+            0 => Some(Self::Plain),
+            1 => Some(Self::Delta),
+            _ => None,
+        }
+    }
+}
+
+/// An error that can occur while encoding addresses.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Snafu)]
+pub enum EncodeError {
+    /// The output buffer is too small
+    BufferTooSmall,
+}
+
+/// An error that can occur while decoding addresses, with the offset of the
+/// byte where decoding failed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Snafu)]
+pub enum DecodeError {
+    /// The input ended in the middle of the encoding.
+    #[snafu(display("input ends early at byte {offset}"))]
+    Truncated {
+        /// The length of the input.
+        offset: usize,
+    },
+    /// The input's checksum doesn't match its bytes, so it was corrupted or
+    /// cut short.
+    #[snafu(display("checksum mismatch at byte {offset}"))]
+    ChecksumMismatch {
+        /// The offset of the checksum.
+        offset: usize,
+    },
+    /// The input was written by an unsupported version of the format.
+    #[snafu(display("unsupported format version {version} at byte 0"))]
+    UnsupportedVersion {
+        /// The version the input says it was written with.
+        version: u8,
+    },
+    /// The input names an encoding this version doesn't know.
+    #[snafu(display("unknown encoding {encoding} at byte 1"))]
+    UnknownEncoding {
+        /// The encoding byte.
+        encoding: u8,
+    },
+    /// An address or count doesn't fit in a `usize` on this machine.
+    #[snafu(display("value too large at byte {offset}"))]
+    TooLarge {
+        /// The offset of the value's first byte.
+        offset: usize,
+    },
+    /// The output slice is too small for all of the addresses.
+    #[snafu(display("room for {capacity} addresses, but {count} were encoded"))]
+    OutputTooSmall {
+        /// The number of addresses in the input.
+        count: usize,
+        /// The length of the output slice.
+        capacity: usize,
+    },
+}
+
+/// An error that can occur while reading a backtrace back with
+/// [`Backtrace::from_encoded`](crate::Backtrace::from_encoded).
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Snafu)]
+pub enum FromEncodedError {
+    /// The input looked like armored text, but couldn't be decoded.
+    #[snafu(display("bad armored text: {error}"))]
+    Text {
+        /// What went wrong, at which character of the text.
+        error: crate::armor::TextDecodeError,
+    },
+    /// The binary encoding, as given or from inside the armor, couldn't be
+    /// decoded.
+    #[snafu(display("bad encoding: {error}"))]
+    Binary {
+        /// What went wrong, at which byte of the binary encoding.
+        error: DecodeError,
+    },
+}
+
+/// Decodes addresses from either the binary format or its armored text
+/// form, telling them apart by whether the input starts with the armor's
+/// prefix.
+///
+/// Along with the number of addresses, this returns the stop reason that
+/// follows them and the build ID in the header, if there are any.
+#[cfg(feature = "std")]
+pub(crate) fn decode_any(input: &[u8], out: &mut [usize]) -> Result<Decoded, FromEncodedError> {
+    use crate::armor::{decode_text, PREFIX};
+
+    let decode = |bytes: &[u8], out: &mut [usize]| {
+        let (count, rest, build_id) =
+            decode_ips_until(bytes, out).map_err(|error| FromEncodedError::Binary { error })?;
+        Ok(Decoded {
+            count,
+            stop: decode_stop(rest),
+            build_id,
+        })
+    };
+    let text = core::str::from_utf8(input).ok().filter(|text| {
+        let text = text.trim_start().as_bytes();
+        text.len() >= PREFIX.len() && text[..PREFIX.len()].eq_ignore_ascii_case(PREFIX.as_bytes())
+    });
+    let Some(text) = text else {
+        return decode(input, out);
+    };
+
+    // Armor never takes fewer characters than the bytes it holds.
+    let mut bytes = std::vec![0; text.len()];
+    let len = decode_text(text, &mut bytes).map_err(|error| FromEncodedError::Text { error })?;
+    decode(&bytes[..len], out)
+}
+
+/// What [`decode_any`] read.
+#[cfg(feature = "std")]
+pub(crate) struct Decoded {
+    /// The number of addresses.
+    pub(crate) count: usize,
+    pub(crate) stop: Option<BacktraceStop>,
+    pub(crate) build_id: Option<BuildId>,
+}
+
+/// Encodes a list of addresses into `out`, along with the running program's
+/// [build ID](crate::build_id), returning the number of bytes written.
+///
+/// # Errors
+///
+/// Returns [`EncodeError::BufferTooSmall`] if `out` can't hold the whole
+/// encoding. A buffer of [`max_encoded_len`] bytes is always big enough.
+pub fn encode_ips(
+    ips: impl ExactSizeIterator<Item = usize>,
+    encoding: FrameEncoding,
+    out: &mut [u8],
+) -> Result<usize, EncodeError> {
+    encode_ips_with_build_id(ips, encoding, build_id(), out)
+}
+
+/// Encodes a list of addresses like [`encode_ips`], but with the given build
+/// ID instead of the running program's.
+///
+/// A build ID longer than [`MAX_BUILD_ID_LEN`] is left out, as if there
+/// weren't one.
+///
+/// # Errors
+///
+/// Returns [`EncodeError::BufferTooSmall`] if `out` can't hold the whole
+/// encoding. A buffer of [`max_encoded_len`] bytes is always big enough.
+pub fn encode_ips_with_build_id(
+    ips: impl ExactSizeIterator<Item = usize>,
+    encoding: FrameEncoding,
+    build_id: Option<&[u8]>,
+    out: &mut [u8],
+) -> Result<usize, EncodeError> {
+    encode_all(ips, encoding, build_id, None, out)
+}
+
+/// Encodes a backtrace's addresses and its stop reason, along with the
+/// running program's build ID, for
+/// [`Backtrace::encode`](crate::Backtrace::encode).
+pub(crate) fn encode_backtrace(
+    ips: impl ExactSizeIterator<Item = usize>,
+    encoding: FrameEncoding,
+    stop: Option<BacktraceStop>,
+    out: &mut [u8],
+) -> Result<usize, EncodeError> {
+    encode_all(ips, encoding, build_id(), stop, out)
+}
+
+fn encode_all(
+    ips: impl ExactSizeIterator<Item = usize>,
+    encoding: FrameEncoding,
+    build_id: Option<&[u8]>,
+    stop: Option<BacktraceStop>,
+    out: &mut [u8],
+) -> Result<usize, EncodeError> {
+    let build_id = build_id
+        .filter(|id| id.len() <= MAX_BUILD_ID_LEN)
+        .unwrap_or_default();
+    let mut writer = Writer::new(out);
+    writer.push(FORMAT_VERSION)?;
+    writer.push(encoding.to_byte())?;
+    writer.push(ADDRESS_WIDTH as u8)?;
+    writer.push(build_id.len() as u8)?;
+    writer.extend(build_id)?;
+    writer.varint(ips.len() as u64)?;
+
+    let mut previous = 0usize;
+    for ip in ips {
+        match encoding {
+            FrameEncoding::Plain => writer.extend(&ip.to_le_bytes())?,
+            FrameEncoding::Delta => {
+                let delta = ip.wrapping_sub(previous) as isize as i64;
+                writer.varint(zigzag(delta))?;
+            }
+        }
+        previous = ip;
+    }
+    if let Some(stop) = stop {
+        write_stop(&mut writer, stop)?;
+    }
+    writer.finish()
+}
+
+/// Decodes a list of addresses written by [`encode_ips`] into `out`,
+/// returning the number of addresses.
+///
+/// Either encoding can be read, since the header says which was used.
+///
+/// # Errors
+///
+/// Returns an error if the input isn't a complete encoding in a supported
+/// version of the format, or if `out` can't hold every address.
+pub fn decode_ips(bytes: &[u8], out: &mut [usize]) -> Result<usize, DecodeError> {
+    decode_ips_until(bytes, out).map(|(count, ..)| count)
+}
+
+/// Reads the build ID from the header of an encoding written by
+/// [`encode_ips`], or [`None`] if it doesn't have one.
+///
+/// Encodings from version 1 of the format, or from programs without a build
+/// ID, don't have one.
+///
+/// # Errors
+///
+/// Returns an error if the input fails its checksum, or if its header isn't
+/// complete or is from an unsupported version of the format.
+pub fn decode_build_id(bytes: &[u8]) -> Result<Option<BuildId>, DecodeError> {
+    let bytes = checked_body(bytes)?;
+    let mut reader = Reader { bytes, offset: 0 };
+    read_header(&mut reader).map(|header| header.build_id)
+}
+
+/// Returns the bytes before the checksum at the end of `bytes`, checking
+/// that they match it, or all of `bytes` if they're from a version of the
+/// format without one.
+fn checked_body(bytes: &[u8]) -> Result<&[u8], DecodeError> {
+    let version = *bytes.first().ok_or(DecodeError::Truncated { offset: 0 })?;
+    if !(1..=FORMAT_VERSION).contains(&version) {
+        return Err(DecodeError::UnsupportedVersion { version });
+    }
+    if version < CHECKSUM_VERSION {
+        return Ok(bytes);
+    }
+    let offset = bytes
+        .len()
+        .checked_sub(CHECKSUM_LEN)
+        .filter(|&offset| offset > 0)
+        .ok_or(DecodeError::Truncated {
+            offset: bytes.len(),
+        })?;
+    let (body, checksum) = bytes.split_at(offset);
+    let crc = !body.iter().fold(!0, |crc, &byte| crc32_update(crc, byte));
+    if checksum != crc.to_le_bytes() {
+        return Err(DecodeError::ChecksumMismatch { offset });
+    }
+    Ok(body)
+}
+
+/// The header of an encoding, up to the address count.
+struct Header {
+    encoding: FrameEncoding,
+    width: usize,
+    build_id: Option<BuildId>,
+}
+
+fn read_header(reader: &mut Reader<'_>) -> Result<Header, DecodeError> {
+    let version = reader.byte()?;
+    if !(1..=FORMAT_VERSION).contains(&version) {
+        return Err(DecodeError::UnsupportedVersion { version });
+    }
+    let encoding = reader.byte()?;
+    let encoding =
+        FrameEncoding::from_byte(encoding).ok_or(DecodeError::UnknownEncoding { encoding })?;
+    let width = usize::from(reader.byte()?);
+    let mut build_id = None;
+    if version >= BUILD_ID_VERSION {
+        let offset = reader.offset;
+        let len = usize::from(reader.byte()?);
+        let id = reader.bytes(len)?;
+        if !id.is_empty() {
+            build_id = Some(BuildId::new(id).ok_or(DecodeError::TooLarge { offset })?);
+        }
+    }
+    Ok(Header {
+        encoding,
+        width,
+        build_id,
+    })
+}
+
+/// Decodes addresses like [`decode_ips`], also returning the bytes between
+/// them and the checksum, and the build ID in the header.
+fn decode_ips_until<'a>(
+    bytes: &'a [u8],
+    out: &mut [usize],
+) -> Result<(usize, &'a [u8], Option<BuildId>), DecodeError> {
+    let bytes = checked_body(bytes)?;
+    let mut reader = Reader { bytes, offset: 0 };
+    let Header {
+        encoding,
+        width,
+        build_id,
+    } = read_header(&mut reader)?;
+    let count = reader.usize_varint()?;
+    if count > out.len() {
+        return Err(DecodeError::OutputTooSmall {
+            count,
+            capacity: out.len(),
+        });
+    }
+
+    let mut previous = 0usize;
+    for slot in &mut out[..count] {
+        let ip = match encoding {
+            FrameEncoding::Plain => reader.address(width)?,
+            FrameEncoding::Delta => {
+                let offset = reader.offset;
+                let delta = unzigzag(reader.varint()?);
+                let ip = (previous as i64).wrapping_add(delta);
+                usize::try_from(ip as u64 & width_mask(width))
+                    .map_err(|_| DecodeError::TooLarge { offset })?
+            }
+        };
+        *slot = ip;
+        previous = ip;
+    }
+    Ok((count, &bytes[reader.offset..], build_id))
+}
+
+/// Writes the stop reason that [`Backtrace::encode`](crate::Backtrace::encode)
+/// puts after the addresses.
+fn write_stop(writer: &mut Writer<'_>, stop: BacktraceStop) -> Result<(), EncodeError> {
+    match stop {
+        BacktraceStop::EndOfStack => writer.push(1)?,
+        BacktraceStop::Root => writer.push(2)?,
+        BacktraceStop::MaxDepth => writer.push(3)?,
+        BacktraceStop::Stopped => writer.push(4)?,
+        BacktraceStop::Fuel => writer.push(8)?,
+        BacktraceStop::Loop => writer.push(9)?,
+        BacktraceStop::Error(UnwindError::Busy) => writer.push(7)?,
+        BacktraceStop::Error(error) => match error.to_code() {
+            Some(code) => {
+                writer.push(5)?;
+                writer.varint(zigzag(i64::from(code)))?;
+            }
+            None => writer.push(6)?,
+        },
+    }
+    Ok(())
+}
+
+/// Reads the stop reason written by [`write_stop`], or [`None`] if there
+/// isn't one that this version knows.
+#[cfg(feature = "std")]
+fn decode_stop(bytes: &[u8]) -> Option<BacktraceStop> {
+    let mut reader = Reader { bytes, offset: 0 };
+    Some(match reader.byte().ok()? {
+        1 => BacktraceStop::EndOfStack,
+        2 => BacktraceStop::Root,
+        3 => BacktraceStop::MaxDepth,
+        4 => BacktraceStop::Stopped,
+        5 => {
+            let code = i32::try_from(unzigzag(reader.varint().ok()?)).ok()?;
+            BacktraceStop::Error(UnwindError::from_code(code).err()?)
+        }
+        6 => BacktraceStop::Error(UnwindError::Interrupted),
+        7 => BacktraceStop::Error(UnwindError::Busy),
+        8 => BacktraceStop::Fuel,
+        9 => BacktraceStop::Loop,
+        _ => return None,
+    })
+}
+
+/// Writes `value` into `out` as a varint, returning the number of bytes
+/// written.
+pub(crate) fn encode_varint(value: u64, out: &mut [u8]) -> Result<usize, EncodeError> {
+    let mut writer = Writer::new(out);
+    writer.varint(value)?;
+    Ok(writer.len)
+}
+
+/// Reads a varint starting at `bytes[offset]`, returning it and the offset
+/// of the byte after it.
+#[cfg(feature = "std")]
+pub(crate) fn decode_varint(bytes: &[u8], offset: usize) -> Result<(u64, usize), DecodeError> {
+    let mut reader = Reader { bytes, offset };
+    let value = reader.varint()?;
+    Ok((value, reader.offset))
+}
+
+/// Returns the most bytes that [`encode_ips`] can write for `count`
+/// addresses, in either encoding, or that
+/// [`Backtrace::encode`](crate::Backtrace::encode) can write for a backtrace
+/// of `count` frames.
+pub const fn max_encoded_len(count: usize) -> usize {
+    // A varint takes a byte per seven bits, so a zigzagged delta can take
+    // one more byte than a plain address.
+    let per_ip = (ADDRESS_WIDTH * 8).div_ceil(7);
+    HEADER_LEN + MAX_BUILD_ID_LEN + 10 + count * per_ip + STOP_LEN + CHECKSUM_LEN
+}
+
+/// The width of an address on this machine, which goes in the header.
+const ADDRESS_WIDTH: usize = core::mem::size_of::<usize>();
+
+/// Keeps the low `width` bytes of a value, so deltas between addresses
+/// narrower than 64 bits wrap the way the encoder's did.
+const fn width_mask(width: usize) -> u64 {
+    match width {
+        0 => 0,
+        1..=7 => (1 << (width * 8)) - 1,
+        _ => u64::MAX,
+    }
+}
+
+/// Maps signed values to unsigned ones so that small magnitudes of either
+/// sign become small numbers: 0, -1, 1, -2, ... become 0, 1, 2, 3, ...
+const fn zigzag(value: i64) -> u64 {
+    ((value << 1) ^ (value >> 63)) as u64
+}
+
+const fn unzigzag(value: u64) -> i64 {
+    (value >> 1) as i64 ^ -((value & 1) as i64)
+}
+
+/// Adds a byte to a CRC-32 that hasn't been finished by inverting it.
+const fn crc32_update(mut crc: u32, byte: u8) -> u32 {
+    crc ^= byte as u32;
+    let mut bit = 0;
+    while bit < 8 {
+        crc = (crc >> 1) ^ (0xedb8_8320 & (crc & 1).wrapping_neg());
+        bit += 1;
+    }
+    crc
+}
+
+/// Writes bytes into a buffer, keeping a CRC-32 of them as it goes so that
+/// the checksum doesn't take a second pass.
+struct Writer<'a> {
+    out: &'a mut [u8],
+    len: usize,
+    crc: u32,
+}
+
+impl<'a> Writer<'a> {
+    fn new(out: &'a mut [u8]) -> Self {
+        Self {
+            out,
+            len: 0,
+            crc: !0,
+        }
+    }
+
+    fn push(&mut self, byte: u8) -> Result<(), EncodeError> {
+        let slot = self
+            .out
+            .get_mut(self.len)
+            .ok_or(EncodeError::BufferTooSmall)?;
+        *slot = byte;
+        self.len += 1;
+        self.crc = crc32_update(self.crc, byte);
+        Ok(())
+    }
+
+    /// Writes the checksum of everything written so far, returning the
+    /// total number of bytes written.
+    fn finish(mut self) -> Result<usize, EncodeError> {
+        let crc = !self.crc;
+        self.extend(&crc.to_le_bytes())?;
+        Ok(self.len)
+    }
+
+    fn extend(&mut self, bytes: &[u8]) -> Result<(), EncodeError> {
+        bytes.iter().try_for_each(|&byte| self.push(byte))
+    }
+
+    fn varint(&mut self, mut value: u64) -> Result<(), EncodeError> {
+        while value >= 0x80 {
+            self.push(value as u8 | 0x80)?;
+            value >>= 7;
+        }
+        self.push(value as u8)
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8], DecodeError> {
+        let bytes =
+            self.bytes
+                .get(self.offset..self.offset + len)
+                .ok_or(DecodeError::Truncated {
+                    offset: self.bytes.len(),
+                })?;
+        self.offset += len;
+        Ok(bytes)
+    }
+
+    fn byte(&mut self) -> Result<u8, DecodeError> {
+        let byte = *self.bytes.get(self.offset).ok_or(DecodeError::Truncated {
+            offset: self.bytes.len(),
+        })?;
+        self.offset += 1;
+        Ok(byte)
+    }
+
+    fn varint(&mut self) -> Result<u64, DecodeError> {
+        let start = self.offset;
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = self.byte()?;
+            let bits = u64::from(byte & 0x7f);
+            if shift == 63 && bits > 1 {
+                return Err(DecodeError::TooLarge { offset: start });
+            }
+            value |= bits << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(DecodeError::TooLarge { offset: start })
+    }
+
+    fn usize_varint(&mut self) -> Result<usize, DecodeError> {
+        let offset = self.offset;
+        usize::try_from(self.varint()?).map_err(|_| DecodeError::TooLarge { offset })
+    }
+
+    fn address(&mut self, width: usize) -> Result<usize, DecodeError> {
+        let offset = self.offset;
+        let mut value = 0u64;
+        for index in 0..width {
+            let byte = u64::from(self.byte()?);
+            if index >= 8 {
+                if byte != 0 {
+                    return Err(DecodeError::TooLarge { offset });
+                }
+            } else {
+                value |= byte << (index * 8);
+            }
+        }
+        usize::try_from(value).map_err(|_| DecodeError::TooLarge { offset })
+    }
+}
@@ -0,0 +1,69 @@
+//! Detection of reentrant unwinding.
+//!
+//! If unwinding faults (for example, by reading through a corrupted stack
+//! pointer) and the fault handler tries to unwind as well, the second attempt
+//! will usually fault again in the same place. The high-level capturing and
+//! printing functions hold a [`ReentrancyGuard`] so that the nested attempt
+//! can bail out instead.
+//!
+//! This is best-effort. On the single-core V5, a flag that's already set can
+//! only mean that unwinding was interrupted by something that started
+//! unwinding again, but a fault that kills the program mid-unwind (without
+//! running destructors) leaves the flag set for good. With the `std` feature
+//! enabled the flag is per-thread, so that unrelated threads don't see each
+//! other as reentrant.
+
+/// Held for the duration of a capture or print; see the module docs.
+#[derive(Debug)]
+pub(crate) struct ReentrancyGuard(());
+
+#[cfg(not(feature = "std"))]
+mod flag {
+    use core::sync::atomic::{AtomicBool, Ordering};
+
+    static ACTIVE: AtomicBool = AtomicBool::new(false);
+
+    pub(super) fn acquire() -> bool {
+        !ACTIVE.swap(true, Ordering::Acquire)
+    }
+
+    pub(super) fn release() {
+        ACTIVE.store(false, Ordering::Release);
+    }
+}
+
+#[cfg(feature = "std")]
+mod flag {
+    use std::cell::Cell;
+
+    std::thread_local! {
+        static ACTIVE: Cell<bool> = const { Cell::new(false) };
+    }
+
+    // `try_with` rather than `with`, which panics while the thread is
+    // exiting. Unwinding is refused then.
+    pub(super) fn acquire() -> bool {
+        ACTIVE
+            .try_with(|active| !active.replace(true))
+            .unwrap_or(false)
+    }
+
+    pub(super) fn release() {
+        let _ = ACTIVE.try_with(|active| active.set(false));
+    }
+}
+
+impl ReentrancyGuard {
+    /// Marks unwinding as active, or returns `None` if it already was.
+    pub(crate) fn enter() -> Option<Self> {
+        // `then` rather than `then_some`, since dropping an eagerly created
+        // guard would clear the flag.
+        flag::acquire().then(|| Self(()))
+    }
+}
+
+impl Drop for ReentrancyGuard {
+    fn drop(&mut self) {
+        flag::release();
+    }
+}
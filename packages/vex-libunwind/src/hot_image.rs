@@ -0,0 +1,153 @@
+//! Recognizing frames in VEXos "hot" images.
+//!
+//! A hot image is loaded separately from (and at a different address than) the
+//! cold image it calls into. Recording where it lives with
+//! [`HotImage::record_location`] lets frames inside it be recognized and their
+//! addresses rebased to the hot image's link-time layout for symbolication.
+//!
+//! # Limitations
+//!
+//! Recording a hot image's location doesn't change how `libunwind` unwinds.
+//! The bundled `libunwind` is built for bare metal, so it only ever searches
+//! the exception index table between the `__exidx_start` and `__exidx_end`
+//! symbols of the image it's linked into, and has no way to be handed
+//! another one at runtime. Stepping out of a hot frame therefore only works
+//! if the hot image's exidx entries are reachable from the table `libunwind`
+//! was linked against; otherwise, the walk ends at the first hot frame.
+
+use core::{
+    ops::Range,
+    sync::atomic::{fence, AtomicUsize, Ordering},
+};
+
+/// The location of a loaded hot image.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HotImageDescriptor {
+    /// The address range of the hot image's code.
+    pub text: Range<usize>,
+    /// The address the hot image was loaded at.
+    pub load_base: usize,
+}
+
+/// Even while stable, odd while a location is being written.
+static SEQUENCE: AtomicUsize = AtomicUsize::new(0);
+/// The generation of the recorded image, or zero if there isn't one.
+static GENERATION: AtomicUsize = AtomicUsize::new(0);
+static NEXT_GENERATION: AtomicUsize = AtomicUsize::new(1);
+static TEXT_START: AtomicUsize = AtomicUsize::new(0);
+static TEXT_END: AtomicUsize = AtomicUsize::new(0);
+static LOAD_BASE: AtomicUsize = AtomicUsize::new(0);
+
+/// How many times a reader retries before giving up on a location that's
+/// being written (for example, by code that the reader interrupted).
+const READ_ATTEMPTS: usize = 64;
+
+fn lock() -> usize {
+    loop {
+        let sequence = SEQUENCE.load(Ordering::Relaxed);
+        if sequence % 2 == 0
+            && SEQUENCE
+                .compare_exchange_weak(sequence, sequence + 1, Ordering::Acquire, Ordering::Relaxed)
+                .is_ok()
+        {
+            return sequence;
+        }
+        core::hint::spin_loop();
+    }
+}
+
+fn unlock(sequence: usize) {
+    SEQUENCE.store(sequence + 2, Ordering::Release);
+}
+
+/// The process-wide location of the loaded hot image.
+///
+/// Only one hot image's location is kept at a time, matching VEXos, which
+/// loads at most one. Recording a new image's location replaces the previous
+/// one rather than adding a second one.
+#[derive(Debug)]
+pub struct HotImage;
+
+impl HotImage {
+    /// Records the location of a loaded hot image, replacing any image that
+    /// was previously recorded.
+    ///
+    /// This is only used to recognize and rebase hot frames; see the
+    /// [module docs](crate::hot_image) for why it doesn't help `libunwind` step
+    /// out of them. The location is kept until the returned guard is
+    /// dropped.
+    pub fn record_location(desc: HotImageDescriptor) -> HotImageGuard {
+        let generation = NEXT_GENERATION.fetch_add(1, Ordering::Relaxed);
+
+        let sequence = lock();
+        TEXT_START.store(desc.text.start, Ordering::Relaxed);
+        TEXT_END.store(desc.text.end, Ordering::Relaxed);
+        LOAD_BASE.store(desc.load_base, Ordering::Relaxed);
+        GENERATION.store(generation, Ordering::Relaxed);
+        unlock(sequence);
+
+        HotImageGuard { generation }
+    }
+
+    /// Returns the location of the current hot image, if one was recorded.
+    pub fn current() -> Option<HotImageDescriptor> {
+        for _ in 0..READ_ATTEMPTS {
+            let sequence = SEQUENCE.load(Ordering::Acquire);
+            if sequence % 2 != 0 {
+                core::hint::spin_loop();
+                continue;
+            }
+
+            let generation = GENERATION.load(Ordering::Relaxed);
+            let desc = HotImageDescriptor {
+                text: TEXT_START.load(Ordering::Relaxed)..TEXT_END.load(Ordering::Relaxed),
+                load_base: LOAD_BASE.load(Ordering::Relaxed),
+            };
+
+            fence(Ordering::Acquire);
+            if SEQUENCE.load(Ordering::Relaxed) == sequence {
+                return (generation != 0).then_some(desc);
+            }
+        }
+        None
+    }
+
+    /// Checks whether the given address is inside the recorded hot image's
+    /// code.
+    pub fn contains(ip: usize) -> bool {
+        Self::current().is_some_and(|desc| desc.text.contains(&ip))
+    }
+
+    /// Converts an address inside the recorded hot image into an offset
+    /// from its load base. Addresses outside of the hot image are returned
+    /// unchanged.
+    ///
+    /// The descriptor isn't checked when it's recorded, so if its load base
+    /// is above the start of its code, addresses below the load base wrap
+    /// around rather than panicking.
+    pub fn rebase(ip: usize) -> usize {
+        match Self::current() {
+            Some(desc) if desc.text.contains(&ip) => ip.wrapping_sub(desc.load_base),
+            _ => ip,
+        }
+    }
+}
+
+/// Keeps a hot image's location recorded until dropped.
+#[derive(Debug)]
+#[must_use = "the hot image's location is forgotten when the guard is dropped"]
+pub struct HotImageGuard {
+    generation: usize,
+}
+
+impl Drop for HotImageGuard {
+    fn drop(&mut self) {
+        let sequence = lock();
+        // A newer location may have replaced this one, in which case it
+        // must be left alone.
+        if GENERATION.load(Ordering::Relaxed) == self.generation {
+            GENERATION.store(0, Ordering::Relaxed);
+        }
+        unlock(sequence);
+    }
+}
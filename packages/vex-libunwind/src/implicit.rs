@@ -0,0 +1,103 @@
+//! Capturing backtraces into snafu errors.
+
+use snafu::GenerateImplicitData;
+
+use crate::Backtrace;
+#[cfg(not(feature = "disabled"))]
+use crate::UnwindCursor;
+
+/// The methods that snafu generates on context selectors, which construct
+/// errors (and so capture their implicit data) on behalf of their caller.
+#[cfg(not(feature = "disabled"))]
+const SELECTOR_METHODS: [&str; 3] = ["into_error", "fail", "build"];
+
+/// Captures a backtrace of where a snafu error was created, so that error
+/// types can carry one as implicit data. Only available with the `snafu`
+/// feature.
+///
+/// ```
+/// use snafu::prelude::*;
+/// use vex_libunwind::Backtrace;
+///
+/// #[derive(Debug, Snafu)]
+/// enum DriveError {
+///     /// The motor on port {port} is disconnected.
+///     Disconnected {
+///         port: u8,
+///         #[snafu(implicit, backtrace(false))]
+///         backtrace: Backtrace<16>,
+///     },
+/// }
+///
+/// fn spin(port: u8) -> Result<(), DriveError> {
+///     DisconnectedSnafu { port }.fail()
+/// }
+///
+/// let DriveError::Disconnected { backtrace, .. } = spin(3).unwrap_err();
+/// println!("created at:\n{backtrace}");
+/// ```
+///
+/// The backtrace is captured as with [`Backtrace::capture`], so it's empty
+/// if capture has been turned off with
+/// [`set_capture_enabled`](crate::set_capture_enabled) or another capture is
+/// in progress. It starts in the function that created the error: frames of
+/// snafu itself and of the `into_error`, `fail`, and `build` methods that it
+/// generates are left out. They're recognized by name, so on the V5, where
+/// procedure names usually aren't available, the generated methods' frames
+/// are left in unless they were inlined (as they usually are in release
+/// builds).
+///
+/// snafu expects a field named `backtrace` to hold its own backtrace type,
+/// so a field of this type by that name needs `backtrace(false)` as well as
+/// `implicit`.
+impl<const N: usize> GenerateImplicitData for Backtrace<N> {
+    #[cfg(not(feature = "disabled"))]
+    #[inline(always)] // Inlining keeps this function from appearing in backtraces
+    fn generate() -> Self {
+        Self::capture_skipping(is_snafu_frame)
+    }
+
+    #[cfg(feature = "disabled")]
+    #[inline(always)]
+    fn generate() -> Self {
+        Self::empty()
+    }
+}
+
+/// Returns `true` if the cursor's frame is in snafu or one of the methods
+/// that it generates on context selectors, judging by its procedure name.
+#[cfg(not(feature = "disabled"))]
+fn is_snafu_frame(cursor: &UnwindCursor) -> bool {
+    let mut buf = [0; 256];
+    let Ok((name, _)) = cursor.procedure_name_lossy(&mut buf) else {
+        return false;
+    };
+    name.contains("snafu")
+        || SELECTOR_METHODS
+            .iter()
+            .any(|method| has_path_segment(name, method))
+}
+
+/// Returns `true` if `segment` is one of the segments of the path that
+/// `name` mangles (in the legacy scheme, where each segment is preceded by
+/// its length and the path ends with `E`) or spells out with `::`.
+#[cfg(not(feature = "disabled"))]
+fn has_path_segment(name: &str, segment: &str) -> bool {
+    name.match_indices(segment).any(|(start, _)| {
+        let (before, after) = (&name[..start], &name[start + segment.len()..]);
+        let mangled = ends_with_length(before, segment.len())
+            && after.starts_with(|c: char| c == 'E' || c.is_ascii_digit());
+        let spelled_out = before.ends_with("::") && (after.is_empty() || after.starts_with("::"));
+        mangled || spelled_out
+    })
+}
+
+/// Returns `true` if `s` ends with `len` in decimal.
+#[cfg(not(feature = "disabled"))]
+fn ends_with_length(s: &str, len: usize) -> bool {
+    let width = len.checked_ilog10().unwrap_or(0) as usize + 1;
+    s.len()
+        .checked_sub(width)
+        .and_then(|start| s.get(start..))
+        .is_some_and(|digits| digits.parse() == Ok(len))
+}
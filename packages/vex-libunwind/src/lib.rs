@@ -3,7 +3,7 @@
 //! ```no_run
 //! # use vex_libunwind::*;
 //! let context = UnwindContext::new().unwrap();
-//! let mut cursor = UnwindCursor::new(&context);
+//! let mut cursor = UnwindCursor::new(&context).unwrap();
 //!
 //! loop {
 //!     // Print instruction pointer (i.e. "program counter")
@@ -15,16 +15,128 @@
 //!     }
 //! }
 //! ```
+//!
+//! # Panics
+//!
+//! Stepping, walking with [`UnwindCursor::frames`], and capturing with
+//! [`Backtrace::capture`] or [`Backtrace::try_capture`] never panic, so
+//! they're safe to use from a `#[panic_handler]`. Methods that read a cursor
+//! through a shared reference, such as [`UnwindCursor::register`], panic if
+//! they're called from within [`UnwindCursor::with_raw`]'s closure; their
+//! `try_` versions fail with [`UnwindError::Busy`] instead, and are what the
+//! walk uses internally.
+//!
+//! # Features
+//!
+//! - `std`: Makes the guard against reentrant unwinding per-thread rather than
+//!   global, for use on hosts. Implies `alloc`.
+//! - `alloc`: Adds `UnwindContext::backtrace_vec`, which collects a context's
+//!   frames into a `Vec` for easy inspection in tests and host tools.
+//! - `core-registers-only`: Makes floating-point register access fail with
+//!   [`UnwindError::BadRegister`] so that unwinding never touches the FPU. The
+//!   V5's `unw_getcontext` already captures only the core registers, but
+//!   [`UnwindContext`] can't be made smaller: `libunwind` copies the full-sized
+//!   register state (including space for the VFP registers) from it when
+//!   initializing a cursor.
+//! - `disabled`: Compiles backtrace capture out, for libraries that want to
+//!   capture backtraces without making every user pay for `libunwind`.
+//!   [`Backtrace`] becomes zero-sized and always empty, capturing and printing
+//!   functions do nothing, and none of them refer to `libunwind`, so it isn't
+//!   linked in unless [`UnwindCursor`] and friends are used directly. The API
+//!   doesn't change, so no `cfg`s are needed downstream.
+//! - `cycle-counter`: Adds `measure_walk_cost`, which times a walk with the
+//!   Cortex-A9's cycle counter. Only available on the V5.
+//! - `trace`: Adds `set_trace_hook`, for seeing each call into `libunwind` and
+//!   what it returned, such as each step's frames before and after. Without it,
+//!   none of the tracing is compiled in.
+//! - `dwarf`: Adds the [`dwarf`](crate::dwarf) module, which finds the source
+//!   file and line of an address in a program's DWARF line tables, if they're
+//!   shipped along with it.
+//! - `snafu`: Implements snafu's `GenerateImplicitData` for [`Backtrace`], so
+//!   that snafu error types can capture one with `#[snafu(implicit)]`.
 #![no_std]
+#![feature(linkage, panic_info_message)]
+
+#[cfg(feature = "alloc")]
+extern crate alloc;
+#[cfg(feature = "std")]
+extern crate std;
 
-use core::{cell::RefCell, ffi::CStr, fmt::Debug, mem::MaybeUninit};
+#[cfg(all(feature = "std", target_arch = "arm"))]
+compile_error!("the `std` feature is only for hosts, since VEXos has no standard library");
+
+use core::{
+    cell::{RefCell, RefMut},
+    ffi::CStr,
+    fmt::Debug,
+    mem::{size_of, zeroed, MaybeUninit},
+    ops::Range,
+    ptr,
+};
 
 use snafu::Snafu;
-pub use vex_libunwind_sys::registers;
 use vex_libunwind_sys::*;
 
+pub mod armor;
+mod backtrace;
+mod baseline;
+mod build_id;
+mod calibrate;
+#[cfg(feature = "dwarf")]
+pub mod dwarf;
+pub mod encoding;
+#[cfg(not(feature = "disabled"))]
+mod guard;
+pub mod hot_image;
+#[cfg(feature = "snafu")]
+mod implicit;
+pub mod memory;
+mod memory_map;
+mod panic_record;
+mod proc_info;
+pub mod profiler;
+mod recorder;
+pub mod registers;
+mod report;
+mod sink;
+mod slot;
+mod stack_bounds;
+mod style;
+mod symbolize;
+mod trace;
+mod traced;
+mod walk;
+
+pub use backtrace::{
+    caller_ip, capture_enabled, capture_soa, count_frames, count_frames_in, nth_frame,
+    return_address, set_capture_enabled, write_backtrace, write_backtrace_bytes,
+    write_backtrace_cached, write_backtrace_machine, Backtrace, BacktraceStatus, BacktraceStop,
+    FilteredBacktrace, MAX_CAPTURE_STACK_BYTES,
+};
+pub use baseline::{capture_baseline, captured_baseline, Baseline, TrimmedBacktrace};
+pub use build_id::{build_id, parse_build_id_note, BuildId, BuildIdMismatch, MAX_BUILD_ID_LEN};
+#[cfg(all(target_arch = "arm", feature = "cycle-counter"))]
+pub use calibrate::measure_walk_cost;
+pub use calibrate::measure_walk_cost_with;
+pub use memory_map::{is_in_text, text_range, MemoryMap, MemoryRegion, RegionKind};
+pub use panic_record::{PanicRecord, PANIC_FILE_BYTES, PANIC_MESSAGE_BYTES};
+pub use proc_info::ProcInfo;
+pub use recorder::{FlightRecorder, RecordedBacktrace};
+pub use report::{
+    write_crash_report, write_crash_report_with, ReportOptions, MAX_INSTRUCTION_BYTES,
+    MAX_REPORT_FRAMES,
+};
+pub use slot::{BacktraceSlot, SlotBacktrace};
+pub use stack_bounds::StackBounds;
+pub use style::{BacktraceStyle, FrameOrder, Styled};
+pub use symbolize::{RawAddresses, Symbol, Symbolize, SymbolizedBacktrace};
+#[cfg(feature = "trace")]
+pub use trace::{set_trace_hook, TraceEvent};
+pub use traced::Traced;
+pub use walk::{CancellableFrames, Frame, FramePairs, Frames, WalkConfig, WalkEnd, CYCLE_WINDOW};
+
 /// An error that can occur during unwinding.
-#[derive(Debug, Snafu)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Snafu)]
 pub enum UnwindError {
     /// Unspecified/general error.
     Unspecified,
@@ -46,6 +158,10 @@ pub enum UnwindError {
     BadVersion,
     /// No unwind info found
     NoInfo,
+    /// The walk was interrupted by its caller; see [`Frames::cancel_when`].
+    Interrupted,
+    /// The cursor was already in use; see [`UnwindCursor::try_register`].
+    Busy,
     /// An error with an unknown error code occured
     #[snafu(display("libunwind error {code}"))]
     Unknown {
@@ -76,6 +192,27 @@ impl UnwindError {
             })
         }
     }
+
+    /// Returns the `libunwind` error code that [`UnwindError::from_code`]
+    /// turns into this error, or [`None`] for
+    /// [`Interrupted`](UnwindError::Interrupted) and
+    /// [`Busy`](UnwindError::Busy), which don't come from `libunwind`.
+    pub(crate) const fn to_code(self) -> Option<uw_error_t> {
+        Some(match self {
+            UnwindError::Unspecified => error::UNW_EUNSPEC,
+            UnwindError::NoMemory => error::UNW_ENOMEM,
+            UnwindError::BadRegister => error::UNW_EBADREG,
+            UnwindError::WriteToReadOnlyRegister => error::UNW_EREADONLYREG,
+            UnwindError::StopUnwinding => error::UNW_ESTOPUNWIND,
+            UnwindError::InvalidIP => error::UNW_EINVALIDIP,
+            UnwindError::BadFrame => error::UNW_EBADFRAME,
+            UnwindError::BadValue => error::UNW_EINVAL,
+            UnwindError::BadVersion => error::UNW_EBADVERSION,
+            UnwindError::NoInfo => error::UNW_ENOINFO,
+            UnwindError::Interrupted | UnwindError::Busy => return None,
+            UnwindError::Unknown { code } => code,
+        })
+    }
 }
 
 /// Holds a snapshot of the state of the CPU's registers at a certain point of
@@ -92,29 +229,211 @@ impl UnwindContext {
     /// unwinding.
     #[inline(always)] // Inlining keeps this function from appearing in backtraces
     pub fn new() -> Result<Self, UnwindError> {
-        let mut inner = MaybeUninit::<unw_context_t>::uninit();
+        // The context is captured in place rather than into a temporary, so
+        // that only one copy of it is ever on the stack.
+        let mut context = Self::empty();
+        context.capture()?;
+        Ok(context)
+    }
+
+    /// Creates a placeholder context that holds no CPU state, for
+    /// initializing a field that [`UnwindContext::capture`] fills in later.
+    ///
+    /// The placeholder's registers are all zero. Initializing a cursor from it
+    /// fails with [`UnwindError::BadFrame`] rather than walking from address
+    /// zero; use [`UnwindContext::is_initialized`] to check for it first.
+    pub const fn empty() -> Self {
+        // SAFETY: The context struct is plain data, so all zeroes is valid.
+        Self {
+            inner: RefCell::new(unsafe { zeroed() }),
+        }
+    }
+
+    /// Creates a context from the registers that a scheduler saved when it
+    /// suspended a task, so that the task's stack can be walked from another
+    /// task (for example, to see where a hung task is stuck).
+    ///
+    /// Copy the task's saved registers out of wherever the scheduler keeps
+    /// them (usually the top of the task's stack, which its control block
+    /// points to) into a [`TaskRegs`], then pass the context to
+    /// [`UnwindCursor::new`] as usual. The walk starts at the instruction the
+    /// task was suspended at and reads the task's stack, so the task must
+    /// stay suspended until the walk is done.
+    ///
+    /// The context is laid out for the V5's `libunwind`, which reads only
+    /// the core registers from it. On hosts, the real `libunwind` expects a
+    /// different layout, so contexts made this way can only be walked with
+    /// the `vex-libunwind-sys` mock there.
+    pub fn from_task_registers(registers: &TaskRegs) -> Self {
+        let mut context = Self::empty();
+        context.write_core_registers(registers);
+        context
+    }
+
+    /// Overwrites the core registers held by the context with a register
+    /// file read by [`UnwindCursor::register_file`], leaving the rest of
+    /// the context as it was.
+    ///
+    /// This is an advanced tool for experiments like switching between
+    /// coroutines, where a frame's registers are saved and later put back.
+    /// Applying the registers is only a write to the context: a cursor
+    /// created from it afterward starts walking at the frame they describe,
+    /// as with [`UnwindContext::from_task_registers`], but nothing here
+    /// transfers control. Resuming execution from such a context (with
+    /// `unw_resume`) is as unsafe as a `longjmp`: the frame's stack must
+    /// still hold what it did when the registers were read, and nothing in
+    /// between is dropped.
+    ///
+    /// The context is laid out for the V5's `libunwind`. On hosts, the values
+    /// are truncated to 32 bits and the real `libunwind` expects a different
+    /// layout, so contexts changed this way can only be walked with the
+    /// `vex-libunwind-sys` mock there.
+    pub fn apply_register_file(&mut self, registers: &RegisterFile) {
+        let [r @ .., sp, lr, pc] = registers.r.map(|value| value as u32);
+        self.write_core_registers(&TaskRegs { r, sp, lr, pc });
+    }
+
+    fn write_core_registers(&mut self, registers: &TaskRegs) {
+        // SAFETY: The context starts with the 16 core registers as 32-bit
+        // words, in the same order as `TaskRegs`, and is large enough for
+        // them.
+        unsafe {
+            *self.as_mut_ptr().cast::<TaskRegs>() = *registers;
+        }
+    }
+
+    /// Replaces the context with a snapshot of the current CPU state, as if
+    /// it had been created with [`UnwindContext::new`].
+    #[inline(always)] // Inlining keeps this function from appearing in backtraces
+    pub fn capture(&mut self) -> Result<(), UnwindError> {
         // SAFETY: `unw_getcontext` initializes the context struct.
-        let inner = unsafe {
-            UnwindError::from_code(unw_getcontext(inner.as_mut_ptr()))?;
-            inner.assume_init()
-        };
-        Ok(Self {
-            inner: RefCell::new(inner),
-        })
+        let code = unsafe { unw_getcontext(self.as_mut_ptr()) };
+        trace::trace_event!(Context { code });
+        UnwindError::from_code(code)?;
+        Ok(())
+    }
+
+    /// Walks the whole call chain starting at this context, collecting its
+    /// frames innermost first.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the cursor can't be initialized or unwinding
+    /// fails partway through, in which case the frames found so far are
+    /// lost; use [`Backtrace`] to keep them.
+    #[cfg(feature = "alloc")]
+    pub fn backtrace_vec(&self) -> Result<alloc::vec::Vec<Frame>, UnwindError> {
+        let mut cursor = UnwindCursor::new(self)?;
+        cursor.frames(WalkConfig::new()).collect()
+    }
+
+    /// Checks whether the context holds a snapshot of the CPU state, rather
+    /// than being a placeholder from [`UnwindContext::empty`].
+    pub fn is_initialized(&self) -> bool {
+        is_captured(&self.inner.borrow())
     }
 
     /// Returns the underlying `libunwind` object.
     pub fn as_mut_ptr(&mut self) -> *mut unw_context_t {
         &mut *self.inner.get_mut()
     }
+
+    /// Returns the size in bytes of the underlying `libunwind` object.
+    pub const fn raw_size() -> usize {
+        size_of::<unw_context_t>()
+    }
+}
+
+/// The core registers of a suspended task, for
+/// [`UnwindContext::from_task_registers`].
+///
+/// This is the layout of the core registers at the start of the V5
+/// `libunwind`'s context: 16 32-bit words holding r0-r12, sp, lr, and pc. `pc`
+/// is where the task will resume, and `sp` is its stack pointer at that
+/// point. Schedulers save the registers in their own order, so they have to
+/// be copied into this struct field by field.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct TaskRegs {
+    /// r0-r12.
+    pub r: [u32; 13],
+    /// The stack pointer, r13.
+    pub sp: u32,
+    /// The link register, r14.
+    pub lr: u32,
+    /// The program counter, r15.
+    pub pc: u32,
+}
+
+const _: () = assert!(size_of::<TaskRegs>() <= size_of::<unw_context_t>());
+
+/// The core registers r0-r15 of a frame, as read all at once by
+/// [`UnwindCursor::register_file`] and put back by
+/// [`UnwindContext::apply_register_file`].
+///
+/// `r[n]` holds register `rn`, so `r[13]` is the stack pointer, `r[14]` the
+/// link register, and `r[15]` the program counter.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct RegisterFile {
+    /// r0-r15.
+    pub r: [usize; 16],
+}
+
+impl RegisterFile {
+    /// Returns the stack pointer, r13.
+    pub const fn sp(&self) -> usize {
+        self.r[13]
+    }
+
+    /// Returns the link register, r14.
+    pub const fn lr(&self) -> usize {
+        self.r[14]
+    }
+
+    /// Returns the program counter, r15.
+    pub const fn pc(&self) -> usize {
+        self.r[15]
+    }
+}
+
+impl Default for UnwindContext {
+    /// Creates a placeholder context; see [`UnwindContext::empty`].
+    fn default() -> Self {
+        Self::empty()
+    }
+}
+
+/// Checks whether a context has been captured. Every captured context has a
+/// nonzero stack pointer, so only a placeholder is all zeroes.
+fn is_captured(context: &unw_context_t) -> bool {
+    // SAFETY: The context struct is plain data, so it can be read as bytes.
+    let bytes = unsafe {
+        core::slice::from_raw_parts(
+            ptr::from_ref(context).cast::<u8>(),
+            size_of::<unw_context_t>(),
+        )
+    };
+    bytes.iter().any(|&byte| byte != 0)
 }
 
 impl Debug for UnwindContext {
     fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
-        f.debug_struct("UnwindContext").finish_non_exhaustive()
+        let mut s = f.debug_struct("UnwindContext");
+        if let Ok(frame) = UnwindCursor::new(self).and_then(|cursor| Frame::from_cursor(&cursor)) {
+            s.field("ip", &(frame.ip as *const ()))
+                .field("sp", &(frame.sp as *const ()))
+                .finish()
+        } else {
+            s.finish_non_exhaustive()
+        }
     }
 }
 
+// Fault handlers are written with this budget in mind, so it shouldn't grow
+// unnoticed.
+#[cfg(target_arch = "arm")]
+const _: () = assert!(UnwindCursor::PEAK_INIT_STACK <= 744);
+
 /// A cursor that can move up the call chain and gather information about stack
 /// frames.
 ///
@@ -129,20 +448,130 @@ pub struct UnwindCursor {
 impl UnwindCursor {
     /// Initializes a cursor for local unwinding using the state captured by the
     /// given [`UnwindContext`].
+    ///
+    /// # Errors
+    ///
+    /// Returns [`UnwindError::BadFrame`] if the context is a placeholder that
+    /// was never captured, or another error if `libunwind` can't initialize
+    /// the cursor.
     pub fn new(context: &UnwindContext) -> Result<Self, UnwindError> {
-        let mut cursor = MaybeUninit::<unw_cursor_t>::uninit();
+        let mut context = context.inner.borrow_mut();
+        if !is_captured(&context) {
+            return Err(UnwindError::BadFrame);
+        }
+        // Initialized in place, like the context in `UnwindContext::new`.
+        // SAFETY: The cursor struct is plain data, so all zeroes is valid.
+        let mut cursor = Self {
+            inner: RefCell::new(unsafe { zeroed() }),
+        };
         // SAFETY: `unw_init_local` initializes the cursor struct. A reference to
         // `context` is not stored in the cursor.
-        let cursor = unsafe {
-            UnwindError::from_code(unw_init_local(
-                cursor.as_mut_ptr(),
-                &mut *context.inner.borrow_mut(),
-            ))?;
-            cursor.assume_init()
+        let code = unsafe { unw_init_local(cursor.inner.get_mut(), &mut *context) };
+        trace::trace_event!(Cursor { code });
+        UnwindError::from_code(code)?;
+        Ok(cursor)
+    }
+
+    /// Initializes a cursor for local unwinding using the state captured by the
+    /// given [`UnwindContext`], consuming it.
+    ///
+    /// `libunwind` copies the captured registers into the cursor, so the
+    /// context isn't needed once the cursor exists. Taking it by value lets its
+    /// storage be reused right away instead of staying live for the rest of
+    /// the walk, which matters on the V5's small fault-handler stacks.
+    ///
+    /// Both objects are still live while the cursor is initialized, so
+    /// capturing a context and beginning to walk it needs
+    /// [`UnwindCursor::PEAK_INIT_STACK`] bytes of stack for them. This can't
+    /// be avoided by capturing straight into the cursor: `unw_init_local`
+    /// copies the context into a cursor that has already been constructed, and
+    /// the two must not overlap.
+    ///
+    /// # Errors
+    ///
+    /// See [`UnwindCursor::new`].
+    pub fn from_context(mut context: UnwindContext) -> Result<Self, UnwindError> {
+        // Owning the context means it can be accessed without a `RefCell`
+        // borrow, which keeps panics out of `Backtrace::try_capture`.
+        if !is_captured(context.inner.get_mut()) {
+            return Err(UnwindError::BadFrame);
+        }
+        // SAFETY: The cursor struct is plain data, so all zeroes is valid.
+        let mut cursor = Self {
+            inner: RefCell::new(unsafe { zeroed() }),
         };
-        Ok(Self {
-            inner: RefCell::new(cursor),
-        })
+        // SAFETY: See `UnwindCursor::new`.
+        let code = unsafe { unw_init_local(cursor.as_mut_ptr(), context.as_mut_ptr()) };
+        trace::trace_event!(Cursor { code });
+        UnwindError::from_code(code)?;
+        Ok(cursor)
+    }
+
+    /// Returns the underlying `libunwind` object.
+    pub(crate) fn as_mut_ptr(&mut self) -> *mut unw_cursor_t {
+        self.inner.get_mut()
+    }
+
+    /// The number of bytes taken up by an [`UnwindContext`] and an
+    /// [`UnwindCursor`] together, which is the most stack that capturing a
+    /// context and beginning to walk it uses for the two. On the V5, this is
+    /// 744 bytes.
+    pub const PEAK_INIT_STACK: usize = size_of::<UnwindContext>() + size_of::<UnwindCursor>();
+
+    /// Returns the size in bytes of the underlying `libunwind` object.
+    pub const fn raw_size() -> usize {
+        size_of::<unw_cursor_t>()
+    }
+
+    /// In debug builds, checks that the cursor's current frame is in the
+    /// calling function, panicking if it isn't.
+    ///
+    /// [`UnwindContext::new`] has to be inlined into its caller for a
+    /// backtrace to start there, and inlining isn't guaranteed. Calling this
+    /// right after creating a cursor from a new context (in the same function)
+    /// catches the case where it wasn't, and the backtrace starts inside
+    /// `UnwindContext::new` instead.
+    ///
+    /// The check compares the procedure containing the current frame with the
+    /// procedure that called this method, so it can't tell apart two frames
+    /// in the same procedure, and it's skipped for procedures without unwind
+    /// info. It's compiled out of release builds.
+    ///
+    /// # Panics
+    ///
+    /// In debug builds, panics if the current frame is in a procedure other
+    /// than the caller's, such as after the cursor has been stepped.
+    #[inline(always)]
+    #[track_caller]
+    pub fn debug_assert_starts_in_caller(&self) {
+        if cfg!(debug_assertions) {
+            self.assert_starts_in_caller();
+        }
+    }
+
+    // Not inlined, so that stepping out of this function's own frame reaches
+    // the caller of `debug_assert_starts_in_caller`.
+    #[inline(never)]
+    #[track_caller]
+    fn assert_starts_in_caller(&self) {
+        let Ok(start) = self.proc_range() else {
+            return;
+        };
+        let Ok(mut here) = capture_cursor() else {
+            return;
+        };
+        if !matches!(here.step(), Ok(true)) {
+            return;
+        }
+        let Ok(caller) = here.proc_range() else {
+            return;
+        };
+        assert!(
+            start == caller,
+            "backtrace starts at {:#x}, outside of the calling function at {caller:#x?} \
+             (was `UnwindContext::new` inlined?)",
+            self.register(registers::UNW_REG_IP).unwrap_or(0),
+        );
     }
 
     /// Advances to the next (older) frame of the call chain.
@@ -163,10 +592,115 @@ impl UnwindCursor {
     ///   frame is invalid
     /// - [`UnwindError::BadFrame`] if the next frame is invalid
     pub fn step(&mut self) -> Result<bool, UnwindError> {
-        let code = UnwindError::from_code(unsafe { unw_step(&mut *self.inner.borrow_mut()) })?;
+        // SAFETY: The cursor was initialized when it was created.
+        let code = UnwindError::from_code(unsafe { trace::step(self.inner.get_mut()) })?;
         Ok(code == UNW_STEP_SUCCESS)
     }
 
+    /// Advances to the next (older) frame of the call chain, like
+    /// [`UnwindCursor::step`], which it's the same as.
+    ///
+    /// Stepping takes the cursor mutably, so unlike reading a register, it
+    /// can't find the cursor in use and never panics; see
+    /// [`UnwindCursor::try_register`]. This is here so that code that sticks
+    /// to the `try_` methods doesn't have to check which ones need it.
+    ///
+    /// # Errors
+    ///
+    /// See [`UnwindCursor::step`].
+    pub fn try_step(&mut self) -> Result<bool, UnwindError> {
+        self.step()
+    }
+
+    /// Checks whether the cursor can step back down to a newer frame, which
+    /// it never can.
+    ///
+    /// `libunwind` recovers a caller's registers from the callee's unwind
+    /// info, which says how the callee saved them. Nothing says how to get
+    /// from a caller back to the callee it called, so stepping only goes one
+    /// way. To go back to a newer frame, take a
+    /// [`checkpoint`](UnwindCursor::checkpoint) there before stepping past
+    /// it, and [`restore`](UnwindCursor::restore) it later.
+    pub const fn can_step_down(&self) -> bool {
+        false
+    }
+
+    /// Would step to the next newer frame of the call chain, but always
+    /// fails, since `libunwind` cursors only walk toward older frames. See
+    /// [`UnwindCursor::can_step_down`].
+    ///
+    /// This exists so that the limitation is found by looking for the
+    /// method, rather than only by reading about [`UnwindCursor::step`].
+    ///
+    /// # Errors
+    ///
+    /// Always returns [`UnwindError::BadValue`], for an unsupported operation,
+    /// and leaves the cursor where it was.
+    pub fn step_down(&mut self) -> Result<bool, UnwindError> {
+        Err(UnwindError::BadValue)
+    }
+
+    /// Saves the cursor's position in the call chain, to go back to later
+    /// with [`UnwindCursor::restore`].
+    ///
+    /// This is a copy of the cursor's state, so it's cheap to take, but makes
+    /// the same assumption the cursor does: that the stack it's walking
+    /// hasn't changed since the context was captured.
+    pub fn checkpoint(&self) -> CursorCheckpoint {
+        CursorCheckpoint {
+            raw: *self.inner.borrow(),
+        }
+    }
+
+    /// Moves the cursor back to a position saved by
+    /// [`UnwindCursor::checkpoint`].
+    ///
+    /// The checkpoint should come from a cursor walking the same context.
+    /// Restoring one from another context (or after the stack it describes
+    /// has been unwound or overwritten) gives a cursor that reads whatever is
+    /// in that memory now.
+    pub fn restore(&mut self, checkpoint: &CursorCheckpoint) {
+        *self.inner.get_mut() = checkpoint.raw;
+    }
+
+    /// Returns the range of stack memory taken up by the cursor's current
+    /// frame, `[sp, cfa)`: from its stack pointer up to its canonical frame
+    /// address, which is the stack pointer of its caller.
+    ///
+    /// The caller's stack pointer is found by stepping a copy of the cursor,
+    /// which leaves this one where it is. The ranges of consecutive frames
+    /// meet, so dumping a frame's range gives its locals, spilled registers,
+    /// and (on the V5) the return address it saved. A frame that interrupted
+    /// code on another stack, such as a fault handler, may not be next to its
+    /// caller's frame, so the range spans whatever lies between the stacks.
+    ///
+    /// # Errors
+    ///
+    /// - [`UnwindError::NoInfo`] if the frame is the outermost one, so nothing
+    ///   says where it ends
+    /// - [`UnwindError::BadFrame`] if the caller's stack pointer is below this
+    ///   frame's
+    /// - any error from reading the stack pointer or stepping; see
+    ///   [`UnwindCursor::register`] and [`UnwindCursor::step`]
+    pub fn frame_stack_range(&self) -> Result<Range<usize>, UnwindError> {
+        let sp = self.register(registers::UNW_REG_SP)?;
+        let mut caller = self.clone();
+        if !caller.step()? {
+            return Err(UnwindError::NoInfo);
+        }
+        let cfa = caller.register(registers::UNW_REG_SP)?;
+        if cfa < sp {
+            return Err(UnwindError::BadFrame);
+        }
+        Ok(sp..cfa)
+    }
+
+    /// Returns an iterator over the frames of the call chain, starting with
+    /// the cursor's current frame.
+    pub fn frames<'a>(&'a mut self, config: WalkConfig<'a>) -> Frames<'a> {
+        Frames::new(self, config)
+    }
+
     /// Retrieves the value of the given register for the cursor's current
     /// frame.
     ///
@@ -185,6 +719,166 @@ impl UnwindCursor {
         Ok(reg_value)
     }
 
+    /// Retrieves the value of the given register for the cursor's current
+    /// frame like [`UnwindCursor::register`], but fails instead of panicking
+    /// if the cursor is already in use.
+    ///
+    /// Methods that take the cursor by shared reference borrow it for the
+    /// length of their call into `libunwind`, which never calls back into
+    /// them. The one borrow that lasts longer is
+    /// [`with_raw`](UnwindCursor::with_raw)'s, which holds the cursor while
+    /// its closure runs, so calling the cursor's other methods from within
+    /// the closure (or from code it calls, such as a logger formatting the
+    /// cursor) finds it in use. A crash handler that can't rule that out can
+    /// use this so that a borrow panic doesn't bury the crash it's reporting.
+    ///
+    /// # Errors
+    ///
+    /// - [`UnwindError::Busy`] if the cursor is already in use
+    /// - otherwise, see [`UnwindCursor::register`]
+    pub fn try_register(&self, register: unw_regnum_t) -> Result<usize, UnwindError> {
+        let mut cursor = self.try_borrow_raw()?;
+        let mut reg_value = 0;
+        UnwindError::from_code(unsafe { unw_get_reg(&mut *cursor, register, &mut reg_value) })?;
+        Ok(reg_value)
+    }
+
+    /// Retrieves the values of several registers for the cursor's current
+    /// frame at once, writing the value of `registers[i]` to `values[i]`.
+    ///
+    /// This is cheaper than calling [`UnwindCursor::register`] for each one,
+    /// since the cursor is only borrowed once.
+    ///
+    /// # Errors
+    ///
+    /// - [`UnwindError::BadValue`] if `registers` and `values` are different
+    ///   lengths, in which case nothing is read
+    /// - otherwise, stops at and returns the first error; see
+    ///   [`UnwindCursor::register`]. The values of the registers before it have
+    ///   been written by then. Use [`UnwindCursor::read_registers_each`] to
+    ///   read the rest regardless.
+    pub fn read_registers(
+        &self,
+        registers: &[unw_regnum_t],
+        values: &mut [usize],
+    ) -> Result<(), UnwindError> {
+        if registers.len() != values.len() {
+            return Err(UnwindError::BadValue);
+        }
+        let mut cursor = self.inner.borrow_mut();
+        for (&register, value) in registers.iter().zip(values) {
+            UnwindError::from_code(unsafe { unw_get_reg(&mut *cursor, register, value) })?;
+        }
+        Ok(())
+    }
+
+    /// Retrieves the values of several registers for the cursor's current
+    /// frame at once like [`UnwindCursor::read_registers`], but records each
+    /// register's result in `results[i]` instead of stopping at the first
+    /// error.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`UnwindError::BadValue`] if `registers` and `results` are
+    /// different lengths, in which case nothing is read. Errors reading
+    /// individual registers go in `results` instead.
+    pub fn read_registers_each(
+        &self,
+        registers: &[unw_regnum_t],
+        results: &mut [Result<usize, UnwindError>],
+    ) -> Result<(), UnwindError> {
+        if registers.len() != results.len() {
+            return Err(UnwindError::BadValue);
+        }
+        let mut cursor = self.inner.borrow_mut();
+        for (&register, result) in registers.iter().zip(results) {
+            let mut value = 0;
+            *result =
+                UnwindError::from_code(unsafe { unw_get_reg(&mut *cursor, register, &mut value) })
+                    .map(|_| value);
+        }
+        Ok(())
+    }
+
+    /// Reads the registers in `which` that can be recovered in the cursor's
+    /// current frame into the start of `out`, in order, returning how many
+    /// were written.
+    ///
+    /// Registers that can't be read in this frame are skipped rather than
+    /// leaving a gap, so `out` only holds as many values as there were
+    /// available registers. This makes for the smallest record of a frame
+    /// when only a few registers are wanted, at the cost of not saying which
+    /// were skipped; record the frame with
+    /// [`UnwindCursor::read_registers_each`] instead if that matters.
+    /// Reading stops once `out` is full.
+    ///
+    /// # Errors
+    ///
+    /// Returns the first error other than [`UnwindError::BadRegister`]; see
+    /// [`UnwindCursor::register`].
+    pub fn snapshot_regs(
+        &self,
+        which: &[unw_regnum_t],
+        out: &mut [usize],
+    ) -> Result<usize, UnwindError> {
+        let mut cursor = self.inner.borrow_mut();
+        let mut filled = 0;
+        for &register in which {
+            let Some(slot) = out.get_mut(filled) else {
+                break;
+            };
+            match UnwindError::from_code(unsafe { unw_get_reg(&mut *cursor, register, slot) }) {
+                Ok(_) => filled += 1,
+                Err(UnwindError::BadRegister) => {}
+                Err(error) => return Err(error),
+            }
+        }
+        Ok(filled)
+    }
+
+    /// Reads all of the core registers, r0-r15, of the cursor's current
+    /// frame into a [`RegisterFile`], for putting back later with
+    /// [`UnwindContext::apply_register_file`].
+    ///
+    /// In the frame a cursor starts at, and in signal frames, every register
+    /// holds the value it had in that frame. In other frames, only the
+    /// callee-saved registers are recovered: the V5's `libunwind` reports
+    /// the rest with whatever values they had in the frame below, so only
+    /// apply a register file of an ordinary frame if that's good enough.
+    ///
+    /// # Errors
+    ///
+    /// Returns the first error from reading a register; see
+    /// [`UnwindCursor::register`].
+    pub fn register_file(&self) -> Result<RegisterFile, UnwindError> {
+        let mut file = RegisterFile::default();
+        self.read_registers(&registers::CORE_REGISTERS, &mut file.r)?;
+        Ok(file)
+    }
+
+    /// Returns an iterator over the core registers that can be recovered in
+    /// the cursor's current frame, along with their values.
+    ///
+    /// Ordinary frames only preserve the callee-saved registers
+    /// ([`registers::CALLEE_SAVED_REGISTERS`]), while signal frames preserve
+    /// every core register ([`registers::CORE_REGISTERS`]). Registers that
+    /// can't be read are skipped.
+    pub fn available_registers(&self) -> impl Iterator<Item = (unw_regnum_t, usize)> {
+        let candidates: &[unw_regnum_t] = if self.is_signal_frame().unwrap_or(false) {
+            &registers::CORE_REGISTERS
+        } else {
+            &registers::CALLEE_SAVED_REGISTERS
+        };
+        let mut results: [_; registers::CORE_REGISTERS.len()] =
+            core::array::from_fn(|_| Err(UnwindError::BadRegister));
+        // The slices are the same length by construction, so this can't fail.
+        let _ = self.read_registers_each(candidates, &mut results[..candidates.len()]);
+        candidates
+            .iter()
+            .zip(results)
+            .filter_map(|(&register, result)| Some((register, result.ok()?)))
+    }
+
     /// Sets the value of the given register in the cursor's current frame to
     /// the given value.
     ///
@@ -215,6 +909,21 @@ impl UnwindCursor {
     /// Retrieves the value of the given floating point register for the
     /// cursor's current frame.
     ///
+    /// On the V5, these are the VFP `d0`-`d31` registers, named by the
+    /// `UNW_ARM_D*` constants in [`registers`]. Their 64-bit contents are
+    /// returned as raw bits.
+    ///
+    /// `unw_getcontext` only captures the core registers on the V5. The VFP
+    /// registers are read from the FPU the first time one of them is accessed
+    /// through the cursor, so they hold the FPU's state at that time rather
+    /// than when the context was captured. In a signal frame built from an
+    /// exception's saved state, that means they aren't recovered from the
+    /// exception at all.
+    ///
+    /// With the `core-registers-only` feature enabled, this always fails with
+    /// [`UnwindError::BadRegister`] instead, without touching the FPU (which
+    /// faults if it's disabled).
+    ///
     /// # Errors
     ///
     /// This function may return one of the following errors:
@@ -222,17 +931,47 @@ impl UnwindCursor {
     /// - [`UnwindError::Unspecified`] if an unspecified error occurred
     /// - [`UnwindError::BadRegister`] if the register was invalid or
     ///   inaccessible in the current frame
-    pub fn fp_register(&self, register: unw_regnum_t) -> Result<usize, UnwindError> {
-        let mut reg_value = 0;
+    pub fn fp_register(&self, register: unw_regnum_t) -> Result<unw_fpreg_t, UnwindError> {
+        if cfg!(feature = "core-registers-only") {
+            return Err(UnwindError::BadRegister);
+        }
+        let mut reg_value = Default::default();
         UnwindError::from_code(unsafe {
-            unw_get_reg(&mut *self.inner.borrow_mut(), register, &mut reg_value)
+            unw_get_fpreg(&mut *self.inner.borrow_mut(), register, &mut reg_value)
         })?;
         Ok(reg_value)
     }
 
+    /// Retrieves the contents of NEON quadword register `q0`-`q15` (given by
+    /// its number, 0-15) for the cursor's current frame, as raw bytes.
+    ///
+    /// `libunwind` only deals in doubleword registers, so this reads `qN` as
+    /// the pair `d(2N)` and `d(2N+1)` that it overlaps with, using
+    /// [`UnwindCursor::fp_register`]. The bytes are in little-endian order,
+    /// the way the V5 would store the register in memory: `d(2N)` makes up
+    /// the first eight bytes and `d(2N+1)` the last eight.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`UnwindError::BadRegister`] if `register` is larger than 15,
+    /// or an error as described in [`UnwindCursor::fp_register`].
+    pub fn q_register(&self, register: u32) -> Result<[u8; 16], UnwindError> {
+        if register > 15 {
+            return Err(UnwindError::BadRegister);
+        }
+        let low = registers::UNW_ARM_D0 + 2 * register as unw_regnum_t;
+        let mut bytes = [0; 16];
+        bytes[..8].copy_from_slice(&self.fp_register(low)?.to_le_bytes());
+        bytes[8..].copy_from_slice(&self.fp_register(low + 1)?.to_le_bytes());
+        Ok(bytes)
+    }
+
     /// Sets the value of the given floating-point register in the cursor's
     /// current frame to the given value.
     ///
+    /// With the `core-registers-only` feature enabled, this always fails with
+    /// [`UnwindError::BadRegister`]; see [`UnwindCursor::fp_register`].
+    ///
     /// # Safety
     ///
     /// The caller must ensure that updating the stack frame as described above
@@ -251,13 +990,60 @@ impl UnwindCursor {
         register: unw_regnum_t,
         value: unw_fpreg_t,
     ) -> Result<(), UnwindError> {
+        if cfg!(feature = "core-registers-only") {
+            return Err(UnwindError::BadRegister);
+        }
         UnwindError::from_code(unsafe {
             unw_set_fpreg(&mut *self.inner.borrow_mut(), register, value)
         })?;
         Ok(())
     }
 
-    /// Checks whether the given register is a floating-point register.
+    /// Reads the link register (LR, r14) of the current frame.
+    ///
+    /// LR holds the return address when a function is called, so in the
+    /// innermost frame of a context captured in a leaf function (or in an
+    /// exception handler's saved context), it's still the address the frame
+    /// will return to. That's useful when the frame has no unwind info and
+    /// [`UnwindCursor::step`] can't find its caller: LR names the caller
+    /// anyway.
+    ///
+    /// LR and the instruction pointer
+    /// ([`UNW_REG_IP`](registers::UNW_REG_IP)) hold different things. The
+    /// instruction pointer is where the frame is executing, while LR is
+    /// wherever it was last set: once a function makes a call of its own, its
+    /// LR is overwritten with a return address inside itself, and only the
+    /// copy it saved on the stack still points to its caller. After stepping,
+    /// the new frame's instruction pointer is the previous frame's recovered
+    /// return address, and its LR is whatever `libunwind` could restore.
+    ///
+    /// # Errors
+    ///
+    /// See [`UnwindCursor::register`].
+    pub fn lr(&self) -> Result<usize, UnwindError> {
+        self.register(registers::UNW_ARM_LR)
+    }
+
+    /// Reads the saved program status register (SPSR) of the current frame.
+    ///
+    /// An SPSR is only saved when an exception is taken, so this is only
+    /// meaningful for signal frames, and DWARF has no register number for the
+    /// CPSR of ordinary frames. The bundled `libunwind` doesn't track SPSRs at
+    /// all, so in practice this currently always fails; it exists so that
+    /// callers don't need to change if that support is added.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`UnwindError::BadRegister`] if the SPSR isn't available in the
+    /// current frame, or another error as described in
+    /// [`UnwindCursor::register`].
+    pub fn status_register(&self) -> Result<usize, UnwindError> {
+        self.register(registers::UNW_ARM_SPSR)
+    }
+
+    /// Checks whether the given register is a floating-point register, i.e.
+    /// one that must be accessed with [`UnwindCursor::fp_register`] rather
+    /// than [`UnwindCursor::register`].
     pub fn is_fp_register(&self, register: unw_regnum_t) -> bool {
         unsafe { unw_is_fpreg(&mut *self.inner.borrow_mut(), register) > 0 }
     }
@@ -282,27 +1068,332 @@ impl UnwindCursor {
         Ok(code > 0)
     }
 
+    /// Checks whether the current frame is a signal frame like
+    /// [`UnwindCursor::is_signal_frame`], but fails with
+    /// [`UnwindError::Busy`] instead of panicking if the cursor is in use.
+    #[cfg(not(feature = "disabled"))]
+    pub(crate) fn try_is_signal_frame(&self) -> Result<bool, UnwindError> {
+        let code = unsafe { unw_is_signal_frame(&mut *self.try_borrow_raw()?) };
+        UnwindError::from_code(code)?;
+        Ok(code > 0)
+    }
+
+    /// Retrieves the address range of the procedure containing the cursor's
+    /// current frame.
+    ///
+    /// The range starts at the procedure's first instruction and ends just
+    /// after its last one.
+    ///
+    /// # Errors
+    ///
+    /// If `libunwind` was unable to find unwind info for the procedure,
+    /// [`UnwindError::NoInfo`] is returned.
+    pub fn proc_range(&self) -> Result<Range<usize>, UnwindError> {
+        Ok(self.proc_info()?.range())
+    }
+
+    /// Retrieves the instruction pointer's offset from the start of the
+    /// procedure containing the cursor's current frame.
+    ///
+    /// This is the offset half of [`UnwindCursor::procedure_name`], but it's
+    /// computed from the procedure's unwind info rather than a symbol table,
+    /// so it also works on the V5.
+    ///
+    /// # Errors
+    ///
+    /// If `libunwind` was unable to find unwind info for the procedure,
+    /// [`UnwindError::NoInfo`] is returned. Errors from reading the
+    /// instruction pointer are described in [`UnwindCursor::register`].
+    pub fn offset_in_proc(&self) -> Result<u64, UnwindError> {
+        let start = self.proc_range()?.start;
+        let ip = self.register(registers::UNW_REG_IP)?;
+        Ok(ip.wrapping_sub(start) as u64)
+    }
+
+    /// Retrieves information about the procedure containing the cursor's
+    /// current frame.
+    ///
+    /// # Errors
+    ///
+    /// If `libunwind` was unable to find unwind info for the procedure,
+    /// [`UnwindError::NoInfo`] is returned.
+    pub fn proc_info(&self) -> Result<ProcInfo, UnwindError> {
+        let mut info = MaybeUninit::<unw_proc_info_t>::uninit();
+        let mut cursor = self.inner.borrow_mut();
+        // SAFETY: `unw_get_proc_info` initializes the info struct when it
+        // succeeds.
+        unsafe {
+            let code = unw_get_proc_info(&mut *cursor, info.as_mut_ptr());
+            trace::trace_event!(ProcInfo {
+                ip: trace::frame(&mut *cursor).ip,
+                code,
+            });
+            UnwindError::from_code(code)?;
+            Ok(ProcInfo::from_raw(info.assume_init()))
+        }
+    }
+
+    /// Retrieves the name of the procedure containing the cursor's current
+    /// frame, along with the instruction pointer's offset from the start of
+    /// that procedure.
+    ///
+    /// The name is written into `buf` (truncated if it doesn't fit) and
+    /// returned as a string slice borrowing from it.
+    ///
+    /// `libunwind` finds names using the dynamic linker's symbol table, so this
+    /// is only available when unwinding on a host. There is no symbol table to
+    /// consult on the V5 itself.
+    ///
+    /// Only the procedure's own name is found. If the frame is in code that
+    /// was inlined into it, the inlined functions aren't named, since that
+    /// takes DWARF line info that `libunwind` doesn't read.
+    ///
+    /// # Errors
+    ///
+    /// This function may return one of the following errors:
+    ///
+    /// - [`UnwindError::Unspecified`] if `libunwind` was unable to determine
+    ///   the name of the procedure
+    /// - [`UnwindError::BadValue`] if the procedure's name isn't valid UTF-8;
+    ///   use [`UnwindCursor::procedure_name_lossy`] to get it anyway
+    pub fn procedure_name<'a>(&self, buf: &'a mut [u8]) -> Result<(&'a str, u64), UnwindError> {
+        let (len, offset) = self.raw_procedure_name(buf)?;
+        let name = core::str::from_utf8(&buf[..len]).map_err(|_| UnwindError::BadValue)?;
+        Ok((name, offset))
+    }
+
+    /// Retrieves the name of the procedure containing the cursor's current
+    /// frame like [`UnwindCursor::procedure_name`], but replaces any invalid
+    /// UTF-8 in it with U+FFFD REPLACEMENT CHARACTER instead of failing.
+    ///
+    /// Mangled names are ASCII, so invalid UTF-8 means that the symbol table
+    /// is corrupt or the name is unusual. Either way, printing what there is
+    /// beats dropping the name, so this is what backtraces are printed with.
+    ///
+    /// Replacements are written into `buf`, and a replacement character
+    /// takes up to three bytes more than what it replaces, so a name with
+    /// invalid UTF-8 near the end of a full buffer is cut short.
+    ///
+    /// # Errors
+    ///
+    /// - [`UnwindError::Unspecified`] if `libunwind` was unable to determine
+    ///   the name of the procedure
+    pub fn procedure_name_lossy<'a>(
+        &self,
+        buf: &'a mut [u8],
+    ) -> Result<(&'a str, u64), UnwindError> {
+        let (len, offset) = self.raw_procedure_name(buf)?;
+        let len = replace_invalid_utf8(buf, len);
+        // `replace_invalid_utf8` leaves only valid UTF-8.
+        let name = core::str::from_utf8(&buf[..len]).map_err(|_| UnwindError::BadValue)?;
+        Ok((name, offset))
+    }
+
+    /// Returns a hash of the name of the procedure containing the cursor's
+    /// current frame, for grouping frames by function without keeping their
+    /// names around.
+    ///
+    /// The name is looked up as by [`UnwindCursor::procedure_name`], into a
+    /// 256-byte buffer on the stack, and hashed with 64-bit FNV-1a. The hash
+    /// doesn't depend on where code was loaded, so hashes from different runs
+    /// and builds can be compared. Different names can hash the same,
+    /// though rarely, and so do names that only differ after their first 255
+    /// bytes, which are cut short.
+    ///
+    /// Looking names up is slow and only works on hosts. Where that's too
+    /// slow, or on the V5, the [`start_ip`](ProcInfo::start_ip) of
+    /// [`UnwindCursor::proc_info`] is a cheaper key that's just as unique,
+    /// but only within one run of one build: it changes whenever the code
+    /// moves.
+    ///
+    /// # Errors
+    ///
+    /// - [`UnwindError::Unspecified`] if `libunwind` was unable to determine
+    ///   the name of the procedure
+    pub fn proc_name_hash(&self) -> Result<u64, UnwindError> {
+        const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+        const PRIME: u64 = 0x0100_0000_01b3;
+
+        let mut buf = [0; 256];
+        let (len, _) = self.raw_procedure_name(&mut buf)?;
+        Ok(buf[..len].iter().fold(OFFSET_BASIS, |hash, &byte| {
+            (hash ^ u64::from(byte)).wrapping_mul(PRIME)
+        }))
+    }
+
+    /// Writes the name of the current frame's procedure into `buf`, returning
+    /// the length of the name (without its terminator) and the offset.
+    fn raw_procedure_name(&self, buf: &mut [u8]) -> Result<(usize, u64), UnwindError> {
+        read_procedure_name(&mut self.inner.borrow_mut(), buf)
+    }
+
+    /// Writes the name of the current frame's procedure into `buf` like
+    /// [`UnwindCursor::raw_procedure_name`], but fails with
+    /// [`UnwindError::Busy`] instead of panicking if the cursor is in use.
+    pub(crate) fn try_raw_procedure_name(
+        &self,
+        buf: &mut [u8],
+    ) -> Result<(usize, u64), UnwindError> {
+        read_procedure_name(&mut *self.try_borrow_raw()?, buf)
+    }
+
+    /// Borrows the underlying `libunwind` object, failing with
+    /// [`UnwindError::Busy`] if it's already borrowed.
+    fn try_borrow_raw(&self) -> Result<RefMut<'_, unw_cursor_t>, UnwindError> {
+        self.inner.try_borrow_mut().map_err(|_| UnwindError::Busy)
+    }
+
     /// Returns the name of the given register as a string, or [`None`] if the
     /// register does not exist.
-    pub fn register_name(&self, register: unw_regnum_t) -> Option<&'static CStr> {
-        let unknown = c"unknown register";
-        // SAFETY: libunwind guarantees string is statically allocated and valid
-        let str = unsafe { CStr::from_ptr(unw_regname(&mut *self.inner.borrow_mut(), register)) };
-        if str == unknown {
-            None
-        } else {
-            Some(str)
-        }
+    ///
+    /// This doesn't depend on the cursor; see [`registers::register_name`].
+    pub const fn register_name(&self, register: unw_regnum_t) -> Option<&'static CStr> {
+        registers::register_name(register)
+    }
+
+    /// Runs the given closure with a pointer to the underlying `libunwind`
+    /// object, for calling `libunwind` functions that this crate doesn't wrap.
+    ///
+    /// The cursor is borrowed for the duration of the closure, so the pointer
+    /// must not be used after the closure returns.
+    ///
+    /// # Panics
+    ///
+    /// Calling this cursor's methods from within the closure will panic, since
+    /// the cursor is already borrowed.
+    pub fn with_raw<R>(&self, f: impl FnOnce(*mut unw_cursor_t) -> R) -> R {
+        f(&mut *self.inner.borrow_mut())
     }
 }
 
+/// Writes the name of the cursor's current procedure into `buf`, returning
+/// the length of the name (without its terminator) and the offset.
+fn read_procedure_name(
+    cursor: &mut unw_cursor_t,
+    buf: &mut [u8],
+) -> Result<(usize, u64), UnwindError> {
+    let mut offset = 0;
+    UnwindError::from_code(unsafe {
+        unw_get_proc_name(cursor, buf.as_mut_ptr().cast(), buf.len(), &mut offset)
+    })?;
+    let len = CStr::from_bytes_until_nul(buf)
+        .map_err(|_| UnwindError::BadValue)?
+        .to_bytes()
+        .len();
+    Ok((len, offset as u64))
+}
+
 impl Debug for UnwindCursor {
     fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         let mut s = f.debug_struct("UnwindCursor");
-        if let Ok(ip) = self.register(registers::UNW_REG_IP) {
+        if let Ok(ip) = self.try_register(registers::UNW_REG_IP) {
             s.field("ip", &(ip as *const ())).finish()
         } else {
             s.finish_non_exhaustive()
         }
     }
 }
+
+/// A saved position of an [`UnwindCursor`]; see
+/// [`UnwindCursor::checkpoint`].
+#[derive(Clone, Copy)]
+pub struct CursorCheckpoint {
+    raw: unw_cursor_t,
+}
+
+impl Debug for CursorCheckpoint {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("CursorCheckpoint").finish_non_exhaustive()
+    }
+}
+
+/// A step of [`capture_cursor`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CaptureStage {
+    /// Capturing the context with `unw_getcontext`.
+    Context,
+    /// Initializing the cursor with `unw_init_local`.
+    Cursor,
+}
+
+impl core::fmt::Display for CaptureStage {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str(match self {
+            Self::Context => "capture the context (unw_getcontext)",
+            Self::Cursor => "initialize the cursor (unw_init_local)",
+        })
+    }
+}
+
+/// Replaces each invalid UTF-8 sequence in `buf[..len]` with U+FFFD
+/// REPLACEMENT CHARACTER in place, returning the new length.
+///
+/// What's pushed past the end of `buf` is dropped, cutting the name at a
+/// character boundary.
+fn replace_invalid_utf8(buf: &mut [u8], mut len: usize) -> usize {
+    const REPLACEMENT: &[u8] = "\u{fffd}".as_bytes();
+    let mut start = 0;
+    let mut truncated = false;
+    loop {
+        let Err(error) = core::str::from_utf8(&buf[start..len]) else {
+            return len;
+        };
+        let bad = start + error.valid_up_to();
+        let bad_len = match error.error_len() {
+            Some(bad_len) => bad_len,
+            // An incomplete character at the end was cut off by truncation,
+            // rather than being invalid.
+            None if truncated => return bad,
+            None => len - bad,
+        };
+        if bad + REPLACEMENT.len() > buf.len() {
+            return bad;
+        }
+        // A replacement is never shorter than what it replaces.
+        let grow = REPLACEMENT.len() - bad_len;
+        truncated |= len + grow > buf.len();
+        let new_len = (len + grow).min(buf.len());
+        buf.copy_within(bad + bad_len..new_len - grow, bad + REPLACEMENT.len());
+        buf[bad..bad + REPLACEMENT.len()].copy_from_slice(REPLACEMENT);
+        start = bad + REPLACEMENT.len();
+        len = new_len;
+    }
+}
+
+/// An error returned by [`capture_cursor`], recording which step failed.
+#[derive(Debug, Snafu)]
+#[snafu(display("failed to {stage}: {source}"))]
+pub struct CaptureError {
+    /// The step that failed.
+    pub stage: CaptureStage,
+    /// The error that `libunwind` returned.
+    pub source: UnwindError,
+}
+
+impl From<CaptureError> for UnwindError {
+    fn from(error: CaptureError) -> Self {
+        error.source
+    }
+}
+
+/// Captures the current CPU state and initializes a cursor for unwinding from
+/// the calling function, in one call.
+///
+/// This is equivalent to calling [`UnwindContext::new`] and then
+/// [`UnwindCursor::from_context`], except that an error says which of the two
+/// failed.
+///
+/// # Errors
+///
+/// Returns a [`CaptureError`] tagged with the [`CaptureStage`] that failed.
+#[inline(always)] // Inlining keeps this function from appearing in backtraces
+pub fn capture_cursor() -> Result<UnwindCursor, CaptureError> {
+    let context = UnwindContext::new().map_err(|source| CaptureError {
+        stage: CaptureStage::Context,
+        source,
+    })?;
+    UnwindCursor::from_context(context).map_err(|source| CaptureError {
+        stage: CaptureStage::Cursor,
+        source,
+    })
+}
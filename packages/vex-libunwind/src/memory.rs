@@ -0,0 +1,37 @@
+//! Helpers for reading and writing words of raw target memory.
+//!
+//! Memory readers that work from a copy of the target's memory (for example, a
+//! stack dump sent from a V5 over serial) need to turn bytes into
+//! [`unw_word_t`]s in the target's byte order. The V5 is little-endian, so
+//! these helpers always use little-endian order, regardless of the byte order
+//! of the machine they run on.
+//!
+//! This crate doesn't wrap a remote unwinding API for these to plug into:
+//! LLVM `libunwind` is built for native-only unwinding, both in the bundled
+//! V5 library and in the host build. They're provided for custom memory
+//! readers in the meantime.
+
+use vex_libunwind_sys::unw_word_t;
+
+/// The size of an [`unw_word_t`] in bytes.
+pub const WORD_SIZE: usize = core::mem::size_of::<unw_word_t>();
+
+/// Reads a little-endian word from the start of `bytes`.
+///
+/// # Panics
+///
+/// Panics if `bytes` is shorter than [`WORD_SIZE`].
+pub fn read_word_le(bytes: &[u8]) -> unw_word_t {
+    let mut word = [0; WORD_SIZE];
+    word.copy_from_slice(&bytes[..WORD_SIZE]);
+    unw_word_t::from_le_bytes(word)
+}
+
+/// Writes `word` to the start of `bytes` in little-endian order.
+///
+/// # Panics
+///
+/// Panics if `bytes` is shorter than [`WORD_SIZE`].
+pub fn write_word_le(bytes: &mut [u8], word: unw_word_t) {
+    bytes[..WORD_SIZE].copy_from_slice(&word.to_le_bytes());
+}
@@ -0,0 +1,355 @@
+//! Descriptions of which addresses hold what.
+
+use core::{
+    cell::UnsafeCell,
+    ops::Range,
+    ptr,
+    sync::atomic::{AtomicPtr, AtomicU8, Ordering},
+};
+
+/// What a [`MemoryRegion`] holds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum RegionKind {
+    /// Program code, which is both readable and executable.
+    Code,
+    /// RAM holding data, such as statics or the heap.
+    Data,
+    /// RAM used as a stack.
+    Stack,
+    /// Memory-mapped peripherals, where reads can have side effects, so
+    /// nothing should read them just to look around.
+    Device,
+}
+
+impl RegionKind {
+    /// Checks whether memory of this kind can be read without side effects.
+    pub const fn is_readable(self) -> bool {
+        !matches!(self, Self::Device)
+    }
+
+    /// Checks whether memory of this kind holds code.
+    pub const fn is_executable(self) -> bool {
+        matches!(self, Self::Code)
+    }
+}
+
+/// A named range of addresses in a [`MemoryMap`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct MemoryRegion {
+    /// The addresses in the region. The end is exclusive, so regions that
+    /// share a boundary address don't overlap.
+    pub range: Range<usize>,
+    /// What the region holds.
+    pub kind: RegionKind,
+    /// A name for the region, for printing.
+    pub name: &'static str,
+}
+
+impl MemoryRegion {
+    const EMPTY: Self = Self {
+        range: 0..0,
+        kind: RegionKind::Data,
+        name: "",
+    };
+}
+
+/// The process-wide map set with [`MemoryMap::set_global`], or null.
+static GLOBAL: AtomicPtr<MemoryMap<'static>> = AtomicPtr::new(ptr::null_mut());
+
+/// Where VEXos loads a program's cold image.
+const COLD_IMAGE_BASE: usize = 0x0380_0000;
+/// Where VEXos loads a program's hot image, if it has one.
+const HOT_IMAGE_BASE: usize = 0x0780_0000;
+/// The end of the memory VEXos gives to user programs.
+const USER_MEMORY_END: usize = 0x0800_0000;
+
+/// The regions of [`MemoryMap::v5_default`].
+const V5_REGIONS: [MemoryRegion; 2] = [
+    MemoryRegion {
+        range: COLD_IMAGE_BASE..HOT_IMAGE_BASE,
+        kind: RegionKind::Data,
+        name: "cold image",
+    },
+    MemoryRegion {
+        range: HOT_IMAGE_BASE..USER_MEMORY_END,
+        kind: RegionKind::Code,
+        name: "hot image",
+    },
+];
+
+/// The linker symbols read by [`MemoryMap::from_linker_symbols`]. They're
+/// weak, so a program that doesn't define them still links, with each missing
+/// symbol's address reading as null.
+mod symbols {
+    extern "C" {
+        #[linkage = "extern_weak"]
+        pub(super) static __text_start: *const u8;
+        #[linkage = "extern_weak"]
+        pub(super) static __text_end: *const u8;
+        #[linkage = "extern_weak"]
+        pub(super) static __heap_start: *const u8;
+        #[linkage = "extern_weak"]
+        pub(super) static __stack_top: *const u8;
+    }
+
+    /// Returns the address range between two symbols, if both are defined
+    /// and in order.
+    pub(super) fn range(start: *const u8, end: *const u8) -> Option<core::ops::Range<usize>> {
+        let (start, end) = (start as usize, end as usize);
+        (start != 0 && start <= end).then_some(start..end)
+    }
+}
+
+/// The regions of [`MemoryMap::from_linker_symbols`], filled in the first
+/// time it's called.
+struct LinkerRegions(UnsafeCell<[MemoryRegion; 3]>);
+
+// SAFETY: The regions are only written once, before `LINKER_STATE` says
+// they're ready, and never after.
+unsafe impl Sync for LinkerRegions {}
+
+const UNFILLED: u8 = 0;
+const FILLING: u8 = 1;
+const FILLED: u8 = 2;
+/// Set to `FILLED` once `LINKER_REGIONS` is filled in. A map that can't be
+/// made from the symbols is never stored, leaving this `UNFILLED`.
+static LINKER_STATE: AtomicU8 = AtomicU8::new(UNFILLED);
+static LINKER_REGIONS: LinkerRegions = LinkerRegions(UnsafeCell::new([
+    MemoryRegion::EMPTY,
+    MemoryRegion::EMPTY,
+    MemoryRegion::EMPTY,
+]));
+
+/// A description of which address ranges of a program's memory hold code,
+/// data, and stacks.
+///
+/// The map borrows its regions from a slice, so it never allocates and is
+/// usually built from a `const` array:
+///
+/// ```
+/// # use vex_libunwind::{MemoryMap, MemoryRegion, RegionKind};
+/// const REGIONS: [MemoryRegion; 2] = [
+///     MemoryRegion { range: 0x0380_0000..0x0400_0000, kind: RegionKind::Code, name: "user" },
+///     MemoryRegion { range: 0x0400_0000..0x0800_0000, kind: RegionKind::Data, name: "heap" },
+/// ];
+/// const MAP: MemoryMap<'static> = MemoryMap::new(&REGIONS);
+///
+/// assert!(MAP.is_executable(0x0380_0010));
+/// assert!(!MAP.is_executable(0x0400_0000));
+/// ```
+///
+/// Regions may overlap. Where they do, the region that starts last wins, so a
+/// stack can be described inside the RAM region that holds it. Of regions
+/// that start at the same address, the one later in the slice wins.
+///
+/// Functions that need a map take one as a parameter. For integrations that
+/// want to describe the program's memory once for everyone, a process-wide
+/// map can be set with [`MemoryMap::set_global`]. Most programs don't need to
+/// write a map at all, since [`MemoryMap::global`] falls back to one built
+/// from the program's linker symbols.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct MemoryMap<'a> {
+    regions: &'a [MemoryRegion],
+}
+
+impl<'a> MemoryMap<'a> {
+    /// Creates a map of the given regions.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the regions aren't sorted by the start of their ranges. In a
+    /// `const`, this is a compile-time error.
+    pub const fn new(regions: &'a [MemoryRegion]) -> Self {
+        let mut i = 1;
+        while i < regions.len() {
+            assert!(
+                regions[i - 1].range.start <= regions[i].range.start,
+                "memory regions must be sorted by start address"
+            );
+            i += 1;
+        }
+        Self { regions }
+    }
+
+    /// Returns the map's regions, sorted by start address.
+    pub const fn regions(&self) -> &'a [MemoryRegion] {
+        self.regions
+    }
+
+    /// Returns the region containing the given address, or [`None`] if the
+    /// address isn't in any region.
+    pub fn classify(&self, addr: usize) -> Option<&'a MemoryRegion> {
+        // Only regions that start at or before the address can contain it,
+        // and the latest-starting one that does wins.
+        let candidates = self
+            .regions
+            .partition_point(|region| region.range.start <= addr);
+        self.regions[..candidates]
+            .iter()
+            .rev()
+            .find(|region| region.range.contains(&addr))
+    }
+
+    /// Checks whether the given address holds code.
+    pub fn is_executable(&self, addr: usize) -> bool {
+        self.classify(addr)
+            .is_some_and(|region| region.kind.is_executable())
+    }
+
+    /// Checks whether the given address is in a region that can be read
+    /// without side effects.
+    pub fn is_readable(&self, addr: usize) -> bool {
+        self.classify(addr)
+            .is_some_and(|region| region.kind.is_readable())
+    }
+}
+
+impl MemoryMap<'static> {
+    /// Returns a map of the memory VEXos gives to user programs, for when
+    /// nothing more is known about the program's layout.
+    ///
+    /// The map has two regions: the cold image, loaded at `0x0380_0000`, and
+    /// the hot image region from `0x0780_0000` up to the end of user memory
+    /// at `0x0800_0000`. The cold image holds the program's code along with
+    /// its statics, heap, and stacks, but where one ends and the next begins
+    /// depends on how it was linked, so the region is only known to be RAM:
+    /// it's [`RegionKind::Data`], and none of it counts as code. The hot image
+    /// region is [`RegionKind::Code`].
+    pub const fn v5_default() -> Self {
+        Self::new(&V5_REGIONS)
+    }
+
+    /// Returns a map built from the program's linker symbols, falling back
+    /// to [`MemoryMap::v5_default`] if they're missing.
+    ///
+    /// Four symbols are read, which the vexide linker script defines:
+    ///
+    /// | Symbol         | Used as                                   |
+    /// |----------------|-------------------------------------------|
+    /// | `__text_start` | The start of the [`RegionKind::Code`] region |
+    /// | `__text_end`   | The end of the [`RegionKind::Code`] region   |
+    /// | `__heap_start` | The start of the heap and stack's [`RegionKind::Data`] region |
+    /// | `__stack_top`  | The end of the heap and stack's [`RegionKind::Data`] region   |
+    ///
+    /// The rest of user memory, from the cold image base to the end of user
+    /// memory, is a [`RegionKind::Data`] region beneath these, covering the
+    /// program's statics. The hot image region is left out, since a hot
+    /// image's code is recorded with [`HotImage`](crate::hot_image::HotImage)
+    /// instead.
+    ///
+    /// The symbols are weak references, so a program that doesn't define
+    /// them still links. If any of them is missing, or they aren't in order
+    /// within user memory, the default map is returned. The map is built the
+    /// first time this is called and reused after that.
+    pub fn from_linker_symbols() -> Self {
+        if LINKER_STATE.load(Ordering::Acquire) == FILLED {
+            // SAFETY: The regions were filled in and won't be written again.
+            return Self::new(unsafe { &*LINKER_REGIONS.0.get() });
+        }
+        let Some(regions) = linker_regions() else {
+            return Self::v5_default();
+        };
+        // If another caller is filling the regions in (for example, code
+        // that this call interrupted), don't wait for it. The default map
+        // will do until it's done.
+        if LINKER_STATE
+            .compare_exchange(UNFILLED, FILLING, Ordering::Acquire, Ordering::Relaxed)
+            .is_err()
+        {
+            return Self::v5_default();
+        }
+        // SAFETY: The `FILLING` state keeps anyone else from touching the
+        // regions until they're marked `FILLED`.
+        let stored = unsafe {
+            *LINKER_REGIONS.0.get() = regions;
+            &*LINKER_REGIONS.0.get()
+        };
+        LINKER_STATE.store(FILLED, Ordering::Release);
+        Self::new(stored)
+    }
+
+    /// Sets the process-wide map returned by [`MemoryMap::global`].
+    ///
+    /// The map can only be set once, so that code holding the previous map
+    /// never sees it change. Returns `false` without replacing it if it was
+    /// already set.
+    pub fn set_global(map: &'static MemoryMap<'static>) -> bool {
+        GLOBAL
+            .compare_exchange(
+                ptr::null_mut(),
+                ptr::from_ref(map).cast_mut(),
+                Ordering::Release,
+                Ordering::Relaxed,
+            )
+            .is_ok()
+    }
+
+    /// Returns the process-wide map set with [`MemoryMap::set_global`], or
+    /// [`MemoryMap::from_linker_symbols`] if none has been set.
+    pub fn global() -> Self {
+        // SAFETY: The pointer is either null or came from a `&'static` in
+        // `set_global`, and is never written through.
+        match unsafe { GLOBAL.load(Ordering::Acquire).as_ref() } {
+            Some(map) => *map,
+            None => Self::from_linker_symbols(),
+        }
+    }
+}
+
+/// Returns the addresses of the program's code, between the `__text_start`
+/// and `__text_end` linker symbols, or [`None`] if the program doesn't define
+/// them.
+pub fn text_range() -> Option<Range<usize>> {
+    // SAFETY: Reading a weak symbol's address is always allowed; it's null
+    // if the symbol is missing.
+    unsafe { symbols::range(symbols::__text_start, symbols::__text_end) }
+}
+
+/// Checks whether an instruction address is in the program's code.
+///
+/// This is the check to make before trusting an address that might be
+/// garbage, such as a return address read off the stack. Bit 0 of the
+/// address is ignored, since it only marks Thumb code on ARM and isn't part
+/// of the instruction's location.
+///
+/// The address is looked up in [`MemoryMap::global`], so a map set with
+/// [`MemoryMap::set_global`] decides, and otherwise the text region from the
+/// program's linker symbols (see [`MemoryMap::from_linker_symbols`]) does.
+/// If the program doesn't define the symbols either, only the hot image
+/// region counts as code, as in [`MemoryMap::v5_default`], so addresses in
+/// the cold image are never trusted.
+pub fn is_in_text(ip: usize) -> bool {
+    MemoryMap::global().is_executable(ip & !1)
+}
+
+/// Builds the regions of [`MemoryMap::from_linker_symbols`], if the symbols
+/// describe a sensible layout.
+fn linker_regions() -> Option<[MemoryRegion; 3]> {
+    use symbols::*;
+
+    let text = text_range()?;
+    // SAFETY: As in `text_range`.
+    let ram = unsafe { range(__heap_start, __stack_top)? };
+    let in_order = COLD_IMAGE_BASE <= text.start && text.end <= ram.start;
+    if !in_order || ram.end > USER_MEMORY_END {
+        return None;
+    }
+    Some([
+        MemoryRegion {
+            range: COLD_IMAGE_BASE..USER_MEMORY_END,
+            kind: RegionKind::Data,
+            name: "user memory",
+        },
+        MemoryRegion {
+            range: text,
+            kind: RegionKind::Code,
+            name: "text",
+        },
+        MemoryRegion {
+            range: ram,
+            kind: RegionKind::Data,
+            name: "heap and stack",
+        },
+    ])
+}
@@ -0,0 +1,172 @@
+//! Keeping what a panic said alongside where it happened.
+
+use core::{
+    fmt::{self, Write},
+    panic::PanicInfo,
+};
+
+/// How many bytes of a panic's message a [`PanicRecord`] keeps.
+pub const PANIC_MESSAGE_BYTES: usize = 192;
+/// How many bytes of a panic's file path a [`PanicRecord`] keeps.
+pub const PANIC_FILE_BYTES: usize = 64;
+
+/// Written where part of a message or path was cut off.
+const ELLIPSIS: &str = "...";
+
+/// A panic's message and location, copied out of its [`PanicInfo`] so they
+/// can be kept with a backtrace after the panic handler's arguments are
+/// gone.
+///
+/// Both are kept in fixed-size buffers, so making a record never allocates.
+/// A message longer than [`PANIC_MESSAGE_BYTES`] keeps its beginning, and a
+/// path longer than [`PANIC_FILE_BYTES`] keeps its end, which has the file's
+/// name. Either being cut off is recorded, and printing the record marks the
+/// cut with `...`.
+///
+/// ```ignore
+/// #[panic_handler]
+/// fn panic(info: &PanicInfo) -> ! {
+///     let record = PanicRecord::from_panic(info);
+///     let backtrace = Backtrace::<32>::capture();
+///     println!("{record}\n{backtrace}");
+///     loop {}
+/// }
+/// ```
+#[derive(Clone)]
+pub struct PanicRecord {
+    message: Truncated<PANIC_MESSAGE_BYTES>,
+    file: Truncated<PANIC_FILE_BYTES>,
+    /// The line and column, if the panic had a location.
+    line_column: Option<(u32, u32)>,
+}
+
+impl PanicRecord {
+    /// Copies the message and location of a panic.
+    pub fn from_panic(info: &PanicInfo<'_>) -> Self {
+        let mut message = Truncated::new();
+        if let Some(arguments) = info.message() {
+            // The writer never fails; it stops copying when it's full.
+            let _ = message.write_fmt(*arguments);
+        } else if let Some(payload) = info.payload().downcast_ref::<&str>() {
+            let _ = message.write_str(payload);
+        }
+
+        let mut file = Truncated::new();
+        let line_column = info.location().map(|location| {
+            file.copy_tail(location.file());
+            (location.line(), location.column())
+        });
+
+        Self {
+            message,
+            file,
+            line_column,
+        }
+    }
+
+    /// Returns as much of the panic's message as fit.
+    pub fn message(&self) -> &str {
+        self.message.as_str()
+    }
+
+    /// Checks whether the end of the message was cut off.
+    pub const fn message_truncated(&self) -> bool {
+        self.message.truncated
+    }
+
+    /// Returns the file, line, and column where the panic happened, if the
+    /// panic says.
+    ///
+    /// The file is as much of the end of the path as fit.
+    pub fn location(&self) -> Option<(&str, u32, u32)> {
+        let (line, column) = self.line_column?;
+        Some((self.file.as_str(), line, column))
+    }
+
+    /// Checks whether the start of the location's file path was cut off.
+    pub const fn file_truncated(&self) -> bool {
+        self.file.truncated
+    }
+}
+
+impl fmt::Debug for PanicRecord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PanicRecord")
+            .field("message", &self.message())
+            .field("message_truncated", &self.message_truncated())
+            .field("location", &self.location())
+            .field("file_truncated", &self.file_truncated())
+            .finish()
+    }
+}
+
+/// Prints the record the way Rust prints a panic, as `panicked at
+/// {file}:{line}:{column}:` followed by the message on the next line.
+impl fmt::Display for PanicRecord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("panicked")?;
+        if let Some((file, line, column)) = self.location() {
+            f.write_str(" at ")?;
+            if self.file_truncated() {
+                f.write_str(ELLIPSIS)?;
+            }
+            write!(f, "{file}:{line}:{column}")?;
+        }
+        writeln!(f, ":")?;
+        f.write_str(self.message())?;
+        if self.message_truncated() {
+            f.write_str(ELLIPSIS)?;
+        }
+        Ok(())
+    }
+}
+
+/// A string in a fixed-size buffer that remembers whether anything didn't fit.
+#[derive(Clone)]
+struct Truncated<const N: usize> {
+    bytes: [u8; N],
+    len: usize,
+    truncated: bool,
+}
+
+impl<const N: usize> Truncated<N> {
+    const fn new() -> Self {
+        Self {
+            bytes: [0; N],
+            len: 0,
+            truncated: false,
+        }
+    }
+
+    fn as_str(&self) -> &str {
+        // Only whole characters are ever copied in.
+        core::str::from_utf8(&self.bytes[..self.len]).unwrap_or_default()
+    }
+
+    /// Replaces the contents with as much of the end of `s` as fits.
+    fn copy_tail(&mut self, s: &str) {
+        let mut start = s.len().saturating_sub(N);
+        while !s.is_char_boundary(start) {
+            start += 1;
+        }
+        self.len = s.len() - start;
+        self.bytes[..self.len].copy_from_slice(&s.as_bytes()[start..]);
+        self.truncated = start > 0;
+    }
+}
+
+impl<const N: usize> Write for Truncated<N> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        if self.truncated {
+            return Ok(());
+        }
+        let mut count = s.len().min(N - self.len);
+        while !s.is_char_boundary(count) {
+            count -= 1;
+        }
+        self.bytes[self.len..][..count].copy_from_slice(&s.as_bytes()[..count]);
+        self.len += count;
+        self.truncated = count < s.len();
+        Ok(())
+    }
+}
@@ -0,0 +1,71 @@
+//! Information about the procedure containing a frame.
+
+use core::ops::Range;
+
+use vex_libunwind_sys::unw_proc_info_t;
+
+/// Information about a procedure, as found in its unwind info.
+///
+/// A procedure here is a piece of machine code, so the functions inlined into
+/// it are part of its range and have no entries of their own.
+#[derive(Debug, Clone, Copy)]
+pub struct ProcInfo {
+    raw: unw_proc_info_t,
+}
+
+impl ProcInfo {
+    pub(crate) const fn from_raw(raw: unw_proc_info_t) -> Self {
+        Self { raw }
+    }
+
+    /// Returns the address of the procedure's first instruction.
+    pub const fn start_ip(&self) -> usize {
+        self.raw.start_ip
+    }
+
+    /// Returns the address just after the procedure's last instruction.
+    pub const fn end_ip(&self) -> usize {
+        self.raw.end_ip
+    }
+
+    /// Returns the procedure's address range.
+    pub const fn range(&self) -> Range<usize> {
+        self.raw.start_ip..self.raw.end_ip
+    }
+
+    /// Checks whether the given address is inside the procedure.
+    pub const fn contains(&self, ip: usize) -> bool {
+        self.raw.start_ip <= ip && ip < self.raw.end_ip
+    }
+
+    /// Returns the address of the procedure's language-specific data area, if
+    /// it has one.
+    pub const fn lsda(&self) -> Option<usize> {
+        match self.raw.lsda {
+            0 => None,
+            lsda => Some(lsda),
+        }
+    }
+
+    /// Returns the address of the procedure's personality routine, if it has
+    /// one.
+    pub const fn handler(&self) -> Option<usize> {
+        match self.raw.handler {
+            0 => None,
+            handler => Some(handler),
+        }
+    }
+
+    /// Returns the underlying `libunwind` struct, for fields that don't have
+    /// an accessor.
+    ///
+    /// On the V5, `libunwind` finds procedures in the ARM exception index
+    /// table, and fills in only `start_ip`, `end_ip`, `lsda`, `handler`,
+    /// `unwind_info` (the address of the procedure's exception table entry),
+    /// and `flags` (bit 0 is set for a single-word entry and bit 1 for 32-bit
+    /// scopes). The rest are zero. On hosts, the fields are filled in from
+    /// DWARF unwind info as documented on [`unw_proc_info_t`].
+    pub const fn as_raw(&self) -> &unw_proc_info_t {
+        &self.raw
+    }
+}
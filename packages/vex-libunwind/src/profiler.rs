@@ -0,0 +1,669 @@
+//! Finding out where a program spends its time by sampling its call chain.
+//!
+//! A [`SamplingProfiler`] records the instruction pointers of the call chain
+//! each time [`sample`](SamplingProfiler::sample) is called. It doesn't own a
+//! timer: call `sample` from a timer interrupt (or a high-priority task that
+//! wakes up periodically), and the chains it records show what the program
+//! was doing at each tick. Samples are kept in a buffer of words provided by
+//! the caller, so nothing is allocated.
+//!
+//! ```
+//! # use core::sync::atomic::AtomicUsize;
+//! # use vex_libunwind::profiler::*;
+//! const ZERO: AtomicUsize = AtomicUsize::new(0);
+//! static BUFFER: [AtomicUsize; 4096] = [ZERO; 4096];
+//! static PROFILER: SamplingProfiler<'static> =
+//!     SamplingProfiler::new(ProfilerConfig::new().skip_frames(1), &BUFFER);
+//!
+//! fn on_timer_tick() {
+//!     PROFILER.sample();
+//! }
+//!
+//! PROFILER.start();
+//! on_timer_tick();
+//! let profile = PROFILER.stop();
+//!
+//! let mut hottest = [(0, 0); 10];
+//! let len = profile.hottest(&mut hottest);
+//! for &(ip, count) in &hottest[..len] {
+//!     println!("{ip:#x}: {count} of {} samples", profile.sample_count());
+//! }
+//! ```
+
+use core::{
+    fmt::{self, Write},
+    ops::Range,
+    sync::atomic::{AtomicBool, AtomicUsize, Ordering},
+};
+
+#[cfg(not(feature = "disabled"))]
+use crate::{capture_enabled, guard::ReentrancyGuard, UnwindContext, UnwindCursor, WalkConfig};
+use crate::{sink::write_chunked, Symbolize};
+
+/// Options controlling what a [`SamplingProfiler`] records for each sample.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ProfilerConfig {
+    skip: usize,
+    max_depth: usize,
+    proc_starts: bool,
+}
+
+impl ProfilerConfig {
+    /// Creates a configuration that records up to 16 frames of each sample,
+    /// starting with the function that called
+    /// [`sample`](SamplingProfiler::sample).
+    pub const fn new() -> Self {
+        Self {
+            skip: 0,
+            max_depth: 16,
+            proc_starts: false,
+        }
+    }
+
+    /// Leaves out the `count` innermost frames of each sample, such as the
+    /// timer callback and the interrupt handler that called it, so that each
+    /// sample starts in the code that was interrupted.
+    pub const fn skip_frames(mut self, count: usize) -> Self {
+        self.skip = count;
+        self
+    }
+
+    /// Records at most `depth` frames of each sample, after the skipped ones.
+    ///
+    /// Each sample steps through at most this many frames (plus the skipped
+    /// ones), which bounds how long [`sample`](SamplingProfiler::sample)
+    /// takes.
+    pub const fn max_depth(mut self, depth: usize) -> Self {
+        self.max_depth = depth;
+        self
+    }
+
+    /// Records the start address of each frame's procedure along with its
+    /// instruction pointer, when `enabled` is `true`, so that samples can be
+    /// grouped by function with [`Profile::functions`].
+    ///
+    /// The cursor has already found each procedure's unwind info to step
+    /// through it, so this costs little time, but it takes an extra word of
+    /// the buffer per frame.
+    pub const fn record_proc_starts(mut self, enabled: bool) -> Self {
+        self.proc_starts = enabled;
+        self
+    }
+
+    /// The number of words each frame takes.
+    const fn stride(&self) -> usize {
+        1 + self.proc_starts as usize
+    }
+}
+
+impl Default for ProfilerConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A profiler that records the call chain each time it's asked to, for
+/// finding out which code runs most; see the [module docs](self).
+///
+/// Each sample takes one word of the buffer for its length and one for each
+/// frame (or two, with
+/// [`record_proc_starts`](ProfilerConfig::record_proc_starts)). Samples that
+/// don't fit once the buffer is full are counted as [dropped](Profile::dropped)
+/// rather than overwriting older ones.
+#[derive(Debug)]
+pub struct SamplingProfiler<'a> {
+    #[cfg(not(feature = "disabled"))]
+    config: ProfilerConfig,
+    /// The number of words each frame takes.
+    stride: usize,
+    buffer: &'a [AtomicUsize],
+    /// The number of words of the buffer holding finished samples.
+    used: AtomicUsize,
+    samples: AtomicUsize,
+    dropped: AtomicUsize,
+    running: AtomicBool,
+    /// Set while a sample is being recorded.
+    #[cfg(not(feature = "disabled"))]
+    sampling: AtomicBool,
+}
+
+impl<'a> SamplingProfiler<'a> {
+    /// Creates a stopped profiler that keeps its samples in `buffer`.
+    pub const fn new(config: ProfilerConfig, buffer: &'a [AtomicUsize]) -> Self {
+        Self {
+            #[cfg(not(feature = "disabled"))]
+            config,
+            stride: config.stride(),
+            buffer,
+            used: AtomicUsize::new(0),
+            samples: AtomicUsize::new(0),
+            dropped: AtomicUsize::new(0),
+            running: AtomicBool::new(false),
+            #[cfg(not(feature = "disabled"))]
+            sampling: AtomicBool::new(false),
+        }
+    }
+
+    /// Starts recording samples, adding to any recorded before.
+    pub fn start(&self) {
+        self.running.store(true, Ordering::Release);
+    }
+
+    /// Stops recording samples and returns what was recorded.
+    pub fn stop(&self) -> Profile<'_> {
+        self.running.store(false, Ordering::Release);
+        self.report()
+    }
+
+    /// Returns what has been recorded so far, without stopping.
+    ///
+    /// A sample being recorded while this is called isn't included.
+    pub fn report(&self) -> Profile<'_> {
+        let used = self.used.load(Ordering::Acquire);
+        Profile {
+            words: &self.buffer[..used],
+            stride: self.stride,
+            samples: self.samples.load(Ordering::Relaxed),
+            dropped: self.dropped.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Records the call chain of the calling function, returning whether a
+    /// sample was recorded.
+    ///
+    /// This is safe to call from an interrupt handler. It never waits: if
+    /// it interrupted another call to `sample`, or a backtrace capture or
+    /// print, it gives up and counts the sample as dropped, as it does when
+    /// the buffer is full. Nothing is allocated, and the walk stops after
+    /// the configured [`max_depth`](ProfilerConfig::max_depth), or sooner
+    /// where a corrupted call chain loops back on itself.
+    ///
+    /// Nothing is recorded while the profiler is stopped, while capture is
+    /// turned off with [`set_capture_enabled`](crate::set_capture_enabled),
+    /// or with the `disabled` feature.
+    #[cfg(not(feature = "disabled"))]
+    #[inline(always)] // Inlining keeps this function from appearing in samples
+    pub fn sample(&self) -> bool {
+        if !self.running.load(Ordering::Acquire) || !capture_enabled() {
+            return false;
+        }
+        if self.sampling.swap(true, Ordering::Acquire) {
+            self.dropped.fetch_add(1, Ordering::Relaxed);
+            return false;
+        }
+        let guard = ReentrancyGuard::enter();
+        let recorded =
+            guard.is_some() && UnwindContext::new().is_ok_and(|context| self.record(context));
+        drop(guard);
+        self.sampling.store(false, Ordering::Release);
+        if !recorded {
+            self.dropped.fetch_add(1, Ordering::Relaxed);
+        }
+        recorded
+    }
+
+    /// Records nothing, since the `disabled` feature is enabled.
+    #[cfg(feature = "disabled")]
+    #[inline(always)]
+    #[allow(clippy::missing_const_for_fn)] // Not `const`, to match the real `sample`
+    pub fn sample(&self) -> bool {
+        false
+    }
+
+    /// Walks the context's call chain into the free part of the buffer.
+    #[cfg(not(feature = "disabled"))]
+    fn record(&self, context: UnwindContext) -> bool {
+        let Ok(mut cursor) = UnwindCursor::from_context(context) else {
+            return false;
+        };
+        let mut frames = cursor.frames(WalkConfig::new());
+        for _ in 0..self.config.skip {
+            if !matches!(frames.next(), Some(Ok(_))) {
+                return false;
+            }
+        }
+
+        let start = self.used.load(Ordering::Relaxed);
+        let free = self.buffer.len() - start;
+        let depth = self
+            .config
+            .max_depth
+            .min(free.saturating_sub(1) / self.stride);
+        let mut len = 0;
+        while len < depth {
+            let Some(Ok(frame)) = frames.next() else {
+                break;
+            };
+            let word = start + 1 + len * self.stride;
+            self.buffer[word].store(frame.ip, Ordering::Relaxed);
+            if self.config.proc_starts {
+                let proc_start = frames.cursor().proc_range().map_or(0, |range| range.start);
+                self.buffer[word + 1].store(proc_start, Ordering::Relaxed);
+            }
+            len += 1;
+        }
+        if len == 0 {
+            return false;
+        }
+        self.buffer[start].store(len, Ordering::Relaxed);
+        self.used
+            .store(start + 1 + len * self.stride, Ordering::Release);
+        self.samples.fetch_add(1, Ordering::Relaxed);
+        true
+    }
+}
+
+/// The samples recorded by a [`SamplingProfiler`], from
+/// [`stop`](SamplingProfiler::stop) or [`report`](SamplingProfiler::report).
+#[derive(Debug, Clone, Copy)]
+pub struct Profile<'a> {
+    words: &'a [AtomicUsize],
+    stride: usize,
+    samples: usize,
+    dropped: usize,
+}
+
+impl<'a> Profile<'a> {
+    /// Returns the number of samples recorded.
+    pub const fn sample_count(&self) -> usize {
+        self.samples
+    }
+
+    /// Returns the number of samples that couldn't be recorded, because the
+    /// buffer was full, capture was already in progress, or unwinding
+    /// failed.
+    pub const fn dropped(&self) -> usize {
+        self.dropped
+    }
+
+    /// Returns an iterator over the recorded samples, oldest first.
+    pub const fn samples(&self) -> Samples<'a> {
+        Samples {
+            words: self.words,
+            stride: self.stride,
+        }
+    }
+
+    /// Returns the number of samples with a frame inside `range`: how often
+    /// the code there was running or waiting on something it called. With a
+    /// procedure's range, this is its inclusive time.
+    pub fn count_in(&self, range: Range<usize>) -> usize {
+        self.samples()
+            .filter(|sample| sample.ips().any(|ip| range.contains(&ip)))
+            .count()
+    }
+
+    /// Returns the number of samples whose innermost frame is inside
+    /// `range`: how often the code there was itself running. With a
+    /// procedure's range, this is its self time.
+    pub fn self_count_in(&self, range: Range<usize>) -> usize {
+        self.samples()
+            .filter(|sample| range.contains(&sample.leaf()))
+            .count()
+    }
+
+    /// Counts how many samples each innermost instruction pointer appears in,
+    /// writing `(ip, count)` pairs to `out`, most common first, and
+    /// returning the number of pairs written.
+    ///
+    /// Once `out` is full, instruction pointers that aren't in it yet are
+    /// left out, so with a small `out`, the commonest pointers can be missed
+    /// if they first appear late.
+    pub fn hottest(&self, out: &mut [(usize, usize)]) -> usize {
+        let mut len = 0;
+        for sample in self.samples() {
+            let leaf = sample.leaf();
+            if let Some((_, count)) = out[..len].iter_mut().find(|(ip, _)| *ip == leaf) {
+                *count += 1;
+            } else if let Some(pair) = out.get_mut(len) {
+                *pair = (leaf, 1);
+                len += 1;
+            }
+        }
+        out[..len].sort_unstable_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        len
+    }
+
+    /// Counts the samples each function appears in, into a table of up to
+    /// `N` functions.
+    ///
+    /// Frames are grouped by their procedure's start address, if it was
+    /// [recorded](ProfilerConfig::record_proc_starts), and otherwise by
+    /// their instruction pointer. Each function's self count is the number
+    /// of samples whose innermost frame is in it, and its total count is the
+    /// number of samples it's anywhere in, counting recursive calls once.
+    ///
+    /// Once the table is full, functions that aren't in it yet are counted
+    /// in its [other](FunctionTable::other) bucket, so with a small table,
+    /// busy functions that first appear late can be missed. The rows are
+    /// sorted by self count, then total count, highest first.
+    pub fn functions<const N: usize>(&self) -> FunctionTable<N> {
+        let mut table = FunctionTable::new(self.samples);
+        for sample in self.samples() {
+            for (depth, function) in sample.function_keys().enumerate() {
+                if !sample
+                    .function_keys()
+                    .take(depth)
+                    .any(|key| key == function)
+                {
+                    table.add(function, depth == 0);
+                }
+            }
+        }
+        table.rows[..table.len].sort_unstable_by(|a, b| {
+            (b.self_samples, b.total_samples)
+                .cmp(&(a.self_samples, a.total_samples))
+                .then(a.function.cmp(&b.function))
+        });
+        table
+    }
+
+    /// Returns an object that prints the samples in the collapsed-stack
+    /// ("folded") format read by `inferno` and `flamegraph.pl`, naming frames
+    /// with `resolver`.
+    ///
+    /// Each distinct call chain is printed once, in the order it was first
+    /// sampled, as its frames from the outermost to the innermost separated
+    /// by `;`, then a space and the number of samples with that chain:
+    ///
+    /// ```text
+    /// main;opcontrol;drive_to 42
+    /// main;0x3801234 7
+    /// ```
+    ///
+    /// Frames the resolver doesn't know are printed as hex addresses, so
+    /// with [`RawAddresses`](crate::RawAddresses), every frame is left for a
+    /// host tool to symbolize. Any `;` in a name is printed as `:`, since it
+    /// would otherwise split the frame in two.
+    ///
+    /// Finding the distinct chains takes time quadratic in the number of
+    /// samples, which is meant for after profiling has stopped rather than
+    /// inside a timer interrupt.
+    pub const fn folded<'r, S: Symbolize>(&self, resolver: &'r S) -> Folded<'a, 'r, S> {
+        Folded {
+            profile: *self,
+            resolver,
+        }
+    }
+}
+
+/// A [`Profile`] printed in the collapsed-stack format; see
+/// [`Profile::folded`].
+#[derive(Debug)]
+pub struct Folded<'a, 'r, S> {
+    profile: Profile<'a>,
+    resolver: &'r S,
+}
+
+impl<S: Symbolize> Folded<'_, '_, S> {
+    /// Prints the samples to a transport that takes bytes, passing along the
+    /// transport's errors; see
+    /// [`Backtrace::write_bytes`](crate::Backtrace::write_bytes).
+    ///
+    /// # Errors
+    ///
+    /// Returns the first error returned by `sink`.
+    pub fn write_bytes<E>(&self, sink: impl FnMut(&[u8]) -> Result<(), E>) -> Result<(), E> {
+        write_chunked(self, sink)
+    }
+
+    fn write_frame(&self, w: &mut impl Write, ip: usize) -> fmt::Result {
+        let Some(symbol) = self.resolver.symbolize(ip) else {
+            return write!(w, "{ip:#x}");
+        };
+        symbol
+            .name
+            .chars()
+            .try_for_each(|c| w.write_char(if c == ';' { ':' } else { c }))
+    }
+}
+
+impl<S: Symbolize> fmt::Display for Folded<'_, '_, S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let samples = self.profile.samples();
+        for (index, sample) in samples.clone().enumerate() {
+            if samples.clone().take(index).any(|earlier| earlier == sample) {
+                continue;
+            }
+            let count = samples
+                .clone()
+                .skip(index)
+                .filter(|later| *later == sample)
+                .count();
+            for (depth, ip) in sample.ips().rev().enumerate() {
+                if depth > 0 {
+                    f.write_char(';')?;
+                }
+                self.write_frame(f, ip)?;
+            }
+            writeln!(f, " {count}")?;
+        }
+        Ok(())
+    }
+}
+
+/// What a row of a [`FunctionTable`] counts the samples of.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum FunctionKey {
+    /// The procedure starting at this address.
+    Proc(usize),
+    /// The code at this instruction pointer, whose procedure wasn't found or
+    /// wasn't recorded.
+    Ip(usize),
+}
+
+/// How many samples a function appears in; one row of a [`FunctionTable`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FunctionSamples {
+    /// The function.
+    pub function: FunctionKey,
+    /// The number of samples whose innermost frame is in the function.
+    pub self_samples: usize,
+    /// The number of samples with a frame in the function.
+    pub total_samples: usize,
+}
+
+/// The samples of a [`Profile`] counted by function, from
+/// [`Profile::functions`].
+///
+/// It prints as a table of self and total counts with a row per function,
+/// and a row for the [other](FunctionTable::other) bucket if anything's in
+/// it:
+///
+/// ```text
+/// samples by function (of 42):
+///     self   total  function
+///       12      30  0x3801000
+///        3       9  0x3802044 (ip)
+///        2       5  (other)
+/// ```
+///
+/// Functions that are only known by an instruction pointer are marked with
+/// `(ip)`.
+#[derive(Debug, Clone)]
+pub struct FunctionTable<const N: usize> {
+    rows: [FunctionSamples; N],
+    len: usize,
+    other: (usize, usize),
+    samples: usize,
+}
+
+impl<const N: usize> FunctionTable<N> {
+    const fn new(samples: usize) -> Self {
+        Self {
+            rows: [FunctionSamples {
+                function: FunctionKey::Ip(0),
+                self_samples: 0,
+                total_samples: 0,
+            }; N],
+            len: 0,
+            other: (0, 0),
+            samples,
+        }
+    }
+
+    fn add(&mut self, function: FunctionKey, leaf: bool) {
+        let (rows, free) = self.rows.split_at_mut(self.len);
+        let (self_samples, total_samples) =
+            if let Some(row) = rows.iter_mut().find(|row| row.function == function) {
+                (&mut row.self_samples, &mut row.total_samples)
+            } else if let Some(row) = free.first_mut() {
+                row.function = function;
+                self.len += 1;
+                (&mut row.self_samples, &mut row.total_samples)
+            } else {
+                (&mut self.other.0, &mut self.other.1)
+            };
+        *self_samples += usize::from(leaf);
+        *total_samples += 1;
+    }
+
+    /// Returns the functions' counts, highest self count first.
+    pub fn rows(&self) -> &[FunctionSamples] {
+        &self.rows[..self.len]
+    }
+
+    /// Returns the self and total counts of the functions that didn't fit in
+    /// the table.
+    ///
+    /// Since these add up several functions, the total count can be more
+    /// than the number of samples.
+    pub const fn other(&self) -> (usize, usize) {
+        self.other
+    }
+
+    /// Returns the number of samples counted.
+    pub const fn sample_count(&self) -> usize {
+        self.samples
+    }
+
+    /// Moves every row after the first `rows` into the other bucket, such as
+    /// to print the top ten functions of a larger table.
+    pub fn truncate(&mut self, rows: usize) {
+        for row in self.rows.get(rows..self.len).unwrap_or(&[]) {
+            self.other.0 += row.self_samples;
+            self.other.1 += row.total_samples;
+        }
+        self.len = self.len.min(rows);
+    }
+
+    /// Prints the table to a transport that takes bytes, passing along the
+    /// transport's errors; see
+    /// [`Backtrace::write_bytes`](crate::Backtrace::write_bytes).
+    ///
+    /// # Errors
+    ///
+    /// Returns the first error returned by `sink`.
+    pub fn write_bytes<E>(&self, sink: impl FnMut(&[u8]) -> Result<(), E>) -> Result<(), E> {
+        write_chunked(self, sink)
+    }
+}
+
+impl<const N: usize> fmt::Display for FunctionTable<N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "samples by function (of {}):", self.samples)?;
+        f.write_str("    self   total  function\n")?;
+        for row in self.rows() {
+            write!(f, "  {:>6}  {:>6}  ", row.self_samples, row.total_samples)?;
+            match row.function {
+                FunctionKey::Proc(start) => writeln!(f, "{start:#x}")?,
+                FunctionKey::Ip(ip) => writeln!(f, "{ip:#x} (ip)")?,
+            }
+        }
+        if self.other != (0, 0) {
+            writeln!(f, "  {:>6}  {:>6}  (other)", self.other.0, self.other.1)?;
+        }
+        Ok(())
+    }
+}
+
+/// An iterator over the samples of a [`Profile`].
+#[derive(Debug, Clone)]
+pub struct Samples<'a> {
+    words: &'a [AtomicUsize],
+    stride: usize,
+}
+
+impl<'a> Iterator for Samples<'a> {
+    type Item = Sample<'a>;
+
+    fn next(&mut self) -> Option<Sample<'a>> {
+        let (len, rest) = self.words.split_first()?;
+        let words = len.load(Ordering::Relaxed).saturating_mul(self.stride);
+        let (frames, rest) = rest.split_at(words.min(rest.len()));
+        self.words = rest;
+        Some(Sample {
+            frames,
+            stride: self.stride,
+        })
+    }
+}
+
+/// One call chain recorded by a [`SamplingProfiler`], with at least one
+/// frame.
+#[derive(Debug, Clone, Copy)]
+pub struct Sample<'a> {
+    frames: &'a [AtomicUsize],
+    stride: usize,
+}
+
+impl Sample<'_> {
+    /// Returns the number of frames in the sample.
+    pub const fn len(&self) -> usize {
+        self.frames.len() / self.stride
+    }
+
+    /// Returns whether the sample has no frames, which is never the case for
+    /// a recorded one.
+    pub const fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    /// Returns the instruction pointer of the innermost frame.
+    pub fn leaf(&self) -> usize {
+        self.frames
+            .first()
+            .map_or(0, |ip| ip.load(Ordering::Relaxed))
+    }
+
+    /// Returns the frames' instruction pointers, innermost first.
+    pub fn ips(&self) -> impl DoubleEndedIterator<Item = usize> + ExactSizeIterator + '_ {
+        self.frames
+            .chunks(self.stride)
+            .map(|frame| frame[0].load(Ordering::Relaxed))
+    }
+
+    /// Returns the start addresses of the frames' procedures, innermost
+    /// first, or [`None`] for frames whose procedure couldn't be found or
+    /// when they weren't [recorded](ProfilerConfig::record_proc_starts).
+    pub fn proc_starts(
+        &self,
+    ) -> impl DoubleEndedIterator<Item = Option<usize>> + ExactSizeIterator + '_ {
+        self.frames.chunks(self.stride).map(|frame| {
+            frame
+                .get(1)
+                .map(|start| start.load(Ordering::Relaxed))
+                .filter(|&start| start != 0)
+        })
+    }
+
+    /// Returns the address each frame is grouped under by
+    /// [`Profile::functions`]: its procedure's start, or failing that, its
+    /// instruction pointer.
+    fn function_keys(&self) -> impl Iterator<Item = FunctionKey> + '_ {
+        self.ips()
+            .zip(self.proc_starts())
+            .map(|(ip, start)| match start {
+                Some(start) => FunctionKey::Proc(start),
+                None => FunctionKey::Ip(ip),
+            })
+    }
+}
+
+impl PartialEq for Sample<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len() && self.ips().eq(other.ips())
+    }
+}
+
+impl Eq for Sample<'_> {}
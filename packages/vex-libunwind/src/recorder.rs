@@ -0,0 +1,274 @@
+//! Keeping the last few backtraces of a running program, for finding out
+//! where it was before something went wrong.
+
+use core::{
+    cell::UnsafeCell,
+    fmt,
+    mem::MaybeUninit,
+    ptr,
+    sync::atomic::{fence, AtomicUsize, Ordering},
+};
+
+#[cfg(feature = "std")]
+use crate::encoding::{decode_varint, DecodeError, FromEncodedError};
+use crate::{
+    encoding::{encode_varint, max_encoded_len, EncodeError, FrameEncoding},
+    Backtrace,
+};
+
+/// The most bytes the varint length before each record's backtrace takes.
+const LENGTH_LEN: usize = 5;
+
+/// A ring of the last `SLOTS` backtraces recorded with
+/// [`record`](FlightRecorder::record), each holding up to `DEPTH` frames,
+/// meant to be kept in a `static`.
+///
+/// Bugs that only happen during a match are hard to catch in the act.
+/// Recording a backtrace every so often from a periodic task or timer
+/// interrupt keeps a history of where the program has been, which can be
+/// [dumped](FlightRecorder::encode) after the fact:
+///
+/// ```
+/// # use vex_libunwind::FlightRecorder;
+/// fn now() -> u64 {
+///     // For example, the brain's system time in microseconds.
+///     # 0
+/// }
+///
+/// static RECORDER: FlightRecorder<8, 16> = FlightRecorder::new(now);
+///
+/// RECORDER.record();
+/// for record in RECORDER.snapshot() {
+///     println!("#{} at {}:\n{}", record.sequence, record.time, record.backtrace);
+/// }
+/// ```
+///
+/// Recording only captures instruction and stack pointers, into the slot
+/// itself, so it's as cheap as [`Backtrace::capture`] and never allocates.
+/// Each slot has a version that's odd while it's being written, so reading
+/// a slot that a `record` interrupted (or that's being written on another
+/// thread) is detected, and the slot is left out rather than read torn.
+pub struct FlightRecorder<const SLOTS: usize, const DEPTH: usize> {
+    clock: fn() -> u64,
+    /// The sequence number of the next record.
+    next: AtomicUsize,
+    slots: [RecorderSlot<DEPTH>; SLOTS],
+}
+
+// SAFETY: Records are only written by the `record` call that claimed their
+// slot, and only trusted by readers if the slot wasn't claimed meanwhile.
+unsafe impl<const SLOTS: usize, const DEPTH: usize> Sync for FlightRecorder<SLOTS, DEPTH> {}
+
+impl<const SLOTS: usize, const DEPTH: usize> FlightRecorder<SLOTS, DEPTH> {
+    /// The most bytes that [`encode`](FlightRecorder::encode) can write.
+    pub const MAX_ENCODED_LEN: usize = SLOTS * (10 + 10 + LENGTH_LEN + max_encoded_len(DEPTH));
+
+    /// Creates an empty recorder that timestamps its records with `clock`.
+    ///
+    /// Timestamps are only stored and handed back, so `clock` can count in
+    /// any unit. It's called while recording, so it should be cheap and safe
+    /// to call wherever [`record`](FlightRecorder::record) is.
+    pub const fn new(clock: fn() -> u64) -> Self {
+        Self {
+            clock,
+            next: AtomicUsize::new(0),
+            slots: [RecorderSlot::<DEPTH>::EMPTY; SLOTS],
+        }
+    }
+
+    /// Records a backtrace of the calling function and its callers in place
+    /// of the oldest record, returning whether it did.
+    ///
+    /// Called from a timer interrupt, the backtrace goes through the
+    /// interrupt's frame into the code it interrupted. It's captured as with
+    /// [`Backtrace::capture`], so it's empty if capture is turned off or
+    /// another capture or print is in progress. If the slot is still being
+    /// written by a `record` that this one interrupted, nothing is recorded
+    /// and this returns `false`.
+    #[inline(always)] // Inlining keeps this function from appearing in backtraces
+    pub fn record(&self) -> bool {
+        if SLOTS == 0 {
+            return false;
+        }
+        let sequence = self.next.fetch_add(1, Ordering::Relaxed);
+        let slot = &self.slots[sequence % SLOTS];
+        let version = slot.version.load(Ordering::Relaxed);
+        if version % 2 == 1
+            || slot
+                .version
+                .compare_exchange(
+                    version,
+                    version.wrapping_add(1),
+                    Ordering::Acquire,
+                    Ordering::Relaxed,
+                )
+                .is_err()
+        {
+            return false;
+        }
+        fence(Ordering::Release);
+
+        // SAFETY: The slot was just claimed, so nothing else is writing the
+        // record, and readers won't trust what they read of it until it's
+        // released.
+        let record = unsafe { &mut *slot.record.get() };
+        record.sequence = sequence;
+        record.time = (self.clock)();
+        record.backtrace.recapture();
+        slot.version
+            .store(version.wrapping_add(2), Ordering::Release);
+        true
+    }
+
+    /// Returns an iterator over copies of the records, oldest first.
+    ///
+    /// Records that are being written while they're copied are left out, as
+    /// are slots that haven't been recorded into yet. Each record's
+    /// [`sequence`](RecordedBacktrace::sequence) number counts the calls to
+    /// [`record`](FlightRecorder::record), so gaps between them show where
+    /// records were left out or overwritten.
+    pub fn snapshot(&self) -> impl Iterator<Item = RecordedBacktrace<DEPTH>> + '_ {
+        let oldest = self.next.load(Ordering::Relaxed);
+        (0..SLOTS).filter_map(move |offset| self.slots[oldest.wrapping_add(offset) % SLOTS].read())
+    }
+
+    /// Encodes a [snapshot](FlightRecorder::snapshot) of the records into
+    /// `out`, returning the number of bytes written.
+    ///
+    /// Each record is its sequence number and timestamp as varints, then the
+    /// length of its backtrace's encoding as a varint, then the backtrace as
+    /// encoded by [`Backtrace::encode`]. Host tools can read the records
+    /// back with [`RecordedBacktrace::decode_all`].
+    ///
+    /// # Errors
+    ///
+    /// Returns [`EncodeError::BufferTooSmall`] if `out` can't hold every
+    /// record. A buffer of [`MAX_ENCODED_LEN`](FlightRecorder::MAX_ENCODED_LEN)
+    /// bytes is always big enough.
+    pub fn encode(&self, encoding: FrameEncoding, out: &mut [u8]) -> Result<usize, EncodeError> {
+        let mut len = 0;
+        for record in self.snapshot() {
+            len += encode_varint(record.sequence as u64, &mut out[len..])?;
+            len += encode_varint(record.time, &mut out[len..])?;
+
+            // The backtrace's length comes first, but isn't known until it's
+            // encoded, so it's encoded after the most room the length needs
+            // and moved back once the length is written.
+            let start = len + LENGTH_LEN;
+            let backtrace = out.get_mut(start..).ok_or(EncodeError::BufferTooSmall)?;
+            let backtrace_len = record.backtrace.encode(encoding, backtrace)?;
+            len += encode_varint(backtrace_len as u64, &mut out[len..])?;
+            out.copy_within(start..start + backtrace_len, len);
+            len += backtrace_len;
+        }
+        Ok(len)
+    }
+}
+
+impl<const SLOTS: usize, const DEPTH: usize> fmt::Debug for FlightRecorder<SLOTS, DEPTH> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FlightRecorder")
+            .field("next", &self.next.load(Ordering::Relaxed))
+            .finish_non_exhaustive()
+    }
+}
+
+/// A backtrace kept by a [`FlightRecorder`].
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct RecordedBacktrace<const DEPTH: usize> {
+    /// How many records were made before this one.
+    pub sequence: usize,
+    /// What the recorder's clock said when this was recorded.
+    pub time: u64,
+    /// The backtrace.
+    pub backtrace: Backtrace<DEPTH>,
+}
+
+impl<const DEPTH: usize> RecordedBacktrace<DEPTH> {
+    /// An empty record, for filling unused slots with.
+    const EMPTY: Self = Self {
+        sequence: 0,
+        time: 0,
+        backtrace: Backtrace::empty(),
+    };
+
+    /// Reads back every record written by [`FlightRecorder::encode`], oldest
+    /// first. Only available with the `std` feature.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the input isn't a whole number of records, or a
+    /// record's backtrace can't be decoded or has more than `DEPTH` frames.
+    /// The offsets in errors from decoding a backtrace count from the start
+    /// of that backtrace's encoding.
+    #[cfg(feature = "std")]
+    pub fn decode_all(input: &[u8]) -> Result<std::vec::Vec<Self>, FromEncodedError> {
+        let binary = |error| FromEncodedError::Binary { error };
+        let too_large = |offset| binary(DecodeError::TooLarge { offset });
+        let mut records = std::vec::Vec::new();
+        let mut offset = 0;
+        while offset < input.len() {
+            let (sequence, next) = decode_varint(input, offset).map_err(binary)?;
+            let sequence = usize::try_from(sequence).map_err(|_| too_large(offset))?;
+            let (time, next) = decode_varint(input, next).map_err(binary)?;
+            let (len, start) = decode_varint(input, next).map_err(binary)?;
+            let end = usize::try_from(len)
+                .ok()
+                .and_then(|len| start.checked_add(len))
+                .ok_or_else(|| too_large(next))?;
+            let encoded = input.get(start..end).ok_or(binary(DecodeError::Truncated {
+                offset: input.len(),
+            }))?;
+            records.push(Self {
+                sequence,
+                time,
+                backtrace: Backtrace::from_encoded(encoded)?,
+            });
+            offset = end;
+        }
+        Ok(records)
+    }
+}
+
+/// One of a [`FlightRecorder`]'s records, with the version that tells
+/// readers whether it's safe to read.
+struct RecorderSlot<const DEPTH: usize> {
+    /// Zero if the slot has never been recorded into, odd while it's being
+    /// written, and even otherwise.
+    version: AtomicUsize,
+    record: UnsafeCell<RecordedBacktrace<DEPTH>>,
+}
+
+impl<const DEPTH: usize> RecorderSlot<DEPTH> {
+    #[allow(clippy::declare_interior_mutable_const)] // Only used to fill arrays of fresh slots
+    const EMPTY: Self = Self {
+        version: AtomicUsize::new(0),
+        record: UnsafeCell::new(RecordedBacktrace::EMPTY),
+    };
+
+    /// Copies the record, or returns [`None`] if there isn't one or it was
+    /// written while being copied.
+    fn read(&self) -> Option<RecordedBacktrace<DEPTH>> {
+        let before = self.version.load(Ordering::Acquire);
+        if before == 0 || before % 2 == 1 {
+            return None;
+        }
+        // SAFETY: The copy may be torn by a `record` running meanwhile, so
+        // it's kept uninitialized until the version shows that it isn't.
+        let copy = unsafe {
+            ptr::read_volatile(
+                self.record
+                    .get()
+                    .cast::<MaybeUninit<RecordedBacktrace<DEPTH>>>(),
+            )
+        };
+        fence(Ordering::Acquire);
+        if self.version.load(Ordering::Relaxed) != before {
+            return None;
+        }
+        // SAFETY: The version didn't change, so the record wasn't written
+        // while it was copied.
+        Some(unsafe { copy.assume_init() })
+    }
+}
@@ -0,0 +1,472 @@
+//! Register numbers and names.
+//!
+//! This module re-exports the `UNW_*` register number constants from
+//! [`vex_libunwind_sys::registers`], which also documents how the V5's VFP
+//! registers are numbered.
+
+use core::{ffi::CStr, fmt, str::FromStr};
+
+use snafu::Snafu;
+pub use vex_libunwind_sys::registers::*;
+use vex_libunwind_sys::unw_regnum_t;
+
+/// The core registers that the ARM procedure call standard requires callees to
+/// preserve, and which can therefore be recovered in every frame: r4-r11, sp,
+/// lr, and pc.
+pub const CALLEE_SAVED_REGISTERS: [unw_regnum_t; 11] = [
+    UNW_ARM_R4,
+    UNW_ARM_R5,
+    UNW_ARM_R6,
+    UNW_ARM_R7,
+    UNW_ARM_R8,
+    UNW_ARM_R9,
+    UNW_ARM_R10,
+    UNW_ARM_R11,
+    UNW_ARM_SP,
+    UNW_ARM_LR,
+    UNW_ARM_PC,
+];
+
+/// Every core register, r0-r15. All of them are preserved in signal frames.
+pub const CORE_REGISTERS: [unw_regnum_t; 16] = [
+    UNW_ARM_R0,
+    UNW_ARM_R1,
+    UNW_ARM_R2,
+    UNW_ARM_R3,
+    UNW_ARM_R4,
+    UNW_ARM_R5,
+    UNW_ARM_R6,
+    UNW_ARM_R7,
+    UNW_ARM_R8,
+    UNW_ARM_R9,
+    UNW_ARM_R10,
+    UNW_ARM_R11,
+    UNW_ARM_R12,
+    UNW_ARM_SP,
+    UNW_ARM_LR,
+    UNW_ARM_PC,
+];
+
+/// The VFP registers that `libunwind` can unwind on the V5, d0-d31.
+const FP_REGISTERS: [unw_regnum_t; 32] = {
+    let mut registers = [0; 32];
+    let mut i = 0;
+    while i < registers.len() {
+        registers[i] = UNW_ARM_D0 + i as unw_regnum_t;
+        i += 1;
+    }
+    registers
+};
+
+/// Returns the numbers of the V5's core registers, r0-r15, which are read
+/// with [`UnwindCursor::register`](crate::UnwindCursor::register).
+///
+/// Together with [`all_fp`], this lets register dumps adapt to the target's
+/// register set rather than hardcoding it.
+pub const fn all() -> &'static [unw_regnum_t] {
+    &CORE_REGISTERS
+}
+
+/// Returns the numbers of the V5's floating-point registers, d0-d31, which
+/// are read with
+/// [`UnwindCursor::fp_register`](crate::UnwindCursor::fp_register).
+///
+/// The single-precision registers s0-s31 are left out, since they overlap
+/// d0-d15 and `libunwind` can't unwind them.
+pub const fn all_fp() -> &'static [unw_regnum_t] {
+    &FP_REGISTERS
+}
+
+/// Returns the name of the register with the given `libunwind` register
+/// number, or [`None`] if it isn't a core or VFP register.
+///
+/// This matches the names `libunwind` itself uses for the V5's registers,
+/// including `pc` and `sp` for [`UNW_REG_IP`] and [`UNW_REG_SP`], but is
+/// looked up in a static table so that no cursor is needed. The names are
+/// always those of the V5's registers, even when running on a host.
+pub const fn register_name(register: unw_regnum_t) -> Option<&'static CStr> {
+    let register = match register {
+        UNW_REG_IP => RegisterId::Pc,
+        UNW_REG_SP => RegisterId::Sp,
+        register => match RegisterId::from_number(register) {
+            Some(register) => register,
+            None => return None,
+        },
+    };
+    Some(register.c_name())
+}
+
+/// The names of [`RegisterId::ALL`], in the same order.
+const NAMES: [&CStr; 80] = [
+    c"r0", c"r1", c"r2", c"r3", c"r4", c"r5", c"r6", c"r7", c"r8", c"r9", c"r10", c"r11", c"r12",
+    c"sp", c"lr", c"pc", c"s0", c"s1", c"s2", c"s3", c"s4", c"s5", c"s6", c"s7", c"s8", c"s9",
+    c"s10", c"s11", c"s12", c"s13", c"s14", c"s15", c"s16", c"s17", c"s18", c"s19", c"s20", c"s21",
+    c"s22", c"s23", c"s24", c"s25", c"s26", c"s27", c"s28", c"s29", c"s30", c"s31", c"d0", c"d1",
+    c"d2", c"d3", c"d4", c"d5", c"d6", c"d7", c"d8", c"d9", c"d10", c"d11", c"d12", c"d13", c"d14",
+    c"d15", c"d16", c"d17", c"d18", c"d19", c"d20", c"d21", c"d22", c"d23", c"d24", c"d25", c"d26",
+    c"d27", c"d28", c"d29", c"d30", c"d31",
+];
+
+/// A core or VFP register of the V5's ARM processor.
+///
+/// `RegisterId`s display as, and can be parsed from, the register's
+/// conventional lowercase name: `r0`-`r12`, `sp`, `lr`, `pc`, `s0`-`s31`, and
+/// `d0`-`d31`. When parsing, the following aliases are also accepted:
+///
+/// | Alias         | Register        |
+/// |---------------|-----------------|
+/// | `fp`          | [`RegisterId::R11`] |
+/// | `ip`          | [`RegisterId::R12`] |
+/// | `r13`         | [`RegisterId::Sp`]  |
+/// | `r14`         | [`RegisterId::Lr`]  |
+/// | `r15`         | [`RegisterId::Pc`]  |
+///
+/// Note that `ip` follows the ARM procedure call standard, where it names the
+/// intra-procedure-call scratch register r12. It is *not* the instruction
+/// pointer that [`UNW_REG_IP`] refers to; that's the program counter, `pc`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[repr(i32)]
+pub enum RegisterId {
+    /// `r0`
+    R0 = UNW_ARM_R0,
+    /// `r1`
+    R1 = UNW_ARM_R1,
+    /// `r2`
+    R2 = UNW_ARM_R2,
+    /// `r3`
+    R3 = UNW_ARM_R3,
+    /// `r4`
+    R4 = UNW_ARM_R4,
+    /// `r5`
+    R5 = UNW_ARM_R5,
+    /// `r6`
+    R6 = UNW_ARM_R6,
+    /// `r7`
+    R7 = UNW_ARM_R7,
+    /// `r8`
+    R8 = UNW_ARM_R8,
+    /// `r9`
+    R9 = UNW_ARM_R9,
+    /// `r10`
+    R10 = UNW_ARM_R10,
+    /// `r11`
+    R11 = UNW_ARM_R11,
+    /// `r12`
+    R12 = UNW_ARM_R12,
+    /// `sp`
+    Sp = UNW_ARM_SP,
+    /// `lr`
+    Lr = UNW_ARM_LR,
+    /// `pc`
+    Pc = UNW_ARM_PC,
+    /// `s0`
+    S0 = UNW_ARM_S0,
+    /// `s1`
+    S1 = UNW_ARM_S1,
+    /// `s2`
+    S2 = UNW_ARM_S2,
+    /// `s3`
+    S3 = UNW_ARM_S3,
+    /// `s4`
+    S4 = UNW_ARM_S4,
+    /// `s5`
+    S5 = UNW_ARM_S5,
+    /// `s6`
+    S6 = UNW_ARM_S6,
+    /// `s7`
+    S7 = UNW_ARM_S7,
+    /// `s8`
+    S8 = UNW_ARM_S8,
+    /// `s9`
+    S9 = UNW_ARM_S9,
+    /// `s10`
+    S10 = UNW_ARM_S10,
+    /// `s11`
+    S11 = UNW_ARM_S11,
+    /// `s12`
+    S12 = UNW_ARM_S12,
+    /// `s13`
+    S13 = UNW_ARM_S13,
+    /// `s14`
+    S14 = UNW_ARM_S14,
+    /// `s15`
+    S15 = UNW_ARM_S15,
+    /// `s16`
+    S16 = UNW_ARM_S16,
+    /// `s17`
+    S17 = UNW_ARM_S17,
+    /// `s18`
+    S18 = UNW_ARM_S18,
+    /// `s19`
+    S19 = UNW_ARM_S19,
+    /// `s20`
+    S20 = UNW_ARM_S20,
+    /// `s21`
+    S21 = UNW_ARM_S21,
+    /// `s22`
+    S22 = UNW_ARM_S22,
+    /// `s23`
+    S23 = UNW_ARM_S23,
+    /// `s24`
+    S24 = UNW_ARM_S24,
+    /// `s25`
+    S25 = UNW_ARM_S25,
+    /// `s26`
+    S26 = UNW_ARM_S26,
+    /// `s27`
+    S27 = UNW_ARM_S27,
+    /// `s28`
+    S28 = UNW_ARM_S28,
+    /// `s29`
+    S29 = UNW_ARM_S29,
+    /// `s30`
+    S30 = UNW_ARM_S30,
+    /// `s31`
+    S31 = UNW_ARM_S31,
+    /// `d0`
+    D0 = UNW_ARM_D0,
+    /// `d1`
+    D1 = UNW_ARM_D1,
+    /// `d2`
+    D2 = UNW_ARM_D2,
+    /// `d3`
+    D3 = UNW_ARM_D3,
+    /// `d4`
+    D4 = UNW_ARM_D4,
+    /// `d5`
+    D5 = UNW_ARM_D5,
+    /// `d6`
+    D6 = UNW_ARM_D6,
+    /// `d7`
+    D7 = UNW_ARM_D7,
+    /// `d8`
+    D8 = UNW_ARM_D8,
+    /// `d9`
+    D9 = UNW_ARM_D9,
+    /// `d10`
+    D10 = UNW_ARM_D10,
+    /// `d11`
+    D11 = UNW_ARM_D11,
+    /// `d12`
+    D12 = UNW_ARM_D12,
+    /// `d13`
+    D13 = UNW_ARM_D13,
+    /// `d14`
+    D14 = UNW_ARM_D14,
+    /// `d15`
+    D15 = UNW_ARM_D15,
+    /// `d16`
+    D16 = UNW_ARM_D16,
+    /// `d17`
+    D17 = UNW_ARM_D17,
+    /// `d18`
+    D18 = UNW_ARM_D18,
+    /// `d19`
+    D19 = UNW_ARM_D19,
+    /// `d20`
+    D20 = UNW_ARM_D20,
+    /// `d21`
+    D21 = UNW_ARM_D21,
+    /// `d22`
+    D22 = UNW_ARM_D22,
+    /// `d23`
+    D23 = UNW_ARM_D23,
+    /// `d24`
+    D24 = UNW_ARM_D24,
+    /// `d25`
+    D25 = UNW_ARM_D25,
+    /// `d26`
+    D26 = UNW_ARM_D26,
+    /// `d27`
+    D27 = UNW_ARM_D27,
+    /// `d28`
+    D28 = UNW_ARM_D28,
+    /// `d29`
+    D29 = UNW_ARM_D29,
+    /// `d30`
+    D30 = UNW_ARM_D30,
+    /// `d31`
+    D31 = UNW_ARM_D31,
+}
+
+impl RegisterId {
+    /// Every register, ordered by register number.
+    pub const ALL: [Self; 80] = [
+        Self::R0,
+        Self::R1,
+        Self::R2,
+        Self::R3,
+        Self::R4,
+        Self::R5,
+        Self::R6,
+        Self::R7,
+        Self::R8,
+        Self::R9,
+        Self::R10,
+        Self::R11,
+        Self::R12,
+        Self::Sp,
+        Self::Lr,
+        Self::Pc,
+        Self::S0,
+        Self::S1,
+        Self::S2,
+        Self::S3,
+        Self::S4,
+        Self::S5,
+        Self::S6,
+        Self::S7,
+        Self::S8,
+        Self::S9,
+        Self::S10,
+        Self::S11,
+        Self::S12,
+        Self::S13,
+        Self::S14,
+        Self::S15,
+        Self::S16,
+        Self::S17,
+        Self::S18,
+        Self::S19,
+        Self::S20,
+        Self::S21,
+        Self::S22,
+        Self::S23,
+        Self::S24,
+        Self::S25,
+        Self::S26,
+        Self::S27,
+        Self::S28,
+        Self::S29,
+        Self::S30,
+        Self::S31,
+        Self::D0,
+        Self::D1,
+        Self::D2,
+        Self::D3,
+        Self::D4,
+        Self::D5,
+        Self::D6,
+        Self::D7,
+        Self::D8,
+        Self::D9,
+        Self::D10,
+        Self::D11,
+        Self::D12,
+        Self::D13,
+        Self::D14,
+        Self::D15,
+        Self::D16,
+        Self::D17,
+        Self::D18,
+        Self::D19,
+        Self::D20,
+        Self::D21,
+        Self::D22,
+        Self::D23,
+        Self::D24,
+        Self::D25,
+        Self::D26,
+        Self::D27,
+        Self::D28,
+        Self::D29,
+        Self::D30,
+        Self::D31,
+    ];
+
+    /// Returns the `libunwind` register number of this register.
+    pub const fn number(self) -> unw_regnum_t {
+        self as unw_regnum_t
+    }
+
+    /// Returns the register with the given `libunwind` register number, or
+    /// [`None`] if it isn't a core or VFP register.
+    pub const fn from_number(number: unw_regnum_t) -> Option<Self> {
+        match Self::index_of(number) {
+            Some(index) => Some(Self::ALL[index]),
+            None => None,
+        }
+    }
+
+    /// Returns the position of the given register number in [`Self::ALL`].
+    const fn index_of(number: unw_regnum_t) -> Option<usize> {
+        match number {
+            UNW_ARM_R0..=UNW_ARM_PC => Some((number - UNW_ARM_R0) as usize),
+            UNW_ARM_S0..=UNW_ARM_S31 => Some(16 + (number - UNW_ARM_S0) as usize),
+            UNW_ARM_D0..=UNW_ARM_D31 => Some(48 + (number - UNW_ARM_D0) as usize),
+            _ => None,
+        }
+    }
+
+    /// Returns the register's name as displayed, as a C string.
+    pub const fn c_name(self) -> &'static CStr {
+        match Self::index_of(self.number()) {
+            Some(index) => NAMES[index],
+            None => unreachable!(),
+        }
+    }
+
+    /// Checks whether this is a floating-point register, which must be
+    /// accessed with
+    /// [`UnwindCursor::fp_register`](crate::UnwindCursor::fp_register).
+    pub const fn is_fp(self) -> bool {
+        self.number() >= UNW_ARM_S0
+    }
+}
+
+impl From<RegisterId> for unw_regnum_t {
+    fn from(register: RegisterId) -> Self {
+        register.number()
+    }
+}
+
+impl fmt::Display for RegisterId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // The names are all ASCII.
+        f.write_str(self.c_name().to_str().unwrap_or_default())
+    }
+}
+
+/// The error returned when parsing an unknown register name.
+#[derive(Debug, Snafu)]
+#[snafu(display("unknown register name"))]
+pub struct ParseRegisterIdError;
+
+impl FromStr for RegisterId {
+    type Err = ParseRegisterIdError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let register = match s {
+            "fp" => Self::R11,
+            "ip" => Self::R12,
+            "sp" => Self::Sp,
+            "lr" => Self::Lr,
+            "pc" => Self::Pc,
+            _ => {
+                let bank = s.get(..1).ok_or(ParseRegisterIdError)?;
+                let index = parse_index(&s[1..]).ok_or(ParseRegisterIdError)?;
+                let (first, count) = match bank {
+                    "r" => (UNW_ARM_R0, 16),
+                    "s" => (UNW_ARM_S0, 32),
+                    "d" => (UNW_ARM_D0, 32),
+                    _ => return Err(ParseRegisterIdError),
+                };
+                if index >= count {
+                    return Err(ParseRegisterIdError);
+                }
+                Self::from_number(first + index).ok_or(ParseRegisterIdError)?
+            }
+        };
+        Ok(register)
+    }
+}
+
+/// Parses a register index, rejecting signs and leading zeros so that each
+/// register has exactly one spelling.
+fn parse_index(s: &str) -> Option<unw_regnum_t> {
+    let canonical = !s.is_empty() && (s == "0" || !s.starts_with('0'));
+    if !canonical || !s.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    s.parse().ok()
+}
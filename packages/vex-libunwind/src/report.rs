@@ -0,0 +1,199 @@
+//! Printing everything a fault handler knows in one go.
+
+use core::fmt::{self, Write};
+#[cfg(not(feature = "disabled"))]
+use core::ptr;
+
+#[cfg(not(feature = "disabled"))]
+use crate::{
+    backtrace::{write_frames, write_named_frame},
+    registers, UnwindCursor,
+};
+use crate::{build_id, UnwindContext};
+
+/// The most frames [`write_crash_report`] prints.
+pub const MAX_REPORT_FRAMES: usize = 64;
+
+/// The most instruction bytes [`write_crash_report_with`] prints.
+pub const MAX_INSTRUCTION_BYTES: usize = 32;
+
+/// Options for what [`write_crash_report_with`] adds to a crash report.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ReportOptions {
+    instruction_bytes: usize,
+}
+
+impl ReportOptions {
+    /// Creates options for the same report that [`write_crash_report`]
+    /// prints.
+    pub const fn new() -> Self {
+        Self {
+            instruction_bytes: 0,
+        }
+    }
+
+    /// Prints up to `count` bytes of code, in hex, starting at the faulting
+    /// frame's instruction pointer, after the registers. At most
+    /// [`MAX_INSTRUCTION_BYTES`] are printed.
+    ///
+    /// Reading memory at the crash site is only safe if it's mapped, which
+    /// can't be taken for granted: the fault may have been caused by jumping
+    /// to a bad address. So the bytes are only read if the instruction
+    /// pointer is inside a procedure that has unwind info, and not past that
+    /// procedure's end, since the linker put the procedure's code there.
+    /// Otherwise, the bytes are noted as unavailable. This can't catch code
+    /// that was unloaded after its unwind info was registered, such as a hot
+    /// image that was replaced without being unregistered.
+    pub const fn instruction_bytes(mut self, count: usize) -> Self {
+        self.instruction_bytes = count;
+        self
+    }
+}
+
+/// Prints a crash report for the given context: what went wrong, the
+/// registers of the faulting frame, and a backtrace.
+///
+/// This is meant to be the only thing a fault handler has to call. The
+/// report looks like this:
+///
+/// ```text
+/// crash report:
+///   fault: data abort at 0x00000004
+///   build id: 9f8e2c0b41d6a3e57c1a90b2d4f6e8a1c3b5d7f9
+/// registers:
+///   r0  0x00000000
+///   ...
+///   pc  0x03801234
+/// stack backtrace:
+///      0: 0x3801234
+///      1: 0x3800f10
+///   ...
+/// ```
+///
+/// The `fault` line is left out if `fault` is `None`, and the `build id` line
+/// if the program doesn't have a [build ID](crate::build_id), which tells a
+/// host tool which binary to symbolize the report with. The registers are the
+/// V5's core registers, r0-r12, sp, lr, and pc; any that can't be read are
+/// left out. The backtrace is printed as by
+/// [`write_backtrace`](crate::write_backtrace), but stops after
+/// [`MAX_REPORT_FRAMES`] frames, so a corrupted stack that loops can't keep
+/// the handler printing forever.
+///
+/// With the `disabled` feature enabled, the registers are left out and the
+/// backtrace is the same one-line note that
+/// [`write_backtrace`](crate::write_backtrace) prints.
+///
+/// # Errors
+///
+/// Returns an error if writing to `w` fails.
+pub fn write_crash_report(
+    w: &mut impl Write,
+    context: &UnwindContext,
+    fault: Option<&dyn fmt::Display>,
+) -> fmt::Result {
+    write_crash_report_with(w, context, fault, ReportOptions::new())
+}
+
+/// Prints a crash report like [`write_crash_report`], with the additions
+/// turned on in `options`.
+///
+/// With [`ReportOptions::instruction_bytes`], the registers are followed by
+/// the bytes at the instruction pointer:
+///
+/// ```text
+/// instruction bytes:
+///   0x03801234: 00 00 90 e5 1e ff 2f e1
+/// ```
+///
+/// # Errors
+///
+/// Returns an error if writing to `w` fails.
+pub fn write_crash_report_with(
+    w: &mut impl Write,
+    context: &UnwindContext,
+    fault: Option<&dyn fmt::Display>,
+    options: ReportOptions,
+) -> fmt::Result {
+    w.write_str("crash report:\n")?;
+    if let Some(fault) = fault {
+        writeln!(w, "  fault: {fault}")?;
+    }
+    if let Some(id) = build_id() {
+        w.write_str("  build id: ")?;
+        id.iter().try_for_each(|byte| write!(w, "{byte:02x}"))?;
+        w.write_char('\n')?;
+    }
+    write_report_body(w, context, options)
+}
+
+#[cfg(not(feature = "disabled"))]
+fn write_report_body(
+    w: &mut impl Write,
+    context: &UnwindContext,
+    options: ReportOptions,
+) -> fmt::Result {
+    w.write_str("registers:\n")?;
+    match UnwindCursor::new(context) {
+        Ok(cursor) => {
+            write_registers(w, &cursor)?;
+            if options.instruction_bytes > 0 {
+                write_instruction_bytes(w, &cursor, options.instruction_bytes)?;
+            }
+        }
+        Err(error) => writeln!(w, "  (unavailable: {error})")?,
+    }
+    write_frames(w, context, MAX_REPORT_FRAMES, write_named_frame)
+}
+
+#[cfg(feature = "disabled")]
+fn write_report_body(
+    w: &mut impl Write,
+    context: &UnwindContext,
+    _options: ReportOptions,
+) -> fmt::Result {
+    crate::write_backtrace(w, context)
+}
+
+/// Prints each of the core registers that can be read in the cursor's frame,
+/// one per line.
+#[cfg(not(feature = "disabled"))]
+fn write_registers(w: &mut impl Write, cursor: &UnwindCursor) -> fmt::Result {
+    const WIDTH: usize = 2 + 2 * core::mem::size_of::<usize>();
+
+    for &register in registers::all() {
+        let (Some(name), Ok(value)) = (
+            registers::register_name(register).and_then(|name| name.to_str().ok()),
+            cursor.register(register),
+        ) else {
+            continue;
+        };
+        writeln!(w, "  {name:<3} {value:#0WIDTH$x}")?;
+    }
+    Ok(())
+}
+
+/// Prints up to `count` bytes of code at the cursor's instruction pointer, if
+/// they're known to be inside its procedure.
+#[cfg(not(feature = "disabled"))]
+fn write_instruction_bytes(w: &mut impl Write, cursor: &UnwindCursor, count: usize) -> fmt::Result {
+    const WIDTH: usize = 2 + 2 * core::mem::size_of::<usize>();
+
+    w.write_str("instruction bytes:\n")?;
+    let (ip, range) = match (cursor.register(registers::UNW_REG_IP), cursor.proc_range()) {
+        (Ok(ip), Ok(range)) if range.contains(&ip) => (ip, range),
+        (Ok(ip), Ok(_)) => return writeln!(w, "  (unavailable: {ip:#x} is outside its procedure)"),
+        (Err(error), _) | (_, Err(error)) => return writeln!(w, "  (unavailable: {error})"),
+    };
+
+    write!(w, "  {ip:#0WIDTH$x}:")?;
+    let end = range
+        .end
+        .min(ip.saturating_add(count.min(MAX_INSTRUCTION_BYTES)));
+    for address in ip..end {
+        // SAFETY: The address is inside a procedure that has unwind info, so
+        // it's code that the linker placed and that's loaded.
+        let byte = unsafe { ptr::read_volatile(address as *const u8) };
+        write!(w, " {byte:02x}")?;
+    }
+    w.write_char('\n')
+}
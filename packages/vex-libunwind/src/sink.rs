@@ -0,0 +1,67 @@
+//! Printing to transports that take bytes rather than implementing
+//! [`fmt::Write`].
+
+use core::fmt::{self, Write};
+
+/// How many bytes are collected before they're handed to the sink.
+const CHUNK_SIZE: usize = 64;
+
+/// Buffers formatted output and hands it to a byte sink in chunks, holding on
+/// to the sink's error, which `fmt::Write` can't carry.
+struct ChunkWriter<F, E> {
+    sink: F,
+    chunk: [u8; CHUNK_SIZE],
+    len: usize,
+    error: Option<E>,
+}
+
+impl<F: FnMut(&[u8]) -> Result<(), E>, E> ChunkWriter<F, E> {
+    fn flush(&mut self) -> Result<(), E> {
+        let len = core::mem::take(&mut self.len);
+        if len == 0 {
+            return Ok(());
+        }
+        (self.sink)(&self.chunk[..len])
+    }
+}
+
+impl<F: FnMut(&[u8]) -> Result<(), E>, E> Write for ChunkWriter<F, E> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let mut bytes = s.as_bytes();
+        while !bytes.is_empty() {
+            let count = bytes.len().min(CHUNK_SIZE - self.len);
+            self.chunk[self.len..][..count].copy_from_slice(&bytes[..count]);
+            self.len += count;
+            bytes = &bytes[count..];
+
+            if self.len == CHUNK_SIZE {
+                if let Err(error) = self.flush() {
+                    self.error = Some(error);
+                    return Err(fmt::Error);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Formats `value` into `sink`, a few dozen bytes at a time, stopping at the
+/// first error the sink returns.
+pub(crate) fn write_chunked<E>(
+    value: &impl fmt::Display,
+    sink: impl FnMut(&[u8]) -> Result<(), E>,
+) -> Result<(), E> {
+    let mut writer = ChunkWriter {
+        sink,
+        chunk: [0; CHUNK_SIZE],
+        len: 0,
+        error: None,
+    };
+    // The values printed here only fail when the writer does, so the sink's
+    // error is all there is to report.
+    let _ = write!(writer, "{value}");
+    match writer.error.take() {
+        Some(error) => Err(error),
+        None => writer.flush(),
+    }
+}
@@ -0,0 +1,119 @@
+//! Static storage for backtraces captured where allocating isn't an option.
+
+use core::{
+    cell::UnsafeCell,
+    fmt,
+    ops::Deref,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use crate::Backtrace;
+
+/// A reserved place to capture a [`Backtrace`] into, meant to be kept in a
+/// `static`.
+///
+/// Panic and fault handlers may run because memory has run out, so they
+/// shouldn't allocate (or put a large backtrace on what might be a nearly
+/// exhausted stack). A slot sets the buffer aside ahead of time:
+///
+/// ```
+/// # use vex_libunwind::BacktraceSlot;
+/// static SLOT: BacktraceSlot<64> = BacktraceSlot::new();
+///
+/// if let Some(backtrace) = SLOT.capture() {
+///     println!("{backtrace}");
+/// }
+/// ```
+///
+/// Only one backtrace can be held in a slot at a time. It stays claimed until
+/// the returned [`SlotBacktrace`] is dropped, and capture attempts in the
+/// meantime (from other threads, or from a handler that interrupted the
+/// holder) get `None` rather than waiting.
+pub struct BacktraceSlot<const N: usize = 32> {
+    claimed: AtomicBool,
+    backtrace: UnsafeCell<Backtrace<N>>,
+}
+
+// SAFETY: The backtrace is only accessed through a `SlotBacktrace`, and only
+// one of those exists at a time thanks to `claimed`.
+unsafe impl<const N: usize> Sync for BacktraceSlot<N> {}
+
+impl<const N: usize> BacktraceSlot<N> {
+    /// Creates an empty slot.
+    pub const fn new() -> Self {
+        Self {
+            claimed: AtomicBool::new(false),
+            backtrace: UnsafeCell::new(Backtrace::empty()),
+        }
+    }
+
+    /// Captures a backtrace of the calling function and its callers into the
+    /// slot, or returns `None` if the slot is already holding one.
+    ///
+    /// As with [`Backtrace::capture`], unwinding errors end the backtrace
+    /// early, and a capture that starts while another capture or print is in
+    /// progress (or while capture is turned off) yields an empty backtrace.
+    /// Nothing is allocated.
+    #[inline(always)] // Inlining keeps this function from appearing in backtraces
+    pub fn capture(&self) -> Option<SlotBacktrace<'_, N>> {
+        if self.claimed.swap(true, Ordering::Acquire) {
+            return None;
+        }
+        let slot = SlotBacktrace { slot: self };
+
+        // SAFETY: The slot was just claimed, so nothing else is accessing the
+        // backtrace.
+        unsafe { (*self.backtrace.get()).recapture() };
+        Some(slot)
+    }
+}
+
+impl<const N: usize> Default for BacktraceSlot<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const N: usize> fmt::Debug for BacktraceSlot<N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BacktraceSlot")
+            .field("claimed", &self.claimed.load(Ordering::Relaxed))
+            .finish_non_exhaustive()
+    }
+}
+
+/// A backtrace held in a [`BacktraceSlot`].
+///
+/// The slot is released when this is dropped.
+#[must_use = "the slot is released when the backtrace is dropped"]
+pub struct SlotBacktrace<'a, const N: usize> {
+    slot: &'a BacktraceSlot<N>,
+}
+
+impl<const N: usize> Deref for SlotBacktrace<'_, N> {
+    type Target = Backtrace<N>;
+
+    fn deref(&self) -> &Backtrace<N> {
+        // SAFETY: The slot is claimed for as long as this exists, and it's only
+        // written to before this is handed out.
+        unsafe { &*self.slot.backtrace.get() }
+    }
+}
+
+impl<const N: usize> fmt::Debug for SlotBacktrace<'_, N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+
+impl<const N: usize> fmt::Display for SlotBacktrace<'_, N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&**self, f)
+    }
+}
+
+impl<const N: usize> Drop for SlotBacktrace<'_, N> {
+    fn drop(&mut self) {
+        self.slot.claimed.store(false, Ordering::Release);
+    }
+}
@@ -0,0 +1,122 @@
+//! Recognizing backtraces captured on the brink of a stack overflow.
+
+use core::ops::Range;
+
+/// The address range of a stack, which grows down from `hi` toward `lo`.
+///
+/// A task that overflows its stack usually crashes somewhere unremarkable, so
+/// its backtrace looks like any other. If the bounds of the running task's
+/// stack are registered with [`StackBounds::set_current`], captured
+/// backtraces compare their innermost stack pointer against the lower bound
+/// and are marked with [`Backtrace::overflow_suspected`] when it's too close.
+///
+/// Without the `std` feature, there is one registration for the whole
+/// program, which a scheduler can replace whenever it switches tasks. With it,
+/// each thread has its own.
+///
+/// [`Backtrace::overflow_suspected`]: crate::Backtrace::overflow_suspected
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct StackBounds {
+    /// The lowest address of the stack, which it overflows past.
+    pub lo: usize,
+    /// The address just past the top of the stack, where it starts.
+    pub hi: usize,
+}
+
+impl StackBounds {
+    /// How close to the lower bound a stack pointer can be before an
+    /// overflow is suspected, in bytes.
+    ///
+    /// This is about what a fault handler needs to run, so a stack pointer
+    /// this close to the bound could hardly have gone much further.
+    pub const GUARD_BYTES: usize = 512;
+
+    /// How far below the upper bound a stack pointer can be while still
+    /// counting as near the top of the stack, in bytes.
+    ///
+    /// The function a stack starts in has only just begun using it, so its
+    /// frame is within this of the top.
+    pub const TOP_BYTES: usize = 256;
+
+    /// Returns the bounds as an address range.
+    pub const fn range(&self) -> Range<usize> {
+        self.lo..self.hi
+    }
+
+    /// Checks whether a stack pointer is below the stack's lower bound or
+    /// within [`StackBounds::GUARD_BYTES`] of it.
+    pub const fn overflow_suspected(&self, sp: usize) -> bool {
+        sp < self.lo.saturating_add(Self::GUARD_BYTES)
+    }
+
+    /// Checks whether a stack pointer is within [`StackBounds::TOP_BYTES`]
+    /// below the stack's upper bound, as the oldest frame's would be.
+    pub const fn is_near_top(&self, sp: usize) -> bool {
+        sp <= self.hi && self.hi - sp <= Self::TOP_BYTES
+    }
+
+    /// Registers the bounds of the stack that the calling code is running on,
+    /// replacing any bounds that were registered before, or clears the
+    /// registration with `None`.
+    ///
+    /// The bounds of the main stack can be made from the symbols that a
+    /// linker script defines around it; call this from the scheduler's task
+    /// switch hook to track other tasks' stacks.
+    pub fn set_current(bounds: Option<Self>) {
+        current::set(bounds);
+    }
+
+    /// Returns the bounds registered with [`StackBounds::set_current`], if
+    /// there are any.
+    pub fn current() -> Option<Self> {
+        current::get()
+    }
+}
+
+#[cfg(not(feature = "std"))]
+mod current {
+    use core::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::StackBounds;
+
+    static LO: AtomicUsize = AtomicUsize::new(0);
+    /// Zero while nothing is registered.
+    static HI: AtomicUsize = AtomicUsize::new(0);
+
+    pub(super) fn set(bounds: Option<StackBounds>) {
+        // Clearing the upper bound first keeps a reader that interrupts this
+        // from pairing the new lower bound with the old upper one.
+        HI.store(0, Ordering::Release);
+        if let Some(bounds) = bounds {
+            LO.store(bounds.lo, Ordering::Release);
+            HI.store(bounds.hi, Ordering::Release);
+        }
+    }
+
+    pub(super) fn get() -> Option<StackBounds> {
+        let hi = HI.load(Ordering::Acquire);
+        let lo = LO.load(Ordering::Acquire);
+        (hi != 0 && HI.load(Ordering::Acquire) == hi).then_some(StackBounds { lo, hi })
+    }
+}
+
+#[cfg(feature = "std")]
+mod current {
+    use std::cell::Cell;
+
+    use super::StackBounds;
+
+    std::thread_local! {
+        static CURRENT: Cell<Option<StackBounds>> = const { Cell::new(None) };
+    }
+
+    // `try_with` for the same reason as in the reentrancy guard: `with`
+    // panics while the thread is exiting.
+    pub(super) fn set(bounds: Option<StackBounds>) {
+        let _ = CURRENT.try_with(|current| current.set(bounds));
+    }
+
+    pub(super) fn get() -> Option<StackBounds> {
+        CURRENT.try_with(Cell::get).ok().flatten()
+    }
+}
@@ -0,0 +1,241 @@
+//! Options for how backtraces are printed.
+
+use core::fmt::{self, Write};
+
+use crate::{Backtrace, Symbol, SymbolizedBacktrace, TrimmedBacktrace};
+
+const DIM: &str = "\x1b[2m";
+const BOLD: &str = "\x1b[1m";
+const BOLD_YELLOW: &str = "\x1b[1;33m";
+const RED: &str = "\x1b[31m";
+const RESET: &str = "\x1b[0m";
+
+/// Options for printing a backtrace with [`Backtrace::display`] or
+/// [`SymbolizedBacktrace::display`].
+///
+/// The default style is the same plain text that the backtraces' `Display`
+/// implementations print.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BacktraceStyle {
+    order: FrameOrder,
+    color: bool,
+    narrow: Option<usize>,
+    stack_usage: bool,
+    module_base: Option<usize>,
+}
+
+impl BacktraceStyle {
+    /// Creates the default, plain style.
+    pub const fn new() -> Self {
+        Self {
+            order: FrameOrder::InnermostFirst,
+            color: false,
+            narrow: None,
+            stack_usage: false,
+            module_base: None,
+        }
+    }
+
+    /// Prints the frames in the given order.
+    ///
+    /// Frames are numbered from the innermost, `0`, in either order, so that
+    /// a frame's number is the same however it's printed. When the outermost
+    /// frame comes first, the header says so, and the notes about frames
+    /// missing past the outermost one come before it rather than at the end.
+    pub const fn order(mut self, order: FrameOrder) -> Self {
+        self.order = order;
+        self
+    }
+
+    /// Colors the output with ANSI escape sequences, for reading on a
+    /// terminal over the serial console: addresses are dimmed, names are
+    /// bold, and notes about missing frames are red. Names that are only a
+    /// guess at where the code came from, because they
+    /// [may hide inlined code](crate::Symbol::may_have_inlines), are bold
+    /// yellow instead. Every colored span is reset before the end of its line.
+    ///
+    /// Leave this off for output that goes anywhere else, such as the brain's
+    /// screen, which doesn't understand the escape sequences.
+    pub const fn color(mut self, color: bool) -> Self {
+        self.color = color;
+        self
+    }
+
+    /// Fits each frame onto a line at most `max_width` characters wide, for
+    /// small screens like the brain's, which fits about 48.
+    ///
+    /// Frames are printed as `#NN name+offset`, with names that don't fit cut
+    /// short with an ellipsis. Frames without names are printed as the lowest
+    /// five hex digits of their address, as in `#NN ..1f2a0`.
+    pub const fn narrow(mut self, max_width: usize) -> Self {
+        self.narrow = Some(max_width);
+        self
+    }
+
+    /// Appends each frame's stack usage, as measured by
+    /// [`Backtrace::stack_usage`], to the frame's line, as in `(+0x120 stack)`.
+    /// Frames whose usage can't be measured are printed as usual.
+    ///
+    /// This is left out of narrow output, which has no room for it.
+    pub const fn stack_usage(mut self, stack_usage: bool) -> Self {
+        self.stack_usage = stack_usage;
+        self
+    }
+
+    /// Appends each frame's address relative to `module_base`, as computed
+    /// by [`Frame::relative_ip`], to the frame's line, as in
+    /// `(base+0x1234)`, for looking frames up in a symbol file that wasn't
+    /// linked at the address the code runs at. Frames below the base are
+    /// printed as usual.
+    ///
+    /// This is left out of narrow output, which has no room for it.
+    ///
+    /// [`Frame::relative_ip`]: crate::Frame::relative_ip
+    pub const fn module_base(mut self, module_base: usize) -> Self {
+        self.module_base = Some(module_base);
+        self
+    }
+
+    pub(crate) const fn outermost_first(&self) -> bool {
+        matches!(self.order, FrameOrder::OutermostFirst)
+    }
+
+    /// Calls `f` with the index of each of `len` frames, in this style's
+    /// order.
+    pub(crate) fn for_each_frame(
+        &self,
+        len: usize,
+        f: impl FnMut(usize) -> fmt::Result,
+    ) -> fmt::Result {
+        match self.order {
+            FrameOrder::InnermostFirst => (0..len).try_for_each(f),
+            FrameOrder::OutermostFirst => (0..len).rev().try_for_each(f),
+        }
+    }
+
+    pub(crate) const fn narrow_width(&self) -> Option<usize> {
+        self.narrow
+    }
+
+    pub(crate) const fn shows_stack_usage(&self) -> bool {
+        self.stack_usage
+    }
+
+    pub(crate) const fn relative_base(&self) -> Option<usize> {
+        self.module_base
+    }
+
+    fn paint(&self, w: &mut impl Write, sgr: &str, text: impl fmt::Display) -> fmt::Result {
+        if self.color {
+            write!(w, "{sgr}{text}{RESET}")
+        } else {
+            write!(w, "{text}")
+        }
+    }
+
+    pub(crate) fn write_address(&self, w: &mut impl Write, address: usize) -> fmt::Result {
+        self.paint(w, DIM, format_args!("{address:#010x}"))
+    }
+
+    pub(crate) fn write_name(&self, w: &mut impl Write, symbol: Symbol<'_>) -> fmt::Result {
+        self.paint(w, name_sgr(symbol), symbol.name)
+    }
+
+    /// Prints a frame in the style set up by [`BacktraceStyle::narrow`].
+    pub(crate) fn write_narrow_frame(
+        &self,
+        w: &mut impl Write,
+        index: usize,
+        ip: usize,
+        name: Option<Symbol<'_>>,
+        max_width: usize,
+    ) -> fmt::Result {
+        write!(w, "#{index:02} ")?;
+        let Some(symbol) = name else {
+            return self.paint(w, DIM, format_args!("..{:05x}", ip & 0xf_ffff));
+        };
+
+        let (name, offset, sgr) = (symbol.name, symbol.offset, name_sgr(symbol));
+        let prefix = 2 + decimal_len(index).max(2);
+        let suffix = 3 + hex_len(offset);
+        let room = max_width.saturating_sub(prefix + suffix);
+        if name.chars().count() <= room {
+            self.paint(w, sgr, name)?;
+        } else if let Some(room) = room.checked_sub(1) {
+            // Cut on a character boundary, leaving a column for the ellipsis.
+            let end = name
+                .char_indices()
+                .nth(room)
+                .map_or(name.len(), |(end, _)| end);
+            self.paint(w, sgr, format_args!("{}\u{2026}", &name[..end]))?;
+        }
+        write!(w, "+{offset:#x}")
+    }
+
+    pub(crate) fn write_note(&self, w: &mut impl Write, note: &str) -> fmt::Result {
+        self.paint(w, RED, note)
+    }
+}
+
+/// The order that [`BacktraceStyle::order`] prints frames in.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum FrameOrder {
+    /// The frame the backtrace was captured in comes first, followed by its
+    /// callers, as gdb prints them.
+    #[default]
+    InnermostFirst,
+    /// The outermost frame comes first, followed by its callees, ending with
+    /// the frame the backtrace was captured in, as Python prints tracebacks.
+    OutermostFirst,
+}
+
+/// A backtrace printed in a particular [`BacktraceStyle`].
+#[derive(Debug, Clone, Copy)]
+pub struct Styled<'a, T> {
+    value: &'a T,
+    style: BacktraceStyle,
+}
+
+impl<'a, T> Styled<'a, T> {
+    pub(crate) const fn new(value: &'a T, style: BacktraceStyle) -> Self {
+        Self { value, style }
+    }
+}
+
+impl<const N: usize> fmt::Display for Styled<'_, Backtrace<N>> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.value.write_styled(f, self.style)
+    }
+}
+
+impl<const N: usize> fmt::Display for Styled<'_, SymbolizedBacktrace<'_, N>> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.value.write_styled(f, self.style)
+    }
+}
+
+impl<const N: usize> fmt::Display for Styled<'_, TrimmedBacktrace<'_, N>> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.value.write_styled(f, self.style)
+    }
+}
+
+/// Returns the color of a symbol's name: yellow if it's a guess.
+const fn name_sgr(symbol: Symbol<'_>) -> &'static str {
+    if symbol.may_have_inlines {
+        BOLD_YELLOW
+    } else {
+        BOLD
+    }
+}
+
+const fn decimal_len(value: usize) -> usize {
+    match value.checked_ilog10() {
+        Some(log) => log as usize + 1,
+        None => 1,
+    }
+}
+
+fn hex_len(value: u64) -> usize {
+    (64 - value.leading_zeros() as usize).div_ceil(4).max(1)
+}
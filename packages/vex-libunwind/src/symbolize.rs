@@ -0,0 +1,191 @@
+//! Naming the frames of a backtrace after it's been captured.
+//!
+//! Capturing a [`Backtrace`] only records addresses, which keeps it fast
+//! enough for the moment of failure. Looking up names can be much slower (the
+//! symbols might come from a file on the SD card), so it's done separately, by
+//! wrapping the backtrace in a [`SymbolizedBacktrace`] and resolving it with a
+//! [`Symbolize`] implementation whenever it's convenient, or never.
+//!
+//! Each frame gets one name: the procedure that the compiler emitted code
+//! for. Functions inlined into it don't have frames of their own, and naming
+//! them takes the DWARF line info that `libunwind` doesn't read, so that's
+//! left to resolvers that have it. [`Symbol::may_have_inlines`] records
+//! whether a name might be hiding some.
+
+use core::fmt::{self, Write};
+
+use crate::{
+    backtrace::{write_frame, write_overflow_note, write_status_note, write_stop_note},
+    sink::write_chunked,
+    Backtrace, BacktraceStyle, Frame, Styled,
+};
+
+/// The name of the procedure containing an address.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Symbol<'a> {
+    /// The procedure's name.
+    pub name: &'a str,
+    /// The address's offset from the start of the procedure.
+    pub offset: u64,
+    /// Whether the address might be in code inlined from other functions,
+    /// which `name` doesn't mention.
+    ///
+    /// A resolver that reads inline info from the debug info can tell, and
+    /// should set this only when the address is in an inlined call. One that
+    /// only has a symbol table can't, and should always set it.
+    pub may_have_inlines: bool,
+}
+
+impl<'a> Symbol<'a> {
+    /// Makes a symbol from a name found in a symbol table, which can't tell
+    /// whether there's inlined code at the address. Only hosts' `libunwind`
+    /// can find names to make these from.
+    #[cfg(all(not(target_arch = "arm"), not(feature = "disabled")))]
+    pub(crate) const fn from_symbol_table(name: &'a str, offset: u64) -> Self {
+        Self {
+            name,
+            offset,
+            may_have_inlines: true,
+        }
+    }
+}
+
+/// Looks up the procedures that addresses belong to.
+pub trait Symbolize {
+    /// Returns the symbol containing the given address, or `None` if it isn't
+    /// known.
+    ///
+    /// For every frame but the innermost, the address is a return address,
+    /// which is just past the call instruction and may be past the end of the
+    /// calling procedure when the call is its last instruction.
+    fn symbolize(&self, ip: usize) -> Option<Symbol<'_>>;
+}
+
+/// A resolver that doesn't know any names, for output that leaves every
+/// address to be resolved on a host.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RawAddresses;
+
+impl Symbolize for RawAddresses {
+    fn symbolize(&self, _ip: usize) -> Option<Symbol<'_>> {
+        None
+    }
+}
+
+/// A [`Backtrace`] along with the names of its frames, as far as they've been
+/// resolved.
+///
+/// This stores a [`Symbol`] per frame on top of the backtrace, so it's kept
+/// separate from [`Backtrace`] for code that never resolves names on the
+/// device.
+#[derive(Debug, Clone)]
+pub struct SymbolizedBacktrace<'a, const N: usize = 32> {
+    backtrace: Backtrace<N>,
+    symbols: [Option<Symbol<'a>>; N],
+}
+
+impl<'a, const N: usize> SymbolizedBacktrace<'a, N> {
+    /// Wraps a backtrace that doesn't have any names yet.
+    pub const fn new(backtrace: Backtrace<N>) -> Self {
+        Self {
+            backtrace,
+            symbols: [None; N],
+        }
+    }
+
+    /// Looks up the names of the frames that haven't been named yet.
+    ///
+    /// Frames that the resolver doesn't know are left unnamed, so several
+    /// resolvers can be tried one after another.
+    pub fn resolve(&mut self, resolver: &'a impl Symbolize) {
+        let frames = self.backtrace.frames();
+        for (symbol, frame) in self.symbols.iter_mut().zip(frames) {
+            if symbol.is_none() {
+                *symbol = resolver.symbolize(frame.ip);
+            }
+        }
+    }
+
+    /// Looks up the names of the frames that haven't been named yet, like
+    /// [`SymbolizedBacktrace::resolve`], but passing the resolver each address
+    /// relative to `module_base`, as computed by [`Frame::relative_ip`].
+    ///
+    /// This is for resolvers built from a symbol file that wasn't linked at
+    /// the address the code runs at. Frames below the base are skipped.
+    pub fn resolve_relative(&mut self, resolver: &'a impl Symbolize, module_base: usize) {
+        let frames = self.backtrace.frames();
+        for (symbol, frame) in self.symbols.iter_mut().zip(frames) {
+            if let (None, Some(ip)) = (*symbol, frame.relative_ip(module_base)) {
+                *symbol = resolver.symbolize(ip);
+            }
+        }
+    }
+
+    /// Returns the underlying backtrace.
+    pub const fn backtrace(&self) -> &Backtrace<N> {
+        &self.backtrace
+    }
+
+    /// Returns the captured frames along with their names, innermost first.
+    pub fn frames(&self) -> impl Iterator<Item = (&Frame, Option<Symbol<'a>>)> + '_ {
+        self.backtrace
+            .frames()
+            .iter()
+            .zip(self.symbols.iter().copied())
+    }
+}
+
+impl<const N: usize> SymbolizedBacktrace<'_, N> {
+    /// Prints the backtrace to a transport that takes bytes, passing along
+    /// the transport's errors; see [`Backtrace::write_bytes`].
+    ///
+    /// # Errors
+    ///
+    /// Returns the first error returned by `sink`.
+    pub fn write_bytes<E>(&self, sink: impl FnMut(&[u8]) -> Result<(), E>) -> Result<(), E> {
+        write_chunked(self, sink)
+    }
+
+    /// Returns an object that prints the backtrace in the given style.
+    pub const fn display(&self, style: BacktraceStyle) -> Styled<'_, Self> {
+        Styled::new(self, style)
+    }
+
+    pub(crate) fn write_styled(&self, w: &mut impl Write, style: BacktraceStyle) -> fmt::Result {
+        if write_status_note(w, self.backtrace.status(), style)? {
+            return Ok(());
+        }
+        write_overflow_note(w, self.backtrace.overflow_suspected(), style)?;
+        let frames = self.backtrace.frames();
+        if style.outermost_first() {
+            write_stop_note(w, self.backtrace.stop_reason(), frames.len())?;
+        }
+        style.for_each_frame(frames.len(), |index| {
+            let stack_usage = self.backtrace.frame_stack_usage(index);
+            write_frame(
+                w,
+                index,
+                &frames[index],
+                self.symbols[index],
+                stack_usage,
+                style,
+            )
+        })?;
+        if style.outermost_first() {
+            return Ok(());
+        }
+        write_stop_note(w, self.backtrace.stop_reason(), frames.len())
+    }
+}
+
+impl<const N: usize> From<Backtrace<N>> for SymbolizedBacktrace<'_, N> {
+    fn from(backtrace: Backtrace<N>) -> Self {
+        Self::new(backtrace)
+    }
+}
+
+impl<const N: usize> fmt::Display for SymbolizedBacktrace<'_, N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.write_styled(f, BacktraceStyle::new())
+    }
+}
@@ -0,0 +1,186 @@
+//! Reporting each call into `libunwind`, for working out why a walk went
+//! wrong.
+//!
+//! With the `trace` feature, a hook set with [`set_trace_hook`] is handed a
+//! [`TraceEvent`] for each context captured, cursor initialized, step taken,
+//! and procedure looked up. Without it, none of this is compiled in, and
+//! stepping calls `unw_step` directly.
+
+#[cfg(feature = "trace")]
+use core::{
+    ffi::c_int,
+    ptr,
+    sync::atomic::{AtomicPtr, Ordering},
+};
+
+use vex_libunwind_sys::{unw_cursor_t, unw_step};
+#[cfg(feature = "trace")]
+use vex_libunwind_sys::{unw_get_reg, uw_error_t, UNW_STEP_SUCCESS};
+
+#[cfg(feature = "trace")]
+use crate::{registers, Frame};
+
+/// Something `libunwind` was asked to do, and what came of it, as handed to
+/// the hook set with [`set_trace_hook`].
+///
+/// Codes are `libunwind`'s return values: zero for success (or, for a step,
+/// for reaching the end of the stack), positive for a successful step, and
+/// negative for an error, which [`UnwindError::from_code`] turns into an
+/// [`UnwindError`].
+///
+/// [`UnwindError`]: crate::UnwindError
+/// [`UnwindError::from_code`]: crate::UnwindError::from_code
+#[cfg(feature = "trace")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum TraceEvent {
+    /// A context was captured with `unw_getcontext`.
+    Context {
+        /// What `unw_getcontext` returned.
+        code: uw_error_t,
+    },
+    /// A cursor was initialized from a context with `unw_init_local`.
+    Cursor {
+        /// What `unw_init_local` returned.
+        code: uw_error_t,
+    },
+    /// A cursor was stepped to its caller with `unw_step`.
+    Step {
+        /// The frame the cursor was at before the step. Registers that
+        /// couldn't be read are zero.
+        before: Frame,
+        /// What `unw_step` returned.
+        code: c_int,
+        /// The frame the cursor stepped to, if it did.
+        after: Option<Frame>,
+    },
+    /// A procedure's info was looked up with `unw_get_proc_info`.
+    ProcInfo {
+        /// The instruction pointer of the cursor's frame, or zero if it
+        /// couldn't be read.
+        ip: usize,
+        /// What `unw_get_proc_info` returned; [`UnwindError::NoInfo`] means
+        /// there was no unwind info for the address.
+        ///
+        /// [`UnwindError::NoInfo`]: crate::UnwindError::NoInfo
+        code: uw_error_t,
+    },
+}
+
+/// The hook, as a data pointer so that it can be swapped atomically. Null
+/// means there isn't one.
+#[cfg(feature = "trace")]
+static HOOK: AtomicPtr<()> = AtomicPtr::new(ptr::null_mut());
+
+/// Sets the function that's handed a [`TraceEvent`] for each call into
+/// `libunwind`, or removes it with `None`. Only available with the `trace`
+/// feature.
+///
+/// The hook is global and is called from whichever thread (or handler) is
+/// unwinding, in the middle of the walk. Events are plain data, so handing
+/// them over never allocates, and a hook that doesn't allocate either is
+/// safe to use from a panic handler. The hook shouldn't capture or print a
+/// backtrace itself: it's called while the cursor it's reporting on is in
+/// use, and the reentrancy guard makes such backtraces come out empty.
+///
+/// This crate doesn't depend on a logging crate, so forwarding events to
+/// one is up to the hook:
+///
+/// ```ignore
+/// vex_libunwind::set_trace_hook(Some(|event| log::trace!("{event:?}")));
+/// ```
+///
+/// Events are made of integers, so a hook can also hand them to `defmt`
+/// field by field, keeping `core::fmt` out of the fault path altogether:
+///
+/// ```ignore
+/// fn defmt_hook(event: &TraceEvent) {
+///     match *event {
+///         TraceEvent::Step { before, code, after } => defmt::trace!(
+///             "step {=usize:x} {=usize:x} -> {=i32} {=usize:x}",
+///             before.ip,
+///             before.sp,
+///             code,
+///             after.map_or(0, |frame| frame.ip),
+///         ),
+///         TraceEvent::ProcInfo { ip, code } => {
+///             defmt::trace!("proc info {=usize:x} -> {=i32}", ip, code)
+///         }
+///         _ => {}
+///     }
+/// }
+/// ```
+#[cfg(feature = "trace")]
+pub fn set_trace_hook(hook: Option<fn(&TraceEvent)>) {
+    let hook = hook.map_or(ptr::null_mut(), |hook| hook as *mut ());
+    HOOK.store(hook, Ordering::Release);
+}
+
+#[cfg(feature = "trace")]
+fn hook() -> Option<fn(&TraceEvent)> {
+    let hook = HOOK.load(Ordering::Acquire);
+    // SAFETY: Non-null values are only ever stored by `set_trace_hook`, from
+    // a function pointer of this type.
+    (!hook.is_null()).then(|| unsafe { core::mem::transmute::<*mut (), fn(&TraceEvent)>(hook) })
+}
+
+/// Hands the event made by `event` to the hook, if there is one.
+#[cfg(feature = "trace")]
+pub(crate) fn emit(event: impl FnOnce() -> TraceEvent) {
+    if let Some(hook) = hook() {
+        hook(&event());
+    }
+}
+
+/// Hands a [`TraceEvent`] variant to the hook, or does nothing without the
+/// `trace` feature, in which case the arguments aren't even evaluated.
+macro_rules! trace_event {
+    ($($event:tt)*) => {
+        #[cfg(feature = "trace")]
+        $crate::trace::emit(|| $crate::trace::TraceEvent::$($event)*);
+    };
+}
+pub(crate) use trace_event;
+
+/// Steps a cursor with `unw_step`, telling the hook where it went from and
+/// to.
+///
+/// # Safety
+///
+/// `cursor` must point to an initialized cursor.
+pub(crate) unsafe fn step(cursor: *mut unw_cursor_t) -> core::ffi::c_int {
+    #[cfg(feature = "trace")]
+    if let Some(hook) = hook() {
+        // SAFETY: The caller guarantees that the cursor is initialized.
+        unsafe {
+            let before = frame(cursor);
+            let code = unw_step(cursor);
+            let after = (code == UNW_STEP_SUCCESS).then(|| frame(cursor));
+            hook(&TraceEvent::Step {
+                before,
+                code,
+                after,
+            });
+            return code;
+        }
+    }
+    // SAFETY: The caller guarantees that the cursor is initialized.
+    unsafe { unw_step(cursor) }
+}
+
+/// Reads the frame a cursor is at, leaving registers that can't be read as
+/// zero.
+///
+/// # Safety
+///
+/// `cursor` must point to an initialized cursor.
+#[cfg(feature = "trace")]
+pub(crate) unsafe fn frame(cursor: *mut unw_cursor_t) -> Frame {
+    let (mut ip, mut sp) = (0, 0);
+    // SAFETY: The caller guarantees that the cursor is initialized.
+    unsafe {
+        unw_get_reg(cursor, registers::UNW_REG_IP, &mut ip);
+        unw_get_reg(cursor, registers::UNW_REG_SP, &mut sp);
+    }
+    Frame { ip, sp }
+}
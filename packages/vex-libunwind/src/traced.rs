@@ -0,0 +1,122 @@
+//! Errors that remember where they were created.
+
+use core::{
+    fmt,
+    ops::{Deref, DerefMut},
+};
+
+use crate::Backtrace;
+
+/// An error along with a backtrace of where it was created, for finding out
+/// where an `Err` came from after it's been passed up a long way.
+///
+/// Converting an error into a `Traced` captures the backtrace, so `?` does it
+/// automatically:
+///
+/// ```
+/// # use vex_libunwind::Traced;
+/// #[derive(Debug)]
+/// struct MotorError;
+///
+/// fn spin() -> Result<(), MotorError> {
+///     Err(MotorError)
+/// }
+///
+/// fn drive() -> Result<(), Traced<MotorError>> {
+///     spin()?;
+///     Ok(())
+/// }
+///
+/// let error = drive().unwrap_err();
+/// println!("{:?} at\n{}", *error, error.backtrace());
+/// ```
+///
+/// The backtrace starts in the function that converted the error, and holds
+/// up to `N` frames, so nothing is allocated. It's captured as with
+/// [`Backtrace::capture`], so it's empty if capture has been turned off with
+/// [`set_capture_enabled`](crate::set_capture_enabled), and with the
+/// `disabled` feature, a `Traced` is no bigger than the error itself.
+#[derive(Debug, Clone)]
+pub struct Traced<E, const N: usize = 32> {
+    error: E,
+    backtrace: Backtrace<N>,
+}
+
+impl<E, const N: usize> Traced<E, N> {
+    /// Wraps an error, capturing a backtrace of the calling function and its
+    /// callers.
+    #[inline(always)] // Inlining keeps this function from appearing in backtraces
+    pub fn new(error: E) -> Self {
+        Self {
+            error,
+            backtrace: Backtrace::capture(),
+        }
+    }
+
+    /// Wraps an error along with a backtrace that was captured already.
+    pub const fn with_backtrace(error: E, backtrace: Backtrace<N>) -> Self {
+        Self { error, backtrace }
+    }
+
+    /// Returns the backtrace of where the error was created.
+    pub const fn backtrace(&self) -> &Backtrace<N> {
+        &self.backtrace
+    }
+
+    /// Returns the error, dropping the backtrace.
+    pub fn into_inner(self) -> E {
+        self.error
+    }
+
+    /// Returns the error and the backtrace.
+    pub fn into_parts(self) -> (E, Backtrace<N>) {
+        (self.error, self.backtrace)
+    }
+
+    /// Turns the error into another one, keeping the backtrace of where the
+    /// original was created.
+    pub fn map<F>(self, f: impl FnOnce(E) -> F) -> Traced<F, N> {
+        Traced {
+            error: f(self.error),
+            backtrace: self.backtrace,
+        }
+    }
+}
+
+impl<E, const N: usize> From<E> for Traced<E, N> {
+    #[inline(always)] // Inlining keeps this function from appearing in backtraces
+    fn from(error: E) -> Self {
+        Self::new(error)
+    }
+}
+
+impl<E, const N: usize> Deref for Traced<E, N> {
+    type Target = E;
+
+    fn deref(&self) -> &E {
+        &self.error
+    }
+}
+
+impl<E, const N: usize> DerefMut for Traced<E, N> {
+    fn deref_mut(&mut self) -> &mut E {
+        &mut self.error
+    }
+}
+
+/// Prints the error, then the backtrace on the lines after it.
+impl<E: fmt::Display, const N: usize> fmt::Display for Traced<E, N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}", self.error)?;
+        fmt::Display::fmt(&self.backtrace, f)
+    }
+}
+
+/// The error's source is the wrapped error's source, since the wrapped
+/// error's own message is already part of this one's.
+#[cfg(feature = "std")]
+impl<E: std::error::Error, const N: usize> std::error::Error for Traced<E, N> {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        self.error.source()
+    }
+}
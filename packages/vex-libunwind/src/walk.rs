@@ -0,0 +1,450 @@
+//! Walking the call chain one frame at a time.
+
+use core::ops::Range;
+
+use vex_libunwind_sys::registers;
+
+use crate::{ProcInfo, StackBounds, UnwindCursor, UnwindError};
+
+/// A snapshot of a single stack frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub struct Frame {
+    /// The frame's instruction pointer.
+    pub ip: usize,
+    /// The frame's stack pointer.
+    pub sp: usize,
+}
+
+impl Frame {
+    /// How close to the end of its procedure a return address has to be for
+    /// [`possible_tail_call`](Frame::possible_tail_call) to flag it: the
+    /// length of the longest call instruction.
+    pub const TAIL_CALL_SLACK: usize = 4;
+
+    /// Reads the frame the given cursor is currently pointing to.
+    ///
+    /// This reads the registers with [`UnwindCursor::try_register`], so it
+    /// never panics.
+    ///
+    /// # Errors
+    ///
+    /// See [`UnwindCursor::try_register`].
+    pub fn from_cursor(cursor: &UnwindCursor) -> Result<Self, UnwindError> {
+        Ok(Self {
+            ip: cursor.try_register(registers::UNW_REG_IP)?,
+            sp: cursor.try_register(registers::UNW_REG_SP)?,
+        })
+    }
+
+    /// Returns the frame's instruction pointer as an offset from the address
+    /// a module was loaded at, or [`None`] if it's below that address.
+    ///
+    /// A host tool can look the offset up in a symbol file linked at any
+    /// address, so builds of relocated code can share one. On the V5, the
+    /// cold image is loaded at `0x0380_0000`, and the start of its code (if
+    /// the linker script marks it) is [`text_range`](crate::text_range)'s
+    /// start. A hot image's base is the
+    /// [`load_base`](crate::hot_image::HotImageDescriptor::load_base) its
+    /// location was recorded with.
+    pub const fn relative_ip(&self, module_base: usize) -> Option<usize> {
+        self.ip.checked_sub(module_base)
+    }
+
+    /// Guesses whether frames may be missing between this frame and its
+    /// callee because the callee was reached by a tail call, given the info
+    /// of this frame's procedure.
+    ///
+    /// A function that ends by calling another can jump to it instead, so the
+    /// callee returns straight to this frame and the function that jumped has
+    /// no frame of its own. `libunwind` can't recover such frames. This flags
+    /// a frame whose return address is within the last
+    /// [`TAIL_CALL_SLACK`](Frame::TAIL_CALL_SLACK) bytes of its procedure (or
+    /// just past its end), where the call was the procedure's last
+    /// instruction. That's only a heuristic: calls to functions that never
+    /// return end procedures the same way, and a tail call from a function in
+    /// the middle of its caller isn't caught.
+    ///
+    /// Only the frames of callers have return addresses, so the answer means
+    /// nothing for the innermost frame or for the frame a signal or fault
+    /// interrupted.
+    pub const fn possible_tail_call(&self, proc_info: &ProcInfo) -> bool {
+        proc_info.start_ip() < self.ip
+            && self.ip <= proc_info.end_ip()
+            && proc_info.end_ip() - self.ip <= Self::TAIL_CALL_SLACK
+    }
+}
+
+/// How many of the most recent frames a [`Frames`] walk remembers, to notice
+/// the call chain looping back on itself.
+pub const CYCLE_WINDOW: usize = 8;
+
+/// The size of the buffer used to look up names for
+/// [`WalkConfig::stop_at_symbol`].
+const SYMBOL_BUFFER_SIZE: usize = 128;
+
+/// Options controlling how a [`Frames`] iterator walks the call chain.
+#[derive(Debug, Clone, Default)]
+pub struct WalkConfig<'a> {
+    stop_ranges: &'a [Range<usize>],
+    stop_symbol: Option<&'a str>,
+    stop_unwinding_is_end: bool,
+    entry_ranges: &'a [Range<usize>],
+}
+
+impl<'a> WalkConfig<'a> {
+    /// Creates a configuration that walks all the way to the end of the stack.
+    pub const fn new() -> Self {
+        Self {
+            stop_ranges: &[],
+            stop_symbol: None,
+            stop_unwinding_is_end: false,
+            entry_ranges: &[],
+        }
+    }
+
+    /// Ends the walk at the first frame whose instruction pointer is inside
+    /// any of the given ranges, as if the end of the stack was reached.
+    ///
+    /// This is useful for leaving frames from code that isn't interesting to
+    /// the reader (such as the RTOS kernel) out of a backtrace. The frame
+    /// inside the range is not yielded.
+    pub const fn stop_in_ranges(mut self, ranges: &'a [Range<usize>]) -> Self {
+        self.stop_ranges = ranges;
+        self
+    }
+
+    /// Ends the walk after the first frame in a procedure with the given name,
+    /// such as `"main"`, leaving out the runtime startup code below it. The
+    /// frame in the named procedure is yielded. If no procedure has the name,
+    /// the walk goes to the end of the stack as usual.
+    ///
+    /// Names are looked up with [`UnwindCursor::procedure_name`], which only
+    /// works on hosts; on the V5, use [`WalkConfig::stop_in_ranges`] with the
+    /// address range of `main` instead. Names longer than 127 bytes never
+    /// match.
+    pub const fn stop_at_symbol(mut self, name: &'a str) -> Self {
+        self.stop_symbol = Some(name);
+        self
+    }
+
+    /// Treats [`UnwindError::StopUnwinding`] from a step as the end of the
+    /// stack rather than an error, when `enabled` is `true`. By default, it's
+    /// yielded like any other error.
+    ///
+    /// `libunwind` returns this error when unwinding was halted on purpose
+    /// rather than because something went wrong: for example, when a
+    /// personality routine or stop function asked for it, or when code at the
+    /// bottom of a stack (such as an RTOS task's entry point) has unwind info
+    /// saying it can't be unwound past. Walks that are expected to reach such
+    /// a frame can turn this on to end cleanly there.
+    pub const fn stop_unwinding_ends_walk(mut self, enabled: bool) -> Self {
+        self.stop_unwinding_is_end = enabled;
+        self
+    }
+
+    /// Gives the address ranges of code that stacks start in, such as an
+    /// RTOS's task entry stubs or the runtime's startup code.
+    ///
+    /// Code like this often has no unwind info, so stepping past it fails
+    /// with [`UnwindError::NoInfo`]. That error would usually mean a gap in
+    /// the unwind tables, but from a frame inside these ranges, it's taken to
+    /// mean the walk reached the root of the call tree: the walk ends cleanly
+    /// and [`Frames::end`] says [`WalkEnd::Root`]. A frame whose stack
+    /// pointer is near the top of the registered [`StackBounds`] counts as a
+    /// root too, even without any ranges given.
+    pub const fn entry_ranges(mut self, ranges: &'a [Range<usize>]) -> Self {
+        self.entry_ranges = ranges;
+        self
+    }
+
+    fn should_stop(&self, frame: &Frame) -> bool {
+        self.stop_ranges
+            .iter()
+            .any(|range| range.contains(&frame.ip))
+    }
+
+    fn is_last(&self, cursor: &UnwindCursor) -> bool {
+        let Some(symbol) = self.stop_symbol else {
+            return false;
+        };
+        let mut buf = [0; SYMBOL_BUFFER_SIZE];
+        // Compared as bytes, since a name that isn't UTF-8 can't match.
+        cursor
+            .try_raw_procedure_name(&mut buf)
+            .is_ok_and(|(len, _)| buf.get(..len) == Some(symbol.as_bytes()))
+    }
+}
+
+/// Checks whether a frame looks like the oldest one on its stack: it's in one
+/// of the entry ranges, or its stack pointer is near the top of the
+/// registered stack.
+pub(crate) fn is_plausible_root(frame: &Frame, entry_ranges: &[Range<usize>]) -> bool {
+    entry_ranges.iter().any(|range| range.contains(&frame.ip))
+        || StackBounds::current().is_some_and(|bounds| bounds.is_near_top(frame.sp))
+}
+
+/// Why a [`Frames`] walk ended; see [`Frames::end`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum WalkEnd {
+    /// `libunwind` reported that there are no more frames.
+    EndOfStack,
+    /// Unwind info ran out at a frame that looks like the root of the call
+    /// tree, as described at [`WalkConfig::entry_ranges`].
+    Root,
+    /// The [`WalkConfig`] ended the walk early.
+    Stopped,
+    /// A frame repeated one of the last [`CYCLE_WINDOW`] frames, so the call
+    /// chain loops back on itself, as only a corrupted stack does.
+    /// [`UnwindError::BadFrame`] was yielded in place of the repeated frame.
+    Loop,
+    /// The walk failed with the given error, which was also yielded.
+    Error(UnwindError),
+}
+
+/// An iterator over the frames of a call chain, starting with the cursor's
+/// current frame and moving toward older frames.
+///
+/// If stepping fails, the error is yielded and the iterator ends.
+///
+/// A stack corrupted so that a frame's saved registers lead back to a frame
+/// that was already walked would otherwise be walked around forever. Each
+/// frame is compared with the last [`CYCLE_WINDOW`] frames, and if both its
+/// instruction and stack pointers match one of them, the walk yields
+/// [`UnwindError::BadFrame`] and ends there, with [`Frames::end`] saying
+/// [`WalkEnd::Loop`]. Recursion, however deep, moves
+/// the stack pointer with every call, so it never matches. A cycle of more
+/// than [`CYCLE_WINDOW`] frames isn't noticed, so capturing and counting
+/// functions still stop after a fixed number of frames.
+///
+/// Walking never panics, so it's safe to do from a panic handler: the
+/// cursor's registers are read with [`UnwindCursor::try_register`], and
+/// nothing on the way from one frame to the next unwraps, indexes out of
+/// bounds, or overflows. Whatever the [`WalkConfig`] and the hook set with
+/// `set_trace_hook` run is up to them.
+#[derive(Debug)]
+pub struct Frames<'a> {
+    cursor: &'a mut UnwindCursor,
+    config: WalkConfig<'a>,
+    started: bool,
+    /// Set once the frame that ends the walk has been yielded.
+    last: bool,
+    done: bool,
+    seen: usize,
+    /// The frame yielded last.
+    current: Option<Frame>,
+    /// The last [`CYCLE_WINDOW`] frames yielded, with the one yielded `n`
+    /// frames into the walk at `n % CYCLE_WINDOW`.
+    recent: [Frame; CYCLE_WINDOW],
+    end: Option<WalkEnd>,
+}
+
+impl<'a> Frames<'a> {
+    /// Creates an iterator that walks the given cursor.
+    pub fn new(cursor: &'a mut UnwindCursor, config: WalkConfig<'a>) -> Self {
+        Self {
+            cursor,
+            config,
+            started: false,
+            last: false,
+            done: false,
+            seen: 0,
+            current: None,
+            recent: [Frame { ip: 0, sp: 0 }; CYCLE_WINDOW],
+            end: None,
+        }
+    }
+
+    /// Returns the number of frames yielded so far.
+    pub const fn frames_seen(&self) -> usize {
+        self.seen
+    }
+
+    /// Returns why the walk ended, or [`None`] if it hasn't yet.
+    ///
+    /// This tells apart the ways a walk can end without an error, which all
+    /// look the same from the iterator. In particular, a walk that ran out of
+    /// unwind info ends with [`WalkEnd::Root`] if that happened somewhere the
+    /// stack could plausibly start, and with an [`UnwindError::NoInfo`] error
+    /// otherwise.
+    pub const fn end(&self) -> Option<WalkEnd> {
+        self.end
+    }
+
+    /// Returns the cursor being walked, which points to the frame yielded
+    /// last.
+    #[cfg(not(feature = "disabled"))]
+    pub(crate) fn cursor(&self) -> &UnwindCursor {
+        self.cursor
+    }
+
+    /// Makes the walk stop early if `cancel` returns `true`.
+    ///
+    /// `cancel` is called between frames, before stepping to each frame after
+    /// the first. Once it returns `true`, [`UnwindError::Interrupted`] is
+    /// yielded and the iterator ends. The frames yielded before that are
+    /// still valid, so a task can collect what it has so far and give up on
+    /// the rest of an expensive walk.
+    pub const fn cancel_when<F: FnMut() -> bool>(self, cancel: F) -> CancellableFrames<'a, F> {
+        CancellableFrames {
+            frames: self,
+            cancel,
+        }
+    }
+
+    /// Pairs each frame with its caller, yielding `(callee, caller)` for
+    /// every frame but the last, which has no caller.
+    ///
+    /// A walk of `n` frames gives `n - 1` pairs. If stepping fails, the error
+    /// is yielded in place of the pair that would have needed the frame, and
+    /// the iterator ends.
+    pub const fn pairs(self) -> FramePairs<'a> {
+        FramePairs {
+            frames: self,
+            callee: None,
+        }
+    }
+
+    fn advance(&mut self) -> Result<Option<Frame>, UnwindError> {
+        if self.started {
+            if self.last {
+                self.end = Some(WalkEnd::Stopped);
+                return Ok(None);
+            }
+            if !self.step()? {
+                return Ok(None);
+            }
+        }
+        self.started = true;
+
+        let frame = Frame::from_cursor(self.cursor)?;
+        if self
+            .recent
+            .iter()
+            .take(self.seen)
+            .any(|seen| *seen == frame)
+        {
+            self.end = Some(WalkEnd::Loop);
+            return Err(UnwindError::BadFrame);
+        }
+        if self.config.should_stop(&frame) {
+            self.end = Some(WalkEnd::Stopped);
+            return Ok(None);
+        }
+        self.last = self.config.is_last(self.cursor);
+        self.current = Some(frame);
+        if let Some(slot) = self.recent.get_mut(self.seen % CYCLE_WINDOW) {
+            *slot = frame;
+        }
+        Ok(Some(frame))
+    }
+
+    /// Steps to the next frame, returning `false` and recording why if the
+    /// walk ends cleanly instead.
+    fn step(&mut self) -> Result<bool, UnwindError> {
+        let end = match self.cursor.step() {
+            Ok(true) => return Ok(true),
+            Ok(false) => WalkEnd::EndOfStack,
+            Err(UnwindError::StopUnwinding) if self.config.stop_unwinding_is_end => {
+                WalkEnd::Stopped
+            }
+            Err(UnwindError::NoInfo)
+                if self
+                    .current
+                    .is_some_and(|frame| is_plausible_root(&frame, self.config.entry_ranges)) =>
+            {
+                WalkEnd::Root
+            }
+            Err(error) => return Err(error),
+        };
+        self.end = Some(end);
+        Ok(false)
+    }
+}
+
+impl Iterator for Frames<'_> {
+    type Item = Result<Frame, UnwindError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        let result = self.advance();
+        match result {
+            Ok(Some(_)) => self.seen = self.seen.saturating_add(1),
+            Ok(None) => self.done = true,
+            Err(error) => {
+                self.done = true;
+                self.end.get_or_insert(WalkEnd::Error(error));
+            }
+        }
+        result.transpose()
+    }
+}
+
+/// A [`Frames`] iterator that can be stopped partway through; see
+/// [`Frames::cancel_when`].
+#[derive(Debug)]
+pub struct CancellableFrames<'a, F> {
+    frames: Frames<'a>,
+    cancel: F,
+}
+
+impl<F> CancellableFrames<'_, F> {
+    /// Returns the number of frames yielded so far.
+    pub const fn frames_seen(&self) -> usize {
+        self.frames.seen
+    }
+}
+
+impl<F: FnMut() -> bool> Iterator for CancellableFrames<'_, F> {
+    type Item = Result<Frame, UnwindError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.frames.started && !self.frames.done && (self.cancel)() {
+            self.frames.done = true;
+            self.frames.end = Some(WalkEnd::Error(UnwindError::Interrupted));
+            return Some(Err(UnwindError::Interrupted));
+        }
+        self.frames.next()
+    }
+}
+
+/// A [`Frames`] iterator that yields each frame along with its caller; see
+/// [`Frames::pairs`].
+#[derive(Debug)]
+pub struct FramePairs<'a> {
+    frames: Frames<'a>,
+    /// The caller from the pair yielded last, which is the next pair's callee.
+    callee: Option<Frame>,
+}
+
+impl FramePairs<'_> {
+    /// Returns why the walk ended, or [`None`] if it hasn't yet; see
+    /// [`Frames::end`].
+    pub const fn end(&self) -> Option<WalkEnd> {
+        self.frames.end
+    }
+}
+
+impl Iterator for FramePairs<'_> {
+    type Item = Result<(Frame, Frame), UnwindError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let callee = match self.callee.take() {
+            Some(callee) => callee,
+            None => match self.frames.next()? {
+                Ok(callee) => callee,
+                Err(error) => return Some(Err(error)),
+            },
+        };
+        let caller = match self.frames.next()? {
+            Ok(caller) => caller,
+            Err(error) => return Some(Err(error)),
+        };
+        self.callee = Some(caller);
+        Some(Ok((callee, caller)))
+    }
+}
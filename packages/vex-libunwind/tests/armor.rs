@@ -0,0 +1,116 @@
+//! Round trips through the text armor, including the kinds of mistakes a
+//! person copying it by hand makes.
+
+use vex_libunwind::{
+    armor::*,
+    encoding::{encode_ips, max_encoded_len, FrameEncoding},
+    Backtrace,
+};
+
+fn armored(bytes: &[u8], line_width: Option<usize>) -> String {
+    let mut text = String::new();
+    encode_text(bytes, line_width, &mut text).unwrap();
+    text
+}
+
+fn decoded(text: &str) -> Result<Vec<u8>, TextDecodeError> {
+    let mut out = vec![0; text.len()];
+    let len = decode_text(text, &mut out)?;
+    out.truncate(len);
+    Ok(out)
+}
+
+#[test]
+fn every_length_round_trips() {
+    let bytes: Vec<u8> = (0..=255).rev().collect();
+    for len in 0..64 {
+        let text = armored(&bytes[..len], None);
+        assert!(text.starts_with(PREFIX));
+        assert_eq!(decoded(&text).unwrap(), bytes[..len], "{text}");
+    }
+}
+
+#[test]
+fn encoded_backtrace_round_trips() {
+    let backtrace = Backtrace::<32>::capture();
+    let ips = backtrace.frames().iter().map(|frame| frame.ip);
+    let mut bytes = vec![0; max_encoded_len(ips.len())];
+    let len = encode_ips(ips, FrameEncoding::Delta, &mut bytes).unwrap();
+    bytes.truncate(len);
+
+    let text = armored(&bytes, Some(48));
+    assert_eq!(decoded(&text).unwrap(), bytes);
+}
+
+#[test]
+fn lines_are_wrapped_between_groups() {
+    let bytes = [0x5a; 40];
+    let text = armored(&bytes, Some(24));
+    let lines: Vec<_> = text.lines().collect();
+    assert!(lines.len() > 1, "{text}");
+    assert!(lines.iter().all(|line| line.len() <= 24), "{text}");
+    assert!(
+        lines[1..].iter().all(|line| !line.starts_with('-')),
+        "{text}"
+    );
+    assert_eq!(decoded(&text).unwrap(), bytes);
+}
+
+#[test]
+fn transcription_differences_are_tolerated() {
+    let bytes = b"\x00\x01\x10\xff\x80\x7f hello";
+    let text = armored(bytes, None);
+    let sloppy: String = text[PREFIX.len()..]
+        .to_lowercase()
+        .replace('0', "o")
+        .replace('1', "l")
+        .chars()
+        .flat_map(|c| [c, ' '])
+        .collect();
+    let sloppy = format!("\n  {}{sloppy}\n", PREFIX.to_lowercase());
+    assert_eq!(decoded(&sloppy).unwrap(), bytes);
+}
+
+#[test]
+fn mistakes_are_caught() {
+    let bytes = b"stack backtrace";
+    let text = armored(bytes, None);
+
+    for (offset, c) in text.char_indices().skip(PREFIX.len()) {
+        if c == '-' {
+            continue;
+        }
+        let replacement = if c == 'A' { 'B' } else { 'A' };
+        let mut typo = text.clone();
+        typo.replace_range(offset..offset + 1, &replacement.to_string());
+        assert!(
+            matches!(
+                decoded(&typo),
+                Err(TextDecodeError::ChecksumMismatch { .. })
+            ),
+            "{typo}"
+        );
+    }
+
+    let swapped = format!(
+        "{PREFIX}{}{}{}",
+        &text[PREFIX.len() + 1..PREFIX.len() + 2],
+        &text[PREFIX.len()..PREFIX.len() + 1],
+        &text[PREFIX.len() + 2..]
+    );
+    assert_ne!(swapped, text);
+    assert!(matches!(
+        decoded(&swapped),
+        Err(TextDecodeError::ChecksumMismatch { offset: 12 })
+    ));
+
+    assert_eq!(decoded("VXC:00"), Err(TextDecodeError::MissingPrefix));
+    assert_eq!(
+        decoded(&format!("{text}!")),
+        Err(TextDecodeError::InvalidCharacter { offset: text.len() })
+    );
+    assert_eq!(
+        decode_text(&text, &mut [0; 4]),
+        Err(TextDecodeError::OutputTooSmall)
+    );
+}
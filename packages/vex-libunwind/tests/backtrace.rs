@@ -0,0 +1,356 @@
+//! Tests for capturing and printing whole backtraces.
+#![cfg(not(feature = "disabled"))]
+
+use core::fmt::{self, Write};
+
+use vex_libunwind::*;
+
+#[test]
+fn capture_starts_at_caller() {
+    let context = UnwindContext::new().unwrap();
+    let cursor = UnwindCursor::new(&context).unwrap();
+    let caller = cursor.proc_range().unwrap();
+
+    let backtrace = Backtrace::<16>::capture();
+    let first = backtrace.frames().first().expect("backtrace is empty");
+    assert!(caller.contains(&first.ip), "{backtrace}");
+}
+
+#[test]
+fn write_backtrace_lists_every_frame() {
+    let context = UnwindContext::new().unwrap();
+    let mut cursor = UnwindCursor::new(&context).unwrap();
+    let frames = cursor.frames(WalkConfig::new()).count();
+
+    let mut output = String::new();
+    write_backtrace(&mut output, &context).unwrap();
+    assert!(output.starts_with("stack backtrace:\n"), "{output}");
+    assert_eq!(output.lines().count(), frames + 1, "{output}");
+}
+
+/// A writer that tries to print and capture backtraces of its own, like a
+/// fault handler that runs while a backtrace is being printed.
+#[derive(Default)]
+struct ReentrantWriter {
+    nested_output: String,
+    nested_frames: Option<usize>,
+}
+
+impl Write for ReentrantWriter {
+    fn write_str(&mut self, _: &str) -> fmt::Result {
+        if self.nested_frames.is_none() {
+            let context = UnwindContext::new().unwrap();
+            write_backtrace(&mut self.nested_output, &context)?;
+            self.nested_frames = Some(Backtrace::<16>::capture().frames().len());
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn reentry_bails_out() {
+    let context = UnwindContext::new().unwrap();
+    let mut writer = ReentrantWriter::default();
+    write_backtrace(&mut writer, &context).unwrap();
+
+    assert!(
+        writer.nested_output.contains("recursive fault"),
+        "{}",
+        writer.nested_output
+    );
+    assert_eq!(writer.nested_frames, Some(0));
+
+    // The guard is released once the outer print finishes.
+    let mut output = String::new();
+    write_backtrace(&mut output, &context).unwrap();
+    assert!(!output.contains("recursive fault"), "{output}");
+    assert!(!Backtrace::<16>::capture().frames().is_empty());
+}
+
+#[test]
+fn capture_soa_matches_frames() {
+    let mut ips = [0; 16];
+    let mut offsets = [0; 16];
+    let len = capture_soa(&mut ips, &mut offsets).unwrap();
+
+    let context = UnwindContext::new().unwrap();
+    let mut cursor = UnwindCursor::new(&context).unwrap();
+    let mut index = 0;
+    while index < len {
+        let ip = cursor.register(registers::UNW_REG_IP).unwrap();
+        let range = cursor.proc_range().unwrap();
+        // Both walks start in this function, but at different call sites.
+        if index > 0 {
+            assert_eq!(ips[index], ip);
+        }
+        assert!(range.contains(&ips[index]));
+        assert_eq!(offsets[index], (ips[index] - range.start) as u64);
+
+        index += 1;
+        if !cursor.step().unwrap() {
+            break;
+        }
+    }
+    assert_eq!(index, len);
+}
+
+#[test]
+#[should_panic]
+fn capture_soa_rejects_mismatched_lengths() {
+    let _ = capture_soa(&mut [0; 4], &mut [0; 3]);
+}
+
+/// A resolver that knows a single procedure.
+struct OneSymbol {
+    range: core::ops::Range<usize>,
+    name: String,
+}
+
+impl Symbolize for OneSymbol {
+    fn symbolize(&self, ip: usize) -> Option<Symbol<'_>> {
+        self.range.contains(&ip).then(|| Symbol {
+            name: &self.name,
+            offset: (ip - self.range.start) as u64,
+            may_have_inlines: self.name == "everything",
+        })
+    }
+}
+
+#[test]
+fn symbolized_backtrace_names_resolved_frames() {
+    let context = UnwindContext::new().unwrap();
+    let cursor = UnwindCursor::new(&context).unwrap();
+    let caller = OneSymbol {
+        range: cursor.proc_range().unwrap(),
+        name: "caller".into(),
+    };
+    let everything = OneSymbol {
+        range: 0..usize::MAX,
+        name: "everything".into(),
+    };
+
+    let mut backtrace = SymbolizedBacktrace::new(Backtrace::<16>::capture());
+    let unresolved = backtrace.to_string();
+    assert!(!unresolved.contains(" - "), "{unresolved}");
+
+    backtrace.resolve(&caller);
+    let mut lines = backtrace
+        .to_string()
+        .lines()
+        .skip(1)
+        .map(str::to_owned)
+        .collect::<Vec<_>>();
+    assert!(lines[0].contains(" - caller+0x"), "{lines:#?}");
+    assert!(!lines[1].contains(" - "), "{lines:#?}");
+
+    // Resolving again only fills in what's missing.
+    backtrace.resolve(&everything);
+    lines = backtrace
+        .to_string()
+        .lines()
+        .skip(1)
+        .map(str::to_owned)
+        .collect();
+    assert!(lines[0].contains(" - caller+0x"), "{lines:#?}");
+    // A full backtrace ends with a note after its frames.
+    assert!(
+        lines[1..backtrace.frames().count()]
+            .iter()
+            .all(|line| line.contains(" - everything+0x")),
+        "{lines:#?}"
+    );
+
+    let inline_hints: Vec<_> = backtrace
+        .frames()
+        .map(|(_, symbol)| symbol.unwrap().may_have_inlines)
+        .collect();
+    assert!(!inline_hints[0], "{inline_hints:?}");
+    assert!(
+        inline_hints[1..].iter().all(|&hint| hint),
+        "{inline_hints:?}"
+    );
+}
+
+#[test]
+fn fingerprints_match_for_identical_call_chains() {
+    let same_site: Vec<_> = (0..2).map(|_| Backtrace::<16>::capture()).collect();
+    let other_site = Backtrace::<16>::capture();
+
+    assert_eq!(same_site[0].fingerprint(), same_site[1].fingerprint());
+    assert_ne!(same_site[0].fingerprint(), other_site.fingerprint());
+    assert_ne!(
+        Backtrace::<16>::empty().fingerprint(),
+        other_site.fingerprint()
+    );
+}
+
+#[test]
+fn try_capture_matches_capture() {
+    let (captured, tried) = (Backtrace::<16>::capture(), Backtrace::<16>::try_capture());
+    let tried = tried.unwrap();
+
+    assert_eq!(tried.status(), BacktraceStatus::Captured);
+    assert_eq!(tried.frames().len(), captured.frames().len());
+    assert_eq!(tried.frames()[1..], captured.frames()[1..]);
+}
+
+#[test]
+fn write_bytes_matches_display() {
+    let backtrace = Backtrace::<16>::capture();
+
+    let mut chunks = Vec::new();
+    backtrace
+        .write_bytes(|bytes| {
+            chunks.push(bytes.to_vec());
+            Ok::<_, ()>(())
+        })
+        .unwrap();
+    assert!(chunks
+        .iter()
+        .all(|chunk| !chunk.is_empty() && chunk.len() <= 64));
+    assert_eq!(chunks.concat(), backtrace.to_string().into_bytes());
+}
+
+#[test]
+fn write_bytes_stops_at_sink_error() {
+    #[derive(Debug, PartialEq)]
+    struct Disconnected;
+
+    let backtrace = Backtrace::<16>::capture();
+    assert!(backtrace.to_string().len() > 64, "{backtrace}");
+
+    let mut written = Vec::new();
+    let result = backtrace.write_bytes(|bytes| {
+        if !written.is_empty() {
+            return Err(Disconnected);
+        }
+        written.extend_from_slice(bytes);
+        Ok(())
+    });
+    assert_eq!(result, Err(Disconnected));
+    assert_eq!(written, backtrace.to_string().as_bytes()[..64]);
+}
+
+#[test]
+fn backtrace_from_context_walks_it() {
+    let context = UnwindContext::new().unwrap();
+    let mut cursor = UnwindCursor::new(&context).unwrap();
+    let frames = cursor.frames(WalkConfig::new()).count();
+
+    let backtrace = Backtrace::<64>::try_from(&context).unwrap();
+    assert_eq!(backtrace.frames().len(), frames);
+    assert_eq!(
+        Backtrace::<2>::try_from(&context).unwrap().frames().len(),
+        2
+    );
+}
+
+#[test]
+fn write_backtrace_bytes_matches_write_backtrace() {
+    let context = UnwindContext::new().unwrap();
+    let mut expected = String::new();
+    write_backtrace(&mut expected, &context).unwrap();
+
+    let mut written = Vec::new();
+    write_backtrace_bytes(
+        |bytes| {
+            written.extend_from_slice(bytes);
+            Ok::<_, ()>(())
+        },
+        &context,
+    )
+    .unwrap();
+    assert_eq!(written, expected.into_bytes());
+
+    let result = write_backtrace_bytes(|_| Err("disconnected"), &context);
+    assert_eq!(result, Err("disconnected"));
+}
+
+#[test]
+fn count_frames_matches_capture() {
+    let (captured, counted) = (Backtrace::<64>::capture(), count_frames());
+    assert_eq!(counted.unwrap(), captured.frames().len());
+
+    let context = UnwindContext::new().unwrap();
+    assert_eq!(
+        count_frames_in(&context).unwrap(),
+        Backtrace::<64>::try_from(&context).unwrap().frames().len()
+    );
+}
+
+#[test]
+fn nth_frame_matches_capture() {
+    let (captured, caller) = (Backtrace::<64>::capture(), nth_frame(1));
+    let frames = captured.frames();
+
+    assert_eq!(caller.unwrap(), Some(frames[1]));
+    assert_eq!(nth_frame(frames.len() - 1).unwrap(), frames.last().copied());
+    assert_eq!(nth_frame(frames.len()).unwrap(), None);
+    assert_eq!(nth_frame(usize::MAX).unwrap(), None);
+}
+
+/// Returns the return addresses seen from one level down, along with this
+/// function's own address range.
+#[inline(never)]
+fn return_address_caller() -> ((Option<usize>, Option<usize>), core::ops::Range<usize>) {
+    let addresses = core::hint::black_box(return_address_callee());
+    let range = capture_cursor().unwrap().proc_range().unwrap();
+    (addresses, range)
+}
+
+#[inline(never)]
+fn return_address_callee() -> (Option<usize>, Option<usize>) {
+    (caller_ip(), return_address(1))
+}
+
+#[test]
+fn return_addresses_count_from_the_caller() {
+    let ((caller, grandcaller), caller_range) = return_address_caller();
+    let test_range = capture_cursor().unwrap().proc_range().unwrap();
+
+    let caller = caller.unwrap();
+    assert!(
+        caller_range.contains(&caller),
+        "{caller:#x} is outside {caller_range:#x?}"
+    );
+    let grandcaller = grandcaller.unwrap();
+    assert!(
+        test_range.contains(&grandcaller),
+        "{grandcaller:#x} is outside {test_range:#x?}"
+    );
+    assert_eq!(return_address(usize::MAX), None);
+}
+
+#[test]
+fn crash_report_has_registers_and_frames() {
+    let context = UnwindContext::new().unwrap();
+    let mut report = String::new();
+    write_crash_report(&mut report, &context, Some(&"data abort at 0x4")).unwrap();
+
+    assert!(
+        report.starts_with("crash report:\n  fault: data abort at 0x4\nregisters:\n"),
+        "{report}"
+    );
+    let lines: Vec<_> = report.lines().collect();
+    assert!(
+        lines.iter().any(|line| line.starts_with("  r0  0x")),
+        "{report}"
+    );
+    assert!(
+        lines.iter().any(|line| line.starts_with("  pc  0x")),
+        "{report}"
+    );
+    let backtrace = lines
+        .iter()
+        .position(|&line| line == "stack backtrace:")
+        .unwrap();
+    assert!(lines[backtrace + 1].starts_with("   0: 0x"), "{report}");
+    assert!(lines.len() - backtrace <= MAX_REPORT_FRAMES + 2, "{report}");
+
+    let mut report = String::new();
+    write_crash_report(&mut report, &context, None).unwrap();
+    assert!(
+        report.starts_with("crash report:\nregisters:\n"),
+        "{report}"
+    );
+}
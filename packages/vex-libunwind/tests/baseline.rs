@@ -0,0 +1,102 @@
+//! Tests for trimming the outer frames shared with a baseline.
+#![cfg(not(feature = "disabled"))]
+
+use vex_libunwind::*;
+use vex_libunwind_sys::mock::*;
+
+/// The frames of a task's entry point, the executor that polls it, and the
+/// startup code, as seen from `task_ip` and `executor_ip`.
+fn scaffolding(task_ip: usize, executor_ip: usize) -> Vec<MockFrame> {
+    vec![
+        MockFrame::new(task_ip, 0x7000).in_proc(0x1000..0x1100),
+        MockFrame::new(executor_ip, 0x7100).in_proc(0x2000..0x2100),
+        MockFrame::new(0x3010, 0x7200).in_proc(0x3000..0x3100),
+    ]
+}
+
+fn install(inner: &[usize], task_ip: usize, executor_ip: usize) -> MockGuard {
+    let mut frames: Vec<_> = inner
+        .iter()
+        .enumerate()
+        .map(|(index, &ip)| MockFrame::new(ip, 0x6000 + index * 0x10))
+        .collect();
+    frames.extend(scaffolding(task_ip, executor_ip));
+    MockStack::new(frames).install()
+}
+
+fn ips(frames: &[Frame]) -> Vec<usize> {
+    frames.iter().map(|frame| frame.ip).collect()
+}
+
+#[test]
+fn shared_outer_frames_are_matched_by_procedure() {
+    let mock = install(&[], 0x1010, 0x2040);
+    let baseline = Baseline::<16>::capture();
+    drop(mock);
+    assert_eq!(baseline.ips().collect::<Vec<_>>(), [0x1010, 0x2040, 0x3010]);
+
+    let _mock = install(&[0x9000, 0x9100], 0x1050, 0x2080);
+    let backtrace = Backtrace::<8>::capture();
+    let trimmed = backtrace.trim_common_prefix(&baseline);
+    assert_eq!(ips(trimmed.frames()), [0x9000, 0x9100]);
+    assert_eq!(trimmed.omitted(), 3);
+    assert_eq!(
+        trimmed.to_string(),
+        "stack backtrace:\n   0: 0x00009000\n   1: 0x00009100\n  ... (3 outer frames omitted)\n"
+    );
+    assert_eq!(
+        trimmed
+            .display(BacktraceStyle::new().order(FrameOrder::OutermostFirst))
+            .to_string(),
+        "stack backtrace (most recent call last):\n  ... (3 outer frames omitted)\n   1: 0x00009100\n   0: 0x00009000\n"
+    );
+}
+
+#[test]
+fn baselines_from_backtraces_match_exact_addresses() {
+    let mock = install(&[], 0x1010, 0x2040);
+    let baseline = Baseline::<16>::from(&Backtrace::<8>::capture());
+    drop(mock);
+
+    let _mock = install(&[0x9000], 0x1010, 0x2080);
+    let backtrace = Backtrace::<8>::capture();
+    let trimmed = backtrace.trim_common_prefix(&baseline);
+    assert_eq!(ips(trimmed.frames()), [0x9000, 0x1010, 0x2080]);
+    assert!(trimmed
+        .to_string()
+        .ends_with("  ... (1 outer frame omitted)\n"));
+}
+
+#[test]
+fn only_whole_backtraces_are_trimmed() {
+    let _mock = install(&[], 0x1010, 0x2040);
+    let baseline = Baseline::<16>::capture();
+
+    // The innermost frame is kept even when every frame matches.
+    let whole = Backtrace::<8>::capture();
+    assert_eq!(whole.trim_common_prefix(&baseline).omitted(), 2);
+
+    let truncated = Backtrace::<2>::capture();
+    let trimmed = truncated.trim_common_prefix(&baseline);
+    assert_eq!(trimmed.omitted(), 0);
+    assert_eq!(trimmed.frames().len(), 2);
+    assert_eq!(trimmed.to_string(), truncated.to_string());
+
+    // A baseline smaller than the stack keeps its outermost frames.
+    let small = Baseline::<2>::capture();
+    assert_eq!(small.ips().collect::<Vec<_>>(), [0x2040, 0x3010]);
+}
+
+#[test]
+fn the_program_baseline_is_captured_once() {
+    let mock = install(&[], 0x1010, 0x2040);
+    assert!(captured_baseline().is_none());
+    assert!(capture_baseline());
+    drop(mock);
+    let _mock = install(&[0x9000], 0x1020, 0x2040);
+    assert!(!capture_baseline());
+    assert_eq!(captured_baseline().unwrap().len(), 3);
+
+    let backtrace = Backtrace::<8>::capture();
+    assert_eq!(ips(backtrace.trim_to_baseline().frames()), [0x9000]);
+}
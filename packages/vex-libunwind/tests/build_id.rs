@@ -0,0 +1,46 @@
+//! Checks build ID note parsing, since host tools compare the IDs it finds
+//! to tell whether they have the right binary.
+
+use vex_libunwind::{build_id, parse_build_id_note};
+
+fn note(name: &[u8], kind: u32, id: &[u8]) -> Vec<u8> {
+    let mut note = Vec::new();
+    note.extend((name.len() as u32).to_ne_bytes());
+    note.extend((id.len() as u32).to_ne_bytes());
+    note.extend(kind.to_ne_bytes());
+    note.extend(name);
+    note.resize(note.len().next_multiple_of(4), 0);
+    note.extend(id);
+    note
+}
+
+#[test]
+fn build_id_is_read_from_note() {
+    let id = [0xde, 0xad, 0xbe, 0xef, 0x01, 0x23, 0x45, 0x67];
+    assert_eq!(parse_build_id_note(&note(b"GNU\0", 3, &id)), Some(&id[..]));
+
+    // Trailing padding after the ID is ignored.
+    let mut padded = note(b"GNU\0", 3, &id[..5]);
+    padded.extend([0; 3]);
+    assert_eq!(parse_build_id_note(&padded), Some(&id[..5]));
+}
+
+#[test]
+fn other_notes_are_rejected() {
+    let id = [1, 2, 3, 4];
+    assert_eq!(parse_build_id_note(&note(b"GNU\0", 1, &id)), None);
+    assert_eq!(parse_build_id_note(&note(b"Go\0", 3, &id)), None);
+    assert_eq!(parse_build_id_note(&[]), None);
+
+    let truncated = note(b"GNU\0", 3, &id);
+    assert_eq!(parse_build_id_note(&truncated[..truncated.len() - 1]), None);
+    let mut huge = truncated;
+    huge[..4].copy_from_slice(&u32::MAX.to_ne_bytes());
+    assert_eq!(parse_build_id_note(&huge), None);
+}
+
+#[test]
+fn missing_symbols_mean_no_build_id() {
+    // Host test binaries don't define the linker script's symbols.
+    assert_eq!(build_id(), None);
+}
@@ -0,0 +1,33 @@
+//! Tests for measuring the cost of a walk.
+
+use std::time::Instant;
+
+use vex_libunwind::*;
+
+#[test]
+fn walk_cost_is_nonzero_with_a_clock() {
+    let epoch = Instant::now();
+    let nanos = measure_walk_cost_with(|| epoch.elapsed().as_nanos() as u64);
+    assert_ne!(nanos, 0);
+    assert_ne!(nanos, u64::MAX);
+}
+
+#[test]
+fn walk_cost_counts_calls_to_now() {
+    // With a clock that ticks once per reading, the walk and the overhead
+    // both take one tick, which cancel out.
+    let mut ticks = 0;
+    let cost = measure_walk_cost_with(|| {
+        ticks += 1;
+        ticks
+    });
+    assert_eq!(cost, 0);
+}
+
+// Only built for the V5 (which can't run the test suite from a host), so that
+// the test is ready for an on-device runner.
+#[test]
+#[cfg(all(target_arch = "arm", feature = "cycle-counter"))]
+fn walk_cost_in_cycles_is_nonzero() {
+    assert_ne!(measure_walk_cost(), 0);
+}
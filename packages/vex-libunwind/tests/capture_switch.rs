@@ -0,0 +1,49 @@
+//! Tests for turning backtrace capture off.
+//!
+//! The switch is global, so everything that depends on it is checked in a
+//! single test to keep it from affecting tests running alongside it.
+#![cfg(not(feature = "disabled"))]
+
+use vex_libunwind::*;
+
+#[test]
+fn disabled_capture_is_empty_and_marked() {
+    static SLOT: BacktraceSlot<16> = BacktraceSlot::new();
+    assert!(capture_enabled());
+    let captured = Backtrace::<16>::capture();
+    assert_eq!(captured.status(), BacktraceStatus::Captured);
+    assert!(!captured.frames().is_empty());
+
+    set_capture_enabled(false);
+    let disabled = Backtrace::<16>::capture();
+    let slot = SLOT.capture().unwrap();
+    let (mut ips, mut offsets) = ([0; 16], [0; 16]);
+    let soa = capture_soa(&mut ips, &mut offsets);
+    let traced = Traced::<(), 16>::from(());
+    set_capture_enabled(true);
+
+    assert_eq!(disabled.status(), BacktraceStatus::Disabled);
+    assert!(disabled.frames().is_empty());
+    assert_eq!(disabled.stop_reason(), None);
+    assert_eq!(
+        disabled.to_string(),
+        "stack backtrace:\n  (capture disabled)\n"
+    );
+    assert_eq!(
+        disabled
+            .display(BacktraceStyle::new().color(true))
+            .to_string(),
+        "stack backtrace:\n  \x1b[31m(capture disabled)\x1b[0m\n"
+    );
+    assert_eq!(slot.status(), BacktraceStatus::Disabled);
+    assert_eq!(soa.unwrap(), 0);
+    assert_eq!(traced.backtrace().status(), BacktraceStatus::Disabled);
+    drop(slot);
+
+    // An empty backtrace that was captured is told apart from a disabled one.
+    assert_eq!(
+        Backtrace::<0>::capture().status(),
+        BacktraceStatus::Captured
+    );
+    assert_eq!(SLOT.capture().unwrap().status(), BacktraceStatus::Captured);
+}
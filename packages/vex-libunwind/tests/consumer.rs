@@ -0,0 +1,65 @@
+//! Uses the backtrace API the way a library sprinkling captures into its error
+//! paths would, so that it's checked to build both with and without the
+//! `disabled` feature.
+
+use vex_libunwind::*;
+
+/// An error that records where it was created.
+struct TracedError {
+    backtrace: Backtrace<8>,
+}
+
+impl TracedError {
+    #[inline(always)]
+    fn new() -> Self {
+        Self {
+            backtrace: Backtrace::capture(),
+        }
+    }
+}
+
+#[test]
+fn consumer_builds_and_runs() {
+    let error = TracedError::new();
+    let printed = error.backtrace.to_string();
+    let ips: Vec<usize> = error
+        .backtrace
+        .frames()
+        .iter()
+        .map(|frame| frame.ip)
+        .collect();
+
+    let mut symbolized = SymbolizedBacktrace::new(error.backtrace.clone());
+    symbolized.resolve(&NoSymbols);
+
+    let (mut soa_ips, mut offsets) = ([0; 8], [0; 8]);
+    let soa_len = capture_soa(&mut soa_ips, &mut offsets).unwrap();
+    let tried = Backtrace::<8>::try_capture().unwrap();
+    let counted = count_frames().unwrap();
+
+    if cfg!(feature = "disabled") {
+        assert_eq!(core::mem::size_of::<Backtrace<64>>(), 0);
+        assert_eq!(error.backtrace.status(), BacktraceStatus::Disabled);
+        assert_eq!(printed, "backtraces disabled\n");
+        assert_eq!(symbolized.to_string(), printed);
+        assert!(ips.is_empty());
+        assert_eq!(soa_len, 0);
+        assert_eq!(tried.status(), BacktraceStatus::Disabled);
+        assert_eq!(counted, 0);
+    } else {
+        assert_eq!(error.backtrace.status(), BacktraceStatus::Captured);
+        assert!(printed.starts_with("stack backtrace:\n"), "{printed}");
+        assert!(!ips.is_empty());
+        assert_ne!(soa_len, 0);
+        assert!(!tried.frames().is_empty());
+        assert_ne!(counted, 0);
+    }
+}
+
+struct NoSymbols;
+
+impl Symbolize for NoSymbols {
+    fn symbolize(&self, _ip: usize) -> Option<Symbol<'_>> {
+        None
+    }
+}
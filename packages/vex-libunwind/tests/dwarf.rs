@@ -0,0 +1,155 @@
+#![cfg(feature = "dwarf")]
+//! Tests for looking up source lines in hand-assembled line tables.
+
+use vex_libunwind::dwarf::*;
+
+/// The header fields after `header_length` that come before the directory
+/// and file tables: an instruction length of 2, a line base of -5, a line
+/// range of 14, and the standard opcodes.
+fn parameters(version: u16) -> Vec<u8> {
+    let mut bytes = vec![2];
+    if version >= 4 {
+        bytes.push(1);
+    }
+    bytes.extend([1, -5i8 as u8, 14, 13]);
+    bytes.extend([0, 1, 1, 1, 1, 0, 0, 0, 1, 0, 0, 1]);
+    bytes
+}
+
+/// Assembles a 32-bit line table from its directory and file tables and its
+/// program.
+fn line_table(version: u16, tables: &[u8], program: &[u8]) -> Vec<u8> {
+    let mut header = parameters(version);
+    header.extend(tables);
+
+    let mut unit = version.to_le_bytes().to_vec();
+    if version >= 5 {
+        unit.extend([4, 0]);
+    }
+    unit.extend((header.len() as u32).to_le_bytes());
+    unit.extend(header);
+    unit.extend(program);
+
+    let mut table = (unit.len() as u32).to_le_bytes().to_vec();
+    table.extend(unit);
+    table
+}
+
+fn set_address(address: u32) -> Vec<u8> {
+    let mut op = vec![0, 5, 2];
+    op.extend(address.to_le_bytes());
+    op
+}
+
+/// A program with rows at 0x1000 (file 1, line 10), 0x1004 (file 1, line
+/// 12), and 0x100c (file `second_file`, line 12), ending at 0x1010.
+fn program(second_file: u8) -> Vec<u8> {
+    let mut program = set_address(0x1000);
+    // advance_line by 9, then copy.
+    program.extend([3, 9, 1]);
+    // A special opcode advancing the address by 4 and the line by 2.
+    program.push(48);
+    // set_file, advance_pc by 8, then copy.
+    program.extend([4, second_file, 2, 4, 1]);
+    // advance_pc by 4, then end_sequence.
+    program.extend([2, 2, 0, 1, 1]);
+    program
+}
+
+fn resolve(symbols: &DwarfSymbols<'_>, ip: usize) -> Option<String> {
+    symbols.resolve(ip).map(|location| location.to_string())
+}
+
+#[test]
+fn version_4_addresses_resolve_to_their_rows() {
+    let tables = b"src\0\0main.rs\0\x01\0\0lib.rs\0\0\0\0\0";
+    let section = line_table(4, tables, &program(2));
+    let symbols = DwarfSymbols::from_bytes(&section).unwrap();
+
+    assert_eq!(resolve(&symbols, 0x0fff), None);
+    assert_eq!(resolve(&symbols, 0x1000).as_deref(), Some("src/main.rs:10"));
+    assert_eq!(resolve(&symbols, 0x1003).as_deref(), Some("src/main.rs:10"));
+    assert_eq!(resolve(&symbols, 0x1004).as_deref(), Some("src/main.rs:12"));
+    assert_eq!(resolve(&symbols, 0x100c).as_deref(), Some("lib.rs:12"));
+    assert_eq!(resolve(&symbols, 0x100f).as_deref(), Some("lib.rs:12"));
+    assert_eq!(resolve(&symbols, 0x1010), None);
+
+    let location = symbols.resolve(0x1004).unwrap();
+    assert_eq!(location.directory, Some("src"));
+    assert_eq!(location.file, "main.rs");
+    assert_eq!(location.line, 12);
+}
+
+#[test]
+fn later_line_tables_are_searched() {
+    let mut section = line_table(3, b"\0a.c\0\0\0\0\0", &set_address(0x500));
+    section.extend(line_table(4, b"\0b.c\0\0\0\0\0", &program(1)));
+    let symbols = DwarfSymbols::from_bytes(&section).unwrap();
+
+    assert_eq!(resolve(&symbols, 0x100c).as_deref(), Some("b.c:12"));
+    assert_eq!(resolve(&symbols, 0x500), None);
+}
+
+#[test]
+fn version_5_names_come_from_the_string_sections() {
+    let mut tables = vec![
+        // One directory format (path as a .debug_line_str offset), and two
+        // directories.
+        1, 1, 0x1f, 2,
+    ];
+    tables.extend(0u32.to_le_bytes());
+    tables.extend(6u32.to_le_bytes());
+    // Two file formats (path as an inline string, and directory index as
+    // one byte), and one file.
+    tables.extend([2, 1, 0x08, 2, 0x0b, 1]);
+    tables.extend(b"main.rs\0\x01");
+    // The file register starts at 1 in every version, so the program
+    // switches to file 0.
+    let mut program = vec![4, 0];
+    program.extend(self::program(0));
+
+    let section = line_table(5, &tables, &program);
+    let symbols = DwarfSymbols::from_bytes(&section).unwrap();
+    assert_eq!(resolve(&symbols, 0x1000).as_deref(), Some("main.rs:10"));
+
+    let symbols = symbols.with_line_strings(b"/work\0/work/src\0");
+    assert_eq!(
+        resolve(&symbols, 0x1004).as_deref(),
+        Some("/work/src/main.rs:12")
+    );
+}
+
+#[test]
+fn unsupported_versions_are_errors() {
+    let section = line_table(6, &[], &[]);
+    assert_eq!(
+        DwarfSymbols::from_bytes(&section).unwrap_err(),
+        DwarfError::UnsupportedVersion {
+            version: 6,
+            offset: 4
+        }
+    );
+    assert!(matches!(
+        DwarfSymbols::from_bytes(&section[..3]),
+        Err(DwarfError::Truncated { .. })
+    ));
+}
+
+#[test]
+fn headers_longer_than_header_length_are_errors() {
+    // A version 4 header claiming to be empty, followed by its fields.
+    let mut section = vec![12, 0, 0, 0, 4, 0, 0, 0, 0, 0];
+    section.extend([2, 1, 1, -5i8 as u8, 14, 1]);
+    assert_eq!(
+        DwarfSymbols::from_bytes(&section).unwrap_err(),
+        DwarfError::InvalidHeader { offset: 0 }
+    );
+
+    // A header_length that covers the fields but not the opcode lengths.
+    let mut section = line_table(4, &[0, 0], &[]);
+    section[6] = 6;
+    assert_eq!(
+        DwarfSymbols::from_bytes(&section).unwrap_err(),
+        DwarfError::InvalidHeader { offset: 0 }
+    );
+}
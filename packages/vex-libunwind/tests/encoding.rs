@@ -0,0 +1,331 @@
+//! Round trips through the compact address encodings, which have to decode
+//! exactly what was encoded, on any machine.
+#![cfg(not(feature = "disabled"))]
+
+use vex_libunwind::{encoding::*, Backtrace, BuildIdMismatch, MAX_BUILD_ID_LEN};
+
+fn round_trip(ips: &[usize], encoding: FrameEncoding) -> Vec<u8> {
+    let mut bytes = vec![0; max_encoded_len(ips.len())];
+    let len = encode_ips(ips.iter().copied(), encoding, &mut bytes).unwrap();
+    bytes.truncate(len);
+
+    let mut decoded = vec![0; ips.len()];
+    assert_eq!(decode_ips(&bytes, &mut decoded), Ok(ips.len()));
+    assert_eq!(decoded, ips, "{encoding:?}");
+    bytes
+}
+
+#[test]
+fn captured_backtraces_round_trip() {
+    let backtrace = Backtrace::<32>::capture();
+    let ips: Vec<_> = backtrace.frames().iter().map(|frame| frame.ip).collect();
+    assert!(!ips.is_empty());
+
+    let plain = round_trip(&ips, FrameEncoding::Plain);
+    let delta = round_trip(&ips, FrameEncoding::Delta);
+    assert!(
+        delta.len() < plain.len(),
+        "{} >= {}",
+        delta.len(),
+        plain.len()
+    );
+
+    let mut bytes = [0; 512];
+    let len = backtrace.encode(FrameEncoding::Delta, &mut bytes).unwrap();
+    // The stop reason comes after the addresses, before the checksum.
+    let addresses = delta.len() - 4;
+    assert_eq!(bytes[..addresses], delta[..addresses]);
+    assert!(len > delta.len());
+}
+
+#[test]
+fn clustered_addresses_shrink() {
+    let ips: Vec<_> = (0..40)
+        .map(|i| 0x0380_0000 + (i * 0x1d3) % 0x4000)
+        .collect();
+    let plain = round_trip(&ips, FrameEncoding::Plain);
+    let delta = round_trip(&ips, FrameEncoding::Delta);
+    assert!(
+        delta.len() * 2 < plain.len(),
+        "{} vs {}",
+        delta.len(),
+        plain.len()
+    );
+}
+
+#[test]
+fn pathological_addresses_round_trip() {
+    let alternating: Vec<_> = (0..16)
+        .map(|i| if i % 2 == 0 { 0 } else { usize::MAX - i })
+        .collect();
+    let extremes = [
+        usize::MAX,
+        0,
+        usize::MAX,
+        1,
+        usize::MAX / 2,
+        usize::MAX / 2 + 1,
+    ];
+    for ips in [&alternating[..], &extremes, &[], &[0x1000]] {
+        round_trip(ips, FrameEncoding::Plain);
+        round_trip(ips, FrameEncoding::Delta);
+    }
+}
+
+#[test]
+fn narrow_addresses_wrap_like_the_encoder() {
+    // As a V5 would encode 0xffff_fff0 followed by 0x10: 32-bit deltas of
+    // -0x10 and +0x20, zigzagged.
+    let bytes = [
+        FORMAT_VERSION,
+        1,
+        4,
+        0,
+        2,
+        0x1f,
+        0x40,
+        0x52,
+        0x20,
+        0x2d,
+        0x8c,
+    ];
+    let mut decoded = [0; 2];
+    assert_eq!(decode_ips(&bytes, &mut decoded), Ok(2));
+    assert_eq!(decoded, [0xffff_fff0, 0x10]);
+
+    let plain = [
+        FORMAT_VERSION,
+        0,
+        4,
+        0,
+        1,
+        0x34,
+        0x12,
+        0x80,
+        0x03,
+        0x92,
+        0xe0,
+        0xb1,
+        0xc3,
+    ];
+    assert_eq!(decode_ips(&plain, &mut decoded), Ok(1));
+    assert_eq!(decoded[0], 0x0380_1234);
+}
+
+#[test]
+fn bad_input_is_rejected() {
+    let ips = [0x0380_1000, 0x0380_1100];
+    let bytes = round_trip(&ips, FrameEncoding::Delta);
+    let mut out = [0; 2];
+
+    // Cutting the checksum off fails on its own, and cutting any more fails
+    // the checksum.
+    for len in 0..bytes.len() {
+        let error = if len <= 4 {
+            DecodeError::Truncated { offset: len }
+        } else {
+            DecodeError::ChecksumMismatch { offset: len - 4 }
+        };
+        assert_eq!(decode_ips(&bytes[..len], &mut out), Err(error));
+    }
+    // Version 1 had no checksum, so it has to notice where it ends.
+    let v1 = CORPUS[1].0;
+    for len in 0..v1.len() {
+        assert_eq!(
+            decode_ips(&v1[..len], &mut [0; 8]),
+            Err(DecodeError::Truncated { offset: len })
+        );
+    }
+    assert_eq!(
+        decode_ips(&bytes, &mut out[..1]),
+        Err(DecodeError::OutputTooSmall {
+            count: 2,
+            capacity: 1
+        })
+    );
+
+    let mut bad = bytes.clone();
+    bad[0] = FORMAT_VERSION + 1;
+    assert_eq!(
+        decode_ips(&bad, &mut out),
+        Err(DecodeError::UnsupportedVersion {
+            version: FORMAT_VERSION + 1
+        })
+    );
+    let mut bad = v1.to_vec();
+    bad[1] = 7;
+    assert_eq!(
+        decode_ips(&bad, &mut out),
+        Err(DecodeError::UnknownEncoding { encoding: 7 })
+    );
+
+    let mut small = [0; 4];
+    assert_eq!(
+        encode_ips(ips.into_iter(), FrameEncoding::Plain, &mut small),
+        Err(EncodeError::BufferTooSmall)
+    );
+}
+
+/// Samples of every encoding as earlier versions of the crate wrote them.
+/// They must keep decoding to the same addresses.
+const CORPUS: [(&[u8], &[usize]); 5] = [
+    (include_bytes!("corpus/v1-plain-v5.bin"), &V5_IPS),
+    (include_bytes!("corpus/v1-delta-v5.bin"), &V5_IPS),
+    (include_bytes!("corpus/v1-delta-v5.txt"), &V5_IPS),
+    (include_bytes!("corpus/v1-plain-host.bin"), &HOST_IPS),
+    (include_bytes!("corpus/v1-delta-host.bin"), &HOST_IPS),
+];
+const V5_IPS: [usize; 5] = [
+    0x0380_1234,
+    0x0380_0f10,
+    0x0380_2a00,
+    0x0381_0004,
+    0x0380_0100,
+];
+const HOST_IPS: [usize; 3] = [0x5555_5555_1234, 0x7fff_0000_0010, 0x5555_5555_0ff0];
+
+#[test]
+fn corpus_still_decodes() {
+    for (index, (input, ips)) in CORPUS.into_iter().enumerate() {
+        let backtrace = Backtrace::<8>::from_encoded(input).unwrap();
+        let decoded: Vec<_> = backtrace.frames().iter().map(|frame| frame.ip).collect();
+        assert_eq!(decoded, ips, "sample {index}");
+    }
+}
+
+#[test]
+fn from_encoded_reads_captured_backtraces() {
+    let backtrace = Backtrace::<32>::capture();
+    let mut bytes = [0; 512];
+    let len = backtrace.encode(FrameEncoding::Delta, &mut bytes).unwrap();
+
+    let mut text = String::new();
+    vex_libunwind::armor::encode_text(&bytes[..len], Some(40), &mut text).unwrap();
+    for input in [&bytes[..len], text.as_bytes()] {
+        let decoded = Backtrace::<32>::from_encoded(input).unwrap();
+        assert!(decoded
+            .frames()
+            .iter()
+            .zip(backtrace.frames())
+            .all(|(decoded, frame)| decoded.ip == frame.ip && decoded.sp == 0));
+        assert_eq!(decoded.frames().len(), backtrace.frames().len());
+    }
+}
+
+#[test]
+fn checksums_are_zlibs_crc32() {
+    // The checksum here was computed with Python's `zlib.crc32`.
+    let bytes = [
+        2, 1, 4, 0, 1, 0xe8, 0xc8, 0x80, 0x38, 0xef, 0xbf, 0x8c, 0x52,
+    ];
+    let mut decoded = [0; 1];
+    assert_eq!(decode_ips(&bytes, &mut decoded), Ok(1));
+    assert_eq!(decoded, [0x0380_1234]);
+}
+
+#[test]
+fn flipped_bits_fail_the_checksum() {
+    let backtrace = Backtrace::<32>::capture();
+    let mut bytes = [0; 512];
+    let len = backtrace.encode(FrameEncoding::Delta, &mut bytes).unwrap();
+    let bytes = &bytes[..len];
+    let checksum = len - 4;
+    let mut out = [0; 32];
+
+    // The header, build ID length, count, addresses, stop reason, and the
+    // checksum itself.
+    for offset in [1, 2, 3, 4, 5, len / 2, checksum - 1, checksum, len - 1] {
+        for bit in [0, 3, 7] {
+            let mut corrupted = bytes.to_vec();
+            corrupted[offset] ^= 1 << bit;
+            assert_eq!(
+                decode_ips(&corrupted, &mut out),
+                Err(DecodeError::ChecksumMismatch { offset: checksum }),
+                "bit {bit} of byte {offset}"
+            );
+            assert_eq!(
+                Backtrace::<32>::from_encoded(&corrupted).unwrap_err(),
+                FromEncodedError::Binary {
+                    error: DecodeError::ChecksumMismatch { offset: checksum }
+                }
+            );
+        }
+    }
+    // A flipped version is noticed before the checksum is.
+    let mut corrupted = bytes.to_vec();
+    corrupted[0] ^= 1;
+    assert!(matches!(
+        decode_ips(&corrupted, &mut out),
+        Err(DecodeError::UnsupportedVersion { .. })
+    ));
+}
+
+#[test]
+fn build_ids_are_checked() {
+    let id = [0x9f, 0x8e, 0x2c, 0x0b, 0x41, 0xd6, 0xa3, 0xe5];
+    let ips = [0x0380_1000, 0x0380_1100];
+    let mut bytes = vec![0; max_encoded_len(ips.len())];
+    let len =
+        encode_ips_with_build_id(ips.into_iter(), FrameEncoding::Delta, Some(&id), &mut bytes)
+            .unwrap();
+    bytes.truncate(len);
+    let recorded = decode_build_id(&bytes).unwrap().unwrap();
+    assert_eq!(recorded.as_bytes(), id);
+    let mut decoded = [0; 2];
+    assert_eq!(decode_ips(&bytes, &mut decoded), Ok(2));
+    assert_eq!(decoded, ips);
+
+    let (backtrace, mismatch) = Backtrace::<4>::from_encoded_for(&bytes, &id).unwrap();
+    assert_eq!(backtrace.frames().len(), 2);
+    assert_eq!(mismatch, None);
+    let (_, mismatch) = Backtrace::<4>::from_encoded_for(&bytes, &id[..4]).unwrap();
+    assert_eq!(mismatch, Some(BuildIdMismatch { recorded }));
+    assert_eq!(
+        mismatch.unwrap().to_string(),
+        "backtrace was recorded by build 9f8e2c0b41d6a3e5, not this binary's; its addresses \
+         may be wrong"
+    );
+
+    // Without an ID (or with one too long to keep), there's nothing to check.
+    let long = [0xaa; MAX_BUILD_ID_LEN + 1];
+    let mut bytes = vec![0; max_encoded_len(ips.len())];
+    for build_id in [None, Some(&long[..])] {
+        let len =
+            encode_ips_with_build_id(ips.into_iter(), FrameEncoding::Plain, build_id, &mut bytes)
+                .unwrap();
+        assert_eq!(decode_build_id(&bytes[..len]), Ok(None));
+        let (_, mismatch) = Backtrace::<4>::from_encoded_for(&bytes[..len], &id).unwrap();
+        assert_eq!(mismatch, None);
+    }
+    let (_, mismatch) = Backtrace::<8>::from_encoded_for(CORPUS[0].0, &id).unwrap();
+    assert_eq!(mismatch, None);
+}
+
+#[test]
+fn from_encoded_errors_give_offsets() {
+    let (delta, text) = (CORPUS[1].0, CORPUS[2].0);
+    assert_eq!(
+        Backtrace::<8>::from_encoded(&delta[..7]).unwrap_err(),
+        FromEncodedError::Binary {
+            error: DecodeError::Truncated { offset: 7 }
+        }
+    );
+    assert_eq!(
+        Backtrace::<4>::from_encoded(delta).unwrap_err(),
+        FromEncodedError::Binary {
+            error: DecodeError::OutputTooSmall {
+                count: 5,
+                capacity: 4
+            }
+        }
+    );
+
+    let mut typo = text.to_vec();
+    typo[5] = if typo[5] == b'A' { b'B' } else { b'A' };
+    assert_eq!(
+        Backtrace::<8>::from_encoded(&typo).unwrap_err(),
+        FromEncodedError::Text {
+            error: vex_libunwind::armor::TextDecodeError::ChecksumMismatch { offset: 12 }
+        }
+    );
+}
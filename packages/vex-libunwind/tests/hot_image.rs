@@ -0,0 +1,44 @@
+//! The hot image's location is process-wide, so these checks share a single
+//! test to keep them from racing.
+
+use vex_libunwind::hot_image::{HotImage, HotImageDescriptor};
+
+const fn descriptor(text_start: usize) -> HotImageDescriptor {
+    HotImageDescriptor {
+        text: text_start..text_start + 0x1000,
+        load_base: text_start,
+    }
+}
+
+#[test]
+fn hot_image_location() {
+    assert_eq!(HotImage::current(), None);
+
+    let guard = HotImage::record_location(descriptor(0x0780_0000));
+    assert_eq!(HotImage::current(), Some(descriptor(0x0780_0000)));
+    assert!(HotImage::contains(0x0780_0010));
+    assert!(!HotImage::contains(0x0380_0010));
+    assert_eq!(HotImage::rebase(0x0780_0010), 0x10);
+    assert_eq!(HotImage::rebase(0x0380_0010), 0x0380_0010);
+
+    // Recording an overlapping image replaces the old one...
+    let replacement = HotImage::record_location(descriptor(0x0780_0800));
+    assert_eq!(HotImage::current(), Some(descriptor(0x0780_0800)));
+    assert_eq!(HotImage::rebase(0x0780_0810), 0x10);
+
+    // ...and dropping the replaced guard leaves the new location alone.
+    drop(guard);
+    assert_eq!(HotImage::current(), Some(descriptor(0x0780_0800)));
+
+    drop(replacement);
+    assert_eq!(HotImage::current(), None);
+    assert_eq!(HotImage::rebase(0x0780_0810), 0x0780_0810);
+
+    // A load base above the code wraps instead of panicking.
+    let misplaced = HotImage::record_location(HotImageDescriptor {
+        load_base: 0x0780_0100,
+        ..descriptor(0x0780_0000)
+    });
+    assert_eq!(HotImage::rebase(0x0780_0010), 0x10usize.wrapping_sub(0x100));
+    drop(misplaced);
+}
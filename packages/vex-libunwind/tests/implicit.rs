@@ -0,0 +1,57 @@
+//! Tests for capturing backtraces into snafu errors. Capture is turned off
+//! for the whole program partway through, so this file holds a single test.
+#![cfg(all(feature = "snafu", not(feature = "disabled")))]
+
+use snafu::prelude::*;
+use vex_libunwind::*;
+use vex_libunwind_sys::mock::*;
+
+#[derive(Debug, Snafu)]
+enum DriveError {
+    /// The motor is disconnected.
+    Disconnected {
+        #[snafu(implicit, backtrace(false))]
+        backtrace: Backtrace<8>,
+    },
+}
+
+const fn backtrace_of(error: DriveError) -> Backtrace<8> {
+    let DriveError::Disconnected { backtrace } = error;
+    backtrace
+}
+
+#[test]
+fn snafu_errors_capture_from_where_they_were_created() {
+    let _mock = MockStack::new(vec![
+        MockFrame::new(0x1000, 0x2000)
+            .named("_ZN5snafu20GenerateImplicitData20generate_with_source17h0123456789abcdefE"),
+        MockFrame::new(0x1100, 0x2100).named(
+            "_ZN74_$LT$implicit..DisconnectedSnafu$u20$as$u20$snafu..IntoError$LT$implicit..DriveError$GT$$GT$10into_error17h0123456789abcdefE",
+        ),
+        MockFrame::new(0x1200, 0x2200)
+            .named("_ZN8implicit17DisconnectedSnafu4fail17h0123456789abcdefE"),
+        MockFrame::new(0x1300, 0x2300).named("_ZN8implicit5spin_17h0123456789abcdefE"),
+        MockFrame::new(0x1400, 0x2400).named("drive::failed"),
+    ])
+    .install();
+
+    let backtrace = backtrace_of(DisconnectedSnafu.build());
+    let ips: Vec<_> = backtrace.frames().iter().map(|frame| frame.ip).collect();
+    assert_eq!(ips, [0x1300, 0x1400]);
+    assert_eq!(backtrace.stop_reason(), Some(BacktraceStop::EndOfStack));
+
+    // A corrupted stack that never leaves snafu's frames.
+    let _mock = MockStack::new(vec![
+        MockFrame::new(0x1000, 0x2000).named("_ZN5snafu8into_err17h0123456789abcdefE")
+    ])
+    .loop_back_to(0)
+    .install();
+    let backtrace = backtrace_of(DisconnectedSnafu.build());
+    assert_eq!(backtrace.frames(), []);
+    assert_eq!(backtrace.stop_reason(), Some(BacktraceStop::Loop));
+
+    set_capture_enabled(false);
+    let backtrace = backtrace_of(DisconnectedSnafu.build());
+    set_capture_enabled(true);
+    assert_eq!(backtrace.status(), BacktraceStatus::Disabled);
+}
@@ -0,0 +1,320 @@
+//! Local unwinding tests against a real (host-built) libunwind.
+
+use vex_libunwind::*;
+
+const MAX_FRAMES: usize = 64;
+
+/// Walks the current stack, recording the name of each frame's procedure.
+#[inline(always)]
+fn backtrace_names() -> Vec<String> {
+    let context = UnwindContext::new().unwrap();
+    let mut cursor = UnwindCursor::new(&context).unwrap();
+    let mut names = Vec::new();
+    let mut buf = [0; 256];
+
+    for _ in 0..MAX_FRAMES {
+        if let Ok((name, _)) = cursor.procedure_name(&mut buf) {
+            names.push(name.to_owned());
+        }
+        if !cursor.step().unwrap() {
+            break;
+        }
+    }
+
+    names
+}
+
+#[inline(never)]
+#[no_mangle]
+fn marker_outer() -> Vec<String> {
+    core::hint::black_box(marker_middle())
+}
+
+#[inline(never)]
+#[no_mangle]
+fn marker_middle() -> Vec<String> {
+    core::hint::black_box(marker_inner())
+}
+
+#[inline(never)]
+#[no_mangle]
+fn marker_inner() -> Vec<String> {
+    core::hint::black_box(backtrace_names())
+}
+
+fn position_of(names: &[String], marker: &str) -> usize {
+    names
+        .iter()
+        .position(|name| name.contains(marker))
+        .unwrap_or_else(|| panic!("{marker} not found in backtrace: {names:#?}"))
+}
+
+#[test]
+fn markers_appear_innermost_first() {
+    let names = marker_outer();
+
+    let inner = position_of(&names, "marker_inner");
+    let middle = position_of(&names, "marker_middle");
+    let outer = position_of(&names, "marker_outer");
+    assert!(inner < middle, "{names:#?}");
+    assert!(middle < outer, "{names:#?}");
+}
+
+#[test]
+fn cursor_reads_ip_and_sp() {
+    let context = UnwindContext::new().unwrap();
+    let cursor = UnwindCursor::new(&context).unwrap();
+
+    assert_ne!(cursor.register(registers::UNW_REG_IP).unwrap(), 0);
+    assert_ne!(cursor.register(registers::UNW_REG_SP).unwrap(), 0);
+}
+
+#[test]
+fn stepping_reaches_end_of_stack() {
+    let context = UnwindContext::new().unwrap();
+    let mut cursor = UnwindCursor::new(&context).unwrap();
+
+    let mut frames = 1;
+    while cursor.step().unwrap() {
+        frames += 1;
+        assert!(frames < MAX_FRAMES, "stack walk did not terminate");
+    }
+    assert!(frames > 1);
+}
+
+#[test]
+fn stack_pointer_grows_toward_callers() {
+    let context = UnwindContext::new().unwrap();
+    let mut cursor = UnwindCursor::new(&context).unwrap();
+
+    let mut last_sp = cursor.register(registers::UNW_REG_SP).unwrap();
+    while cursor.step().unwrap() {
+        let sp = cursor.register(registers::UNW_REG_SP).unwrap();
+        assert!(sp >= last_sp, "{sp:#x} is below {last_sp:#x}");
+        last_sp = sp;
+    }
+}
+
+#[test]
+fn with_raw_calls_into_libunwind() {
+    let context = UnwindContext::new().unwrap();
+    let cursor = UnwindCursor::new(&context).unwrap();
+
+    let mut ip = 0;
+    let code = cursor.with_raw(|raw| unsafe {
+        vex_libunwind_sys::unw_get_reg(raw, registers::UNW_REG_IP, &mut ip)
+    });
+    assert_eq!(code, vex_libunwind_sys::error::UNW_ESUCCESS);
+    assert_eq!(ip, cursor.register(registers::UNW_REG_IP).unwrap());
+}
+
+#[test]
+#[should_panic]
+fn with_raw_panics_on_reentry() {
+    let context = UnwindContext::new().unwrap();
+    let cursor = UnwindCursor::new(&context).unwrap();
+
+    cursor.with_raw(|_| cursor.register(registers::UNW_REG_IP).ok());
+}
+
+#[test]
+fn try_register_reports_reentry_instead_of_panicking() {
+    let context = UnwindContext::new().unwrap();
+    let mut cursor = UnwindCursor::new(&context).unwrap();
+
+    let (busy, debug) = cursor.with_raw(|_| {
+        (
+            cursor.try_register(registers::UNW_REG_IP),
+            format!("{cursor:?}"),
+        )
+    });
+    assert_eq!(busy, Err(UnwindError::Busy));
+    assert_eq!(debug, "UnwindCursor { .. }");
+
+    let ip = cursor.register(registers::UNW_REG_IP).unwrap();
+    assert_eq!(cursor.try_register(registers::UNW_REG_IP), Ok(ip));
+    assert_eq!(cursor.try_step(), Ok(true));
+}
+
+#[test]
+fn frame_from_cursor_reports_reentry_instead_of_panicking() {
+    let context = UnwindContext::new().unwrap();
+    let cursor = UnwindCursor::new(&context).unwrap();
+
+    let busy = cursor.with_raw(|_| Frame::from_cursor(&cursor));
+    assert_eq!(busy, Err(UnwindError::Busy));
+    assert!(Frame::from_cursor(&cursor).is_ok());
+}
+
+#[test]
+fn raw_sizes_match_libunwind() {
+    assert_eq!(
+        UnwindContext::raw_size(),
+        vex_libunwind_sys::CONTEXT_SIZE * 8
+    );
+    assert_eq!(UnwindCursor::raw_size(), vex_libunwind_sys::CURSOR_SIZE * 8);
+    assert_ne!(UnwindContext::raw_size(), 0);
+    assert_ne!(UnwindCursor::raw_size(), 0);
+}
+
+#[test]
+fn context_debug_shows_ip_and_sp() {
+    let context = UnwindContext::new().unwrap();
+    let cursor = UnwindCursor::new(&context).unwrap();
+    let ip = cursor.register(registers::UNW_REG_IP).unwrap();
+    let sp = cursor.register(registers::UNW_REG_SP).unwrap();
+
+    let debug = format!("{context:?}");
+    assert!(debug.contains(&format!("ip: {ip:#x}")), "{debug}");
+    assert!(debug.contains(&format!("sp: {sp:#x}")), "{debug}");
+}
+
+#[test]
+fn proc_range_contains_ip() {
+    let context = UnwindContext::new().unwrap();
+    let cursor = UnwindCursor::new(&context).unwrap();
+
+    let ip = cursor.register(registers::UNW_REG_IP).unwrap();
+    let range = cursor.proc_range().unwrap();
+    assert!(range.contains(&ip), "{ip:#x} is outside {range:#x?}");
+}
+
+#[test]
+fn offset_in_proc_is_within_proc() {
+    let context = UnwindContext::new().unwrap();
+    let cursor = UnwindCursor::new(&context).unwrap();
+
+    let offset = cursor.offset_in_proc().unwrap();
+    let range = cursor.proc_range().unwrap();
+    assert!(
+        offset < (range.end - range.start) as u64,
+        "{offset:#x} is outside {range:#x?}"
+    );
+}
+
+#[test]
+fn available_registers_are_callee_saved_in_normal_frames() {
+    let context = UnwindContext::new().unwrap();
+    let cursor = UnwindCursor::new(&context).unwrap();
+    assert!(!cursor.is_signal_frame().unwrap());
+
+    let available: Vec<_> = cursor.available_registers().collect();
+    assert!(!available.is_empty());
+    for (register, value) in available {
+        assert!(registers::CALLEE_SAVED_REGISTERS.contains(&register));
+        assert_eq!(cursor.register(register).unwrap(), value);
+    }
+}
+
+#[test]
+fn register_name_works_while_cursor_is_borrowed() {
+    let context = UnwindContext::new().unwrap();
+    let cursor = UnwindCursor::new(&context).unwrap();
+
+    let name = cursor.with_raw(|_| cursor.register_name(registers::UNW_ARM_R4));
+    assert_eq!(name, Some(c"r4"));
+    assert_eq!(cursor.register_name(registers::UNW_ARM_D31 + 1), None);
+}
+
+#[test]
+fn from_context_matches_new() {
+    let context = UnwindContext::new().unwrap();
+    let expected = UnwindCursor::new(&context).unwrap();
+    let cursor = UnwindCursor::from_context(context).unwrap();
+
+    for register in [registers::UNW_REG_IP, registers::UNW_REG_SP] {
+        assert_eq!(
+            cursor.register(register).unwrap(),
+            expected.register(register).unwrap()
+        );
+    }
+}
+
+#[test]
+fn empty_context_is_uninitialized_until_captured() {
+    let mut context = UnwindContext::default();
+    assert!(!context.is_initialized());
+    assert!(matches!(
+        UnwindCursor::new(&context),
+        Err(UnwindError::BadFrame)
+    ));
+    assert!(matches!(
+        UnwindCursor::from_context(UnwindContext::empty()),
+        Err(UnwindError::BadFrame)
+    ));
+
+    context.capture().unwrap();
+    assert!(context.is_initialized());
+    assert!(UnwindContext::new().unwrap().is_initialized());
+    let cursor = UnwindCursor::new(&context).unwrap();
+    assert_ne!(cursor.register(registers::UNW_REG_SP).unwrap(), 0);
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+fn backtrace_vec_collects_every_frame() {
+    let context = UnwindContext::new().unwrap();
+    let frames = context.backtrace_vec().unwrap();
+
+    let mut cursor = UnwindCursor::new(&context).unwrap();
+    assert_eq!(frames[0], Frame::from_cursor(&cursor).unwrap());
+    assert_eq!(frames.len(), cursor.frames(WalkConfig::new()).count());
+    assert!(frames.windows(2).all(|pair| pair[0].sp <= pair[1].sp));
+}
+
+#[test]
+fn peak_init_stack_covers_both_objects() {
+    assert_eq!(
+        UnwindCursor::PEAK_INIT_STACK,
+        core::mem::size_of::<UnwindContext>() + core::mem::size_of::<UnwindCursor>()
+    );
+}
+
+#[test]
+fn read_registers_matches_register() {
+    let context = UnwindContext::new().unwrap();
+    let cursor = UnwindCursor::new(&context).unwrap();
+    let registers = [registers::UNW_REG_IP, registers::UNW_REG_SP];
+
+    let mut values = [0; 2];
+    cursor.read_registers(&registers, &mut values).unwrap();
+    for (register, value) in registers.into_iter().zip(values) {
+        assert_eq!(cursor.register(register).unwrap(), value);
+    }
+}
+
+#[test]
+fn read_registers_timing() {
+    const ROUNDS: usize = 10_000;
+    let context = UnwindContext::new().unwrap();
+    let cursor = UnwindCursor::new(&context).unwrap();
+    let registers = [registers::UNW_REG_IP, registers::UNW_REG_SP].repeat(8);
+    let mut values = vec![0; registers.len()];
+
+    let start = std::time::Instant::now();
+    for _ in 0..ROUNDS {
+        for (&register, value) in registers.iter().zip(&mut values) {
+            *value = cursor.register(register).unwrap();
+        }
+        core::hint::black_box(&values);
+    }
+    let separate = start.elapsed();
+
+    let start = std::time::Instant::now();
+    for _ in 0..ROUNDS {
+        cursor.read_registers(&registers, &mut values).unwrap();
+        core::hint::black_box(&values);
+    }
+    let batched = start.elapsed();
+
+    eprintln!(
+        "{ROUNDS} reads of {} registers: {separate:?} separately, {batched:?} batched",
+        registers.len()
+    );
+}
+
+#[test]
+fn capture_cursor_starts_in_caller() {
+    let cursor = capture_cursor().unwrap();
+    cursor.debug_assert_starts_in_caller();
+}
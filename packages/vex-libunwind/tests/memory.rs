@@ -0,0 +1,40 @@
+//! Tests for the raw memory helpers.
+
+use vex_libunwind::memory::*;
+
+#[test]
+fn reads_little_endian_words() {
+    let bytes: Vec<u8> = (1..=16).collect();
+    let expected = match WORD_SIZE {
+        4 => 0x0403_0201,
+        8 => 0x0807_0605_0403_0201_u64 as usize,
+        size => panic!("unexpected word size {size}"),
+    };
+    assert_eq!(read_word_le(&bytes), expected);
+    assert_eq!(read_word_le(&[0xff; 16]), usize::MAX);
+    assert_eq!(read_word_le(&[0; 16]), 0);
+}
+
+#[test]
+fn writes_little_endian_words() {
+    let mut bytes = [0xaa; 16];
+    write_word_le(&mut bytes, 0x1234);
+    assert_eq!(bytes[..2], [0x34, 0x12]);
+    assert!(bytes[2..WORD_SIZE].iter().all(|&byte| byte == 0));
+    assert!(bytes[WORD_SIZE..].iter().all(|&byte| byte == 0xaa));
+}
+
+#[test]
+fn words_round_trip() {
+    let mut bytes = [0; WORD_SIZE];
+    for word in [0, 1, 0x8000_0000, usize::MAX, 0xdead_beef] {
+        write_word_le(&mut bytes, word);
+        assert_eq!(read_word_le(&bytes), word);
+    }
+}
+
+#[test]
+#[should_panic]
+fn short_reads_panic() {
+    read_word_le(&[0; WORD_SIZE - 1]);
+}
@@ -0,0 +1,130 @@
+//! Checks the edge behavior of memory map lookups, which everything built on
+//! them relies on.
+
+use vex_libunwind::{is_in_text, text_range, MemoryMap, MemoryRegion, RegionKind};
+
+const fn region(
+    range: core::ops::Range<usize>,
+    kind: RegionKind,
+    name: &'static str,
+) -> MemoryRegion {
+    MemoryRegion { range, kind, name }
+}
+
+const REGIONS: [MemoryRegion; 5] = [
+    region(0x1000..0x2000, RegionKind::Code, "text"),
+    region(0x2000..0x8000, RegionKind::Data, "ram"),
+    region(0x6000..0x7000, RegionKind::Stack, "main stack"),
+    region(0x6000..0x6800, RegionKind::Stack, "task stack"),
+    region(0x9000..0xa000, RegionKind::Device, "peripherals"),
+];
+
+const MAP: MemoryMap<'static> = MemoryMap::new(&REGIONS);
+
+fn name_at(addr: usize) -> Option<&'static str> {
+    MAP.classify(addr).map(|region| region.name)
+}
+
+#[test]
+fn boundaries_are_half_open() {
+    assert_eq!(name_at(0x0fff), None);
+    assert_eq!(name_at(0x1000), Some("text"));
+    assert_eq!(name_at(0x1fff), Some("text"));
+    assert_eq!(name_at(0x2000), Some("ram"));
+    assert_eq!(name_at(0x8000), None);
+    assert_eq!(name_at(0x9fff), Some("peripherals"));
+    assert_eq!(name_at(0xa000), None);
+    assert_eq!(name_at(usize::MAX), None);
+    assert_eq!(name_at(0), None);
+}
+
+#[test]
+fn overlapping_regions_prefer_the_latest_start() {
+    assert_eq!(name_at(0x5fff), Some("ram"));
+    // Both stacks start at 0x6000, so the one later in the slice wins.
+    assert_eq!(name_at(0x6000), Some("task stack"));
+    assert_eq!(name_at(0x6800), Some("main stack"));
+    assert_eq!(name_at(0x6fff), Some("main stack"));
+    // Past the end of the nested regions, the enclosing one applies again.
+    assert_eq!(name_at(0x7000), Some("ram"));
+}
+
+#[test]
+fn kinds_decide_permissions() {
+    assert!(MAP.is_executable(0x1000));
+    assert!(!MAP.is_executable(0x2000));
+    assert!(MAP.is_readable(0x1fff));
+    assert!(MAP.is_readable(0x6000));
+    assert!(!MAP.is_readable(0x9000));
+    assert!(!MAP.is_readable(0x8000));
+}
+
+#[test]
+fn empty_maps_and_regions_contain_nothing() {
+    let empty = MemoryMap::new(&[]);
+    assert_eq!(empty.classify(0x1000), None);
+
+    let regions = [region(0x1000..0x1000, RegionKind::Code, "empty")];
+    assert_eq!(MemoryMap::new(&regions).classify(0x1000), None);
+}
+
+#[test]
+#[should_panic = "sorted"]
+fn unsorted_regions_are_rejected() {
+    let regions = vec![
+        region(0x2000..0x3000, RegionKind::Data, "ram"),
+        region(0x1000..0x2000, RegionKind::Code, "text"),
+    ];
+    MemoryMap::new(&regions);
+}
+
+// The global map is process-wide, and text checks go through it, so they
+// share a single test to keep them from racing.
+#[test]
+fn global_map_is_set_once_and_checks_text() {
+    static OTHER: MemoryMap<'static> = MemoryMap::new(&[]);
+
+    // Host test binaries don't define the linker script's symbols, so only
+    // the hot image counts as code.
+    assert_eq!(MemoryMap::global(), MemoryMap::from_linker_symbols());
+    assert_eq!(text_range(), None);
+    assert!(!is_in_text(0x0380_0000));
+    assert!(is_in_text(0x0780_0000));
+    assert!(is_in_text(0x0800_0000 - 2));
+    assert!(!is_in_text(0x0800_0000));
+    assert!(!is_in_text(is_in_text as usize));
+
+    assert!(MemoryMap::set_global(&MAP));
+    assert!(!MemoryMap::set_global(&OTHER));
+    assert_eq!(MemoryMap::global(), MAP);
+    assert_eq!(MAP.regions().len(), 5);
+
+    assert!(is_in_text(0x1000));
+    assert!(is_in_text(0x1fff));
+    assert!(!is_in_text(0x2000));
+    assert!(!is_in_text(0x0780_0000));
+    // Only the Thumb bit is ignored.
+    assert!(is_in_text(0x1ffe | 1));
+    assert!(!is_in_text(0x0fff));
+}
+
+#[test]
+fn v5_default_covers_user_memory() {
+    let map = MemoryMap::v5_default();
+    let name_at = |addr| map.classify(addr).map(|region| region.name);
+
+    assert_eq!(name_at(0x037f_ffff), None);
+    assert_eq!(name_at(0x0380_0000), Some("cold image"));
+    assert_eq!(name_at(0x0780_0000), Some("hot image"));
+    assert_eq!(name_at(0x0800_0000), None);
+    // Where the cold image's code ends isn't known, so none of it is code.
+    assert!(!map.is_executable(0x0380_0000));
+    assert!(map.is_readable(0x0380_0000));
+    assert!(map.is_executable(0x0780_0000));
+}
+
+#[test]
+fn missing_linker_symbols_fall_back_to_default() {
+    // Host test binaries don't define the vexide linker script's symbols.
+    assert_eq!(MemoryMap::from_linker_symbols(), MemoryMap::v5_default());
+}
@@ -0,0 +1,1160 @@
+//! Tests against the scripted `libunwind` stand-in from `vex-libunwind-sys`.
+#![cfg(not(feature = "disabled"))]
+
+use vex_libunwind::{encoding::FrameEncoding, *};
+use vex_libunwind_sys::{error, mock::*};
+
+#[test]
+fn capture_cursor_succeeds() {
+    let _mock = MockStack::new(vec![MockFrame::new(0x1000, 0x2000)]).install();
+
+    let cursor = capture_cursor().unwrap();
+    assert_eq!(cursor.register(registers::UNW_REG_IP).unwrap(), 0x1000);
+    assert_eq!(cursor.register(registers::UNW_REG_SP).unwrap(), 0x2000);
+}
+
+#[test]
+fn capture_cursor_reports_context_failure() {
+    let _mock = MockStack::new(vec![MockFrame::new(0x1000, 0x2000)])
+        .fail_getcontext(error::UNW_EUNSPEC)
+        .install();
+
+    let error = capture_cursor().unwrap_err();
+    assert_eq!(error.stage, CaptureStage::Context);
+    assert!(matches!(error.source, UnwindError::Unspecified));
+    assert_eq!(mock_calls().init_local, 0);
+}
+
+#[test]
+fn capture_cursor_reports_cursor_failure() {
+    let _mock = MockStack::new(vec![MockFrame::new(0x1000, 0x2000)])
+        .fail_init_local(error::UNW_EINVAL)
+        .install();
+
+    let error = capture_cursor().unwrap_err();
+    assert_eq!(error.stage, CaptureStage::Cursor);
+    assert!(matches!(error.source, UnwindError::BadValue));
+    assert!(error.to_string().contains("unw_init_local"), "{error}");
+}
+
+fn frame_with_r4_and_r6() -> MockFrame {
+    MockFrame::new(0x1000, 0x2000)
+        .with_register(registers::UNW_ARM_R4, 4)
+        .with_register(registers::UNW_ARM_R6, 6)
+}
+
+#[test]
+fn read_registers_stops_at_first_error() {
+    let _mock = MockStack::new(vec![frame_with_r4_and_r6()]).install();
+    let cursor = capture_cursor().unwrap();
+    let registers = [
+        registers::UNW_ARM_R4,
+        registers::UNW_ARM_R5,
+        registers::UNW_ARM_R6,
+    ];
+
+    let mut values = [0; 3];
+    let error = cursor.read_registers(&registers, &mut values).unwrap_err();
+    assert!(matches!(error, UnwindError::BadRegister));
+    assert_eq!(values, [4, 0, 0]);
+    assert_eq!(mock_calls().get_reg, 2);
+
+    let mut short = [0; 2];
+    let error = cursor.read_registers(&registers, &mut short).unwrap_err();
+    assert!(matches!(error, UnwindError::BadValue));
+    assert_eq!(mock_calls().get_reg, 2);
+}
+
+#[test]
+fn read_registers_each_records_every_result() {
+    let _mock = MockStack::new(vec![frame_with_r4_and_r6()]).install();
+    let cursor = capture_cursor().unwrap();
+    let registers = [
+        registers::UNW_ARM_R4,
+        registers::UNW_ARM_R5,
+        registers::UNW_ARM_R6,
+    ];
+
+    let mut results = [Ok(0), Ok(0), Ok(0)];
+    cursor
+        .read_registers_each(&registers, &mut results)
+        .unwrap();
+    assert!(matches!(results[0], Ok(4)));
+    assert!(matches!(results[1], Err(UnwindError::BadRegister)));
+    assert!(matches!(results[2], Ok(6)));
+
+    let mut long = [Ok(0), Ok(0), Ok(0), Ok(0)];
+    let error = cursor
+        .read_registers_each(&registers, &mut long)
+        .unwrap_err();
+    assert!(matches!(error, UnwindError::BadValue));
+    assert!(long.iter().all(|result| matches!(result, Ok(0))));
+}
+
+#[test]
+fn snapshot_regs_skips_unavailable_registers() {
+    let _mock = MockStack::new(vec![frame_with_r4_and_r6()]).install();
+    let cursor = capture_cursor().unwrap();
+    let which = [
+        registers::UNW_REG_IP,
+        registers::UNW_REG_SP,
+        registers::UNW_ARM_R5,
+        registers::UNW_ARM_R4,
+    ];
+
+    let mut values = [0; 4];
+    assert_eq!(cursor.snapshot_regs(&which, &mut values), Ok(3));
+    assert_eq!(values[..3], [0x1000, 0x2000, 4]);
+
+    let mut values = [0; 2];
+    assert_eq!(cursor.snapshot_regs(&which, &mut values), Ok(2));
+    assert_eq!(values, [0x1000, 0x2000]);
+}
+
+#[test]
+fn available_registers_uses_the_candidate_set() {
+    let _mock = MockStack::new(vec![frame_with_r4_and_r6()
+        .with_register(registers::UNW_ARM_R0, 0)
+        .with_register(registers::UNW_ARM_PC, 0x1000)])
+    .install();
+    let cursor = capture_cursor().unwrap();
+
+    let available: Vec<_> = cursor.available_registers().collect();
+    assert_eq!(
+        available,
+        [
+            (registers::UNW_ARM_R4, 4),
+            (registers::UNW_ARM_R6, 6),
+            (registers::UNW_ARM_PC, 0x1000),
+        ]
+    );
+}
+
+#[test]
+fn debug_assert_starts_in_caller_accepts_caller() {
+    // The check steps from the scripted innermost frame to find "its caller",
+    // so this script has both frames in the same procedure.
+    let _mock = MockStack::new(vec![
+        MockFrame::new(0x1010, 0x2000).in_proc(0x1000..0x1100),
+        MockFrame::new(0x1020, 0x2000).in_proc(0x1000..0x1100),
+    ])
+    .install();
+
+    let cursor = capture_cursor().unwrap();
+    cursor.debug_assert_starts_in_caller();
+}
+
+#[test]
+#[cfg_attr(
+    debug_assertions,
+    should_panic(expected = "was `UnwindContext::new` inlined?")
+)]
+fn debug_assert_starts_in_caller_catches_leaked_frame() {
+    let _mock = MockStack::new(vec![
+        MockFrame::new(0x1010, 0x2000).in_proc(0x1000..0x1100),
+        MockFrame::new(0x3010, 0x2100).in_proc(0x3000..0x3100),
+    ])
+    .install();
+
+    let cursor = capture_cursor().unwrap();
+    cursor.debug_assert_starts_in_caller();
+}
+
+#[test]
+fn machine_backtrace_follows_documented_grammar() {
+    let _mock = MockStack::new(vec![
+        MockFrame::new(0x1010, 0x2000)
+            .in_proc(0x1000..0x1100)
+            .named("main_loop"),
+        MockFrame::new(0x3020, 0x2100),
+        MockFrame::new(0x4008, 0x2200)
+            .in_proc(0x4000..0x4100)
+            .named("odd name\n"),
+    ])
+    .install();
+    let mut out = String::new();
+    write_backtrace_machine(&mut out, &UnwindContext::new().unwrap()).unwrap();
+    assert_eq!(
+        out,
+        "backtrace v2\n\
+         #0 0x1010 0x10 main_loop\n\
+         #1 0x3020 - -\n\
+         #2 0x4008 0x8 odd name?\n\
+         end ok\n"
+    );
+    drop(_mock);
+
+    let _mock = stopped_chain().install();
+    let mut out = String::new();
+    write_backtrace_machine(&mut out, &UnwindContext::new().unwrap()).unwrap();
+    let last = out.lines().last().unwrap();
+    assert!(last.starts_with("end error "), "{out}");
+    drop(_mock);
+
+    let _mock = cycle(3).install();
+    let mut out = String::new();
+    write_backtrace_machine(&mut out, &UnwindContext::new().unwrap()).unwrap();
+    assert_eq!(out.lines().filter(|line| line.starts_with('#')).count(), 3);
+    assert!(out.ends_with("#2 0x1020 - -\nend loop\n"), "{out}");
+    drop(_mock);
+
+    let _mock = cycle(CYCLE_WINDOW + 1).install();
+    let mut out = String::new();
+    write_backtrace_machine(&mut out, &UnwindContext::new().unwrap()).unwrap();
+    assert_eq!(
+        out.lines().filter(|line| line.starts_with('#')).count(),
+        1024
+    );
+    assert!(out.ends_with("end limit\n"), "{out}");
+}
+
+/// Prints a backtrace of a mock recursion between two procedures, returning
+/// the output and the number of names that were looked up.
+fn print_recursion(
+    write: impl Fn(&mut String, &UnwindContext) -> core::fmt::Result,
+) -> (String, usize) {
+    let frames = (0..12)
+        .map(|depth| {
+            let (ip, name) = if depth % 2 == 0 {
+                (0x1010, "even")
+            } else {
+                (0x3010, "odd")
+            };
+            MockFrame::new(ip, 0x2000 + depth * 0x10)
+                .in_proc(ip - 0x10..ip + 0x100)
+                .named(name)
+        })
+        .collect();
+    let _mock = MockStack::new(frames).install();
+
+    let context = UnwindContext::new().unwrap();
+    let mut output = String::new();
+    write(&mut output, &context).unwrap();
+    (output, mock_calls().get_proc_name)
+}
+
+#[test]
+fn printing_looping_stacks_ends() {
+    let _mock = cycle(3).install();
+    let context = UnwindContext::new().unwrap();
+    for write in [write_backtrace, write_backtrace_cached::<4>] {
+        let mut out = String::new();
+        write(&mut out, &context).unwrap();
+        assert!(
+            out.ends_with("   2: 0x00001020\n  (unwinding stopped: stack loops back on itself)\n"),
+            "{out}"
+        );
+    }
+    drop(_mock);
+
+    // A loop too long for the cycle check to notice.
+    let _mock = cycle(CYCLE_WINDOW + 1).install();
+    let mut out = String::new();
+    write_backtrace(&mut out, &context).unwrap();
+    assert_eq!(
+        out.lines().filter(|line| line.contains(": 0x")).count(),
+        1024
+    );
+    assert!(
+        out.ends_with("  ... (stopped after 1024 frames)\n"),
+        "{out}"
+    );
+}
+
+#[test]
+fn calibrating_on_looping_stacks_ends() {
+    let _mock = cycle(CYCLE_WINDOW + 1).install();
+    let mut ticks = 0;
+    measure_walk_cost_with(|| {
+        ticks += 1;
+        ticks
+    });
+    // Eight timed runs, each stopping after 1024 frames.
+    assert_eq!(mock_calls().step, 8 * 1023);
+}
+
+#[test]
+fn write_backtrace_cached_looks_up_repeated_frames_once() {
+    let (uncached, uncached_calls) = print_recursion(write_backtrace);
+    let (cached, cached_calls) = print_recursion(write_backtrace_cached::<4>);
+
+    assert_eq!(cached, uncached);
+    assert!(cached.contains(" - odd+0x10"), "{cached}");
+    assert_eq!(uncached_calls, 12);
+    assert_eq!(cached_calls, 2);
+}
+
+#[test]
+fn proc_name_hashes_match_for_the_same_name() {
+    let _mock = MockStack::new(vec![
+        MockFrame::new(0x1010, 0x2000)
+            .in_proc(0x1000..0x1100)
+            .named("drive_to"),
+        MockFrame::new(0x1080, 0x2010)
+            .in_proc(0x1000..0x1100)
+            .named("drive_to"),
+        MockFrame::new(0x3010, 0x2020)
+            .in_proc(0x3000..0x3100)
+            .named("turn_to"),
+        MockFrame::new(0x4010, 0x2030),
+    ])
+    .install();
+    let mut cursor = capture_cursor().unwrap();
+    let mut hashes = Vec::new();
+    loop {
+        hashes.push(cursor.proc_name_hash());
+        if !cursor.step().unwrap() {
+            break;
+        }
+    }
+
+    assert_eq!(hashes[0], hashes[1]);
+    assert_ne!(hashes[0], hashes[2]);
+    // FNV-1a of "drive_to", so hashes stay stable across releases.
+    assert_eq!(hashes[0], Ok(0xf877_3230_749e_feb1));
+    assert_eq!(hashes[3], Err(UnwindError::Unspecified));
+}
+
+#[test]
+fn invalid_utf8_names_are_strict_or_lossy() {
+    let _mock = MockStack::new(vec![MockFrame::new(0x1010, 0x2000)
+        .in_proc(0x1000..0x1100)
+        .named_bytes(b"bad\xffname\xe2\x82")])
+    .install();
+    let cursor = capture_cursor().unwrap();
+
+    let mut buf = [0; 32];
+    assert_eq!(cursor.procedure_name(&mut buf), Err(UnwindError::BadValue));
+    let (name, offset) = cursor.procedure_name_lossy(&mut buf).unwrap();
+    assert_eq!((name, offset), ("bad\u{fffd}name\u{fffd}", 0x10));
+
+    // The replacements push the end of the name out of a small buffer.
+    let mut buf = [0; 12];
+    assert_eq!(
+        cursor.procedure_name_lossy(&mut buf).unwrap().0,
+        "bad\u{fffd}name"
+    );
+    let mut buf = [0; 9];
+    assert_eq!(
+        cursor.procedure_name_lossy(&mut buf).unwrap().0,
+        "bad\u{fffd}nam"
+    );
+
+    let mut output = String::new();
+    write_backtrace(&mut output, &UnwindContext::new().unwrap()).unwrap();
+    assert!(output.contains("bad\u{fffd}name\u{fffd}+0x10"), "{output}");
+}
+
+/// Runs `Backtrace::try_capture` on the given stack, failing the test if it
+/// panics.
+fn try_capture_on(stack: MockStack) -> Result<Backtrace<4>, UnwindError> {
+    let _mock = stack.install();
+    std::panic::catch_unwind(Backtrace::<4>::try_capture).expect("try_capture panicked")
+}
+
+#[test]
+fn try_capture_reports_failures_without_panicking() {
+    let frames = || {
+        (0..8)
+            .map(|depth| MockFrame::new(0x1000 + depth, 0x2000))
+            .collect()
+    };
+
+    let full = try_capture_on(MockStack::new(frames())).unwrap();
+    assert_eq!(full.frames().len(), 4);
+
+    let error = try_capture_on(MockStack::new(frames()).fail_getcontext(error::UNW_EUNSPEC));
+    assert!(matches!(error, Err(UnwindError::Unspecified)));
+
+    let error = try_capture_on(MockStack::new(frames()).fail_init_local(error::UNW_EINVAL));
+    assert!(matches!(error, Err(UnwindError::BadValue)));
+
+    let partial = try_capture_on(MockStack::new(vec![
+        MockFrame::new(0x1000, 0x2000),
+        MockFrame::new(0x1010, 0x2010).fail_step(error::UNW_EBADFRAME),
+        MockFrame::new(0x1020, 0x2020),
+    ]))
+    .unwrap();
+    let ips: Vec<_> = partial.frames().iter().map(|frame| frame.ip).collect();
+    assert_eq!(ips, [0x1000, 0x1010]);
+    assert_eq!(partial.error(), Some(UnwindError::BadFrame));
+}
+
+#[test]
+fn capture_keeps_frames_before_step_failure() {
+    let _mock = MockStack::new(vec![
+        MockFrame::new(0x1000, 0x2000),
+        MockFrame::new(0x1010, 0x2010),
+        MockFrame::new(0x1020, 0x2020).fail_step(error::UNW_EBADFRAME),
+        MockFrame::new(0x1030, 0x2030),
+    ])
+    .install();
+    let backtrace = Backtrace::<8>::capture();
+
+    let ips: Vec<_> = backtrace.frames().iter().map(|frame| frame.ip).collect();
+    assert_eq!(ips, [0x1000, 0x1010, 0x1020]);
+    assert_eq!(backtrace.error(), Some(UnwindError::BadFrame));
+    assert!(
+        backtrace
+            .to_string()
+            .ends_with("   2: 0x00001020\n  (unwinding stopped: Bad frame)\n"),
+        "{backtrace}"
+    );
+    let full = Backtrace::<3>::capture();
+    assert_eq!(full.frames().len(), 3);
+    assert_eq!(full.error(), None);
+}
+
+#[test]
+fn stack_usage_skips_signal_frames() {
+    let _mock = MockStack::new(vec![
+        MockFrame::new(0x1000, 0x2000),
+        MockFrame::new(0x1010, 0x2040),
+        MockFrame::new(0x1020, 0x2100).signal_frame(),
+        MockFrame::new(0x1030, 0x8000_0000),
+        MockFrame::new(0x1040, 0x8000_0020),
+        MockFrame::new(0x1050, 0x1000),
+    ])
+    .install();
+    let backtrace = Backtrace::<8>::capture();
+
+    let usage: Vec<_> = backtrace.stack_usage().map(|(_, usage)| usage).collect();
+    assert_eq!(usage, [Some(0x40), None, None, Some(0x20), None, None]);
+    assert_eq!(backtrace.total_stack_usage(), 0x60);
+
+    let output = backtrace
+        .display(BacktraceStyle::new().stack_usage(true))
+        .to_string();
+    assert!(
+        output.starts_with("stack backtrace:\n   0: 0x00001000 (+0x40 stack)\n   1: 0x00001010\n"),
+        "{output}"
+    );
+    assert!(!backtrace.to_string().contains("stack)"));
+}
+
+/// A mock call chain through `main` and into the runtime startup code.
+fn chain_through(main: &str) -> MockStack {
+    let names = ["handler", "app", main, "__libc_start_main", "_start"];
+    let frames = names
+        .into_iter()
+        .zip(0..)
+        .map(|(name, index)| {
+            let ip = 0x1000 + index * 0x100;
+            MockFrame::new(ip + 0x10, 0x2000 + index * 0x10)
+                .in_proc(ip..ip + 0x100)
+                .named(name)
+        })
+        .collect();
+    MockStack::new(frames)
+}
+
+fn walk_ips(config: WalkConfig<'_>) -> Vec<usize> {
+    let mut cursor = capture_cursor().unwrap();
+    let ips = cursor
+        .frames(config)
+        .map(|frame| frame.unwrap().ip)
+        .collect();
+    ips
+}
+
+#[test]
+fn walk_stops_after_symbol() {
+    let _mock = chain_through("main").install();
+    let ips = walk_ips(WalkConfig::new().stop_at_symbol("main"));
+    assert_eq!(ips, [0x1010, 0x1110, 0x1210]);
+}
+
+#[test]
+fn walk_without_symbol_reaches_end() {
+    let _mock = chain_through("not_main").install();
+    let ips = walk_ips(WalkConfig::new().stop_at_symbol("main"));
+    assert_eq!(ips.len(), 5);
+}
+
+/// A mock call chain that a step deliberately stops partway through.
+fn stopped_chain() -> MockStack {
+    MockStack::new(vec![
+        MockFrame::new(0x1000, 0x2000),
+        MockFrame::new(0x1010, 0x2010).fail_step(error::UNW_ESTOPUNWIND),
+        MockFrame::new(0x1020, 0x2020),
+    ])
+}
+
+#[test]
+fn stop_unwinding_is_an_error_by_default() {
+    let _mock = stopped_chain().install();
+    let mut cursor = capture_cursor().unwrap();
+    let frames: Vec<_> = cursor.frames(WalkConfig::new()).collect();
+    assert!(matches!(
+        frames[..],
+        [Ok(_), Ok(_), Err(UnwindError::StopUnwinding)]
+    ));
+}
+
+#[test]
+fn pairs_match_each_frame_with_its_caller() {
+    let _mock = MockStack::new(vec![
+        MockFrame::new(0x1000, 0x2000),
+        MockFrame::new(0x1010, 0x2010),
+        MockFrame::new(0x1020, 0x2020),
+    ])
+    .install();
+    let mut cursor = capture_cursor().unwrap();
+    let mut pairs = cursor.frames(WalkConfig::new()).pairs();
+    let ips: Vec<_> = pairs
+        .by_ref()
+        .map(|pair| pair.map(|(callee, caller)| (callee.ip, caller.ip)))
+        .collect();
+    assert_eq!(ips, [Ok((0x1000, 0x1010)), Ok((0x1010, 0x1020))]);
+    assert_eq!(pairs.end(), Some(WalkEnd::EndOfStack));
+
+    let mut cursor = capture_cursor().unwrap();
+    let stopped = cursor.frames(WalkConfig::new().stop_in_ranges(&[0..1, 0x1010..0x1011]));
+    assert_eq!(stopped.pairs().count(), 0);
+}
+
+#[test]
+fn return_address_at_end_of_proc_is_possible_tail_call() {
+    let _mock = MockStack::new(vec![
+        MockFrame::new(0x1010, 0x2000).in_proc(0x1000..0x1100),
+        MockFrame::new(0x30fe, 0x2100).in_proc(0x3000..0x3100),
+        MockFrame::new(0x4080, 0x2200).in_proc(0x4000..0x4100),
+    ])
+    .install();
+    let mut cursor = capture_cursor().unwrap();
+    cursor.step().unwrap();
+
+    let flags: Vec<_> = (0..2)
+        .map(|_| {
+            let frame = Frame::from_cursor(&cursor).unwrap();
+            let flag = frame.possible_tail_call(&cursor.proc_info().unwrap());
+            cursor.step().unwrap();
+            flag
+        })
+        .collect();
+    assert_eq!(flags, [true, false]);
+}
+
+#[test]
+fn pairs_yield_the_walk_error() {
+    let _mock = stopped_chain().install();
+    let mut cursor = capture_cursor().unwrap();
+    let pairs: Vec<_> = cursor.frames(WalkConfig::new()).pairs().collect();
+    assert!(matches!(
+        pairs[..],
+        [Ok(_), Err(UnwindError::StopUnwinding)]
+    ));
+}
+
+#[test]
+fn stop_unwinding_can_end_walk() {
+    let _mock = stopped_chain().install();
+    let ips = walk_ips(WalkConfig::new().stop_unwinding_ends_walk(true));
+    assert_eq!(ips, [0x1000, 0x1010]);
+}
+
+fn styled_backtrace(style: BacktraceStyle) -> String {
+    let _mock = MockStack::new(vec![
+        MockFrame::new(0x1010, 0x2000),
+        MockFrame::new(0x2010, 0x2010),
+    ])
+    .install();
+    let mut backtrace = SymbolizedBacktrace::new(Backtrace::<4>::capture());
+    backtrace.resolve(&OneName("app"));
+    backtrace.display(style).to_string()
+}
+
+#[test]
+fn plain_style_matches_display() {
+    let output = styled_backtrace(BacktraceStyle::new());
+    assert_eq!(
+        output,
+        "stack backtrace:\n   0: 0x00001010 - app+0x10\n   1: 0x00002010\n"
+    );
+}
+
+#[test]
+fn color_style_resets_every_span() {
+    let output = styled_backtrace(BacktraceStyle::new().color(true));
+    assert_eq!(
+        output,
+        "stack backtrace:\n   \
+         0: \x1b[2m0x00001010\x1b[0m - \x1b[1mapp\x1b[0m+0x10\n   \
+         1: \x1b[2m0x00002010\x1b[0m\n"
+    );
+}
+
+#[test]
+fn color_style_marks_guessed_names_yellow() {
+    let _mock = MockStack::new(vec![
+        MockFrame::new(0x1010, 0x2000),
+        MockFrame::new(0x1020, 0x2010),
+    ])
+    .install();
+    let mut backtrace = SymbolizedBacktrace::new(Backtrace::<4>::capture());
+    backtrace.resolve(&SymbolTable);
+
+    assert_eq!(
+        backtrace
+            .display(BacktraceStyle::new().color(true))
+            .to_string(),
+        "stack backtrace:\n   \
+         0: \x1b[2m0x00001010\x1b[0m - \x1b[1;33mapp\x1b[0m+0x10\n   \
+         1: \x1b[2m0x00001020\x1b[0m - \x1b[1;33mapp\x1b[0m+0x20\n"
+    );
+    assert_eq!(
+        backtrace
+            .display(BacktraceStyle::new().color(true).narrow(24))
+            .to_string(),
+        "stack backtrace:\n#00 \x1b[1;33mapp\x1b[0m+0x10\n#01 \x1b[1;33mapp\x1b[0m+0x20\n"
+    );
+    assert_eq!(
+        backtrace.to_string(),
+        "stack backtrace:\n   0: 0x00001010 - app+0x10\n   1: 0x00001020 - app+0x20\n"
+    );
+}
+
+/// Names the procedure at 0x1000 as a symbol table would, without knowing
+/// whether code was inlined into it.
+struct SymbolTable;
+
+impl Symbolize for SymbolTable {
+    fn symbolize(&self, ip: usize) -> Option<Symbol<'_>> {
+        let mut symbol = OneName("app").symbolize(ip)?;
+        symbol.may_have_inlines = true;
+        Some(symbol)
+    }
+}
+
+#[test]
+fn narrow_style_fits_frames_to_width() {
+    let _mock = MockStack::new(vec![
+        MockFrame::new(0x1010, 0x2000),
+        MockFrame::new(0x12_3010, 0x2010),
+    ])
+    .install();
+    let mut backtrace = SymbolizedBacktrace::new(Backtrace::<4>::capture());
+    let long = OneName("a_very_long_mangled_function_name_that_overflows");
+    backtrace.resolve(&long);
+
+    let output = backtrace
+        .display(BacktraceStyle::new().narrow(24))
+        .to_string();
+    assert_eq!(
+        output,
+        "stack backtrace:\n#00 a_very_long_ma\u{2026}+0x10\n#01 ..23010\n"
+    );
+    assert!(output.lines().all(|line| line.chars().count() <= 24));
+
+    let output = backtrace
+        .display(BacktraceStyle::new().narrow(64))
+        .to_string();
+    assert!(output.contains("#00 a_very_long_mangled_function_name_that_overflows+0x10\n"));
+}
+
+#[test]
+fn frame_order_keeps_innermost_numbered_zero() {
+    let _mock = MockStack::new(vec![
+        MockFrame::new(0x1010, 0x2000),
+        MockFrame::new(0x2010, 0x2010),
+        MockFrame::new(0x3010, 0x2020),
+    ])
+    .install();
+    let mut backtrace = SymbolizedBacktrace::new(Backtrace::<2>::capture());
+    backtrace.resolve(&OneName("app"));
+
+    let innermost_first = BacktraceStyle::new().order(FrameOrder::InnermostFirst);
+    assert_eq!(
+        backtrace.display(innermost_first).to_string(),
+        "stack backtrace:\n   \
+         0: 0x00001010 - app+0x10\n   \
+         1: 0x00002010\n  \
+         ... (stopped after 2 frames)\n"
+    );
+    assert_eq!(
+        backtrace.display(innermost_first).to_string(),
+        backtrace.to_string()
+    );
+
+    let outermost_first = BacktraceStyle::new().order(FrameOrder::OutermostFirst);
+    assert_eq!(
+        backtrace.display(outermost_first).to_string(),
+        "stack backtrace (most recent call last):\n  \
+         ... (stopped after 2 frames)\n   \
+         1: 0x00002010\n   \
+         0: 0x00001010 - app+0x10\n"
+    );
+    assert_eq!(
+        backtrace.backtrace().display(outermost_first).to_string(),
+        "stack backtrace (most recent call last):\n  \
+         ... (stopped after 2 frames)\n   \
+         1: 0x00002010\n   \
+         0: 0x00001010\n"
+    );
+    assert_eq!(
+        backtrace
+            .backtrace()
+            .display_filtered(outermost_first, |frame| frame.ip != 0x2010)
+            .to_string(),
+        "stack backtrace (most recent call last):\n  \
+         ... (stopped after 2 frames)\n  \
+         ... (1 frame hidden)\n   \
+         0: 0x00001010\n"
+    );
+}
+
+/// Gives the procedure at 0x1000 the wrapped name.
+struct OneName(&'static str);
+
+impl Symbolize for OneName {
+    fn symbolize(&self, ip: usize) -> Option<Symbol<'_>> {
+        (0x1000..0x1100).contains(&ip).then_some(Symbol {
+            name: self.0,
+            offset: (ip - 0x1000) as u64,
+            may_have_inlines: false,
+        })
+    }
+}
+
+#[test]
+fn task_registers_start_walk_in_task() {
+    let _mock = MockStack::new(vec![
+        MockFrame::new(0x1000, 0x2000),
+        MockFrame::new(0x1010, 0x2010),
+        MockFrame::new(0x5000, 0x8000),
+        MockFrame::new(0x5010, 0x8040),
+    ])
+    .install();
+    let registers = TaskRegs {
+        sp: 0x8000,
+        lr: 0x5010,
+        pc: 0x5000,
+        ..Default::default()
+    };
+
+    let context = UnwindContext::from_task_registers(&registers);
+    assert!(context.is_initialized());
+    let mut cursor = UnwindCursor::new(&context).unwrap();
+    let ips: Vec<_> = cursor
+        .frames(WalkConfig::new())
+        .map(|frame| frame.unwrap().ip)
+        .collect();
+    assert_eq!(ips, [0x5000, 0x5010]);
+}
+
+#[test]
+fn filtered_frames_are_hidden_without_renumbering() {
+    let frames = [0x1000, 0x2000, 0x2100, 0x1100, 0x2200]
+        .iter()
+        .enumerate()
+        .map(|(index, &ip)| MockFrame::new(ip, 0x8000 + index * 0x10))
+        .collect();
+    let _mock = MockStack::new(frames).install();
+    let backtrace = Backtrace::<8>::capture();
+    let keep = |frame: &Frame| !(0x2000..0x3000).contains(&frame.ip);
+
+    let kept: Vec<_> = backtrace.filtered(keep).map(|frame| frame.ip).collect();
+    assert_eq!(kept, [0x1000, 0x1100]);
+    assert_eq!(
+        backtrace
+            .display_filtered(BacktraceStyle::new(), keep)
+            .to_string(),
+        "stack backtrace:\n   0: 0x00001000\n  ... (2 frames hidden)\n   3: 0x00001100\n  ... (1 frame hidden)\n"
+    );
+    assert_eq!(
+        backtrace
+            .display_filtered(BacktraceStyle::new(), |_| true)
+            .to_string(),
+        backtrace.to_string()
+    );
+}
+
+#[test]
+fn repeated_frames_end_walk_as_a_loop() {
+    let _mock = cycle(3).install();
+
+    let mut cursor = capture_cursor().unwrap();
+    let mut frames = cursor.frames(WalkConfig::new());
+    let walked: Vec<_> = frames.by_ref().collect();
+    assert_eq!(walked.len(), 4);
+    assert_eq!(walked[3], Err(UnwindError::BadFrame));
+    assert_eq!(frames.end(), Some(WalkEnd::Loop));
+
+    let captures = [
+        Backtrace::<16>::capture(),
+        Backtrace::<16>::try_capture().unwrap(),
+    ];
+    for backtrace in captures {
+        assert_eq!(backtrace.frames().len(), 3);
+        assert_eq!(backtrace.stop_reason(), Some(BacktraceStop::Loop));
+    }
+    let (mut ips, mut offsets) = ([0; 16], [0; 16]);
+    assert_eq!(capture_soa(&mut ips, &mut offsets), Ok(3));
+}
+
+#[test]
+fn capture_soa_keeps_frames_before_a_failed_step() {
+    let _mock = MockStack::new(vec![
+        MockFrame::new(0x1010, 0x2000).in_proc(0x1000..0x1100),
+        // A return address just past the end of a call that never returns.
+        MockFrame::new(0x3000, 0x2010)
+            .in_proc(0x3010..0x3100)
+            .fail_step(error::UNW_EUNSPEC),
+        MockFrame::new(0x4000, 0x2020),
+    ])
+    .install();
+
+    let (mut ips, mut offsets) = ([0; 4], [0; 4]);
+    assert_eq!(capture_soa(&mut ips, &mut offsets), Ok(2));
+    assert_eq!(ips[..2], [0x1010, 0x3000]);
+    assert_eq!(offsets[..2], [0x10, 0]);
+}
+
+/// A corrupted stack of `len` frames whose last frame leads back to its
+/// first, so that stepping never reaches the end.
+fn cycle(len: usize) -> MockStack {
+    let frames = (0..len)
+        .map(|depth| MockFrame::new(0x1000 + depth * 0x10, 0x2000 + depth * 0x20))
+        .collect();
+    MockStack::new(frames).loop_back_to(0)
+}
+
+#[test]
+fn walking_never_panics() {
+    // Enough frames to wrap the cycle window, a name that isn't UTF-8 where
+    // the stop symbol is checked, and a failed step at the end.
+    let mut frames: Vec<_> = (0..2 * CYCLE_WINDOW)
+        .map(|depth| MockFrame::new(0x1000 + depth * 0x10, 0x2000 + depth * 0x20))
+        .collect();
+    frames[1] = frames[1].clone().named_bytes(b"mai\xffn");
+    frames.push(MockFrame::new(0x9000, 0x9000).fail_step(error::UNW_EUNSPEC));
+    frames.push(MockFrame::new(0x9010, 0x9010));
+    let _mock = MockStack::new(frames).install();
+
+    let walked = std::panic::catch_unwind(|| {
+        let mut cursor = capture_cursor().unwrap();
+        let mut frames = cursor.frames(WalkConfig::new().stop_at_symbol("main"));
+        let walked: Vec<_> = frames.by_ref().collect();
+        (
+            walked,
+            frames.end(),
+            Backtrace::<4>::capture().frames().len(),
+        )
+    });
+    let (walked, end, captured) = walked.expect("walking panicked");
+    assert_eq!(walked.len(), 2 * CYCLE_WINDOW + 2);
+    assert_eq!(walked.last(), Some(&Err(UnwindError::Unspecified)));
+    assert_eq!(end, Some(WalkEnd::Error(UnwindError::Unspecified)));
+    assert_eq!(captured, 4);
+}
+
+#[test]
+fn deep_recursion_is_not_a_cycle() {
+    let frames = (0..4 * CYCLE_WINDOW)
+        .map(|depth| MockFrame::new(0x1010, 0x2000 + depth * 0x20))
+        .collect();
+    let _mock = MockStack::new(frames).install();
+
+    let mut cursor = capture_cursor().unwrap();
+    let walked: Result<Vec<_>, _> = cursor.frames(WalkConfig::new()).collect();
+    assert_eq!(walked.unwrap().len(), 4 * CYCLE_WINDOW);
+}
+
+#[test]
+fn register_file_round_trips_through_a_context() {
+    let registers = (0..16).fold(MockFrame::new(0x5000, 0x8000), |frame, n| {
+        let value = match n {
+            13 => 0x8000,
+            15 => 0x5000,
+            n => 0x100 + n as usize,
+        };
+        frame.with_register(n, value)
+    });
+    let _mock = MockStack::new(vec![
+        MockFrame::new(0x1000, 0x2000),
+        registers,
+        MockFrame::new(0x5010, 0x8040),
+    ])
+    .install();
+
+    let mut cursor = capture_cursor().unwrap();
+    assert_eq!(
+        cursor.register_file(),
+        Err(UnwindError::BadRegister),
+        "the mock's first frame has no core registers"
+    );
+    cursor.step().unwrap();
+    let file = cursor.register_file().unwrap();
+    assert_eq!((file.sp(), file.lr(), file.pc()), (0x8000, 0x10e, 0x5000));
+    assert_eq!(file.r[4], 0x104);
+
+    let mut context = UnwindContext::empty();
+    context.apply_register_file(&file);
+    let restored = UnwindCursor::new(&context).unwrap();
+    assert_eq!(restored.register_file(), Ok(file));
+}
+
+#[test]
+fn crash_report_bounds_backtrace_depth() {
+    let frames = (0..MAX_REPORT_FRAMES + 10)
+        .map(|i| MockFrame::new(0x1000 + i * 0x10, 0x2000 + i * 0x10))
+        .collect();
+    let _mock = MockStack::new(frames).install();
+
+    let context = UnwindContext::new().unwrap();
+    let mut report = String::new();
+    write_crash_report(&mut report, &context, None).unwrap();
+    let frame_lines = report.lines().filter(|line| line.contains(": 0x")).count();
+    assert_eq!(frame_lines, MAX_REPORT_FRAMES, "{report}");
+    assert!(
+        report.ends_with(&format!(
+            "  ... (stopped after {MAX_REPORT_FRAMES} frames)\n"
+        )),
+        "{report}"
+    );
+}
+
+#[test]
+fn crash_report_includes_instruction_bytes_inside_proc() {
+    static CODE: [u8; 6] = [0x00, 0x00, 0x90, 0xe5, 0x1e, 0xff];
+    let start = CODE.as_ptr() as usize;
+    let _mock = MockStack::new(vec![
+        MockFrame::new(start + 2, 0x2000).in_proc(start..start + CODE.len()),
+        MockFrame::new(0x1000, 0x2010),
+    ])
+    .install();
+    let context = UnwindContext::new().unwrap();
+
+    let mut report = String::new();
+    let options = ReportOptions::new().instruction_bytes(8);
+    write_crash_report_with(&mut report, &context, None, options).unwrap();
+    let bytes = format!(
+        "instruction bytes:\n  {:#0width$x}: 90 e5 1e ff\n",
+        start + 2,
+        width = 2 + 2 * std::mem::size_of::<usize>()
+    );
+    assert!(report.contains(&bytes), "{report}");
+
+    let mut report = String::new();
+    write_crash_report(&mut report, &context, None).unwrap();
+    assert!(!report.contains("instruction bytes:"), "{report}");
+}
+
+#[test]
+fn crash_report_skips_instruction_bytes_without_proc() {
+    let _mock = MockStack::new(vec![MockFrame::new(0x4, 0x2000)]).install();
+    let context = UnwindContext::new().unwrap();
+    let mut report = String::new();
+    let options = ReportOptions::new().instruction_bytes(4);
+    write_crash_report_with(&mut report, &context, None, options).unwrap();
+    assert!(
+        report.contains("instruction bytes:\n  (unavailable: "),
+        "{report}"
+    );
+}
+
+#[test]
+fn module_base_offsets_are_relative() {
+    let _mock = MockStack::new(vec![
+        MockFrame::new(0x0380_1010, 0x2000),
+        MockFrame::new(0x1000, 0x2010),
+    ])
+    .install();
+    let backtrace = Backtrace::<4>::capture();
+    let frames = backtrace.frames();
+    assert_eq!(frames[0].relative_ip(0x0380_0000), Some(0x1010));
+    assert_eq!(frames[1].relative_ip(0x0380_0000), None);
+
+    let mut symbolized = SymbolizedBacktrace::new(backtrace);
+    symbolized.resolve_relative(&OneName("app"), 0x0380_0000);
+    let output = symbolized
+        .display(BacktraceStyle::new().module_base(0x0380_0000))
+        .to_string();
+    assert_eq!(
+        output,
+        "stack backtrace:\n   0: 0x03801010 (base+0x1010) - app+0x10\n   1: 0x00001000\n"
+    );
+}
+
+/// A stack whose oldest frame, at 0x4000, has no unwind info to step past.
+fn rootless_chain() -> MockStack {
+    MockStack::new(vec![
+        MockFrame::new(0x1000, 0x2000),
+        MockFrame::new(0x4000, 0x2f80).fail_step(error::UNW_ENOINFO),
+        MockFrame::new(0x5000, 0x3000),
+    ])
+}
+
+/// Walks the stack, returning the frames' results and why the walk ended.
+fn walk_to_end(config: WalkConfig<'_>) -> (Vec<Result<usize, UnwindError>>, Option<WalkEnd>) {
+    let mut cursor = capture_cursor().unwrap();
+    let mut frames = cursor.frames(config);
+    let results = frames
+        .by_ref()
+        .map(|frame| frame.map(|frame| frame.ip))
+        .collect();
+    (results, frames.end())
+}
+
+#[test]
+fn missing_info_at_root_ends_walk() {
+    let _mock = rootless_chain().install();
+    let entry = [0x4000..0x4100, 0x9000..0x9100];
+    let (results, end) = walk_to_end(WalkConfig::new().entry_ranges(&entry));
+    assert_eq!(results, [Ok(0x1000), Ok(0x4000)]);
+    assert_eq!(end, Some(WalkEnd::Root));
+
+    StackBounds::set_current(Some(StackBounds {
+        lo: 0x1000,
+        hi: 0x3000,
+    }));
+    let (results, end) = walk_to_end(WalkConfig::new());
+    StackBounds::set_current(None);
+    assert_eq!(results, [Ok(0x1000), Ok(0x4000)]);
+    assert_eq!(end, Some(WalkEnd::Root));
+
+    let mut backtrace = Backtrace::<4>::capture();
+    assert_eq!(backtrace.error(), Some(UnwindError::NoInfo));
+    StackBounds::set_current(Some(StackBounds {
+        lo: 0x1000,
+        hi: 0x3000,
+    }));
+    backtrace = Backtrace::<4>::try_capture().unwrap();
+    StackBounds::set_current(None);
+    assert_eq!(backtrace.error(), None);
+    assert_eq!(backtrace.frames().len(), 2);
+}
+
+#[test]
+fn missing_info_elsewhere_is_an_error() {
+    let mock = rootless_chain().install();
+    let entry = [0x5000..0x5100, 0x9000..0x9100];
+    let (results, end) = walk_to_end(WalkConfig::new().entry_ranges(&entry));
+    assert_eq!(results, [Ok(0x1000), Ok(0x4000), Err(UnwindError::NoInfo)]);
+    assert_eq!(end, Some(WalkEnd::Error(UnwindError::NoInfo)));
+    drop(mock);
+
+    let _mock = MockStack::new(vec![MockFrame::new(0x1000, 0x2000)]).install();
+    let (_, end) = walk_to_end(WalkConfig::new());
+    assert_eq!(end, Some(WalkEnd::EndOfStack));
+    let (_, end) = walk_to_end(WalkConfig::new().stop_in_ranges(&[0..1, 0x1000..0x1001]));
+    assert_eq!(end, Some(WalkEnd::Stopped));
+}
+
+/// Captures a backtrace of the stack each way a capture can be made, and
+/// checks that each one records `stop` and carries it through an encoding.
+fn assert_stop_reason<const N: usize>(stack: MockStack, stop: BacktraceStop) {
+    let _mock = stack.install();
+    let captures = [
+        Backtrace::<N>::capture(),
+        Backtrace::<N>::try_capture().unwrap(),
+        Backtrace::<N>::try_from(&UnwindContext::new().unwrap()).unwrap(),
+    ];
+    for backtrace in captures {
+        assert_eq!(backtrace.stop_reason(), Some(stop), "{backtrace}");
+
+        let mut bytes = vec![0; encoding::max_encoded_len(N)];
+        let len = backtrace.encode(FrameEncoding::Delta, &mut bytes).unwrap();
+        let decoded = Backtrace::<N>::from_encoded(&bytes[..len]).unwrap();
+        assert_eq!(decoded.stop_reason(), Some(stop));
+    }
+}
+
+fn three_frames() -> Vec<MockFrame> {
+    vec![
+        MockFrame::new(0x1000, 0x2000),
+        MockFrame::new(0x1010, 0x2010),
+        MockFrame::new(0x1020, 0x2020),
+    ]
+}
+
+#[test]
+fn stop_reason_is_end_of_stack() {
+    assert_stop_reason::<4>(MockStack::new(three_frames()), BacktraceStop::EndOfStack);
+}
+
+#[test]
+fn stop_reason_is_max_depth() {
+    assert_stop_reason::<2>(MockStack::new(three_frames()), BacktraceStop::MaxDepth);
+
+    let _mock = MockStack::new(three_frames()).install();
+    let backtrace = Backtrace::<2>::capture();
+    assert!(
+        backtrace
+            .to_string()
+            .ends_with("   1: 0x00001010\n  ... (stopped after 2 frames)\n"),
+        "{backtrace}"
+    );
+}
+
+#[test]
+fn stop_reason_is_error() {
+    let mut frames = three_frames();
+    frames[1] = frames[1].clone().fail_step(error::UNW_EBADFRAME);
+    assert_stop_reason::<4>(
+        MockStack::new(frames),
+        BacktraceStop::Error(UnwindError::BadFrame),
+    );
+}
+
+#[test]
+fn stop_reason_is_root() {
+    StackBounds::set_current(Some(StackBounds {
+        lo: 0x1000,
+        hi: 0x3000,
+    }));
+    assert_stop_reason::<4>(rootless_chain(), BacktraceStop::Root);
+    StackBounds::set_current(None);
+    assert_stop_reason::<4>(rootless_chain(), BacktraceStop::Error(UnwindError::NoInfo));
+}
+
+#[test]
+fn stop_reason_is_stopped() {
+    let _mock = MockStack::new(three_frames()).install();
+    let backtrace =
+        Backtrace::<4>::capture_with(WalkConfig::new().stop_in_ranges(&[0..1, 0x1010..0x1011]));
+    assert_eq!(backtrace.frames().len(), 1);
+    assert_eq!(backtrace.stop_reason(), Some(BacktraceStop::Stopped));
+    assert!(
+        backtrace.to_string().ends_with("  (stopped early)\n"),
+        "{backtrace}"
+    );
+
+    let mut bytes = [0; 64];
+    let len = backtrace.encode(FrameEncoding::Plain, &mut bytes).unwrap();
+    let decoded = Backtrace::<4>::from_encoded(&bytes[..len]).unwrap();
+    assert_eq!(decoded.stop_reason(), Some(BacktraceStop::Stopped));
+}
+
+#[test]
+fn stop_reason_is_loop() {
+    assert_stop_reason::<16>(cycle(3), BacktraceStop::Loop);
+
+    let _mock = cycle(3).install();
+    let backtrace = Backtrace::<16>::capture();
+    assert!(
+        backtrace
+            .to_string()
+            .ends_with("   2: 0x00001020\n  (unwinding stopped: stack loops back on itself)\n"),
+        "{backtrace}"
+    );
+}
+
+#[test]
+fn stop_reason_is_fuel() {
+    // A loop too long for the cycle check to notice.
+    assert_stop_reason::<1100>(cycle(CYCLE_WINDOW + 1), BacktraceStop::Fuel);
+
+    let _mock = cycle(CYCLE_WINDOW + 1).install();
+    let backtrace = Backtrace::<1100>::capture();
+    assert_eq!(backtrace.frames().len(), 1024);
+    assert!(
+        backtrace
+            .to_string()
+            .ends_with("  (unwinding stopped: too many frames)\n"),
+        "{backtrace}"
+    );
+    let (mut ips, mut offsets) = (vec![0; 1100], vec![0; 1100]);
+    assert_eq!(capture_soa(&mut ips, &mut offsets), Ok(1024));
+    assert_eq!(count_frames(), Ok(1024));
+}
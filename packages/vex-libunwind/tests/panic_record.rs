@@ -0,0 +1,45 @@
+//! Checks what a `PanicRecord` copies out of a real panic. The panic hook is
+//! process-wide, so everything runs in a single test.
+
+use std::{panic, sync::Mutex};
+
+use vex_libunwind::*;
+
+/// Panics with the given message, returning the record made by the hook.
+fn record_panic(message: &str) -> PanicRecord {
+    static RECORD: Mutex<Option<PanicRecord>> = Mutex::new(None);
+
+    let previous = panic::take_hook();
+    panic::set_hook(Box::new(|info| {
+        *RECORD.lock().unwrap() = Some(PanicRecord::from_panic(info));
+    }));
+    let _ = panic::catch_unwind(|| panic!("{message}"));
+    panic::set_hook(previous);
+    RECORD.lock().unwrap().take().unwrap()
+}
+
+#[test]
+fn panic_message_and_location_are_kept() {
+    let record = record_panic("short and sweet");
+    assert_eq!(record.message(), "short and sweet");
+    assert!(!record.message_truncated());
+    let (file, line, column) = record.location().unwrap();
+    assert!(file.ends_with("panic_record.rs"), "{file}");
+    assert_eq!((line, column), (16, 36));
+    assert_eq!(
+        record.to_string(),
+        format!("panicked at {file}:16:36:\nshort and sweet")
+    );
+
+    // Cut off mid-character, so only whole characters are kept.
+    let long = "é".repeat(PANIC_MESSAGE_BYTES);
+    let record = record_panic(&long);
+    assert!(record.message_truncated());
+    assert_eq!(record.message(), "é".repeat(PANIC_MESSAGE_BYTES / 2));
+    assert!(record.to_string().ends_with("é..."), "{record}");
+
+    let exact = "x".repeat(PANIC_MESSAGE_BYTES);
+    let record = record_panic(&exact);
+    assert!(!record.message_truncated());
+    assert_eq!(record.message(), exact);
+}
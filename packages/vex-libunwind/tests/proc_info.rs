@@ -0,0 +1,28 @@
+//! Tests for procedure info lookups.
+
+use vex_libunwind::*;
+
+#[test]
+fn proc_info_matches_proc_range() {
+    let context = UnwindContext::new().unwrap();
+    let cursor = UnwindCursor::new(&context).unwrap();
+
+    let info = cursor.proc_info().unwrap();
+    assert_eq!(info.range(), cursor.proc_range().unwrap());
+    assert_eq!(info.start_ip(), info.range().start);
+    assert_eq!(info.end_ip(), info.range().end);
+    assert!(info.contains(cursor.register(registers::UNW_REG_IP).unwrap()));
+}
+
+#[test]
+fn raw_proc_info_matches_accessors() {
+    let context = UnwindContext::new().unwrap();
+    let cursor = UnwindCursor::new(&context).unwrap();
+
+    let info = cursor.proc_info().unwrap();
+    let raw = info.as_raw();
+    assert_eq!(raw.start_ip, info.start_ip());
+    assert_eq!(raw.end_ip, info.end_ip());
+    assert_eq!(raw.lsda, info.lsda().unwrap_or(0));
+    assert_eq!(raw.handler, info.handler().unwrap_or(0));
+}
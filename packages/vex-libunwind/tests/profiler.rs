@@ -0,0 +1,210 @@
+//! Tests for the sampling profiler.
+#![cfg(not(feature = "disabled"))]
+
+use std::sync::atomic::AtomicUsize;
+
+use vex_libunwind::{profiler::*, RawAddresses, Symbol, Symbolize};
+use vex_libunwind_sys::mock::*;
+
+fn buffer<const N: usize>() -> [AtomicUsize; N] {
+    std::array::from_fn(|_| AtomicUsize::new(0))
+}
+
+/// A call chain of a timer callback that interrupted `ip`, called from
+/// `0x1100`.
+fn interrupted_at(ip: usize) -> MockGuard {
+    MockStack::new(vec![
+        MockFrame::new(0x9000, 0x2000),
+        MockFrame::new(ip, 0x2100),
+        MockFrame::new(0x1100, 0x2200),
+    ])
+    .install()
+}
+
+#[test]
+fn samples_aggregate_by_innermost_frame() {
+    let buffer = buffer::<64>();
+    let profiler = SamplingProfiler::new(ProfilerConfig::new().skip_frames(1), &buffer);
+    profiler.start();
+    for ip in [0x1010, 0x1020, 0x1010, 0x3000, 0x1010] {
+        let _mock = interrupted_at(ip);
+        assert!(profiler.sample());
+    }
+    let profile = profiler.stop();
+
+    assert_eq!(profile.sample_count(), 5);
+    assert_eq!(profile.dropped(), 0);
+    let first = profile.samples().next().unwrap();
+    assert_eq!(first.ips().collect::<Vec<_>>(), [0x1010, 0x1100]);
+
+    let mut hottest = [(0, 0); 2];
+    assert_eq!(profile.hottest(&mut hottest), 2);
+    assert_eq!(hottest, [(0x1010, 3), (0x1020, 1)]);
+    assert_eq!(profile.self_count_in(0x1000..0x1100), 4);
+    assert_eq!(profile.count_in(0x1100..0x1200), 5);
+}
+
+#[test]
+fn samples_are_only_taken_while_running() {
+    let buffer = buffer::<64>();
+    let profiler = SamplingProfiler::new(ProfilerConfig::new(), &buffer);
+    let _mock = interrupted_at(0x1010);
+
+    assert!(!profiler.sample());
+    profiler.start();
+    assert!(profiler.sample());
+    assert_eq!(profiler.report().sample_count(), 1);
+    profiler.stop();
+    assert!(!profiler.sample());
+
+    let profile = profiler.report();
+    assert_eq!((profile.sample_count(), profile.dropped()), (1, 0));
+    assert_eq!(profile.samples().next().unwrap().leaf(), 0x9000);
+}
+
+#[test]
+fn samples_are_bounded_and_dropped_once_full() {
+    let buffer = buffer::<8>();
+    let profiler = SamplingProfiler::new(ProfilerConfig::new().max_depth(2), &buffer);
+    let _mock = interrupted_at(0x1010);
+    profiler.start();
+    for _ in 0..4 {
+        profiler.sample();
+    }
+
+    let profile = profiler.stop();
+    let lens: Vec<_> = profile.samples().map(|sample| sample.len()).collect();
+    assert_eq!(lens, [2, 2, 1]);
+    assert_eq!((profile.sample_count(), profile.dropped()), (3, 1));
+}
+
+#[test]
+fn samples_of_looping_stacks_end() {
+    let buffer = buffer::<64>();
+    let profiler = SamplingProfiler::new(ProfilerConfig::new().max_depth(16), &buffer);
+    // A corrupted stack whose caller is the interrupted frame again.
+    let _mock = MockStack::new(vec![
+        MockFrame::new(0x9000, 0x2000),
+        MockFrame::new(0x1010, 0x2100),
+    ])
+    .loop_back_to(1)
+    .install();
+    profiler.start();
+    assert!(profiler.sample());
+
+    let profile = profiler.stop();
+    let first = profile.samples().next().unwrap();
+    assert_eq!(first.ips().collect::<Vec<_>>(), [0x9000, 0x1010]);
+}
+
+/// Names the procedures of [`interrupted_at`]'s chains.
+struct Names;
+
+impl Symbolize for Names {
+    fn symbolize(&self, ip: usize) -> Option<Symbol<'_>> {
+        let name = match ip {
+            0x1000..=0x10ff => "drive_to",
+            0x1100..=0x11ff => "opcontrol",
+            0x3000..=0x30ff => "odd;name",
+            _ => return None,
+        };
+        Some(Symbol {
+            name,
+            offset: 0,
+            may_have_inlines: true,
+        })
+    }
+}
+
+#[test]
+fn folded_output_counts_each_chain_outermost_first() {
+    let buffer = buffer::<64>();
+    let profiler = SamplingProfiler::new(ProfilerConfig::new().skip_frames(1), &buffer);
+    profiler.start();
+    for ip in [0x1010, 0x2000, 0x1010, 0x3000, 0x1010] {
+        let _mock = interrupted_at(ip);
+        profiler.sample();
+    }
+    let profile = profiler.stop();
+
+    assert_eq!(
+        profile.folded(&Names).to_string(),
+        "opcontrol;drive_to 3\nopcontrol;0x2000 1\nopcontrol;odd:name 1\n"
+    );
+    assert_eq!(
+        profile.folded(&RawAddresses).to_string(),
+        "0x1100;0x1010 3\n0x1100;0x2000 1\n0x1100;0x3000 1\n"
+    );
+
+    let mut bytes = Vec::new();
+    profile
+        .folded(&Names)
+        .write_bytes(|chunk| {
+            bytes.extend_from_slice(chunk);
+            Ok::<_, ()>(())
+        })
+        .unwrap();
+    assert_eq!(bytes, profile.folded(&Names).to_string().as_bytes());
+}
+
+#[test]
+fn functions_count_self_and_total_samples() {
+    let buffer = buffer::<64>();
+    let config = ProfilerConfig::new().record_proc_starts(true);
+    let profiler = SamplingProfiler::new(config, &buffer);
+    profiler.start();
+    let chains = [
+        // drive_to recursing into itself, called from opcontrol.
+        vec![(0x1010, 0x1000), (0x1020, 0x1000), (0x1110, 0x1100)],
+        vec![(0x1030, 0x1000), (0x1110, 0x1100)],
+        vec![(0x1120, 0x1100)],
+        // Code without unwind info, called from opcontrol.
+        vec![(0x5000, 0), (0x1130, 0x1100)],
+    ];
+    for chain in &chains {
+        let frames = chain
+            .iter()
+            .enumerate()
+            .map(|(index, &(ip, start))| {
+                let frame = MockFrame::new(ip, 0x2000 + index * 0x10);
+                match start {
+                    0 => frame,
+                    start => frame.in_proc(start..start + 0x100),
+                }
+            })
+            .collect();
+        let _mock = MockStack::new(frames).install();
+        assert!(profiler.sample());
+    }
+    let profile = profiler.stop();
+
+    let table = profile.functions::<8>();
+    let rows: Vec<_> = table
+        .rows()
+        .iter()
+        .map(|row| (row.function, row.self_samples, row.total_samples))
+        .collect();
+    assert_eq!(
+        rows,
+        [
+            (FunctionKey::Proc(0x1000), 2, 2),
+            (FunctionKey::Proc(0x1100), 1, 4),
+            (FunctionKey::Ip(0x5000), 1, 1),
+        ]
+    );
+    assert_eq!(table.other(), (0, 0));
+
+    let mut small = profile.functions::<2>();
+    assert_eq!(small.other(), (1, 1));
+    small.truncate(1);
+    assert_eq!(small.rows().len(), 1);
+    assert_eq!(small.other(), (2, 5));
+    assert_eq!(
+        small.to_string(),
+        "samples by function (of 4):\n    self   total  function\n       2       2  0x1000\n       2       5  (other)\n"
+    );
+    assert_eq!(
+        table.to_string().lines().nth(4),
+        Some("       1       1  0x5000 (ip)")
+    );
+}
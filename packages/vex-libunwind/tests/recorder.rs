@@ -0,0 +1,105 @@
+//! Tests for keeping recent backtraces in a flight recorder.
+#![cfg(not(feature = "disabled"))]
+
+use std::cell::Cell;
+
+use vex_libunwind::{encoding::FrameEncoding, *};
+use vex_libunwind_sys::mock::*;
+
+std::thread_local! {
+    static NOW: Cell<u64> = const { Cell::new(0) };
+}
+
+/// A clock that goes up by ten each time it's read.
+fn ticks() -> u64 {
+    NOW.with(|now| {
+        now.set(now.get() + 10);
+        now.get()
+    })
+}
+
+fn ips(backtrace: &Backtrace<4>) -> Vec<usize> {
+    backtrace.frames().iter().map(|frame| frame.ip).collect()
+}
+
+fn install_stack() -> MockGuard {
+    MockStack::new(vec![
+        MockFrame::new(0x1000, 0x2000),
+        MockFrame::new(0x1100, 0x2100),
+        MockFrame::new(0x1200, 0x2200),
+    ])
+    .install()
+}
+
+#[test]
+fn snapshot_keeps_the_newest_records_oldest_first() {
+    static RECORDER: FlightRecorder<3, 4> = FlightRecorder::new(ticks);
+    assert_eq!(RECORDER.snapshot().count(), 0);
+
+    let _mock = install_stack();
+    for _ in 0..5 {
+        assert!(RECORDER.record());
+    }
+
+    let records: Vec<_> = RECORDER.snapshot().collect();
+    let sequences: Vec<_> = records.iter().map(|record| record.sequence).collect();
+    let times: Vec<_> = records.iter().map(|record| record.time).collect();
+    assert_eq!(sequences, [2, 3, 4]);
+    assert_eq!(times, [30, 40, 50]);
+    for record in &records {
+        assert!(!record.backtrace.frames().is_empty());
+        assert_eq!(ips(&record.backtrace), ips(&records[0].backtrace));
+    }
+}
+
+#[test]
+fn records_being_written_are_left_out_of_snapshots() {
+    static RECORDER: FlightRecorder<2, 4> = FlightRecorder::new(snapshot_clock);
+    thread_local! {
+        static SEEN: Cell<Option<usize>> = const { Cell::new(None) };
+    }
+    // Snapshots from the clock run while the record's slot is claimed.
+    fn snapshot_clock() -> u64 {
+        SEEN.with(|seen| seen.set(Some(RECORDER.snapshot().count())));
+        0
+    }
+
+    let _mock = install_stack();
+    assert!(RECORDER.record());
+    assert_eq!(SEEN.with(Cell::get), Some(0));
+    assert!(RECORDER.record());
+    assert_eq!(SEEN.with(Cell::get), Some(1));
+    assert!(RECORDER.record());
+    assert_eq!(SEEN.with(Cell::get), Some(1));
+    assert_eq!(RECORDER.snapshot().count(), 2);
+}
+
+#[test]
+fn encoded_records_decode_to_the_snapshot() {
+    static RECORDER: FlightRecorder<2, 4> = FlightRecorder::new(ticks);
+    let _mock = install_stack();
+    for _ in 0..3 {
+        RECORDER.record();
+    }
+
+    let mut bytes = [0; FlightRecorder::<2, 4>::MAX_ENCODED_LEN];
+    let len = RECORDER.encode(FrameEncoding::Delta, &mut bytes).unwrap();
+    let decoded = RecordedBacktrace::<4>::decode_all(&bytes[..len]).unwrap();
+    let snapshot: Vec<_> = RECORDER.snapshot().collect();
+    assert_eq!(decoded.len(), snapshot.len());
+    for (decoded, record) in decoded.iter().zip(&snapshot) {
+        assert_eq!(decoded.sequence, record.sequence);
+        assert_eq!(decoded.time, record.time);
+        assert_eq!(ips(&decoded.backtrace), ips(&record.backtrace));
+        assert_eq!(
+            decoded.backtrace.stop_reason(),
+            record.backtrace.stop_reason()
+        );
+    }
+
+    assert_eq!(
+        RECORDER.encode(FrameEncoding::Delta, &mut bytes[..len - 1]),
+        Err(encoding::EncodeError::BufferTooSmall)
+    );
+    assert!(RecordedBacktrace::<4>::decode_all(&bytes[..len - 1]).is_err());
+}
@@ -0,0 +1,274 @@
+//! Checks that the register constants follow the DWARF for the ARM
+//! Architecture numbering that libunwind uses.
+
+use vex_libunwind::registers::*;
+
+#[test]
+fn core_registers_match_dwarf_numbering() {
+    let core = [
+        UNW_ARM_R0,
+        UNW_ARM_R1,
+        UNW_ARM_R2,
+        UNW_ARM_R3,
+        UNW_ARM_R4,
+        UNW_ARM_R5,
+        UNW_ARM_R6,
+        UNW_ARM_R7,
+        UNW_ARM_R8,
+        UNW_ARM_R9,
+        UNW_ARM_R10,
+        UNW_ARM_R11,
+        UNW_ARM_R12,
+        UNW_ARM_R13,
+        UNW_ARM_R14,
+        UNW_ARM_R15,
+    ];
+    for (number, register) in core.into_iter().enumerate() {
+        assert_eq!(register, number as i32);
+    }
+}
+
+#[test]
+fn core_register_aliases() {
+    assert_eq!(UNW_ARM_SP, UNW_ARM_R13);
+    assert_eq!(UNW_ARM_LR, UNW_ARM_R14);
+    assert_eq!(UNW_ARM_PC, UNW_ARM_R15);
+    assert_eq!(UNW_ARM_IP, UNW_ARM_R15);
+}
+
+#[test]
+fn eh_data_registers_are_r0_and_r1() {
+    assert_eq!(UNW_ARM_EH_DATA0, UNW_ARM_R0);
+    assert_eq!(UNW_ARM_EH_DATA1, UNW_ARM_R1);
+}
+
+#[test]
+fn eh_data_registers_read_cleanly() {
+    let context = vex_libunwind::UnwindContext::new().unwrap();
+    let cursor = vex_libunwind::UnwindCursor::new(&context).unwrap();
+
+    for register in [UNW_ARM_EH_DATA0, UNW_ARM_EH_DATA1] {
+        match cursor.register(register) {
+            Ok(_) | Err(vex_libunwind::UnwindError::BadRegister) => {}
+            Err(error) => panic!("reading {register} failed with {error:?}"),
+        }
+    }
+}
+
+#[test]
+fn status_registers_match_dwarf_numbering() {
+    let spsrs = [
+        UNW_ARM_SPSR,
+        UNW_ARM_SPSR_FIQ,
+        UNW_ARM_SPSR_IRQ,
+        UNW_ARM_SPSR_ABT,
+        UNW_ARM_SPSR_UND,
+        UNW_ARM_SPSR_SVC,
+    ];
+    for (offset, register) in spsrs.into_iter().enumerate() {
+        assert_eq!(register, 128 + offset as i32);
+    }
+}
+
+#[test]
+fn status_register_reads_cleanly() {
+    let context = vex_libunwind::UnwindContext::new().unwrap();
+    let cursor = vex_libunwind::UnwindCursor::new(&context).unwrap();
+
+    match cursor.status_register() {
+        Ok(_) | Err(vex_libunwind::UnwindError::BadRegister) => {}
+        Err(error) => panic!("reading the SPSR failed with {error:?}"),
+    }
+}
+
+#[test]
+fn lr_matches_register_14() {
+    let context = vex_libunwind::UnwindContext::new().unwrap();
+    let cursor = vex_libunwind::UnwindCursor::new(&context).unwrap();
+
+    assert_eq!(UNW_ARM_LR, UNW_ARM_R14);
+    assert_eq!(cursor.lr().unwrap(), cursor.register(UNW_ARM_R14).unwrap());
+}
+
+#[test]
+fn vfp_registers_match_dwarf_numbering() {
+    assert_eq!(UNW_ARM_S0, 64);
+    assert_eq!(UNW_ARM_S31, 95);
+    assert_eq!(UNW_ARM_D0, 256);
+    assert_eq!(UNW_ARM_D15, 271);
+    assert_eq!(UNW_ARM_D16, 272);
+    assert_eq!(UNW_ARM_D31, 287);
+}
+
+#[cfg(all(target_arch = "arm", not(feature = "core-registers-only")))]
+#[test]
+fn d_registers_round_trip_through_cursor() {
+    use vex_libunwind::{UnwindContext, UnwindCursor};
+
+    let context = UnwindContext::new().unwrap();
+    let cursor = UnwindCursor::new(&context).unwrap();
+
+    for register in [UNW_ARM_D0, UNW_ARM_D15, UNW_ARM_D16, UNW_ARM_D31] {
+        assert!(cursor.is_fp_register(register));
+        let value = 0x0123_4567_89ab_cdef ^ register as u64;
+        unsafe { cursor.set_fp_register(register, value).unwrap() };
+        assert_eq!(cursor.fp_register(register).unwrap(), value);
+    }
+    assert!(!cursor.is_fp_register(UNW_ARM_S0));
+}
+
+#[cfg(all(target_arch = "arm", not(feature = "core-registers-only")))]
+#[test]
+fn q_registers_combine_d_registers() {
+    use vex_libunwind::{UnwindContext, UnwindCursor};
+
+    let context = UnwindContext::new().unwrap();
+    let cursor = UnwindCursor::new(&context).unwrap();
+
+    unsafe {
+        cursor
+            .set_fp_register(UNW_ARM_D30, 0x0706_0504_0302_0100)
+            .unwrap();
+        cursor
+            .set_fp_register(UNW_ARM_D31, 0x0f0e_0d0c_0b0a_0908)
+            .unwrap();
+    }
+    let expected: [u8; 16] = core::array::from_fn(|i| i as u8);
+    assert_eq!(cursor.q_register(15).unwrap(), expected);
+}
+
+#[test]
+fn q_registers_past_q15_are_rejected() {
+    use vex_libunwind::{UnwindContext, UnwindCursor, UnwindError};
+
+    let context = UnwindContext::new().unwrap();
+    let cursor = UnwindCursor::new(&context).unwrap();
+
+    assert!(matches!(
+        cursor.q_register(16),
+        Err(UnwindError::BadRegister)
+    ));
+}
+
+#[cfg(not(target_arch = "arm"))]
+#[test]
+fn arm_d_registers_are_rejected_on_hosts() {
+    use vex_libunwind::{UnwindContext, UnwindCursor, UnwindError};
+
+    let context = UnwindContext::new().unwrap();
+    let cursor = UnwindCursor::new(&context).unwrap();
+
+    assert!(!cursor.is_fp_register(UNW_ARM_D31));
+    assert!(matches!(
+        cursor.fp_register(UNW_ARM_D31),
+        Err(UnwindError::BadRegister)
+    ));
+}
+
+#[test]
+fn register_ids_round_trip() {
+    use vex_libunwind::registers::RegisterId;
+
+    for register in RegisterId::ALL {
+        let name = register.to_string();
+        assert_eq!(name.parse::<RegisterId>().unwrap(), register, "{name}");
+        assert_eq!(RegisterId::from_number(register.into()), Some(register));
+    }
+}
+
+#[test]
+fn register_ids_use_libunwind_numbers() {
+    use vex_libunwind::registers::RegisterId;
+
+    assert_eq!(i32::from(RegisterId::R0), UNW_ARM_R0);
+    assert_eq!(i32::from(RegisterId::Pc), UNW_ARM_PC);
+    assert_eq!(i32::from(RegisterId::S31), UNW_ARM_S31);
+    assert_eq!(i32::from(RegisterId::D0), UNW_ARM_D0);
+    assert_eq!(RegisterId::from_number(16), None);
+    assert_eq!(RegisterId::from_number(UNW_REG_IP), None);
+    assert!(RegisterId::D7.is_fp());
+    assert!(!RegisterId::Lr.is_fp());
+}
+
+#[test]
+fn register_id_aliases() {
+    use vex_libunwind::registers::RegisterId;
+
+    let aliases = [
+        ("fp", RegisterId::R11),
+        ("ip", RegisterId::R12),
+        ("r13", RegisterId::Sp),
+        ("r14", RegisterId::Lr),
+        ("r15", RegisterId::Pc),
+    ];
+    for (alias, register) in aliases {
+        assert_eq!(alias.parse::<RegisterId>().unwrap(), register);
+    }
+}
+
+#[test]
+fn register_id_rejects_unknown_names() {
+    use vex_libunwind::registers::RegisterId;
+
+    for name in [
+        "", "r", "r16", "s32", "d32", "r01", "r+1", "q0", "R0", "pc ",
+    ] {
+        assert!(name.parse::<RegisterId>().is_err(), "{name:?}");
+    }
+}
+
+#[test]
+fn register_names_match_display() {
+    for register in RegisterId::ALL {
+        let name = register_name(register.number()).unwrap();
+        assert_eq!(name.to_str().unwrap(), register.to_string());
+        assert_eq!(name, register.c_name());
+    }
+    assert_eq!(register_name(UNW_REG_IP), Some(c"pc"));
+    assert_eq!(register_name(UNW_REG_SP), Some(c"sp"));
+}
+
+#[test]
+fn listed_registers_have_names() {
+    for &register in all() {
+        assert!(register_name(register).is_some(), "{register}");
+        assert!(!RegisterId::from_number(register).unwrap().is_fp());
+    }
+    for &register in all_fp() {
+        assert!(register_name(register).is_some(), "{register}");
+        assert!(RegisterId::from_number(register).unwrap().is_fp());
+    }
+    assert_eq!(all().len(), 16);
+    assert_eq!(all_fp().len(), 32);
+}
+
+#[test]
+fn unknown_register_names_are_none() {
+    for register in [
+        UNW_ARM_PC + 1,
+        UNW_ARM_S0 - 1,
+        UNW_ARM_S31 + 1,
+        UNW_ARM_D31 + 1,
+        -3,
+    ] {
+        assert_eq!(register_name(register), None, "{register}");
+    }
+}
+
+#[cfg(feature = "core-registers-only")]
+#[test]
+fn fp_registers_are_refused() {
+    let context = vex_libunwind::UnwindContext::new().unwrap();
+    let cursor = vex_libunwind::UnwindCursor::new(&context).unwrap();
+
+    for register in [UNW_ARM_D0, UNW_ARM_D31, 17] {
+        assert!(matches!(
+            cursor.fp_register(register),
+            Err(vex_libunwind::UnwindError::BadRegister)
+        ));
+        assert!(matches!(
+            unsafe { cursor.set_fp_register(register, Default::default()) },
+            Err(vex_libunwind::UnwindError::BadRegister)
+        ));
+    }
+}
@@ -0,0 +1,60 @@
+//! Tests for capturing backtraces into static slots.
+#![cfg(not(feature = "disabled"))]
+
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    cell::Cell,
+};
+
+use vex_libunwind::*;
+
+/// Counts the allocations made by each thread.
+struct CountingAllocator;
+
+std::thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.with(|count| count.set(count.get() + 1));
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) }
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+#[test]
+fn capture_starts_at_caller_without_allocating() {
+    static SLOT: BacktraceSlot<16> = BacktraceSlot::new();
+    let context = UnwindContext::new().unwrap();
+    let cursor = UnwindCursor::new(&context).unwrap();
+    let caller = cursor.proc_range().unwrap();
+
+    let before = ALLOCATIONS.with(Cell::get);
+    let backtrace = SLOT.capture().unwrap();
+    assert_eq!(ALLOCATIONS.with(Cell::get), before);
+
+    let first = backtrace.frames().first().expect("backtrace is empty");
+    assert!(caller.contains(&first.ip), "{backtrace}");
+}
+
+#[test]
+fn slot_is_claimed_until_dropped() {
+    static SLOT: BacktraceSlot<16> = BacktraceSlot::new();
+
+    let backtrace = SLOT.capture().unwrap();
+    assert!(SLOT.capture().is_none());
+    assert!(std::thread::scope(|s| s
+        .spawn(|| SLOT.capture().is_none())
+        .join()
+        .unwrap()));
+
+    drop(backtrace);
+    assert!(!SLOT.capture().unwrap().frames().is_empty());
+}
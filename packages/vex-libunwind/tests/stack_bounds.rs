@@ -0,0 +1,63 @@
+//! Checks that backtraces captured near the end of a registered stack are
+//! marked as suspected overflows. The registration is per-thread with the
+//! `std` feature, so these tests don't interfere with each other.
+#![cfg(not(feature = "disabled"))]
+
+use vex_libunwind::*;
+
+fn current_sp() -> usize {
+    let cursor = capture_cursor().unwrap();
+    cursor.register(registers::UNW_REG_SP).unwrap()
+}
+
+/// Recurses until a backtrace captured along the way starts below `limit`,
+/// returning that backtrace.
+#[inline(never)]
+fn recurse_below(limit: usize) -> Backtrace<4> {
+    let padding = core::hint::black_box([0u8; 256]);
+    let mut backtrace = Backtrace::capture();
+    if backtrace.frames()[0].sp >= limit {
+        backtrace = recurse_below(limit);
+    }
+    core::hint::black_box(&padding);
+    backtrace
+}
+
+#[test]
+fn deep_recursion_is_suspected_overflow() {
+    let sp = current_sp();
+    let bounds = StackBounds {
+        lo: sp - 16 * 1024,
+        hi: sp + 1024,
+    };
+    StackBounds::set_current(Some(bounds));
+    assert_eq!(StackBounds::current(), Some(bounds));
+
+    let shallow = Backtrace::<4>::capture();
+    assert!(!shallow.overflow_suspected());
+    assert!(!shallow.to_string().contains("OVERFLOW"), "{shallow}");
+
+    let deep = recurse_below(bounds.lo + StackBounds::GUARD_BYTES);
+    assert!(deep.overflow_suspected());
+    assert!(
+        deep.to_string()
+            .starts_with("stack backtrace:\n  !!! STACK OVERFLOW SUSPECTED !!!\n"),
+        "{deep}"
+    );
+
+    StackBounds::set_current(None);
+    assert_eq!(StackBounds::current(), None);
+    assert!(!recurse_below(bounds.lo + StackBounds::GUARD_BYTES).overflow_suspected());
+}
+
+#[test]
+fn overflow_is_suspected_within_guard() {
+    let bounds = StackBounds {
+        lo: 0x1000,
+        hi: 0x3000,
+    };
+    assert!(bounds.overflow_suspected(0x0ff0));
+    assert!(bounds.overflow_suspected(0x1000 + StackBounds::GUARD_BYTES - 4));
+    assert!(!bounds.overflow_suspected(0x1000 + StackBounds::GUARD_BYTES));
+    assert_eq!(bounds.range(), 0x1000..0x3000);
+}
@@ -0,0 +1,97 @@
+//! Measures how much stack capturing a backtrace uses, by painting the unused
+//! part of the stack with a known pattern and checking how much of it was
+//! overwritten.
+
+use std::{
+    fmt::{self, Write},
+    ptr, thread,
+};
+
+use vex_libunwind::*;
+
+const PAINT: u8 = 0xa5;
+/// How far below the measuring function's frame the stack is painted.
+const PAINTED_BYTES: usize = 256 * 1024;
+/// Room left for the measuring function's own calls while it paints.
+const MARGIN: usize = 1024;
+
+/// Runs `f` and returns roughly how many bytes of stack it used.
+#[inline(never)]
+fn measure(f: impl FnOnce()) -> usize {
+    let marker = 0u8;
+    let top = ptr::addr_of!(marker) as usize - MARGIN;
+    let bottom = top - PAINTED_BYTES;
+
+    // SAFETY: The test thread's stack is large enough that everything below
+    // this frame down to `bottom` is mapped, and none of it is in use.
+    unsafe {
+        for address in bottom..top {
+            ptr::write_volatile(address as *mut u8, PAINT);
+        }
+    }
+    f();
+    let deepest = (bottom..top)
+        .find(|&address| unsafe { ptr::read_volatile(address as *const u8) } != PAINT)
+        .unwrap_or(top);
+    core::hint::black_box(&marker);
+    top - deepest
+}
+
+fn on_big_stack<R: Send + 'static>(f: impl FnOnce() -> R + Send + 'static) -> R {
+    thread::Builder::new()
+        .stack_size(4 * PAINTED_BYTES)
+        .spawn(f)
+        .unwrap()
+        .join()
+        .unwrap()
+}
+
+struct Sink;
+
+impl Write for Sink {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        core::hint::black_box(s);
+        Ok(())
+    }
+}
+
+#[test]
+fn capture_fits_in_documented_stack() {
+    let used = on_big_stack(|| {
+        measure(|| {
+            core::hint::black_box(Backtrace::<64>::capture());
+        })
+    });
+    let backtrace = core::mem::size_of::<Backtrace<64>>();
+    eprintln!("capture: {used} bytes, including a {backtrace} byte backtrace");
+    assert!(used - backtrace <= MAX_CAPTURE_STACK_BYTES, "{used}");
+}
+
+#[test]
+fn capture_soa_fits_in_documented_stack() {
+    let used = on_big_stack(|| {
+        measure(|| {
+            let mut ips = [0; 64];
+            let mut offsets = [0; 64];
+            capture_soa(&mut ips, &mut offsets).unwrap();
+            core::hint::black_box((ips, offsets));
+        })
+    });
+    let arrays = 64 * (core::mem::size_of::<usize>() + core::mem::size_of::<u64>());
+    eprintln!("capture_soa: {used} bytes, including {arrays} bytes of arrays");
+    assert!(used - arrays <= MAX_CAPTURE_STACK_BYTES, "{used}");
+}
+
+#[test]
+fn write_backtrace_stack_usage() {
+    let used = on_big_stack(|| {
+        measure(|| {
+            let context = UnwindContext::new().unwrap();
+            write_backtrace(&mut Sink, &context).unwrap();
+        })
+    });
+    // Name lookups make this too dependent on the host's dynamic linker to
+    // check against a bound.
+    eprintln!("write_backtrace: {used} bytes");
+    assert!(used > 0);
+}
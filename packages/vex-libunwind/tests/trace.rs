@@ -0,0 +1,52 @@
+//! Tests for the `trace` feature's hook. The hook is global, so this file
+//! holds a single test.
+#![cfg(feature = "trace")]
+
+use std::sync::Mutex;
+
+use vex_libunwind::*;
+use vex_libunwind_sys::mock::*;
+
+static EVENTS: Mutex<Vec<TraceEvent>> = Mutex::new(Vec::new());
+
+fn record(event: &TraceEvent) {
+    EVENTS.lock().unwrap().push(*event);
+}
+
+#[test]
+fn hook_sees_each_call_into_libunwind() {
+    let _mock = MockStack::new(vec![
+        MockFrame::new(0x1000, 0x2000),
+        MockFrame::new(0x1100, 0x2100),
+    ])
+    .install();
+    set_trace_hook(Some(record));
+
+    let mut cursor = capture_cursor().unwrap();
+    let _ = cursor.proc_info();
+    while cursor.step().unwrap() {}
+    set_trace_hook(None);
+
+    let events = EVENTS.lock().unwrap();
+    assert!(
+        matches!(
+            events[..],
+            [
+                TraceEvent::Context { code: 0 },
+                TraceEvent::Cursor { code: 0 },
+                TraceEvent::ProcInfo { ip: 0x1000, .. },
+                TraceEvent::Step {
+                    before: first,
+                    code: 1,
+                    after: Some(second),
+                },
+                TraceEvent::Step {
+                    code: 0,
+                    after: None,
+                    ..
+                },
+            ] if (first.ip, first.sp, second.ip, second.sp) == (0x1000, 0x2000, 0x1100, 0x2100)
+        ),
+        "{events:#?}"
+    );
+}
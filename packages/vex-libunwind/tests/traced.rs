@@ -0,0 +1,49 @@
+//! Tests for errors carrying backtraces of where they were created.
+#![cfg(not(feature = "disabled"))]
+
+use std::fmt;
+
+use vex_libunwind::*;
+use vex_libunwind_sys::mock::*;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct MotorError(u8);
+
+impl fmt::Display for MotorError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "motor on port {} disconnected", self.0)
+    }
+}
+
+const fn spin() -> Result<(), MotorError> {
+    Err(MotorError(3))
+}
+
+fn drive() -> Result<(), Traced<MotorError, 4>> {
+    spin()?;
+    Ok(())
+}
+
+#[test]
+fn question_mark_captures_where_the_error_was_converted() {
+    let _mock = MockStack::new(vec![
+        MockFrame::new(0x1000, 0x2000),
+        MockFrame::new(0x1100, 0x2100),
+    ])
+    .install();
+    let error = drive().unwrap_err();
+
+    assert_eq!(*error, MotorError(3));
+    assert_eq!(error.0, 3);
+    let ips: Vec<_> = error.backtrace().frames().iter().map(|f| f.ip).collect();
+    assert_eq!(ips, [0x1000, 0x1100]);
+    assert_eq!(
+        error.to_string(),
+        format!("motor on port 3 disconnected\n{}", error.backtrace())
+    );
+
+    let mapped = error.clone().map(|error| error.0);
+    assert_eq!(*mapped, 3);
+    assert_eq!(mapped.backtrace().frames(), error.backtrace().frames());
+    assert_eq!(error.into_inner(), MotorError(3));
+}
@@ -0,0 +1,137 @@
+//! Tests for walking the call chain with a [`Frames`] iterator.
+
+use vex_libunwind::*;
+
+#[inline(always)]
+fn cursor() -> UnwindCursor {
+    let context = UnwindContext::new().unwrap();
+    UnwindCursor::new(&context).unwrap()
+}
+
+#[test]
+fn walk_matches_manual_stepping() {
+    let mut cursor = cursor();
+    let mut manual = cursor.clone();
+
+    for frame in cursor.frames(WalkConfig::new()) {
+        let frame = frame.unwrap();
+        assert_eq!(frame, Frame::from_cursor(&manual).unwrap());
+        manual.step().unwrap();
+    }
+    assert!(!manual.step().unwrap());
+}
+
+#[test]
+fn walk_stops_in_range() {
+    let mut cursor = cursor();
+    let ips: Vec<_> = cursor
+        .clone()
+        .frames(WalkConfig::new())
+        .map(|frame| frame.unwrap().ip)
+        .collect();
+    assert!(ips.len() > 3);
+
+    let stop = [0..1, ips[2]..ips[2] + 1];
+    let walked: Vec<_> = cursor
+        .frames(WalkConfig::new().stop_in_ranges(&stop))
+        .map(|frame| frame.unwrap().ip)
+        .collect();
+    assert_eq!(walked, ips[..2]);
+}
+
+#[test]
+fn frames_seen_counts_yielded_frames() {
+    let mut cursor = cursor();
+    let mut frames = cursor.frames(WalkConfig::new());
+    assert_eq!(frames.frames_seen(), 0);
+
+    let mut yielded = 0;
+    while let Some(frame) = frames.next() {
+        frame.unwrap();
+        yielded += 1;
+        assert_eq!(frames.frames_seen(), yielded);
+    }
+    assert!(yielded > 1);
+    assert_eq!(frames.frames_seen(), yielded);
+}
+
+#[test]
+fn walk_cancels_between_frames() {
+    let mut cursor = cursor();
+    let expected: Vec<_> = cursor
+        .clone()
+        .frames(WalkConfig::new())
+        .take(2)
+        .map(Result::unwrap)
+        .collect();
+    assert_eq!(expected.len(), 2);
+
+    let mut checks = 0;
+    let mut frames = cursor.frames(WalkConfig::new()).cancel_when(|| {
+        checks += 1;
+        checks == 2
+    });
+    let walked: Vec<_> = frames.by_ref().collect();
+    assert_eq!(frames.frames_seen(), 2);
+    assert!(frames.next().is_none());
+
+    assert_eq!(walked.len(), 3);
+    assert_eq!(walked[0].as_ref().unwrap(), &expected[0]);
+    assert_eq!(walked[1].as_ref().unwrap(), &expected[1]);
+    assert!(matches!(walked[2], Err(UnwindError::Interrupted)));
+}
+
+#[test]
+fn restore_rewinds_to_checkpoint() {
+    let mut cursor = cursor();
+    for _ in 0..2 {
+        assert!(cursor.step().unwrap());
+    }
+    let checkpoint = cursor.checkpoint();
+    let saved = Frame::from_cursor(&cursor).unwrap();
+    assert!(cursor.step().unwrap());
+    let next = Frame::from_cursor(&cursor).unwrap();
+
+    while cursor.step().unwrap() {}
+    cursor.restore(&checkpoint);
+    assert_eq!(Frame::from_cursor(&cursor).unwrap(), saved);
+    assert!(cursor.step().unwrap());
+    assert_eq!(Frame::from_cursor(&cursor).unwrap(), next);
+}
+
+#[test]
+fn step_down_is_refused_without_moving() {
+    let mut cursor = cursor();
+    assert!(cursor.step().unwrap());
+    let frame = Frame::from_cursor(&cursor).unwrap();
+
+    assert!(!cursor.can_step_down());
+    assert!(matches!(cursor.step_down(), Err(UnwindError::BadValue)));
+    assert_eq!(Frame::from_cursor(&cursor).unwrap(), frame);
+}
+
+#[test]
+fn frame_stack_ranges_meet_and_grow_upward() {
+    let mut cursor = cursor();
+    let mut ranges = Vec::new();
+    loop {
+        let sp = cursor.register(registers::UNW_REG_SP).unwrap();
+        match cursor.frame_stack_range() {
+            Ok(range) => {
+                assert_eq!(range.start, sp);
+                ranges.push(range);
+            }
+            Err(error) => {
+                assert_eq!(error, UnwindError::NoInfo);
+                break;
+            }
+        }
+        assert!(cursor.step().unwrap());
+    }
+
+    assert!(ranges.len() > 1);
+    for pair in ranges.windows(2) {
+        assert!(pair[0].start < pair[0].end, "{ranges:x?}");
+        assert_eq!(pair[0].end, pair[1].start, "{ranges:x?}");
+    }
+}